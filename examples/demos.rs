@@ -0,0 +1,20 @@
+use rand::thread_rng;
+
+use dm_simu_rs::demos;
+
+// Run every demo and print the resulting state, e.g. cargo run --example demos -- qaoa
+fn main() {
+    let names = match std::env::args().nth(1) {
+        Some(name) => vec![name],
+        None => demos::DEMOS.iter().map(|name| name.to_string()).collect()
+    };
+    for name in names {
+        match demos::run_demo(&name, &mut thread_rng()) {
+            Ok(result) => {
+                println!("{} (outcomes {:?}):", name, result.outcomes);
+                println!("{}", result.state);
+            },
+            Err(e) => eprintln!("{}: {}", name, e)
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use rand::RngCore;
+
+use crate::backend::QuantumBackend;
+use crate::config::TolerancePolicy;
+use crate::density_matrix::{DensityMatrix, State};
+use crate::operators::Operator;
+use crate::pauli::PauliString;
+use crate::error::SimulatorError;
+
+// Debug mode checking the physical invariants of a density matrix after every operation, to
+// find which operation of a long run made the state unphysical.
+
+// First operation after which the invariants stopped holding, with everything needed to replay it.
+#[derive(Clone)]
+pub struct Violation {
+    pub step: usize,                // Index of the operation, counting from 0.
+    pub operation: String,          // Name of the operation, e.g. "evolve" or "apply_channel".
+    pub indices: Vec<usize>,        // Target qubits.
+    pub operators: Vec<Operator>,   // Applied operator, or Kraus operators of a channel.
+    pub state_before: DensityMatrix,
+    pub message: String             // Invariant that failed.
+}
+
+#[derive(Clone)]
+pub struct AuditedDensityMatrix {
+    pub state: DensityMatrix,
+    pub tolerance: TolerancePolicy,
+    pub steps: usize,
+    pub violation: Option<Violation>
+}
+
+impl AuditedDensityMatrix {
+    pub fn new(state: DensityMatrix, tolerance: TolerancePolicy) -> Self {
+        AuditedDensityMatrix { state, tolerance, steps: 0, violation: None }
+    }
+
+    // Error describing the first violation, if any.
+    pub fn check(&self) -> Result<(), String> {
+        match &self.violation {
+            Some(v) => Err(format!("Operation {} ({} on {:?}) broke the state: {}", v.step, v.operation, v.indices, v.message)),
+            None => Ok(())
+        }
+    }
+
+    // Run an operation on the state and check the invariants afterwards. The state before the
+    // operation is only kept until the first violation is recorded.
+    fn audit<T, F>(&mut self, operation: &str, indices: &[usize], operators: &[Operator], f: F) -> Result<T, SimulatorError>
+    where
+        F: FnOnce(&mut DensityMatrix) -> Result<T, SimulatorError>
+    {
+        let before = self.violation.is_none().then(|| self.state.clone());
+        let result = f(&mut self.state)?;
+        if let Some(state_before) = before {
+            if let Err(error) = self.state.check_invariants(&self.tolerance) {
+                self.violation = Some(Violation {
+                    step: self.steps,
+                    operation: operation.to_string(),
+                    indices: indices.to_vec(),
+                    operators: operators.to_vec(),
+                    state_before,
+                    message: error.to_string()
+                });
+            }
+        }
+        self.steps += 1;
+        Ok(result)
+    }
+
+    pub fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        self.audit("apply_channel", indices, kraus, |rho| rho.apply_channel(kraus, indices))
+    }
+}
+
+impl QuantumBackend for AuditedDensityMatrix {
+    fn nqubits(&self) -> usize {
+        self.state.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        self.audit("evolve_single", &[index], std::slice::from_ref(op), |rho| rho.evolve_single(op, index))
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.audit("evolve", indices, std::slice::from_ref(op), |rho| rho.evolve(op, indices))
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.audit("measure", &[index], &[], |rho| rho.measure(index, rng))
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        self.state.expectation(pauli_string)
+    }
+
+    fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        AuditedDensityMatrix::apply_channel(self, kraus, indices)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        let indices = (self.state.nqubits..self.state.nqubits + other.state.nqubits).collect::<Vec<_>>();
+        let _ = self.audit("tensor", &indices, &[], |rho| {
+            *rho = DensityMatrix::tensor(rho, &other.state);
+            Ok(())
+        });
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        let index = self.state.nqubits;
+        let _ = self.audit("add_qubit", &[index], &[], |rho| {
+            rho.add_qubit(state);
+            Ok(())
+        });
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.audit("measure_and_remove", &[index], &[], |rho| rho.measure_and_remove(index, rng))
+    }
+}
@@ -0,0 +1,50 @@
+use rand::RngCore;
+
+use crate::config::BufferAllocator;
+use crate::density_matrix::State;
+use crate::operators::Operator;
+use crate::pauli::PauliString;
+use crate::error::SimulatorError;
+
+// Operations shared by every simulator, so that patterns can run on whichever state
+// representation fits: density matrices for noisy runs, state vectors for pure states.
+pub trait QuantumBackend {
+    fn nqubits(&self) -> usize;
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError>;
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError>;
+
+    // Projective measurement of a qubit in the computational basis. The state collapses
+    // onto the sampled outcome and the qubit is kept in the register.
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError>;
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError>;
+
+    // Apply the channel given by its Kraus operators. Only mixed state backends can represent
+    // the result, the others reject it.
+    fn apply_channel(&mut self, _kraus: &[Operator], _indices: &[usize]) -> Result<(), SimulatorError> {
+        Err(SimulatorError::InvalidArgument("This backend cannot apply noise channels.".to_string()))
+    }
+
+    // Append the qubits of other after the qubits of self.
+    fn tensor(&mut self, other: &Self) where Self: Sized;
+
+    // Append a fresh qubit after the existing ones.
+    fn add_qubit(&mut self, state: State);
+
+    // Same as add_qubit, backends with large buffers taking them from the allocator. Fails when
+    // the allocator returns a buffer of the wrong length.
+    fn add_qubit_with(&mut self, state: State, _allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        self.add_qubit(state);
+        Ok(())
+    }
+
+    // Measure a qubit in the computational basis and drop it from the register,
+    // shifting the following qubits down by one.
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError>;
+
+    fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, _allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        self.measure_and_remove(index, rng)
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::density_matrix::{DensityMatrix, State};
+use crate::error::SimulatorError;
+use crate::noise::NoiseModel;
+use crate::operators::Operator;
+use crate::pattern::Pattern;
+use crate::rng::RngConfig;
+use crate::tensor::Tensor;
+use crate::tools::{apply_left, apply_right_adjoint, are_elements_unique};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Many density matrices of the same size stored one after the other, for Monte Carlo studies
+// evolving thousands of small states the same way. Members are processed in parallel with the
+// parallel feature, random numbers being drawn up front so results do not depend on it.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub data: Vec<Complex<f64>>,    // Row-major density matrices, member after member.
+    pub count: usize,
+    pub nqubits: usize
+}
+
+// Outputs of running a pattern on every member of a batch.
+pub struct BatchResult {
+    pub states: Batch,
+    pub outcomes: Vec<HashMap<usize, u8>>
+}
+
+impl Batch {
+    pub fn new(count: usize, nqubits: usize, state: State) -> Self {
+        let member = DensityMatrix::new(nqubits, state);
+        Batch { data: member.data.data.repeat(count), count, nqubits }
+    }
+
+    pub fn from_states(states: &[DensityMatrix]) -> Result<Self, SimulatorError> {
+        let nqubits = states.first().map_or(0, |state| state.nqubits);
+        if let Some(state) = states.iter().find(|state| state.nqubits != nqubits) {
+            return Err(SimulatorError::DimensionMismatch { expected: nqubits, actual: state.nqubits });
+        }
+        let data = states.iter().flat_map(|state| state.data.data.iter().copied()).collect();
+        Ok(Batch { data, count: states.len(), nqubits })
+    }
+
+    fn member_len(&self) -> usize {
+        1 << (2 * self.nqubits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Copy of the i-th member.
+    pub fn get(&self, i: usize) -> Option<DensityMatrix> {
+        let len = self.member_len();
+        self.data.get(i * len..(i + 1) * len).map(|data| DensityMatrix {
+            data: Tensor::from_vec(data.to_vec(), vec![2; 2 * self.nqubits]),
+            size: 1 << self.nqubits,
+            nqubits: self.nqubits
+        })
+    }
+
+    pub fn to_states(&self) -> Vec<DensityMatrix> {
+        (0..self.count).filter_map(|i| self.get(i)).collect()
+    }
+
+    // Apply rho -> U rho U^dagger to every member, the i-th qubit of the operator acting on indices[i].
+    pub fn evolve_all(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        let (size, nqubits) = (1 << self.nqubits, self.nqubits);
+        let evolve = |member: &mut [Complex<f64>]| {
+            apply_left(member, size, &op.data.data, indices, nqubits);
+            apply_right_adjoint(member, size, &op.data.data, indices, nqubits);
+        };
+        let len = self.member_len();
+        #[cfg(feature = "parallel")]
+        self.data.par_chunks_mut(len).for_each(evolve);
+        #[cfg(not(feature = "parallel"))]
+        self.data.chunks_mut(len).for_each(evolve);
+        Ok(())
+    }
+
+    // Measure a qubit of every member in the computational basis, collapsing each onto its own
+    // sampled outcome.
+    pub fn measure_all(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<Vec<u8>, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let draws = (0..self.count).map(|_| rng.gen::<f64>()).collect::<Vec<_>>();
+        let size = 1usize << self.nqubits;
+        let bit = 1 << (self.nqubits - 1 - index);
+        let measure = |(member, draw): (&mut [Complex<f64>], &f64)| {
+            let trace = (0..size).map(|i| member[i * size + i].re).sum::<f64>();
+            let p1 = (0..size).filter(|i| i & bit != 0).map(|i| member[i * size + i].re).sum::<f64>() / trace;
+            let outcome = u8::from(*draw < p1);
+            let kept = if outcome == 1 { p1 } else { 1. - p1 } * trace;
+            for (k, c) in member.iter_mut().enumerate() {
+                let (row, col) = (k / size, k % size);
+                if u8::from(row & bit != 0) != outcome || u8::from(col & bit != 0) != outcome {
+                    *c = Complex::ZERO;
+                } else {
+                    *c /= kept;
+                }
+            }
+            outcome
+        };
+        let len = self.member_len();
+        #[cfg(feature = "parallel")]
+        let outcomes = self.data.par_chunks_mut(len).zip(draws.par_iter()).map(measure).collect();
+        #[cfg(not(feature = "parallel"))]
+        let outcomes = self.data.chunks_mut(len).zip(draws.iter()).map(measure).collect();
+        Ok(outcomes)
+    }
+}
+
+impl Pattern {
+    // Run the pattern with the noise model on every member of the batch, member i drawing its
+    // outcomes from the generator (batch, i) of the config, so results do not depend on the
+    // order the members run in.
+    pub fn simulate_batch(&self, batch: &Batch, noise: &NoiseModel, rng: &RngConfig) -> Result<BatchResult, String> {
+        let run = |i: usize| {
+            let input = batch.get(i).unwrap();
+            self.simulate_with_noise(input, noise, &mut *rng.rng("batch", i as u64))
+        };
+        #[cfg(feature = "parallel")]
+        let results = (0..batch.count).into_par_iter().map(run).collect::<Result<Vec<_>, String>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let results = (0..batch.count).map(run).collect::<Result<Vec<_>, String>>()?;
+
+        let mut states = Batch { data: Vec::new(), count: results.len(), nqubits: self.output_nodes().len() };
+        let mut outcomes = Vec::with_capacity(results.len());
+        for result in results {
+            states.data.extend(result.state.data.data);
+            outcomes.push(result.outcomes);
+        }
+        Ok(BatchResult { states, outcomes })
+    }
+}
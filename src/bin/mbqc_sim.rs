@@ -0,0 +1,106 @@
+use std::fs;
+use std::process::ExitCode;
+
+use dm_simu_rs::circuit::Circuit;
+use dm_simu_rs::config::SimulationConfig;
+use dm_simu_rs::density_matrix::{DensityMatrix, State};
+use dm_simu_rs::noise::NoiseModel;
+use dm_simu_rs::pattern::Pattern;
+use dm_simu_rs::rng::{RngConfig, RngKind};
+use dm_simu_rs::simulator::{Simulator, StateAverage};
+
+const USAGE: &str = "Usage: mbqc-sim <pattern.json | circuit.qasm> [--noise <model.toml>] [--shots <n>] [--seed <seed>] [--save <state file>]
+
+Runs the pattern, or the pattern of the circuit, once per shot and prints the histogram of the
+output bitstrings, qubit 0 first, read out through the readout error of the noise model. Pattern
+inputs start in |+> and circuit qubits in |0>. --save
+writes the output density matrix averaged over the shots as a checkpoint.";
+
+struct Options {
+    input: String,
+    noise: Option<String>,
+    shots: usize,
+    seed: Option<u64>,
+    save: Option<String>
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { input: String::new(), noise: None, shots: 1024, seed: None, save: None };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value.\n\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--noise" => options.noise = Some(value()?),
+            "--shots" => options.shots = value()?.parse().map_err(|_| "--shots needs a number.".to_string())?,
+            "--seed" => options.seed = Some(value()?.parse().map_err(|_| "--seed needs a number.".to_string())?),
+            "--save" => options.save = Some(value()?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            path if options.input.is_empty() && !path.starts_with("--") => options.input = path.to_string(),
+            _ => return Err(format!("Unexpected argument {}.\n\n{}", arg, USAGE))
+        }
+    }
+    if options.input.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(options)
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}.", path, e))
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let options = parse_options(args)?;
+    let source = read(&options.input)?;
+    let (pattern, input_state) = if options.input.ends_with(".qasm") {
+        (Circuit::from_qasm(&source)?.to_pattern(), State::ZERO)
+    } else {
+        (Pattern::from_json(&source)?, State::PLUS)
+    };
+    let noise = match &options.noise {
+        Some(path) => NoiseModel::from_toml(&read(path)?)?,
+        None => NoiseModel::default()
+    };
+    let rng = match options.seed {
+        Some(seed) => RngConfig { kind: RngKind::Pcg64, seed },
+        None => RngConfig { kind: RngKind::Os, seed: 0 }
+    };
+
+    let nqubits = pattern.output_nodes().len();
+    let readout = noise.readout.clone();
+    let simulator = Simulator::new(DensityMatrix::new(pattern.input_nodes().len(), input_state))
+        .load(pattern)
+        .with_noise(noise)
+        .with_config(SimulationConfig { rng, ..SimulationConfig::default() });
+    let mut histogram = vec![0; 1 << nqubits];
+    let mut average = StateAverage::default();
+    simulator.for_each_shot(options.shots, |shot, result| {
+        let mut generator = rng.rng("readout", shot);
+        result.state.sample_with_readout(1, &readout, &mut *generator).into_keys().for_each(|outcome| histogram[outcome as usize] += 1);
+        if options.save.is_some() {
+            average.add(&result.state)?;
+        }
+        Ok(())
+    })?;
+    if let Some(path) = &options.save {
+        average.average()?.save(path)?;
+    }
+    Ok(histogram.iter().enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(outcome, count)| format!("{:0width$b} {}\n", outcome, count, width = nqubits))
+        .collect())
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(output) => {
+            print!("{}", output);
+            ExitCode::SUCCESS
+        },
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use num_complex::Complex;
+use thiserror::Error;
+
+use crate::config::TolerancePolicy;
+use crate::linalg;
+use crate::operators::{OneQubitOp, Operator};
+use crate::pauli::{Pauli, PauliString};
+use crate::tensor::Tensor;
+
+// Standard noise channels, each given as a set of Kraus operators usable with
+// `DensityMatrix::apply_channel`.
+
+fn check_probability(p: f64, name: &str) -> Result<(), String> {
+    if !(0. ..=1.).contains(&p) {
+        return Err(format!("{} should be a probability in [0, 1], got {}.", name, p));
+    }
+    Ok(())
+}
+
+fn scaled(gate: OneQubitOp, factor: f64) -> Operator {
+    let mut op = Operator::one_qubit(gate);
+    op.data.data.iter_mut().for_each(|c| *c *= factor);
+    op
+}
+
+fn one_qubit_op(data: [f64; 4]) -> Operator {
+    Operator {
+        nqubits: 1,
+        data: Tensor::from_vec(data.iter().map(|&x| Complex::new(x, 0.)).collect(), vec![2, 2])
+    }
+}
+
+// Kronecker product of two one qubit operators, the first one acting on the first target qubit.
+fn kron(a: &Operator, b: &Operator, factor: f64) -> Operator {
+    let mut data = vec![Complex::ZERO; 16];
+    for i in 0..4 {
+        for j in 0..4 {
+            data[i * 4 + j] = a.data.data[(i >> 1) * 2 + (j >> 1)] * b.data.data[(i & 1) * 2 + (j & 1)] * factor;
+        }
+    }
+    Operator { nqubits: 2, data: Tensor::from_vec(data, vec![2; 4]) }
+}
+
+// rho -> (1 - p) rho + p I / 2
+pub fn depolarizing(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Depolarizing probability")?;
+    Ok(vec![
+        scaled(OneQubitOp::I, (1. - 3. * p / 4.).sqrt()),
+        scaled(OneQubitOp::X, (p / 4.).sqrt()),
+        scaled(OneQubitOp::Y, (p / 4.).sqrt()),
+        scaled(OneQubitOp::Z, (p / 4.).sqrt()),
+    ])
+}
+
+// rho -> (1 - p) rho + p I / 4 on two qubits.
+pub fn two_qubit_depolarizing(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Depolarizing probability")?;
+    let paulis = [
+        Operator::one_qubit(OneQubitOp::I),
+        Operator::one_qubit(OneQubitOp::X),
+        Operator::one_qubit(OneQubitOp::Y),
+        Operator::one_qubit(OneQubitOp::Z),
+    ];
+    let mut kraus = Vec::with_capacity(16);
+    for (i, a) in paulis.iter().enumerate() {
+        for (j, b) in paulis.iter().enumerate() {
+            let factor = if i == 0 && j == 0 {
+                (1. - 15. * p / 16.).sqrt()
+            } else {
+                (p / 16.).sqrt()
+            };
+            kraus.push(kron(a, b, factor));
+        }
+    }
+    Ok(kraus)
+}
+
+// Shrinks the off-diagonal elements by a factor (1 - p).
+pub fn dephasing(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Dephasing probability")?;
+    Ok(vec![
+        scaled(OneQubitOp::I, (1. - p / 2.).sqrt()),
+        scaled(OneQubitOp::Z, (p / 2.).sqrt()),
+    ])
+}
+
+// Decay from |1> to |0> with probability gamma.
+pub fn amplitude_damping(gamma: f64) -> Result<Vec<Operator>, String> {
+    check_probability(gamma, "Damping rate")?;
+    Ok(vec![
+        one_qubit_op([1., 0., 0., (1. - gamma).sqrt()]),
+        one_qubit_op([0., gamma.sqrt(), 0., 0.]),
+    ])
+}
+
+// Amplitude damping at finite temperature: relaxation with probability gamma towards the thermal
+// state (1 - excited_population) |0><0| + excited_population |1><1| instead of |0>.
+pub fn generalized_amplitude_damping(gamma: f64, excited_population: f64) -> Result<Vec<Operator>, String> {
+    check_probability(gamma, "Damping rate")?;
+    check_probability(excited_population, "Excited state population")?;
+    let (ground, excited) = ((1. - excited_population).sqrt(), excited_population.sqrt());
+    let (decay, kept) = (gamma.sqrt(), (1. - gamma).sqrt());
+    Ok(vec![
+        one_qubit_op([ground, 0., 0., ground * kept]),
+        one_qubit_op([0., ground * decay, 0., 0.]),
+        one_qubit_op([excited * kept, 0., 0., excited]),
+        one_qubit_op([0., 0., excited * decay, 0.]),
+    ])
+}
+
+// Loss of coherence without energy exchange, with scattering probability lambda.
+pub fn phase_damping(lambda: f64) -> Result<Vec<Operator>, String> {
+    check_probability(lambda, "Damping rate")?;
+    Ok(vec![
+        one_qubit_op([1., 0., 0., (1. - lambda).sqrt()]),
+        one_qubit_op([0., 0., 0., lambda.sqrt()]),
+    ])
+}
+
+// Applies X with probability p.
+pub fn bit_flip(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Flip probability")?;
+    Ok(vec![
+        scaled(OneQubitOp::I, (1. - p).sqrt()),
+        scaled(OneQubitOp::X, p.sqrt()),
+    ])
+}
+
+// Applies Z with probability p.
+pub fn phase_flip(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Flip probability")?;
+    Ok(vec![
+        scaled(OneQubitOp::I, (1. - p).sqrt()),
+        scaled(OneQubitOp::Z, p.sqrt()),
+    ])
+}
+
+// Correlated dephasing of two qubits, applying Z on both with probability p.
+pub fn zz_crosstalk(p: f64) -> Result<Vec<Operator>, String> {
+    check_probability(p, "Crosstalk probability")?;
+    Ok(vec![
+        kron(&Operator::one_qubit(OneQubitOp::I), &Operator::one_qubit(OneQubitOp::I), (1. - p).sqrt()),
+        kron(&Operator::one_qubit(OneQubitOp::Z), &Operator::one_qubit(OneQubitOp::Z), p.sqrt()),
+    ])
+}
+
+// First condition of a completely positive trace preserving map that a channel breaks.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CptpViolation {
+    #[error("Choi matrix has a negative eigenvalue {min_eigenvalue}, the map is not completely positive.")]
+    NotCompletelyPositive { min_eigenvalue: f64 },
+    #[error("Sum of K^dagger K differs from the identity by {defect} in spectral norm, the map is not trace preserving.")]
+    NotTracePreserving { defect: f64 }
+}
+
+#[derive(Clone)]
+pub struct ChannelProperties {
+    pub choi_rank: usize,                   // Minimal number of Kraus operators.
+    pub unital: bool,                       // Whether the channel maps I to I.
+    pub trace_preservation_defect: f64,     // Spectral norm of sum_k K_k^dagger K_k - I.
+    pub average_gate_fidelity: f64,         // Against the target unitary, the identity by default.
+}
+
+// Channel given by its Kraus operators, all acting on the same qubits.
+#[derive(Clone)]
+pub struct Channel {
+    pub nqubits: usize,
+    pub kraus: Vec<Operator>
+}
+
+impl Channel {
+    pub fn new(kraus: Vec<Operator>) -> Result<Self, String> {
+        let nqubits = match kraus.first() {
+            Some(k) => k.nqubits,
+            None => return Err("A channel needs at least one Kraus operator.".to_string())
+        };
+        if kraus.iter().any(|k| k.nqubits != nqubits) {
+            return Err("All Kraus operators must act on the same number of qubits.".to_string());
+        }
+        Ok(Channel { nqubits, kraus })
+    }
+
+    pub fn dim(&self) -> usize {
+        1 << self.nqubits
+    }
+
+    // Kraus operators sqrt(lambda) |v> read back row by row from the eigenvectors of the Choi
+    // matrix, so that the result has the minimal number of operators. Eigenvalues are compared
+    // with the unitarity tolerance relative to the largest one.
+    pub fn from_choi(choi: &[Complex<f64>], nqubits: usize, tol: &TolerancePolicy) -> Result<Self, String> {
+        let d = 1 << nqubits;
+        let n = d * d;
+        if choi.len() != n * n {
+            return Err(format!("Choi matrix of {} qubits should have {} entries, got {}.", nqubits, n * n, choi.len()));
+        }
+        if linalg::max_abs_diff(choi, &linalg::adjoint(choi, n)) > tol.unitarity {
+            return Err("Choi matrix is not Hermitian.".to_string());
+        }
+        let (values, vectors) = linalg::eigh(choi, n);
+        let largest = values.iter().fold(0., |m: f64, v| m.max(v.abs()));
+        let cutoff = tol.unitarity * largest.max(1.);
+        if let Some(v) = values.iter().find(|&&v| v < -cutoff) {
+            return Err(format!("Choi matrix has a negative eigenvalue {}, the map is not completely positive.", v));
+        }
+        let kraus = values.iter().enumerate()
+            .filter(|(_, &v)| v > cutoff)
+            .map(|(j, v)| Operator {
+                nqubits,
+                data: Tensor::from_vec((0..n).map(|i| vectors[i * n + j] * v.sqrt()).collect(), vec![2; 2 * nqubits])
+            })
+            .collect::<Vec<_>>();
+        if kraus.is_empty() {
+            return Err("Choi matrix is zero.".to_string());
+        }
+        Channel::new(kraus)
+    }
+
+    // Superoperator S acting on density matrices vectorized row by row, vec(E(rho)) = S vec(rho),
+    // i.e. S = sum_k K_k x conj(K_k).
+    pub fn superoperator(&self) -> Vec<Complex<f64>> {
+        reshuffle(&self.choi(), self.dim())
+    }
+
+    pub fn from_superoperator(superoperator: &[Complex<f64>], nqubits: usize, tol: &TolerancePolicy) -> Result<Self, String> {
+        let d = 1 << nqubits;
+        if superoperator.len() != d * d * d * d {
+            return Err(format!("Superoperator of {} qubits should have {} entries, got {}.", nqubits, d * d * d * d, superoperator.len()));
+        }
+        Channel::from_choi(&reshuffle(superoperator, d), nqubits, tol)
+    }
+
+    // Pauli transfer matrix R_ij = Tr(P_i E(P_j)) / d, real and d^2 x d^2, the Pauli strings
+    // ordered as I..I, I..X, I..Y, I..Z, ... with qubit 0 the slowest.
+    pub fn ptm(&self) -> Vec<f64> {
+        ptm_from_choi(&self.choi(), self.nqubits).expect("Choi matrix of the channel's size")
+    }
+
+    // From S = sum_ij R_ij |P_i>><<P_j| / d, since Tr(P_i P_j) = d delta_ij.
+    pub fn from_ptm(ptm: &[f64], nqubits: usize, tol: &TolerancePolicy) -> Result<Self, String> {
+        let d = 1 << nqubits;
+        let n = d * d;
+        if ptm.len() != n * n {
+            return Err(format!("Pauli transfer matrix of {} qubits should have {} entries, got {}.", nqubits, n * n, ptm.len()));
+        }
+        let paulis = pauli_basis(nqubits).iter().map(PauliString::matrix).collect::<Vec<_>>();
+        let mut superoperator = vec![Complex::ZERO; n * n];
+        for (i, p_i) in paulis.iter().enumerate() {
+            for (j, p_j) in paulis.iter().enumerate() {
+                let r_ij = ptm[i * n + j] / d as f64;
+                if r_ij == 0. {
+                    continue;
+                }
+                for (a, x) in p_i.iter().enumerate() {
+                    for (b, y) in p_j.iter().enumerate() {
+                        superoperator[a * n + b] += x * y.conj() * r_ij;
+                    }
+                }
+            }
+        }
+        Channel::from_superoperator(&superoperator, nqubits, tol)
+    }
+
+    // Channel applying self first and then other, with Kraus operators B_j A_i.
+    pub fn compose(&self, other: &Channel) -> Result<Channel, String> {
+        if other.nqubits != self.nqubits {
+            return Err(format!("Cannot compose a channel on {} qubits with one on {}.", self.nqubits, other.nqubits));
+        }
+        let d = self.dim();
+        let kraus = other.kraus.iter()
+            .flat_map(|b| self.kraus.iter().map(move |a| Operator {
+                nqubits: self.nqubits,
+                data: Tensor::from_vec(linalg::matmul(&b.data.data, &a.data.data, d), vec![2; 2 * self.nqubits])
+            }))
+            .collect();
+        Channel::new(kraus)
+    }
+
+    // Channel acting independently on the qubits of self then on those of other, with Kraus
+    // operators A_i x B_j.
+    pub fn tensor(&self, other: &Channel) -> Channel {
+        let nqubits = self.nqubits + other.nqubits;
+        let kraus = self.kraus.iter()
+            .flat_map(|a| other.kraus.iter().map(move |b| Operator {
+                nqubits,
+                data: Tensor::from_vec(kron_matrices(&a.data.data, self.dim(), &b.data.data, other.dim()), vec![2; 2 * nqubits])
+            }))
+            .collect();
+        Channel { nqubits, kraus }
+    }
+
+    // Choi matrix sum_k |K_k>><<K_k| of size d^2 x d^2, K being vectorized row by row.
+    pub fn choi(&self) -> Vec<Complex<f64>> {
+        let n = self.dim() * self.dim();
+        let mut choi = vec![Complex::ZERO; n * n];
+        for k in &self.kraus {
+            for (i, a) in k.data.data.iter().enumerate() {
+                for (j, b) in k.data.data.iter().enumerate() {
+                    choi[i * n + j] += a * b.conj();
+                }
+            }
+        }
+        choi
+    }
+
+    // Sum of K_k^dagger K_k when adjoint_first, of K_k K_k^dagger otherwise.
+    fn kraus_sum(&self, adjoint_first: bool) -> Vec<Complex<f64>> {
+        let d = self.dim();
+        let mut sum = vec![Complex::ZERO; d * d];
+        for k in &self.kraus {
+            let k_dag = linalg::adjoint(&k.data.data, d);
+            let product = if adjoint_first {
+                linalg::matmul(&k_dag, &k.data.data, d)
+            } else {
+                linalg::matmul(&k.data.data, &k_dag, d)
+            };
+            sum.iter_mut().zip(product.iter()).for_each(|(s, p)| *s += p);
+        }
+        sum
+    }
+
+    // (sum_k |Tr(U^dagger K_k)|^2 + Tr(sum_k K_k^dagger K_k)) / (d (d + 1)), which reduces to
+    // the usual formula for trace preserving channels.
+    pub fn average_gate_fidelity(&self, target: &Operator) -> Result<f64, String> {
+        if target.nqubits != self.nqubits {
+            return Err(format!("Target acts on {} qubits but the channel acts on {}.", target.nqubits, self.nqubits));
+        }
+        let d = self.dim();
+        let overlaps = self.kraus.iter()
+            .map(|k| (0..d * d)
+                .map(|idx| target.data.data[idx].conj() * k.data.data[idx])
+                .sum::<Complex<f64>>()
+                .norm_sqr())
+            .sum::<f64>();
+        let sum = self.kraus_sum(true);
+        let trace = (0..d).map(|i| sum[i * d + i].re).sum::<f64>();
+        Ok((overlaps + trace) / (d * (d + 1)) as f64)
+    }
+
+    // Check that the Choi matrix has no eigenvalue below -tol and that sum_k K_k^dagger K_k is
+    // within tol of the identity in spectral norm. Kraus sets are completely positive by
+    // construction up to rounding, the first check guarding channels built from other data.
+    pub fn is_cptp(&self, tol: f64) -> Result<(), CptpViolation> {
+        let d = self.dim();
+        let (choi_values, _) = linalg::eigh(&self.choi(), d * d);
+        let min_eigenvalue = choi_values.iter().copied().fold(f64::INFINITY, f64::min);
+        if min_eigenvalue < -tol {
+            return Err(CptpViolation::NotCompletelyPositive { min_eigenvalue });
+        }
+        let identity = linalg::identity(d);
+        let difference = self.kraus_sum(true).iter().zip(identity.iter()).map(|(s, i)| s - i).collect::<Vec<_>>();
+        let (values, _) = linalg::eigh(&difference, d);
+        let defect = values.iter().fold(0., |m: f64, v| m.max(v.abs()));
+        if defect > tol {
+            return Err(CptpViolation::NotTracePreserving { defect });
+        }
+        Ok(())
+    }
+
+    pub fn properties(&self, target: Option<&Operator>, tol: &TolerancePolicy) -> Result<ChannelProperties, String> {
+        let d = self.dim();
+        let identity = linalg::identity(d);
+
+        let (choi_values, _) = linalg::eigh(&self.choi(), d * d);
+        let largest = choi_values.iter().fold(0., |m: f64, v| m.max(v.abs()));
+        let choi_rank = choi_values.iter().filter(|v| v.abs() > tol.unitarity * largest.max(1.)).count();
+
+        let unital = linalg::max_abs_diff(&self.kraus_sum(false), &identity) < tol.unitarity;
+
+        let defect = self.kraus_sum(true).iter().zip(identity.iter())
+            .map(|(s, i)| s - i)
+            .collect::<Vec<_>>();
+        let (defect_values, _) = linalg::eigh(&defect, d);
+        let trace_preservation_defect = defect_values.iter().fold(0., |m: f64, v| m.max(v.abs()));
+
+        let average_gate_fidelity = match target {
+            Some(op) => self.average_gate_fidelity(op)?,
+            None => {
+                let data = Tensor::from_vec(identity, vec![2; 2 * self.nqubits]);
+                self.average_gate_fidelity(&Operator { nqubits: self.nqubits, data })?
+            }
+        };
+
+        Ok(ChannelProperties { choi_rank, unital, trace_preservation_defect, average_gate_fidelity })
+    }
+}
+
+// Swap the column index of the left factor with the row index of the right one, mapping the
+// Choi matrix to the superoperator and back, d being the dimension of the channel.
+pub(crate) fn reshuffle(m: &[Complex<f64>], d: usize) -> Vec<Complex<f64>> {
+    let n = d * d;
+    let mut out = vec![Complex::ZERO; n * n];
+    for (idx, x) in m.iter().enumerate() {
+        let (row, col) = (idx / n, idx % n);
+        let (a, c, b, e) = (row / d, row % d, col / d, col % d);
+        out[(a * d + b) * n + c * d + e] = *x;
+    }
+    out
+}
+
+// Pauli transfer matrix of the map with the given Choi matrix, as in `Channel::ptm`. The map need
+// not be completely positive, e.g. when the Choi matrix comes from sampled tomography data.
+pub fn ptm_from_choi(choi: &[Complex<f64>], nqubits: usize) -> Result<Vec<f64>, String> {
+    let d = 1 << nqubits;
+    let n = d * d;
+    if choi.len() != n * n {
+        return Err(format!("Choi matrix of {} qubits should have {} entries, got {}.", nqubits, n * n, choi.len()));
+    }
+    let superoperator = reshuffle(choi, d);
+    let paulis = pauli_basis(nqubits).iter().map(PauliString::matrix).collect::<Vec<_>>();
+    let mut ptm = vec![0.; n * n];
+    for (j, p_j) in paulis.iter().enumerate() {
+        let image = (0..n).map(|r| (0..n).map(|c| superoperator[r * n + c] * p_j[c]).sum::<Complex<f64>>()).collect::<Vec<_>>();
+        for (i, p_i) in paulis.iter().enumerate() {
+            // Pauli matrices are Hermitian, so Tr(P_i A) = sum_{r, c} conj(P_i[c, r]) A[c, r].
+            ptm[i * n + j] = p_i.iter().zip(image.iter()).map(|(p, a)| p.conj() * a).sum::<Complex<f64>>().re / d as f64;
+        }
+    }
+    Ok(ptm)
+}
+
+// Kronecker product of square matrices of dimensions da and db.
+fn kron_matrices(a: &[Complex<f64>], da: usize, b: &[Complex<f64>], db: usize) -> Vec<Complex<f64>> {
+    let n = da * db;
+    let mut out = vec![Complex::ZERO; n * n];
+    for (a_idx, x) in a.iter().enumerate() {
+        let (i, j) = (a_idx / da, a_idx % da);
+        for (b_idx, y) in b.iter().enumerate() {
+            let (k, l) = (b_idx / db, b_idx % db);
+            out[(i * db + k) * n + j * db + l] = x * y;
+        }
+    }
+    out
+}
+
+// All 4^n Pauli strings on n qubits, the identity first.
+fn pauli_basis(nqubits: usize) -> Vec<PauliString> {
+    (0..1usize << (2 * nqubits)).map(|t| PauliString::new((0..nqubits)
+        .map(|q| match (t >> (2 * (nqubits - 1 - q))) & 3 {
+            0 => Pauli::I,
+            1 => Pauli::X,
+            2 => Pauli::Y,
+            _ => Pauli::Z
+        })
+        .collect()))
+        .collect()
+}
+
+pub fn average_gate_fidelity(channel: &Channel, target_unitary: &Operator, tol: &TolerancePolicy) -> Result<f64, String> {
+    if !target_unitary.is_unitary(tol.unitarity) {
+        return Err("Target operator is not unitary.".to_string());
+    }
+    channel.average_gate_fidelity(target_unitary)
+}
+
+// Unitarity u = sum_{i, j > 0} R_ij^2 / (d^2 - 1), R being the Pauli transfer matrix
+// R_ij = Tr(P_i E(P_j)) / d. It equals 1 exactly for unitary channels and is insensitive
+// to which unitary is implemented, so it isolates incoherent noise.
+pub fn unitarity(channel: &Channel) -> f64 {
+    let d = channel.dim();
+    let n = d * d;
+    let ptm = channel.ptm();
+    let sum = (1..n)
+        .flat_map(|i| (1..n).map(move |j| i * n + j))
+        .map(|idx| ptm[idx] * ptm[idx])
+        .sum::<f64>();
+    sum / (n - 1) as f64
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use num_complex::Complex;
+use rand::RngCore;
+
+use crate::density_matrix::DensityMatrix;
+use crate::noise::NoiseModel;
+use crate::pattern::Pattern;
+use crate::runner::{ExecutionCursor, RunResult};
+use crate::tensor::Tensor;
+
+// Checkpoint files for density matrices. Only the upper triangle is stored since rho is
+// Hermitian, as a sparse list of (index, value) entries that can be gzip compressed.
+//
+// Layout: magic, version, flags, precision (bytes per real number, 4 or 8), endianness (< or >),
+// then nqubits (u32), entry count (u64), and for each entry the flat index (u64) and the real and
+// imaginary parts. Numbers are written in the byte order of the machine, recorded in the header
+// so files move between machines. When the compressed flag is set everything after the header is
+// gzip encoded. Run checkpoints append the number of executed commands, the node of every qubit
+// and the measurement outcomes so far, then with the history flag the entangled edges, the clock
+// of the idle noise and the time each node was last active. Version 1 files, without precision and endianness, are
+// little-endian double precision.
+
+const MAGIC: &[u8; 4] = b"DMCK";
+const VERSION: u8 = 2;
+const FLAG_COMPRESSED: u8 = 1;
+const FLAG_RUN: u8 = 2;
+const FLAG_HISTORY: u8 = 4;
+
+// Largest state a checkpoint may describe: 4^15 entries already take 16 GiB. The header is not
+// trusted, and a sparse file of a few bytes can claim any dimension.
+const MAX_QUBITS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Single,
+    Double
+}
+
+impl Precision {
+    fn bytes(&self) -> u8 {
+        match self {
+            Precision::Single => 4,
+            Precision::Double => 8
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointOptions {
+    pub threshold: f64,     // Entries with a modulus at or below this are dropped.
+    pub compress: bool,
+    pub precision: Precision
+}
+
+impl Default for CheckpointOptions {
+    fn default() -> Self {
+        CheckpointOptions { threshold: 0., compress: true, precision: Precision::Double }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckpointReport {
+    pub stored_entries: usize,
+    pub dropped_entries: usize,     // Counting both triangles.
+    pub dropped_norm: f64,          // Frobenius norm of the dropped part, bounds the Hilbert-Schmidt error.
+    pub trace_error: f64,           // |Tr(rho) - Tr(stored)|.
+    pub bytes: usize,               // Size of the file.
+}
+
+// Progress of a pattern run, enough to resume it with ExecutionCursor::restore.
+#[derive(Clone)]
+pub struct RunCheckpoint {
+    pub state: DensityMatrix,
+    pub nodes: Vec<usize>,
+    pub outcomes: HashMap<usize, u8>,
+    pub executed: usize,
+    pub edges: Vec<(usize, usize)>,
+    pub clock: f64,                         // Clock of the idle noise, 0 without idle noise.
+    pub last_active: HashMap<usize, f64>
+}
+
+// Part of an ExecutionCursor written along with the state.
+struct RunProgress<'a> {
+    nodes: &'a [usize],
+    outcomes: &'a HashMap<usize, u8>,
+    executed: usize,
+    edges: &'a [(usize, usize)],
+    clock: f64,
+    last_active: Option<&'a HashMap<usize, f64>>
+}
+
+// Reads numbers in the byte order given by the header.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    big_endian: bool
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self.bytes.get(self.position..self.position + N).ok_or("Truncated checkpoint.")?;
+        self.position += N;
+        let mut array: [u8; N] = bytes.try_into().unwrap();
+        if self.big_endian {
+            array.reverse();
+        }
+        Ok(array)
+    }
+
+    fn u64(&mut self) -> Result<usize, String> {
+        Ok(u64::from_le_bytes(self.take()?) as usize)
+    }
+
+    fn real(&mut self, precision: u8) -> Result<f64, String> {
+        Ok(if precision == 4 { f32::from_le_bytes(self.take()?) as f64 } else { f64::from_le_bytes(self.take()?) })
+    }
+
+    fn is_done(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
+
+fn encode(rho: &DensityMatrix, options: CheckpointOptions, run: Option<RunProgress>) -> Result<(Vec<u8>, CheckpointReport), String> {
+    if options.threshold < 0. {
+        return Err(format!("Threshold should be non negative, got {}.", options.threshold));
+    }
+    let size = rho.size;
+    let mut payload = Vec::new();
+    let mut entries = Vec::new();
+    let (mut dropped_entries, mut dropped_sqr, mut trace_error) = (0, 0., 0.);
+    for i in 0..size {
+        for j in i..size {
+            let value = rho.data.data[i * size + j];
+            if value.norm() > options.threshold {
+                entries.push((i * size + j, value));
+                continue;
+            }
+            let copies = if i == j { 1 } else { 2 };
+            dropped_entries += copies;
+            dropped_sqr += copies as f64 * value.norm_sqr();
+            if i == j {
+                trace_error += value.re;
+            }
+        }
+    }
+    payload.extend_from_slice(&(rho.nqubits as u32).to_ne_bytes());
+    payload.extend_from_slice(&(entries.len() as u64).to_ne_bytes());
+    for (index, value) in &entries {
+        payload.extend_from_slice(&(*index as u64).to_ne_bytes());
+        match options.precision {
+            Precision::Single => [value.re, value.im].iter().for_each(|x| payload.extend_from_slice(&(*x as f32).to_ne_bytes())),
+            Precision::Double => [value.re, value.im].iter().for_each(|x| payload.extend_from_slice(&x.to_ne_bytes()))
+        }
+    }
+    let mut flags = 0;
+    if let Some(run) = run {
+        flags |= FLAG_RUN | FLAG_HISTORY;
+        payload.extend_from_slice(&(run.executed as u64).to_ne_bytes());
+        payload.extend_from_slice(&(run.nodes.len() as u64).to_ne_bytes());
+        run.nodes.iter().for_each(|node| payload.extend_from_slice(&(*node as u64).to_ne_bytes()));
+        let mut outcomes = run.outcomes.iter().collect::<Vec<_>>();
+        outcomes.sort_unstable();
+        payload.extend_from_slice(&(outcomes.len() as u64).to_ne_bytes());
+        for (node, outcome) in outcomes {
+            payload.extend_from_slice(&(*node as u64).to_ne_bytes());
+            payload.push(*outcome);
+        }
+        payload.extend_from_slice(&(run.edges.len() as u64).to_ne_bytes());
+        for (a, b) in run.edges {
+            payload.extend_from_slice(&(*a as u64).to_ne_bytes());
+            payload.extend_from_slice(&(*b as u64).to_ne_bytes());
+        }
+        payload.extend_from_slice(&run.clock.to_ne_bytes());
+        let mut last_active = run.last_active.into_iter().flatten().collect::<Vec<_>>();
+        last_active.sort_unstable_by_key(|(node, _)| **node);
+        payload.extend_from_slice(&(last_active.len() as u64).to_ne_bytes());
+        for (node, time) in last_active {
+            payload.extend_from_slice(&(*node as u64).to_ne_bytes());
+            payload.extend_from_slice(&time.to_ne_bytes());
+        }
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    if options.compress {
+        flags |= FLAG_COMPRESSED;
+    }
+    let endianness = if cfg!(target_endian = "big") { b'>' } else { b'<' };
+    bytes.extend_from_slice(&[flags, options.precision.bytes(), endianness]);
+    if options.compress {
+        let mut encoder = GzEncoder::new(bytes, Compression::default());
+        encoder.write_all(&payload).map_err(|e| e.to_string())?;
+        bytes = encoder.finish().map_err(|e| e.to_string())?;
+    } else {
+        bytes.extend_from_slice(&payload);
+    }
+    let report = CheckpointReport {
+        stored_entries: entries.len(),
+        dropped_entries,
+        dropped_norm: dropped_sqr.sqrt(),
+        trace_error: trace_error.abs(),
+        bytes: bytes.len(),
+    };
+    Ok((bytes, report))
+}
+
+fn decode(bytes: &[u8]) -> Result<RunCheckpoint, String> {
+    if bytes.len() < 6 || &bytes[..4] != MAGIC {
+        return Err("Not a density matrix checkpoint.".to_string());
+    }
+    let flags = bytes[5];
+    let (precision, big_endian, header) = match bytes[4] {
+        1 => (8, false, 6),
+        2 if bytes.len() >= 8 => {
+            let precision = bytes[6];
+            if precision != 4 && precision != 8 {
+                return Err(format!("Unsupported checkpoint precision of {} bytes.", precision));
+            }
+            let big_endian = match bytes[7] {
+                b'<' => false,
+                b'>' => true,
+                other => return Err(format!("Unknown checkpoint byte order {}.", other))
+            };
+            (precision, big_endian, 8)
+        },
+        2 => return Err("Truncated checkpoint.".to_string()),
+        version => return Err(format!("Unsupported checkpoint version {}.", version))
+    };
+    let payload = if flags & FLAG_COMPRESSED != 0 {
+        let mut payload = Vec::new();
+        GzDecoder::new(&bytes[header..]).read_to_end(&mut payload).map_err(|e| e.to_string())?;
+        payload
+    } else {
+        bytes[header..].to_vec()
+    };
+    let mut reader = Reader { bytes: &payload, position: 0, big_endian };
+    let nqubits = u32::from_le_bytes(reader.take()?) as usize;
+    let count = reader.u64()?;
+    if count.checked_mul(8 + 2 * precision as usize).is_none_or(|len| len > payload.len()) {
+        return Err("Checkpoint entry count does not match its length.".to_string());
+    }
+    let entries = (nqubits <= MAX_QUBITS)
+        .then(|| 1usize.checked_shl(nqubits as u32))
+        .flatten()
+        .and_then(|size| size.checked_mul(size).map(|len| (size, len)));
+    let Some((size, len)) = entries else {
+        return Err(format!("Checkpoint of {} qubits exceeds the maximum of {}.", nqubits, MAX_QUBITS));
+    };
+    let mut data = vec![Complex::ZERO; len];
+    for _ in 0..count {
+        let index = reader.u64()?;
+        let value = Complex::new(reader.real(precision)?, reader.real(precision)?);
+        let (i, j) = (index / size, index % size);
+        if i >= size || j < i {
+            return Err(format!("Invalid checkpoint entry index {}.", index));
+        }
+        data[i * size + j] = value;
+        data[j * size + i] = value.conj();
+    }
+    let state = DensityMatrix {
+        data: Tensor::from_vec(data, vec![2; 2 * nqubits]),
+        size,
+        nqubits
+    };
+    let mut checkpoint = RunCheckpoint {
+        state,
+        nodes: Vec::new(),
+        outcomes: HashMap::new(),
+        executed: 0,
+        edges: Vec::new(),
+        clock: 0.,
+        last_active: HashMap::new()
+    };
+    if flags & FLAG_RUN != 0 {
+        checkpoint.executed = reader.u64()?;
+        let nodes = reader.u64()?;
+        checkpoint.nodes = (0..nodes).map(|_| reader.u64()).collect::<Result<_, _>>()?;
+        let outcomes = reader.u64()?;
+        for _ in 0..outcomes {
+            let node = reader.u64()?;
+            checkpoint.outcomes.insert(node, u8::from_le_bytes(reader.take()?));
+        }
+        if flags & FLAG_HISTORY != 0 {
+            let edges = reader.u64()?;
+            for _ in 0..edges {
+                checkpoint.edges.push((reader.u64()?, reader.u64()?));
+            }
+            checkpoint.clock = reader.real(8)?;
+            let active = reader.u64()?;
+            for _ in 0..active {
+                let node = reader.u64()?;
+                checkpoint.last_active.insert(node, reader.real(8)?);
+            }
+        }
+    }
+    if !reader.is_done() {
+        return Err("Checkpoint entry count does not match its length.".to_string());
+    }
+    Ok(checkpoint)
+}
+
+pub fn encode_checkpoint(rho: &DensityMatrix, options: CheckpointOptions) -> Result<(Vec<u8>, CheckpointReport), String> {
+    encode(rho, options, None)
+}
+
+pub fn decode_checkpoint(bytes: &[u8]) -> Result<DensityMatrix, String> {
+    Ok(decode(bytes)?.state)
+}
+
+// Write to a temporary file first, so a crash while writing leaves the previous checkpoint intact.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, bytes).map_err(|e| format!("Cannot write {}: {}.", temporary.display(), e))?;
+    fs::rename(&temporary, path).map_err(|e| format!("Cannot write {}: {}.", path.display(), e))
+}
+
+pub fn save_checkpoint<P: AsRef<Path>>(rho: &DensityMatrix, path: P, options: CheckpointOptions) -> Result<CheckpointReport, String> {
+    let (bytes, report) = encode_checkpoint(rho, options)?;
+    write_atomic(path.as_ref(), &bytes)?;
+    Ok(report)
+}
+
+pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> Result<DensityMatrix, String> {
+    let bytes = fs::read(path.as_ref()).map_err(|e| format!("Cannot read {}: {}.", path.as_ref().display(), e))?;
+    decode_checkpoint(&bytes)
+}
+
+pub fn save_run_checkpoint<P: AsRef<Path>>(cursor: &ExecutionCursor<DensityMatrix>, path: P) -> Result<(), String> {
+    let scheduler = cursor.idle_scheduler();
+    let run = RunProgress {
+        nodes: &cursor.nodes,
+        outcomes: &cursor.outcomes,
+        executed: cursor.executed,
+        edges: cursor.edges(),
+        clock: scheduler.map_or(0., |scheduler| scheduler.clock),
+        last_active: scheduler.map(|scheduler| &scheduler.last_active)
+    };
+    let (bytes, _) = encode(&cursor.backend, CheckpointOptions::default(), Some(run))?;
+    write_atomic(path.as_ref(), &bytes)
+}
+
+pub fn load_run_checkpoint<P: AsRef<Path>>(path: P) -> Result<RunCheckpoint, String> {
+    let bytes = fs::read(path.as_ref()).map_err(|e| format!("Cannot read {}: {}.", path.as_ref().display(), e))?;
+    if bytes.len() < 6 || bytes[5] & FLAG_RUN == 0 {
+        return Err(format!("{} is not a run checkpoint.", path.as_ref().display()));
+    }
+    decode(&bytes)
+}
+
+impl DensityMatrix {
+    // Lossless compressed checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        save_checkpoint(self, path, CheckpointOptions::default()).map(|_| ())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<DensityMatrix, String> {
+        load_checkpoint(path)
+    }
+}
+
+impl Pattern {
+    // Same as simulate_with_noise, saving the run to path every `every` commands. If path holds
+    // a run checkpoint the run resumes from it and input is ignored, with outcomes drawn from rng
+    // from there on. The file is removed once the run completes.
+    pub fn simulate_with_checkpoints<P: AsRef<Path>>(&self, input: DensityMatrix, noise: &NoiseModel, path: P, every: usize, rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+        if every == 0 {
+            return Err("Checkpoints need a period of at least one command.".to_string());
+        }
+        let path = path.as_ref();
+        let mut cursor = if path.exists() {
+            let checkpoint = load_run_checkpoint(path)?;
+            if checkpoint.executed > self.seq().len() {
+                return Err(format!("Checkpoint has {} executed commands but the pattern has {}.", checkpoint.executed, self.seq().len()));
+            }
+            let mut cursor = ExecutionCursor::restore(checkpoint.state, checkpoint.nodes, checkpoint.outcomes, checkpoint.executed)?;
+            cursor.set_noise(noise.clone());
+            cursor.restore_history(checkpoint.edges, checkpoint.clock, checkpoint.last_active);
+            cursor
+        } else {
+            let mut cursor = ExecutionCursor::new(self, input)?;
+            cursor.set_noise(noise.clone());
+            cursor
+        };
+        let start = cursor.executed;
+        for chunk in self.seq()[start..].chunks(every) {
+            cursor.run(chunk, rng)?;
+            save_run_checkpoint(&cursor, path)?;
+        }
+        let result = cursor.finish(self.output_nodes())?;
+        fs::remove_file(path).map_err(|e| format!("Cannot remove {}: {}.", path.display(), e))?;
+        Ok(result)
+    }
+}
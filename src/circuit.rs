@@ -1,11 +1,13 @@
 use std::f64::consts::PI;
+use num_complex::Complex;
 use rand::Rng;
 
+use crate::backend::QuantumBackend;
+use crate::operators::{OneQubitOp, Operator, ThreeQubitsOp, TwoQubitsOp};
 use crate::pattern::Pattern;
 use crate::pattern::Command;
 use crate::pattern::Plane;
 
-#[allow(dead_code)]
 pub fn random_circuit(mut depth: usize, n_qubits: usize) -> Circuit {
     /*
         Random circuit generator only for 1 qubit gates.
@@ -52,9 +54,8 @@ pub fn random_circuit(mut depth: usize, n_qubits: usize) -> Circuit {
     circuit
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
-enum Instruction {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
     CCX(usize, usize, usize),
     RZZ(usize, usize, f64),
     CNOT(usize, usize),
@@ -69,6 +70,37 @@ enum Instruction {
     RY(usize, f64),
     RZ(usize, f64)
 }
+
+impl Instruction {
+    // Qubits the gate acts on, in the order expected by operator().
+    pub fn qubits(&self) -> Vec<usize> {
+        match *self {
+            Instruction::CCX(c1, c2, t) => vec![c1, c2, t],
+            Instruction::RZZ(q1, q2, _) | Instruction::CNOT(q1, q2) | Instruction::SWAP(q1, q2) => vec![q1, q2],
+            Instruction::H(t) | Instruction::S(t) | Instruction::X(t) | Instruction::Y(t) | Instruction::Z(t)
+                | Instruction::I(t) | Instruction::RX(t, _) | Instruction::RY(t, _) | Instruction::RZ(t, _) => vec![t]
+        }
+    }
+
+    pub fn operator(&self) -> Operator {
+        match *self {
+            Instruction::CCX(..) => Operator::three_qubits(ThreeQubitsOp::CCX),
+            Instruction::RZZ(_, _, angle) => rzz(angle),
+            Instruction::CNOT(..) => Operator::two_qubits(TwoQubitsOp::CX),
+            Instruction::SWAP(..) => Operator::two_qubits(TwoQubitsOp::SWAP),
+            Instruction::H(_) => Operator::one_qubit(OneQubitOp::H),
+            Instruction::S(_) => Operator::one_qubit(OneQubitOp::S),
+            Instruction::X(_) => Operator::one_qubit(OneQubitOp::X),
+            Instruction::Y(_) => Operator::one_qubit(OneQubitOp::Y),
+            Instruction::Z(_) => Operator::one_qubit(OneQubitOp::Z),
+            Instruction::I(_) => Operator::one_qubit(OneQubitOp::I),
+            Instruction::RX(_, angle) => Operator::rx(angle),
+            Instruction::RY(_, angle) => Operator::ry(angle),
+            Instruction::RZ(_, angle) => Operator::rz(angle)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Circuit {
     width: usize,
@@ -144,7 +176,9 @@ impl Circuit {
     pub fn ccx(&mut self, control1: usize, control2: usize, target: usize) {
         assert!(control1 < self.width);
         assert!(control2 < self.width);
+        assert!(target < self.width);
         assert!(control1 != control2);
+        assert!(control1 != target && control2 != target);
         self.instructions.push(Instruction::CCX(control1, control2, target))
     }
 
@@ -159,80 +193,126 @@ impl Circuit {
         }
     }
 
-    pub fn transpile(&self) -> Pattern {
-        let mut n_nodes = self.width;
-        let _input: Vec<usize> = (0..n_nodes).collect::<Vec<usize>>();
-        let mut _output: Vec<usize> = Vec::new();
-        let mut _pattern = Pattern::new(_input);
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    // Apply the gates directly on a state of width qubits.
+    pub fn run<B: QuantumBackend>(&self, state: &mut B) -> Result<(), String> {
+        if state.nqubits() != self.width {
+            return Err(format!("Circuit has {} qubits but the state has {}.", self.width, state.nqubits()));
+        }
         for instr in &self.instructions {
+            state.evolve(&instr.operator(), &instr.qubits())?;
+        }
+        Ok(())
+    }
+
+    // Toffoli gates are rewritten with H, T = rz(pi / 4) and CNOT, which is exact up to a global phase.
+    fn decompose(&self) -> Vec<Instruction> {
+        let t = PI / 4.;
+        self.instructions.iter().flat_map(|instr| match *instr {
+            Instruction::CCX(c1, c2, target) => vec![
+                Instruction::H(target),
+                Instruction::CNOT(c2, target), Instruction::RZ(target, -t),
+                Instruction::CNOT(c1, target), Instruction::RZ(target, t),
+                Instruction::CNOT(c2, target), Instruction::RZ(target, -t),
+                Instruction::CNOT(c1, target), Instruction::RZ(c2, t), Instruction::RZ(target, t),
+                Instruction::H(target),
+                Instruction::CNOT(c1, c2), Instruction::RZ(c1, t), Instruction::RZ(c2, -t),
+                Instruction::CNOT(c1, c2),
+            ],
+            other => vec![other]
+        }).collect()
+    }
+
+    // Measurement pattern implementing the circuit with the J / CZ decomposition, up to a global
+    // phase. Input node i and output_nodes()[i] both stand for qubit i.
+    pub fn to_pattern(&self) -> Pattern {
+        let mut n_nodes = self.width;
+        let input: Vec<usize> = (0..n_nodes).collect::<Vec<usize>>();
+        let mut output = input.clone();
+        let mut pattern = Pattern::new(input);
+        for instr in self.decompose() {
             match instr {
                 Instruction::H(target) => {
                     let ancilla = n_nodes;
-                    let (h_ancilla, seq) = self._h_command(_output[*target], ancilla);
-                    _output[*target] = h_ancilla;
-                    _pattern.extend(seq);
+                    let (h_ancilla, seq) = self._h_command(output[target], ancilla);
+                    output[target] = h_ancilla;
+                    pattern.extend(seq);
                     n_nodes += 1
                 }
                 Instruction::X(target) => {
                     let ancilla = [n_nodes, n_nodes + 1];
-                    let (x_ancilla, seq) = self._x_command(_output[*target], ancilla);
-                    _output[*target] = x_ancilla;
-                    _pattern.extend(seq);
+                    let (x_ancilla, seq) = self._x_command(output[target], ancilla);
+                    output[target] = x_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
                 Instruction::Y(target) => {
                     let ancilla = [n_nodes, n_nodes + 1, n_nodes + 2, n_nodes + 3];
-                    let (x_ancilla, seq) = self._y_command(_output[*target], ancilla);
-                    _output[*target] = x_ancilla;
-                    _pattern.extend(seq);
+                    let (y_ancilla, seq) = self._y_command(output[target], ancilla);
+                    output[target] = y_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
                 Instruction::Z(target) => {
                     let ancilla = [n_nodes, n_nodes + 1];
-                    let (z_ancilla, seq) = self._z_command(_output[*target], ancilla);
-                    _output[*target] = z_ancilla;
-                    _pattern.extend(seq);
+                    let (z_ancilla, seq) = self._z_command(output[target], ancilla);
+                    output[target] = z_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
                 Instruction::I(_) => { continue; },
                 Instruction::RX(target, angle) => {
                     let ancilla = [n_nodes, n_nodes + 1];
-                    let (rx_ancilla, seq) = self._rx_command(_output[*target], ancilla, *angle);
-                    _output[*target] = rx_ancilla;
-                    _pattern.extend(seq);
+                    let (rx_ancilla, seq) = self._rx_command(output[target], ancilla, angle);
+                    output[target] = rx_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
                 Instruction::RY(target, angle) => {
                     let ancilla = [n_nodes, n_nodes + 1, n_nodes + 2, n_nodes + 3];
-                    let (ry_ancilla, seq) = self._ry_command(_output[*target], ancilla, *angle);
-                    _output[*target] = ry_ancilla;
-                    _pattern.extend(seq);
+                    let (ry_ancilla, seq) = self._ry_command(output[target], ancilla, angle);
+                    output[target] = ry_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
-                Instruction::RZ(target, angle) => {
+                // S is rz(pi / 2) up to a global phase.
+                Instruction::S(target) | Instruction::RZ(target, _) => {
+                    let angle = if let Instruction::RZ(_, angle) = instr { angle } else { PI / 2. };
                     let ancilla = [n_nodes, n_nodes + 1];
-                    let (rz_ancilla, seq) = self._rz_command(_output[*target], ancilla, *angle);
-                    _output[*target] = rz_ancilla;
-                    _pattern.extend(seq);
+                    let (rz_ancilla, seq) = self._rz_command(output[target], ancilla, angle);
+                    output[target] = rz_ancilla;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
                 Instruction::CNOT(control, target) => {
                     let ancilla = [n_nodes, n_nodes + 1];
-                    let (control_node, target_node, seq) = self._cnot_command(_output[*control], _output[*target], ancilla);
-                    _output[*control] = control_node;
-                    _output[*target] = target_node;
-                    _pattern.extend(seq);
+                    let (control_node, target_node, seq) = self._cnot_command(output[control], output[target], ancilla);
+                    output[control] = control_node;
+                    output[target] = target_node;
+                    pattern.extend(seq);
                     n_nodes += ancilla.len();
                 },
+                Instruction::RZZ(control, target, angle) => {
+                    let ancilla = n_nodes;
+                    let seq = self._rzz_command(output[control], output[target], ancilla, angle);
+                    pattern.extend(seq);
+                    n_nodes += 1;
+                },
                 Instruction::SWAP(target1, target2) => {
-                    let tmp = _output[*target1];
-                    _output[*target1] = _output[*target2];
-                    _output[*target2] = tmp;
+                    output.swap(target1, target2);
                 },
-                _ => { continue; }
+                Instruction::CCX(..) => unreachable!("Toffoli gates are decomposed beforehand.")
             }
         }
-        _pattern
+        pattern.reorder_output_nodes(output).unwrap();
+        pattern
     }
 
     fn _h_command(&self, input_node: usize, ancilla: usize) -> (usize, Vec<Command>) {
@@ -262,9 +342,9 @@ impl Circuit {
         seq.push(Command::E((ancilla[0], ancilla[1])));
         seq.push(Command::E((ancilla[1], ancilla[2])));
         seq.push(Command::E((ancilla[2], ancilla[3])));
-        seq.push(Command::M(input_node, Plane::XY, 0.5, vec![input_node], vec![], 0));
+        seq.push(Command::M(input_node, Plane::XY, 0.5, vec![], vec![], 0));
         seq.push(Command::M(ancilla[0], Plane::XY, 1.0, vec![input_node], vec![], 0));
-        seq.push(Command::M(ancilla[1], Plane::XY, -0.5, vec![], vec![], 0));
+        seq.push(Command::M(ancilla[1], Plane::XY, -0.5, vec![input_node], vec![], 0));
         seq.push(Command::M(ancilla[2], Plane::XY, 0.0, vec![], vec![], 0));
         seq.push(Command::X(ancilla[3], vec![ancilla[0], ancilla[2]]));
         seq.push(Command::Z(ancilla[3], vec![ancilla[0], ancilla[1]]));
@@ -304,7 +384,7 @@ impl Circuit {
         seq.push(Command::M(ancilla[1], Plane::XY, -0.5, vec![input_node], vec![], 0));
         seq.push(Command::M(ancilla[2], Plane::XY, 0.0, vec![], vec![], 0));
         seq.push(Command::X(ancilla[3], vec![ancilla[0], ancilla[2]]));
-        seq.push(Command::Z(ancilla[1], vec![ancilla[0], ancilla[1]]));
+        seq.push(Command::Z(ancilla[3], vec![ancilla[0], ancilla[1]]));
         (ancilla[3], seq)
     }
 
@@ -331,4 +411,28 @@ impl Circuit {
         seq.push(Command::Z(control_node, vec![target_node]));
         (control_node, ancilla[1], seq)
     }
+
+    // The ancilla holds the parity of the two qubits in the X basis, so measuring it in the YZ
+    // plane applies exp(-i angle Z Z / 2), with a Z Z byproduct for the outcome 1.
+    fn _rzz_command(&self, control_node: usize, target_node: usize, ancilla: usize, angle: f64) -> Vec<Command> {
+        vec![
+            Command::N(ancilla),
+            Command::E((control_node, ancilla)),
+            Command::E((target_node, ancilla)),
+            Command::M(ancilla, Plane::YZ, angle / PI, vec![], vec![], 0),
+            Command::Z(control_node, vec![ancilla]),
+            Command::Z(target_node, vec![ancilla]),
+        ]
+    }
+}
+
+// exp(-i angle Z Z / 2)
+fn rzz(angle: f64) -> Operator {
+    let (minus, plus) = (Complex::from_polar(1., -angle / 2.), Complex::from_polar(1., angle / 2.));
+    let mut data = vec![Complex::ZERO; 16];
+    data[0] = minus;
+    data[5] = plus;
+    data[10] = plus;
+    data[15] = minus;
+    Operator::from_matrix(&data, 2).unwrap()
 }
@@ -0,0 +1,126 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::sync::OnceLock;
+
+use num_complex::Complex;
+
+use crate::config::TolerancePolicy;
+use crate::linalg;
+use crate::operators::{OneQubitOp, Operator};
+use crate::pauli::Pauli;
+
+// The 24 single qubit Clifford gates modulo a global phase, as used by C commands. The first
+// seven are I, X, Y, Z, S, S^dagger and H, the other ones follow in the order in which they are
+// reached by multiplying by H and S.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Clifford(usize);
+
+fn gate(op: OneQubitOp) -> Vec<Complex<f64>> {
+    Operator::one_qubit(op).data.data
+}
+
+// Distinct Cliffords have |Tr(A^dagger B)| at most sqrt(2), so products of the exact matrices of
+// the table are matched with a threshold far above any rounding noise.
+const TABLE_MATCH: f64 = 0.5;
+
+// |Tr(A^dagger B)| = 2 exactly when the unitaries A and B are equal up to a phase.
+fn same_up_to_phase(a: &[Complex<f64>], b: &[Complex<f64>], tol: f64) -> bool {
+    let overlap = a.iter().zip(b).map(|(x, y)| x.conj() * y).sum::<Complex<f64>>();
+    (overlap.norm() - 2.).abs() < tol
+}
+
+// Index of a product of table entries.
+fn index_of(m: &[Complex<f64>]) -> Clifford {
+    Clifford(table().iter().position(|c| same_up_to_phase(c, m, TABLE_MATCH)).expect("product of Cliffords"))
+}
+
+fn table() -> &'static Vec<Vec<Complex<f64>>> {
+    static TABLE: OnceLock<Vec<Vec<Complex<f64>>>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table: Vec<Vec<Complex<f64>>> = Vec::new();
+        let push = |table: &mut Vec<Vec<Complex<f64>>>, m: Vec<Complex<f64>>| {
+            if !table.iter().any(|c| same_up_to_phase(c, &m, TABLE_MATCH)) {
+                table.push(m);
+            }
+        };
+        for op in [OneQubitOp::I, OneQubitOp::X, OneQubitOp::Y, OneQubitOp::Z, OneQubitOp::S, OneQubitOp::SDG, OneQubitOp::H] {
+            push(&mut table, gate(op));
+        }
+        let mut i = 0;
+        while i < table.len() {
+            for generator in [gate(OneQubitOp::H), gate(OneQubitOp::S)] {
+                let product = linalg::matmul(&generator, &table[i], 2);
+                push(&mut table, product);
+            }
+            i += 1;
+        }
+        table
+    })
+}
+
+impl Clifford {
+    pub const COUNT: usize = 24;
+
+    pub fn new(index: usize) -> Result<Self, String> {
+        if index >= Clifford::COUNT {
+            return Err(format!("Clifford index {} is not in the range [0-{}].", index, Clifford::COUNT - 1));
+        }
+        Ok(Clifford(index))
+    }
+
+    pub fn identity() -> Self {
+        Clifford(0)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    pub fn matrix(&self) -> &'static [Complex<f64>] {
+        &table()[self.0]
+    }
+
+    pub fn operator(&self) -> Operator {
+        Operator::from_matrix(self.matrix(), 1).unwrap()
+    }
+
+    // Clifford equal to the 2 x 2 unitary up to a phase and the unitarity tolerance, if any.
+    pub fn from_matrix(m: &[Complex<f64>], tol: &TolerancePolicy) -> Option<Self> {
+        table().iter().position(|c| same_up_to_phase(c, m, tol.unitarity)).map(Clifford)
+    }
+
+    // exp(i sign pi / 4 P), the square root of +/- i P.
+    pub fn sqrt_pauli(pauli: Pauli, sign: f64) -> Self {
+        let p = (0..4).map(|k| pauli.element(k / 2, k % 2)).collect::<Vec<_>>();
+        let m = linalg::identity(2).iter().zip(p)
+            .map(|(i, p)| (i + Complex::new(0., sign) * p) * FRAC_1_SQRT_2)
+            .collect::<Vec<_>>();
+        index_of(&m)
+    }
+
+    pub fn pauli(pauli: Pauli) -> Self {
+        index_of(&(0..4).map(|k| pauli.element(k / 2, k % 2)).collect::<Vec<_>>())
+    }
+
+    // Apply self then other, i.e. the product other * self.
+    pub fn then(&self, other: &Clifford) -> Clifford {
+        index_of(&linalg::matmul(other.matrix(), self.matrix(), 2))
+    }
+
+    pub fn adjoint(&self) -> Clifford {
+        index_of(&linalg::adjoint(self.matrix(), 2))
+    }
+
+    // C^dagger P C = sign Q for a Pauli P different from the identity.
+    pub fn conjugate(&self, pauli: Pauli) -> (f64, Pauli) {
+        let p = (0..4).map(|k| pauli.element(k / 2, k % 2)).collect::<Vec<_>>();
+        let m = linalg::matmul(&linalg::matmul(&linalg::adjoint(self.matrix(), 2), &p, 2), self.matrix(), 2);
+        for q in [Pauli::X, Pauli::Y, Pauli::Z] {
+            // Tr(Q M) / 2 is +/- 1 for the matching Pauli and 0 otherwise.
+            let overlap = (0..4).map(|k| q.element(k % 2, k / 2) * m[k]).sum::<Complex<f64>>().re / 2.;
+            if overlap.abs() > 0.5 {
+                return (overlap.signum(), q);
+            }
+        }
+        unreachable!("Cliffords map Pauli operators to Pauli operators.")
+    }
+}
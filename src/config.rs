@@ -0,0 +1,132 @@
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+
+use num_complex::Complex;
+use num_traits::Zero;
+
+use crate::error::SimulatorError;
+use crate::rng::RngConfig;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Numerical thresholds shared by validation, comparison and normalization routines. Lower
+// precision backends need looser values, so they are grouped here instead of being hard-coded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TolerancePolicy {
+    pub trace: f64,         // Allowed deviation of the trace from 1.
+    pub equality: f64,      // Elementwise tolerance when comparing states or operators.
+    pub unitarity: f64,     // Allowed deviation of U U^dagger from the identity.
+    pub eigenvalue: f64,    // Eigenvalues below are treated as zero.
+    pub probability: f64    // Outcomes and states with a smaller weight are treated as impossible.
+}
+
+impl TolerancePolicy {
+    pub const DOUBLE: TolerancePolicy = TolerancePolicy {
+        trace: 1e-10,
+        equality: 1e-10,
+        unitarity: 1e-10,
+        eigenvalue: 1e-12,
+        probability: 1e-12
+    };
+
+    pub const SINGLE: TolerancePolicy = TolerancePolicy {
+        trace: 1e-5,
+        equality: 1e-5,
+        unitarity: 1e-5,
+        eigenvalue: 1e-6,
+        probability: 1e-6
+    };
+}
+
+impl Default for TolerancePolicy {
+    fn default() -> Self {
+        TolerancePolicy::DOUBLE
+    }
+}
+
+// Provider of the large state buffers, the 4^n entries of a density matrix in particular. The
+// buffer is returned zeroed and owned as a Vec, so its memory must come from the global
+// allocator: implementations control where the pages land, e.g. by first touching them from the
+// threads that will process them or by advising huge pages before touching them.
+pub trait BufferAllocator: fmt::Debug + Send + Sync {
+    fn allocate(&self, len: usize) -> Vec<Complex<f64>>;
+
+    // Same as allocate, for the single precision buffers of DensityMatrixF32.
+    fn allocate_f32(&self, len: usize) -> Vec<Complex<f32>>;
+}
+
+// Buffer from the allocator, checked to hold exactly len amplitudes since the states index it
+// without bounds on the allocator. The allocator is user code, so a wrong length is an error.
+pub(crate) fn allocate(allocator: &dyn BufferAllocator, len: usize) -> Result<Vec<Complex<f64>>, SimulatorError> {
+    checked_len(allocator.allocate(len), len)
+}
+
+pub(crate) fn allocate_f32(allocator: &dyn BufferAllocator, len: usize) -> Result<Vec<Complex<f32>>, SimulatorError> {
+    checked_len(allocator.allocate_f32(len), len)
+}
+
+fn checked_len<T>(buffer: Vec<T>, len: usize) -> Result<Vec<T>, SimulatorError> {
+    if buffer.len() != len {
+        return Err(SimulatorError::DimensionMismatch { expected: len, actual: buffer.len() });
+    }
+    Ok(buffer)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemAllocator;
+
+impl BufferAllocator for SystemAllocator {
+    fn allocate(&self, len: usize) -> Vec<Complex<f64>> {
+        vec![Complex::ZERO; len]
+    }
+
+    fn allocate_f32(&self, len: usize) -> Vec<Complex<f32>> {
+        vec![Complex::ZERO; len]
+    }
+}
+
+// Zeroes the buffer chunk by chunk with the parallel feature, so that under a first-touch NUMA
+// policy each page is placed on the node of the thread that later processes the same chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct FirstTouchAllocator {
+    pub chunk: usize    // Amplitudes zeroed per task, ideally the chunk length of the kernels.
+}
+
+impl FirstTouchAllocator {
+    fn zeroed<T: Zero + Copy + Send + Sync>(&self, len: usize) -> Vec<T> {
+        let mut buffer = Vec::with_capacity(len);
+        let zero = |chunk: &mut [MaybeUninit<T>]| chunk.iter_mut().for_each(|x| { x.write(T::zero()); });
+        #[cfg(feature = "parallel")]
+        buffer.spare_capacity_mut()[..len].par_chunks_mut(self.chunk.max(1)).for_each(zero);
+        #[cfg(not(feature = "parallel"))]
+        buffer.spare_capacity_mut()[..len].chunks_mut(self.chunk.max(1)).for_each(zero);
+        // Safety: the first len elements were all written above.
+        unsafe { buffer.set_len(len) };
+        buffer
+    }
+}
+
+impl BufferAllocator for FirstTouchAllocator {
+    fn allocate(&self, len: usize) -> Vec<Complex<f64>> {
+        self.zeroed(len)
+    }
+
+    fn allocate_f32(&self, len: usize) -> Vec<Complex<f32>> {
+        self.zeroed(len)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub tolerance: TolerancePolicy,
+    pub rng: RngConfig,
+    pub allocator: Arc<dyn BufferAllocator>    // Used for the buffers of new and resized states.
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig { tolerance: TolerancePolicy::default(), rng: RngConfig::default(), allocator: Arc::new(SystemAllocator) }
+    }
+}
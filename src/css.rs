@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+
+use rand::RngCore;
+
+use crate::density_matrix::{Basis, DensityMatrix};
+use crate::graph::GraphState;
+use crate::operators::{OneQubitOp, Operator};
+use crate::pattern::{Command, Pattern, Plane};
+use crate::pauli::{Pauli, PauliString};
+use crate::randomized;
+use crate::statevector::StateVector;
+
+// CSS states of two-colorable graphs. Applying H to the vertices of color 1 of a bipartite graph
+// state maps the stabilizer X_v Z_N(v) of every vertex v to X_v X_N(v) when v has color 0 and to
+// Z_v Z_N(v) when v has color 1, since all neighbors have the other color. The state is then the
+// +1 eigenstate of these X and Z parity checks, as in the foliation of CSS codes where the two
+// colors play the primal and dual lattices.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssState {
+    pub graph: GraphState,
+    pub colors: Vec<u8>,
+    pub x_checks: Vec<Vec<usize>>,  // Supports v + N(v) of the vertices of color 0.
+    pub z_checks: Vec<Vec<usize>>   // Supports v + N(v) of the vertices of color 1.
+}
+
+impl GraphState {
+    // Color of every vertex such that edges join different colors, each connected component
+    // starting from color 0 at its smallest vertex, or None if the graph has an odd cycle.
+    pub fn two_coloring(&self) -> Option<Vec<u8>> {
+        let mut colors = vec![None; self.nqubits];
+        for start in 0..self.nqubits {
+            if colors[start].is_some() {
+                continue;
+            }
+            colors[start] = Some(0);
+            let mut queue = VecDeque::from([start]);
+            while let Some(v) = queue.pop_front() {
+                let color = colors[v].unwrap();
+                for w in self.neighbors(v) {
+                    match colors[w] {
+                        None => {
+                            colors[w] = Some(1 - color);
+                            queue.push_back(w);
+                        },
+                        Some(c) if c == color => return None,
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+        colors.into_iter().collect()
+    }
+}
+
+impl CssState {
+    pub fn from_graph(graph: GraphState) -> Result<Self, String> {
+        let colors = graph.two_coloring().ok_or("Graph is not two-colorable.")?;
+        let support = |v: usize| {
+            let mut support = graph.neighbors(v);
+            support.push(v);
+            support.sort_unstable();
+            support.dedup();
+            support
+        };
+        let checks = |color: u8| (0..graph.nqubits).filter(|&v| colors[v] == color).map(support).collect();
+        let (x_checks, z_checks) = (checks(0), checks(1));
+        Ok(CssState { graph, colors, x_checks, z_checks })
+    }
+
+    pub fn nqubits(&self) -> usize {
+        self.graph.nqubits
+    }
+
+    pub fn to_statevector(&self) -> StateVector {
+        let mut state = self.graph.to_statevector();
+        let h = Operator::one_qubit(OneQubitOp::H);
+        (0..self.nqubits()).filter(|&v| self.colors[v] == 1).for_each(|v| state.evolve_single(&h, v).unwrap());
+        state
+    }
+
+    pub fn to_density_matrix(&self) -> DensityMatrix {
+        self.to_statevector().to_density_matrix()
+    }
+
+    // The checks of the basis as Pauli strings: X checks for Basis::X and Z checks for Basis::Z.
+    pub fn checks(&self, basis: Basis) -> Result<Vec<PauliString>, String> {
+        let (supports, pauli) = match basis {
+            Basis::X => (&self.x_checks, Pauli::X),
+            Basis::Z => (&self.z_checks, Pauli::Z),
+            Basis::Y => return Err("CSS checks are X or Z type.".to_string())
+        };
+        Ok(supports.iter().map(|support| {
+            let mut paulis = vec![Pauli::I; self.nqubits()];
+            support.iter().for_each(|&q| paulis[q] = pauli);
+            PauliString::new(paulis)
+        }).collect())
+    }
+}
+
+// Exact expectation of the parity check on every support, all in the X or all in the Z basis.
+pub fn parity_expectations(rho: &DensityMatrix, checks: &[Vec<usize>], basis: Basis) -> Result<Vec<f64>, String> {
+    let pauli = match basis {
+        Basis::X => Pauli::X,
+        Basis::Y => Pauli::Y,
+        Basis::Z => Pauli::Z
+    };
+    checks.iter().map(|support| {
+        let mut paulis = vec![Pauli::I; rho.nqubits];
+        for &q in support {
+            *paulis.get_mut(q).ok_or_else(|| format!("Target qubit {} is not in the range [0-{}].", q, rho.nqubits))? = pauli;
+        }
+        Ok(rho.expectation(&PauliString::new(paulis))?)
+    }).collect()
+}
+
+// Measure every qubit in the basis for each shot and return the parity of each check per shot.
+// Checks of the same basis commute and are read off the same single-qubit outcomes, so one
+// transversal measurement gives all of them.
+pub fn measure_parities(rho: &DensityMatrix, checks: &[Vec<usize>], basis: Basis, shots: usize, rng: &mut dyn RngCore) -> Result<Vec<Vec<u8>>, String> {
+    let n = rho.nqubits;
+    if let Some(&q) = checks.iter().flatten().find(|&&q| q >= n) {
+        return Err(format!("Target qubit {} is not in the range [0-{}].", q, n));
+    }
+    Ok(randomized::sample_in_bases(rho, &vec![basis; n], shots, rng)?.into_iter()
+        .map(|outcome| checks.iter().map(|support| {
+            support.iter().fold(0, |parity, &q| parity ^ ((outcome >> (n - 1 - q)) & 1) as u8)
+        }).collect())
+        .collect())
+}
+
+// Foliated cluster-state pattern of a CSS code: layer t holds one node per code qubit, chained to
+// the same qubit of layer t + 1, and one check node per row of hz on even layers or of hx on odd
+// layers, entangled with the layer nodes of its support. All nodes but the last layer are measured
+// in X, which teleports the code qubits onto the next layer up to a Hadamard, so that the check
+// nodes of every layer read the parity of a Z-type check in the current frame: the Z checks of the
+// code on even layers and its X checks on odd ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoliatedCode {
+    pub pattern: Pattern,
+    pub layers: Vec<Vec<usize>>,    // Code qubit nodes of every layer, the last one being the output.
+    pub checks: Vec<Vec<usize>>,    // Check nodes of every layer, in the order of the rows of hz or hx.
+    pub detectors: Vec<Vec<usize>>  // Sets of measured nodes whose outcomes have even parity without errors.
+}
+
+fn supports(matrix: &[Vec<u8>], n: usize, name: &str) -> Result<Vec<Vec<usize>>, String> {
+    matrix.iter().enumerate().map(|(i, row)| {
+        if row.len() != n {
+            return Err(format!("Row {} of {} has {} columns instead of {}.", i, name, row.len(), n));
+        }
+        if row.iter().any(|&b| b > 1) {
+            return Err(format!("Row {} of {} is not binary.", i, name));
+        }
+        Ok((0..n).filter(|&j| row[j] == 1).collect())
+    }).collect()
+}
+
+// Foliate the code given by its X and Z parity check matrices into `layers` layers, the last one
+// being left unmeasured and corrected so that the output holds H^(layers - 1) applied to the code
+// state selected by the check outcomes. The code qubits start in |+>, so the X checks are already
+// fixed on the first layer while the Z checks are only compared from their second reading on.
+pub fn foliate(hx: &[Vec<u8>], hz: &[Vec<u8>], layers: usize) -> Result<FoliatedCode, String> {
+    let n = hx.first().or(hz.first()).map_or(0, Vec::len);
+    let (x_checks, z_checks) = (supports(hx, n, "hx")?, supports(hz, n, "hz")?);
+    if n == 0 || layers == 0 {
+        return Err("A foliated code needs at least one qubit and one layer.".to_string());
+    }
+    for (a, x) in x_checks.iter().enumerate() {
+        for (b, z) in z_checks.iter().enumerate() {
+            if x.iter().filter(|j| z.contains(j)).count() % 2 == 1 {
+                return Err(format!("X check {} and Z check {} anticommute.", a, b));
+            }
+        }
+    }
+
+    let mut pattern = Pattern::new(Vec::new());
+    let mut next = 0;
+    let mut allocate = |count: usize| {
+        next += count;
+        (next - count..next).collect::<Vec<_>>()
+    };
+    let mut data = vec![allocate(n)];
+    let mut checks: Vec<Vec<usize>> = Vec::new();
+    let mut detectors = Vec::new();
+    data[0].iter().for_each(|&node| pattern.add(Command::N(node)));
+    for t in 0..layers {
+        let supports = if t % 2 == 0 { &z_checks } else { &x_checks };
+        let ancillas = allocate(supports.len());
+        for (&a, support) in ancillas.iter().zip(supports) {
+            pattern.add(Command::N(a));
+            support.iter().for_each(|&j| pattern.add(Command::E((a, data[t][j]))));
+        }
+        // The previous reading of the same checks flipped by the X outcomes of the layer before,
+        // whose teleportation left an X byproduct on the current one.
+        for (c, (&a, support)) in ancillas.iter().zip(supports).enumerate() {
+            if t >= 1 {
+                let mut detector = vec![a];
+                if t >= 2 {
+                    detector.push(checks[t - 2][c]);
+                }
+                detector.extend(support.iter().map(|&j| data[t - 1][j]));
+                detectors.push(detector);
+            }
+        }
+        if t + 1 < layers {
+            let layer = allocate(n);
+            for j in 0..n {
+                pattern.add(Command::N(layer[j]));
+                pattern.add(Command::E((data[t][j], layer[j])));
+            }
+            data.push(layer);
+        }
+        ancillas.iter().for_each(|&a| pattern.add(Command::M(a, Plane::XY, 0., Vec::new(), Vec::new(), 0)));
+        if t + 1 < layers {
+            data[t].iter().for_each(|&node| pattern.add(Command::M(node, Plane::XY, 0., Vec::new(), Vec::new(), 0)));
+        }
+        checks.push(ancillas);
+    }
+    // The layer t - k carries an X byproduct onto the output for odd k and a Z byproduct for even k.
+    let last = layers - 1;
+    for (j, &output) in data[last].iter().enumerate() {
+        let domain = |offset: usize| (offset..=last).step_by(2).map(|k| data[last - k][j]).collect::<Vec<_>>();
+        pattern.add(Command::X(output, domain(1)));
+        pattern.add(Command::Z(output, domain(2)));
+    }
+    Ok(FoliatedCode { pattern, layers: data, checks, detectors })
+}
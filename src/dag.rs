@@ -0,0 +1,106 @@
+use crate::circuit::{Circuit, Instruction};
+use crate::config::TolerancePolicy;
+use crate::linalg;
+use crate::tools::apply_left;
+
+// Commutation DAG of a circuit: one node per gate, and an edge i -> j (i before j) when the two
+// gates do not commute and the order is not already implied by other edges. Gates without a path
+// between them can be reordered freely.
+#[derive(Debug, Clone)]
+pub struct CircuitDag {
+    pub nodes: Vec<Instruction>,
+    pub predecessors: Vec<Vec<usize>>,
+    pub successors: Vec<Vec<usize>>
+}
+
+impl CircuitDag {
+    pub fn new(instructions: &[Instruction], tol: &TolerancePolicy) -> Self {
+        let n = instructions.len();
+        let mut predecessors = vec![Vec::new(); n];
+        let mut successors = vec![Vec::new(); n];
+        // ancestors[j][i] is true when gate i has to run before gate j.
+        let mut ancestors = vec![vec![false; n]; n];
+        for j in 0..n {
+            for i in (0..j).rev() {
+                if ancestors[j][i] || commute(&instructions[i], &instructions[j], tol) {
+                    continue;
+                }
+                predecessors[j].push(i);
+                successors[i].push(j);
+                let inherited = ancestors[i].clone();
+                ancestors[j].iter_mut().zip(inherited).for_each(|(a, b)| *a |= b);
+                ancestors[j][i] = true;
+            }
+            predecessors[j].reverse();
+        }
+        CircuitDag { nodes: instructions.to_vec(), predecessors, successors }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.successors.iter().enumerate()
+            .flat_map(|(i, s)| s.iter().map(move |&j| (i, j)))
+            .collect()
+    }
+
+    // Gates grouped by their earliest time step, each gate coming right after its last predecessor.
+    pub fn layers(&self) -> Vec<Vec<usize>> {
+        let mut level = vec![0; self.len()];
+        let mut layers: Vec<Vec<usize>> = Vec::new();
+        // Predecessors always have smaller indices, so the gate order is a topological order.
+        for j in 0..self.len() {
+            level[j] = self.predecessors[j].iter().map(|&i| level[i] + 1).max().unwrap_or(0);
+            if layers.len() <= level[j] {
+                layers.push(Vec::new());
+            }
+            layers[level[j]].push(j);
+        }
+        layers
+    }
+
+    // Length of the longest chain of non commuting gates.
+    pub fn depth(&self) -> usize {
+        self.layers().len()
+    }
+
+    // Gates in an order respecting every dependency, layer by layer.
+    pub fn topological_order(&self) -> Vec<usize> {
+        self.layers().concat()
+    }
+}
+
+impl Circuit {
+    pub fn dag(&self, tol: &TolerancePolicy) -> CircuitDag {
+        CircuitDag::new(self.instructions(), tol)
+    }
+}
+
+// Two gates commute when they act on disjoint qubits, or when AB = BA on the union of their qubits
+// within the equality tolerance.
+pub fn commute(a: &Instruction, b: &Instruction, tol: &TolerancePolicy) -> bool {
+    let (qubits_a, qubits_b) = (a.qubits(), b.qubits());
+    if !qubits_a.iter().any(|q| qubits_b.contains(q)) {
+        return true;
+    }
+    let mut union = qubits_a.clone();
+    union.extend(qubits_b.iter().filter(|q| !qubits_a.contains(q)));
+    let nqubits = union.len();
+    let size = 1 << nqubits;
+    let embed = |instr: &Instruction, qubits: &[usize]| {
+        let targets = qubits.iter().map(|q| union.iter().position(|u| u == q).unwrap()).collect::<Vec<_>>();
+        let mut matrix = linalg::identity(size);
+        apply_left(&mut matrix, size, &instr.operator().data.data, &targets, nqubits);
+        matrix
+    };
+    let (ma, mb) = (embed(a, &qubits_a), embed(b, &qubits_b));
+    let ab = linalg::matmul(&ma, &mb, size);
+    let ba = linalg::matmul(&mb, &ma, size);
+    linalg::max_abs_diff(&ab, &ba) < tol.equality
+}
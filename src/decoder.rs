@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use crate::pauli::Pauli;
+
+// Classical co-processing during a pattern run: once every node of the syndrome has been
+// measured, the runner hands the outcomes to the decoder and applies the returned corrections.
+pub trait Decoder {
+    fn syndrome_nodes(&self) -> &[usize];
+
+    // Pauli corrections (node, P) for the outcomes, given in the order of syndrome_nodes.
+    fn decode(&mut self, syndrome: &[u8]) -> Result<Vec<(usize, Pauli)>, String>;
+}
+
+// Decoder reading the corrections from a table indexed by syndrome.
+pub struct LookupDecoder {
+    pub syndrome_nodes: Vec<usize>,
+    pub table: HashMap<Vec<u8>, Vec<(usize, Pauli)>>
+}
+
+impl LookupDecoder {
+    pub fn new(syndrome_nodes: Vec<usize>) -> Self {
+        LookupDecoder { syndrome_nodes, table: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, syndrome: Vec<u8>, corrections: Vec<(usize, Pauli)>) {
+        self.table.insert(syndrome, corrections);
+    }
+}
+
+impl Decoder for LookupDecoder {
+    fn syndrome_nodes(&self) -> &[usize] {
+        &self.syndrome_nodes
+    }
+
+    // Syndromes missing from the table need no correction.
+    fn decode(&mut self, syndrome: &[u8]) -> Result<Vec<(usize, Pauli)>, String> {
+        Ok(self.table.get(syndrome).cloned().unwrap_or_default())
+    }
+}
@@ -0,0 +1,113 @@
+use rand::RngCore;
+
+use crate::channels;
+use crate::circuit::Circuit;
+use crate::density_matrix::{DensityMatrix, State};
+use crate::operators::Operator;
+use crate::pattern::{Command, Pattern, Plane};
+use crate::pauli::{Pauli, PauliString};
+use crate::runner::RunResult;
+
+// Small end-to-end runs, shared by the examples, the Python bindings and the tests.
+
+pub const DEMOS: [&str; 4] = ["teleportation", "cz_via_cluster", "noisy_ghz", "qaoa"];
+
+// Teleport ry(theta)|0> along a three node chain: two J(0) = H steps cancel out, leaving the
+// state on node 2 once the X and Z byproducts are corrected.
+pub fn teleportation(theta: f64, rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+    let mut input = DensityMatrix::new(1, State::ZERO);
+    input.evolve_single(&Operator::ry(theta), 0)?;
+    let mut pattern = Pattern::new(vec![0]);
+    pattern.extend(vec![
+        Command::N(1),
+        Command::N(2),
+        Command::E((0, 1)),
+        Command::E((1, 2)),
+        Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+        Command::M(1, Plane::XY, 0., vec![0], vec![], 0),
+        Command::X(2, vec![1]),
+        Command::Z(2, vec![0]),
+    ]);
+    pattern.simulate(input, rng)
+}
+
+// Measure the two input nodes of a 2 x 2 cluster. Each measurement moves its qubit through an
+// H, and the edge between the two output nodes applies CZ, so the output is CZ (H x H)|00>,
+// the two qubit graph state.
+pub fn cz_via_cluster(rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+    let mut pattern = Pattern::new(vec![0, 1]);
+    pattern.extend(vec![
+        Command::N(2),
+        Command::N(3),
+        Command::E((0, 2)),
+        Command::E((1, 3)),
+        Command::E((2, 3)),
+        Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+        Command::M(1, Plane::XY, 0., vec![], vec![], 0),
+        // CZ propagates the X byproduct of each qubit into a Z byproduct on the other one.
+        Command::X(2, vec![0]),
+        Command::Z(2, vec![1]),
+        Command::X(3, vec![1]),
+        Command::Z(3, vec![0]),
+    ]);
+    pattern.simulate(DensityMatrix::new(2, State::ZERO), rng)
+}
+
+// GHZ state prepared by a transpiled H + CNOT ladder, each qubit then going through a
+// depolarizing channel of probability p.
+pub fn noisy_ghz(nqubits: usize, p: f64, rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+    if nqubits == 0 {
+        return Err("A GHZ state needs at least one qubit.".to_string());
+    }
+    let mut circuit = Circuit::new(nqubits);
+    circuit.h(0);
+    for q in 1..nqubits {
+        circuit.cnot(q - 1, q);
+    }
+    let mut result = circuit.to_pattern().simulate(DensityMatrix::new(nqubits, State::ZERO), rng)?;
+    let kraus = channels::depolarizing(p)?;
+    for q in 0..nqubits {
+        result.state.apply_channel(&kraus, &[q])?;
+    }
+    Ok(result)
+}
+
+// One layer QAOA for MaxCut, exp(-i beta sum_q X_q) exp(-i gamma C)|+...+> with the cost
+// C = sum_{(a, b)} (1 - Z_a Z_b) / 2, equal to prod rzz(-gamma) up to a global phase.
+pub fn qaoa(nqubits: usize, edges: &[(usize, usize)], gamma: f64, beta: f64, rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+    if let Some((a, b)) = edges.iter().find(|(a, b)| a == b || *a >= nqubits || *b >= nqubits) {
+        return Err(format!("Invalid edge ({}, {}) on {} qubits.", a, b, nqubits));
+    }
+    let mut circuit = Circuit::new(nqubits);
+    for q in 0..nqubits {
+        circuit.h(q);
+    }
+    for &(a, b) in edges {
+        circuit.rzz(a, b, -gamma);
+    }
+    for q in 0..nqubits {
+        circuit.rx(q, 2. * beta);
+    }
+    circuit.to_pattern().simulate(DensityMatrix::new(nqubits, State::ZERO), rng)
+}
+
+// Expected number of cut edges, sum_{(a, b)} (1 - <Z_a Z_b>) / 2.
+pub fn maxcut_expectation(rho: &DensityMatrix, edges: &[(usize, usize)]) -> Result<f64, String> {
+    edges.iter().try_fold(0., |acc, &(a, b)| {
+        let mut paulis = vec![Pauli::I; rho.nqubits];
+        paulis[a] = Pauli::Z;
+        paulis[b] = Pauli::Z;
+        Ok(acc + (1. - rho.expectation(&PauliString::new(paulis))?) / 2.)
+    })
+}
+
+// Run one of DEMOS with its default parameters.
+pub fn run_demo(name: &str, rng: &mut dyn RngCore) -> Result<RunResult<DensityMatrix>, String> {
+    match name {
+        "teleportation" => teleportation(0.7, rng),
+        "cz_via_cluster" => cz_via_cluster(rng),
+        "noisy_ghz" => noisy_ghz(3, 0.05, rng),
+        "qaoa" => qaoa(3, &[(0, 1), (1, 2), (0, 2)], 0.6, 0.4, rng),
+        _ => Err(format!("Unknown demo {}, expected one of {:?}.", name, DEMOS))
+    }
+}
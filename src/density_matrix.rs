@@ -1,11 +1,19 @@
 use core::fmt;
+use std::collections::HashMap;
 
 use num_complex::Complex;
+use rand::{Rng, RngCore};
 use tensor::Tensor;
 
 use crate::tensor;
-use crate::tools::{bitwise_int_to_bin_vec, complex_approx_eq, are_elements_unique};
+use crate::tools::{bitwise_int_to_bin_vec, complex_approx_eq, are_elements_unique, apply_left, apply_right_adjoint};
 use crate::operators::{OneQubitOp, Operator, TwoQubitsOp};
+use crate::pauli::PauliString;
+use crate::backend::QuantumBackend;
+use crate::linalg;
+use crate::config::{self, BufferAllocator, SimulationConfig, SystemAllocator, TolerancePolicy};
+use crate::error::SimulatorError;
+use crate::noise::{ReadoutError, ReadoutOutcome};
 
 #[pyo3::pyclass]
 #[derive(Copy, Clone)]
@@ -14,20 +22,108 @@ pub enum State {
     PLUS
 }
 
+// Single qubit measurement basis, outcome 0 being the +1 eigenstate of the Pauli.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Basis {
+    X,
+    Y,
+    Z
+}
+
 // 1D representation of a size * size density matrix.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DensityMatrix {
     pub data: Tensor<Complex<f64>>,
     pub size: usize,    // 2 ** nqubits
     pub nqubits: usize
 }
 
+// Fields of a deserialized DensityMatrix, checked before they make a state.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct DensityMatrixRepr {
+    data: Tensor<Complex<f64>>,
+    size: usize,
+    nqubits: usize
+}
+
+#[cfg(feature = "serde")]
+impl DensityMatrixRepr {
+    fn into_state(self, tol: &TolerancePolicy) -> Result<DensityMatrix, SimulatorError> {
+        let rho = DensityMatrix::from_tensor(self.data)?;
+        if rho.nqubits != self.nqubits || rho.size != self.size {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: rho.nqubits });
+        }
+        rho.check_invariants(tol)?;
+        Ok(rho)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl DensityMatrix {
+    // Deserialize a state and check its invariants with the tolerances of the policy.
+    pub fn deserialize_with<'de, D: serde::Deserializer<'de>>(deserializer: D, tol: &TolerancePolicy) -> Result<Self, D::Error> {
+        let repr = <DensityMatrixRepr as serde::Deserialize>::deserialize(deserializer)?;
+        repr.into_state(tol).map_err(serde::de::Error::custom)
+    }
+}
+
+// Plain deserialization has no policy to take the tolerances from and uses the default one.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DensityMatrix {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DensityMatrix::deserialize_with(deserializer, &TolerancePolicy::default())
+    }
+}
+
 impl fmt::Display for DensityMatrix {
+    // The alternate form {:#} prints the summary, with the default tolerances, instead of every
+    // entry.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}", self.summary(&TolerancePolicy::default()));
+        }
         self.print(f)
     }
 }
 
+// Number of eigenvalues listed by DensityMatrix::summary.
+const SUMMARY_EIGENVALUES: usize = 4;
+
+// Overview of a density matrix that stays readable for any number of qubits.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub nqubits: usize,
+    pub trace: Complex<f64>,
+    pub purity: f64,
+    pub top_eigenvalues: Vec<f64>,  // Largest eigenvalues, in decreasing order.
+    pub support: usize              // Rank, i.e. number of eigenvalues above the eigenvalue tolerance.
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DensityMatrix: {} qubits", self.nqubits)?;
+        writeln!(f, "  trace: {:.6}", self.trace.re)?;
+        writeln!(f, "  purity: {:.6}", self.purity)?;
+        let values = self.top_eigenvalues.iter().map(|v| format!("{:.6}", v)).collect::<Vec<_>>();
+        writeln!(f, "  top eigenvalues: [{}]", values.join(", "))?;
+        writeln!(f, "  support: {} / {}", self.support, 1usize << self.nqubits)
+    }
+}
+
 impl DensityMatrix {
+    // Same as new, with the buffer taken from the allocator of the config.
+    pub fn with_config(nqubits: usize, initial_state: State, config: &SimulationConfig) -> Result<Self, SimulatorError> {
+        let size = 1 << nqubits;
+        let mut data = config::allocate(config.allocator.as_ref(), size * size)?;
+        match initial_state {
+            State::PLUS => data.iter_mut().for_each(|x| *x = Complex::new(1. / size as f64, 0.)),
+            State::ZERO => data[0] = Complex::ONE
+        }
+        Ok(DensityMatrix { data: Tensor::from_vec(data, vec![2; 2 * nqubits]), size, nqubits })
+    }
+
     // By default initialize in |0>.
     pub fn new(nqubits: usize, initial_state: State) -> Self {
         let size = 1 << nqubits;
@@ -55,10 +151,10 @@ impl DensityMatrix {
         }
     }
 
-    pub fn from_statevec(statevec: &[Complex<f64>]) -> Result<Self, &'static str> {
+    pub fn from_statevec(statevec: &[Complex<f64>]) -> Result<Self, SimulatorError> {
         let len = statevec.len();
         if !len.is_power_of_two() {
-            return Err("The size of the statevec is not a power of two");
+            return Err(SimulatorError::NotPowerOfTwo(len));
         }
         let nqubits = len.ilog2() as usize;
         let size = len;
@@ -76,17 +172,26 @@ impl DensityMatrix {
         })
     }
 
-    pub fn from_tensor(tensor: Tensor<Complex<f64>>) -> Result<Self, &'static str> {
-        if tensor.shape.len() != 2 {
-            return Err("Tensor has not the right shape.");
-        } else {
-            let nqubits = tensor.shape.len() / 2;
-            Ok(DensityMatrix {
-                data: tensor,
-                size: 2_i32.pow(nqubits as u32) as usize,
-                nqubits
-            })
+    // The tensor holds either the 2^n x 2^n matrix or the 2n indices of size 2 of the state.
+    pub fn from_tensor(tensor: Tensor<Complex<f64>>) -> Result<Self, SimulatorError> {
+        let len = tensor.data.len();
+        let expected = tensor.shape.iter().product::<usize>();
+        if len != expected {
+            return Err(SimulatorError::DimensionMismatch { expected, actual: len });
+        }
+        let size = len.isqrt();
+        if size * size != len || !size.is_power_of_two() {
+            return Err(SimulatorError::NotPowerOfTwo(len));
         }
+        let nqubits = size.ilog2() as usize;
+        if tensor.shape != [size, size] && tensor.shape != vec![2; 2 * nqubits] {
+            return Err(SimulatorError::DimensionMismatch { expected: 2 * nqubits, actual: tensor.shape.len() });
+        }
+        Ok(DensityMatrix {
+            data: Tensor::from_vec(tensor.data, vec![2; 2 * nqubits]),
+            size,
+            nqubits
+        })
     }
     
     pub fn print(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -104,7 +209,7 @@ impl DensityMatrix {
             if i == self.size - 1 {
                 write!(f, "]")?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
         writeln!(f, "\n")
     }
@@ -121,28 +226,157 @@ impl DensityMatrix {
         self.data.set(&indices, value);
     }
 
-    pub fn expectation_single(&self, op: OneQubitOp, index: usize) -> Result<Complex<f64>, &str> {
+    pub fn expectation_single(&self, op: OneQubitOp, index: usize) -> Result<Complex<f64>, SimulatorError> {
         if index >= self.nqubits {
-            return Err("Wrong target qubit.");
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
         }
 
         let op_tensor = Operator::one_qubit(op);
-        let mut result_tensor = self.data.tensordot(&op_tensor.data, (&[1], &[index])).unwrap();
-        result_tensor = result_tensor.moveaxis(&[0], &[index as i32]).unwrap();
-        let dm_result = DensityMatrix::from_tensor(result_tensor).unwrap();
-        Ok(dm_result.trace())
+        let result_tensor = self.data.tensordot(&op_tensor.data, (&[1], &[index]))?;
+        // Trace of the result with its first axis moved back in place, read through a view.
+        let view = result_tensor.view().moveaxis(&[0], &[index as i32])?;
+        (0..self.size).try_fold(Complex::ZERO, |trace, i| {
+            let bits = bitwise_int_to_bin_vec(i, self.nqubits).into_iter().map(usize::from).collect::<Vec<_>>();
+            Ok(trace + view.get(&[bits.as_slice(), &bits].concat())?)
+        })
+    }
+
+    // Compute Tr(rho P) for a Pauli string P covering every qubit.
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: pauli_string.nqubits() });
+        }
+        // P|i> = phase(i)|i ^ x_mask>, so Tr(rho P) = sum_i rho[i, i ^ x_mask] * phase(i).
+        let x_mask = pauli_string.x_mask();
+        let value = (0..self.size)
+            .map(|i| self.data.data[i * self.size + (i ^ x_mask)] * pauli_string.phase(i))
+            .sum::<Complex<f64>>();
+        Ok(value.re)
+    }
+
+    // (<X>, <Y>, <Z>) of the reduced state of one qubit, of norm at most 1 for a normalized state.
+    // With r the reduced state, <X> = 2 Re r01, <Y> = -2 Im r01 and <Z> = r00 - r11.
+    pub fn bloch_vector(&self, qubit: usize) -> Result<[f64; 3], SimulatorError> {
+        if qubit >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index: qubit, nqubits: self.nqubits });
+        }
+        let bit = 1 << (self.nqubits - 1 - qubit);
+        let (mut coherence, mut z) = (Complex::<f64>::ZERO, 0.);
+        for i in (0..self.size).filter(|i| i & bit == 0) {
+            coherence += self.data.data[i * self.size + (i | bit)];
+            z += self.data.data[i * self.size + i].re - self.data.data[(i | bit) * self.size + (i | bit)].re;
+        }
+        Ok([2. * coherence.re, -2. * coherence.im, z])
+    }
+
+    // Compute Tr(rho O) for a Hermitian observable O acting on the given qubits, without building
+    // O on the whole register.
+    pub fn expectation_operator(&self, op: &Operator, indices: &[usize], tol: &TolerancePolicy) -> Result<f64, SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        let dim = 1 << op.nqubits;
+        if linalg::max_abs_diff(&op.data.data, &linalg::adjoint(&op.data.data, dim)) > tol.equality {
+            return Err(SimulatorError::NotHermitian);
+        }
+        let mut product = self.data.data.clone();
+        apply_left(&mut product, self.size, &op.data.data, indices, self.nqubits);
+        Ok((0..self.size).map(|i| product[i * self.size + i].re).sum())
     }
 
     pub fn trace(&self) -> Complex<f64> {
         // Compute sum over each diagonal elements.
-        let mut trace = Complex::ZERO;
-        let mut step = 0;
-        for i in 0..self.size {
-            trace += self.data.get(&[i as u8, step]);
-            step += 1;
+        (0..self.size).map(|i| self.data.data[i * self.size + i]).sum()
+    }
+
+    // Whether the trace is 1 within the trace tolerance of the policy.
+    pub fn is_normalized(&self, tol: &TolerancePolicy) -> bool {
+        (self.trace() - 1.).norm() <= tol.trace
+    }
+
+    // Tr(rho^2), which is 1 for pure states and 1 / 2^n for the maximally mixed state.
+    pub fn purity(&self) -> f64 {
+        // rho is Hermitian so Tr(rho^2) = sum_ij |rho_ij|^2.
+        self.data.data.iter().map(|c| c.norm_sqr()).sum()
+    }
+
+    pub fn summary(&self, tol: &TolerancePolicy) -> Summary {
+        let mut values = self.eigenvalues();
+        values.reverse();
+        Summary {
+            nqubits: self.nqubits,
+            trace: self.trace(),
+            purity: self.purity(),
+            support: values.iter().filter(|&&v| v > tol.eigenvalue).count(),
+            top_eigenvalues: values.into_iter().take(SUMMARY_EIGENVALUES).collect()
+        }
+    }
+
+    // Von Neumann entropy -Tr(rho log2 rho), in bits.
+    pub fn entropy(&self, tol: &TolerancePolicy) -> f64 {
+        self.eigenvalues().iter()
+            .filter(|&&x| x > tol.eigenvalue)
+            .map(|x| -x * x.log2())
+            .sum()
+    }
+
+    // Eigenvalues in ascending order.
+    pub fn eigenvalues(&self) -> Vec<f64> {
+        self.eigh().0
+    }
+
+    // Eigenvalues in ascending order and the matrix whose columns are the matching eigenvectors,
+    // rho = V diag(eigenvalues) V^dagger.
+    pub fn eigh(&self) -> (Vec<f64>, Vec<Complex<f64>>) {
+        linalg::eigh(&self.data.data, self.size)
+    }
+
+    // V diag(f(eigenvalues)) V^dagger, eigenvalues within the tolerance of zero being set to zero
+    // and clearly negative ones, which no density matrix has, being refused.
+    fn matrix_function(&self, f: impl Fn(f64) -> f64, tol: &TolerancePolicy) -> Result<Operator, SimulatorError> {
+        let (values, vectors) = self.eigh();
+        if let Some(&v) = values.iter().find(|&&v| v < -tol.eigenvalue) {
+            return Err(SimulatorError::NotPositive(v));
         }
+        let mapped = values.iter().map(|&v| Complex::new(f(v.max(0.)), 0.)).collect::<Vec<_>>();
+        Ok(Operator {
+            nqubits: self.nqubits,
+            data: Tensor::from_vec(linalg::from_eigen(&mapped, &vectors, self.size), vec![2; 2 * self.nqubits])
+        })
+    }
 
-        trace
+    pub fn sqrtm(&self, tol: &TolerancePolicy) -> Result<Operator, SimulatorError> {
+        self.matrix_function(|v| if v < tol.eigenvalue { 0. } else { v.sqrt() }, tol)
+    }
+
+    // Natural logarithm, defined for full rank states only.
+    pub fn logm(&self, tol: &TolerancePolicy) -> Result<Operator, SimulatorError> {
+        if let Some(&v) = self.eigenvalues().iter().find(|&&v| v < tol.eigenvalue) {
+            return Err(SimulatorError::InvalidArgument(format!("Logarithm needs a full rank state, found eigenvalue {}.", v)));
+        }
+        self.matrix_function(f64::ln, tol)
+    }
+
+    // Entropy of the reduced state on the given qubits, the other ones being traced out.
+    pub fn entanglement_entropy(&self, subsystem: &[usize], tol: &TolerancePolicy) -> Result<f64, SimulatorError> {
+        if !are_elements_unique(subsystem) {
+            return Err(SimulatorError::DuplicateIndices(subsystem.to_vec()));
+        }
+        if let Some(&index) = subsystem.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let traced = (0..self.nqubits).filter(|q| !subsystem.contains(q)).collect::<Vec<_>>();
+        let mut reduced = self.clone();
+        if !traced.is_empty() {
+            reduced.ptrace(&traced)?;
+        }
+        Ok(reduced.entropy(tol))
     }
 
     pub fn normalize(&mut self) {
@@ -152,62 +386,280 @@ impl DensityMatrix {
             .collect::<Vec<_>>();
     }
 
-    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), String> {
+    // Same as normalize, refusing states whose trace is too small to be rescaled meaningfully.
+    pub fn normalize_checked(&mut self, tol: &TolerancePolicy) -> Result<(), SimulatorError> {
+        let trace = self.trace();
+        if trace.norm() < tol.probability {
+            return Err(SimulatorError::NotNormalized(trace.re));
+        }
+        self.normalize();
+        Ok(())
+    }
+
+    // Check that the state is Hermitian and of unit trace, which is cheap enough to run after
+    // every operation.
+    pub fn check_invariants(&self, tol: &TolerancePolicy) -> Result<(), SimulatorError> {
+        let adjoint = linalg::adjoint(&self.data.data, self.size);
+        if linalg::max_abs_diff(&self.data.data, &adjoint) > tol.equality {
+            return Err(SimulatorError::NotHermitian);
+        }
+        let trace = self.trace();
+        if (trace - 1.).norm() > tol.trace {
+            return Err(SimulatorError::NotNormalized(trace.re));
+        }
+        Ok(())
+    }
+
+    // Same as check_invariants, also checking that the state is positive semidefinite.
+    pub fn validate(&self, tol: &TolerancePolicy) -> Result<(), SimulatorError> {
+        self.check_invariants(tol)?;
+        if let Some(&value) = self.eigenvalues().iter().find(|&&x| x < -tol.eigenvalue) {
+            return Err(SimulatorError::NotPositive(value));
+        }
+        Ok(())
+    }
+
+    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
         if index >= self.nqubits {
-            return Err(format!("Target qubit {} is not in the range [0-{}].", index, self.nqubits));
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
         }
         if op.nqubits != 1 {
-            return Err(format!("Passed operator is not a one qubit operator."));
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
         }
+        self.evolve(op, &[index])
+    }
 
-        self.data = op.data.tensordot(&self.data, (&[1], &[index])).unwrap();
-        self.data = self.data.tensordot(&Tensor::from_vec(op.transconj().data.data, vec![2, 2]), (&[index + self.nqubits], &[0])).unwrap();
-        self.data = self.data.moveaxis(&[0, ((self.data.shape.len() - 1)).try_into().unwrap()], &[index.try_into().unwrap(), ((index + self.nqubits)).try_into().unwrap()]).unwrap();
-
+    // Rotate qubit q so that measuring it in Z measures bases[q], the qubits beyond the list
+    // being left alone: H maps X to Z and H S^dagger maps Y to Z.
+    pub fn rotate_to_bases(&mut self, bases: &[Basis]) -> Result<(), SimulatorError> {
+        if bases.len() > self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: bases.len() });
+        }
+        let h = Operator::one_qubit(OneQubitOp::H);
+        let sdg = Operator::one_qubit(OneQubitOp::SDG);
+        for (q, basis) in bases.iter().enumerate() {
+            if *basis == Basis::Y {
+                self.evolve_single(&sdg, q)?;
+            }
+            if *basis != Basis::Z {
+                self.evolve_single(&h, q)?;
+            }
+        }
         Ok(())
     }
 
-    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), String> {
+    fn check_targets(&self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
         if !are_elements_unique(indices) {
-            return Err("Target qubits must be unique.".to_string());
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
         }
-        for &i in indices.iter() {
-            if i >= self.nqubits {
-                return Err(format!("Target qubit {} is not in the range [0-{}].", i, self.nqubits));
-            }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
         }
+        Ok(())
+    }
+
+    // Apply rho -> U rho U^dagger in place, the i-th qubit of the operator acting on indices[i].
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.sandwich(op, indices)
+    }
+
+    // rho -> K rho K^dagger for any operator K, e.g. a Kraus operator. Same as evolve, which
+    // names the unitary case.
+    pub fn sandwich(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.check_targets(op, indices)?;
+        apply_left(&mut self.data.data, self.size, &op.data.data, indices, self.nqubits);
+        apply_right_adjoint(&mut self.data.data, self.size, &op.data.data, indices, self.nqubits);
+        Ok(())
+    }
 
-        let nqb_op = op.nqubits;
-        let first_axe = (0..indices.len()).map(|i| nqb_op + i).collect::<Vec<usize>>();
-        let second_axe = indices;
-        self.data = op.data.tensordot(
-            &self.data, 
-            (&first_axe, &second_axe)).unwrap();
+    // rho -> A rho, e.g. one side of a superoperator. The result is generally not a state.
+    pub fn apply_left(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.check_targets(op, indices)?;
+        apply_left(&mut self.data.data, self.size, &op.data.data, indices, self.nqubits);
+        Ok(())
+    }
 
-        let op_transconj = op.transconj();
-        let first_axe = indices.iter().map(|i| i + self.nqubits).collect::<Vec<usize>>();
-        let second_axe = (0..indices.len()).collect::<Vec<usize>>();
-        self.data = self.data.tensordot(
-            &op_transconj.data,
-            (&first_axe, &second_axe)).unwrap();
+    // rho -> rho B, with B itself rather than its adjoint or transpose.
+    pub fn apply_right(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.check_targets(op, indices)?;
+        apply_right_adjoint(&mut self.data.data, self.size, &op.transconj().data.data, indices, self.nqubits);
+        Ok(())
+    }
 
-        let moveaxis_src_first = (0..indices.len() as i32).collect::<Vec<i32>>();
-        let moveaxis_src_second = (1..(indices.len() + 1) as i32).map(|i| -i).collect();
-        let src = [moveaxis_src_first, moveaxis_src_second].concat();
-        
-        let moveaxis_dest_first = indices.iter().map(|&i| i as i32).collect::<Vec<i32>>();
-        let moveaxis_dest_second = indices.iter().rev().map(|&i| i as i32 + self.nqubits as i32).collect();
-        let dst = [moveaxis_dest_first, moveaxis_dest_second].concat();
+    // Apply the channel rho -> sum_k K_k rho K_k^dagger given by its Kraus operators.
+    pub fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        if kraus.is_empty() {
+            return Err(SimulatorError::InvalidArgument("A channel needs at least one Kraus operator.".to_string()));
+        }
+        if let Some(k) = kraus.iter().find(|k| k.nqubits != indices.len()) {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: k.nqubits });
+        }
 
-        self.data = self.data.moveaxis(&src, &dst).unwrap();
+        let mut result = vec![Complex::ZERO; self.data.data.len()];
+        for k in kraus {
+            let mut branch = DensityMatrix {
+                data: self.data.clone(),
+                size: self.size,
+                nqubits: self.nqubits
+            };
+            branch.evolve(k, indices)?;
+            result.iter_mut()
+                .zip(branch.data.data.iter())
+                .for_each(|(r, b)| *r += b);
+        }
+        self.data.data = result;
 
         Ok(())
     }
 
+    // Joint outcome probabilities of measuring the given qubits in the basis, without collapsing rho.
+    // Outcome k has the bit of qubits[0] as its most significant bit.
+    pub fn outcome_distribution(&self, qubits: &[usize], basis: Basis) -> Result<Vec<f64>, SimulatorError> {
+        if !are_elements_unique(qubits) {
+            return Err(SimulatorError::DuplicateIndices(qubits.to_vec()));
+        }
+        if let Some(&index) = qubits.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let traced = (0..self.nqubits).filter(|q| !qubits.contains(q)).collect::<Vec<_>>();
+        let mut reduced = self.clone();
+        if !traced.is_empty() {
+            reduced.ptrace(&traced)?;
+        }
+        reduced.rotate_to_bases(&vec![basis; reduced.nqubits])?;
+
+        // The reduced state keeps the measured qubits in increasing order.
+        let mut sorted = qubits.to_vec();
+        sorted.sort_unstable();
+        let k = qubits.len();
+        let mut distribution = vec![0.; 1 << k];
+        for i in 0..reduced.size {
+            let outcome = qubits.iter().enumerate().fold(0, |acc, (pos, q)| {
+                let reduced_pos = sorted.iter().position(|s| s == q).unwrap();
+                acc | (((i >> (k - 1 - reduced_pos)) & 1) << (k - 1 - pos))
+            });
+            distribution[outcome] = reduced.data.data[i * reduced.size + i].re.max(0.);
+        }
+        Ok(distribution)
+    }
+
+    // Histogram of shots bitstrings sampled from the diagonal, without collapsing rho. Qubit 0
+    // is the most significant bit of each bitstring.
+    pub fn sample(&self, shots: usize, rng: &mut dyn RngCore) -> HashMap<u64, usize> {
+        let cumulative = self.cumulative_probabilities();
+        let mut histogram = HashMap::new();
+        for _ in 0..shots {
+            *histogram.entry(self.draw(&cumulative, rng)).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    // Same as sample, each bitstring being read out through the readout error.
+    pub fn sample_with_readout(&self, shots: usize, readout: &ReadoutError, rng: &mut dyn RngCore) -> HashMap<u64, usize> {
+        let mut histogram = HashMap::new();
+        for shot in self.sample_shots_with_readout(shots, readout, rng) {
+            *histogram.entry(shot.recorded).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    // Every shot of sample_with_readout in drawing order, along with its ideal bitstring.
+    pub fn sample_shots_with_readout(&self, shots: usize, readout: &ReadoutError, rng: &mut dyn RngCore) -> Vec<ReadoutOutcome<u64>> {
+        let cumulative = self.cumulative_probabilities();
+        (0..shots).map(|_| {
+            let ideal = self.draw(&cumulative, rng);
+            ReadoutOutcome { recorded: readout.apply(ideal, self.nqubits, rng), ideal }
+        }).collect()
+    }
+
+    fn cumulative_probabilities(&self) -> Vec<f64> {
+        (0..self.size)
+            .scan(0., |acc, i| {
+                *acc += self.data.data[i * self.size + i].re.max(0.);
+                Some(*acc)
+            })
+            .collect()
+    }
+
+    fn draw(&self, cumulative: &[f64], rng: &mut dyn RngCore) -> u64 {
+        let total = cumulative.last().copied().unwrap_or(0.);
+        let r = rng.gen::<f64>() * total;
+        cumulative.partition_point(|&c| c <= r).min(self.size - 1) as u64
+    }
+
+    // Measure a qubit in the computational basis, collapsing rho onto the sampled outcome.
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let bit = 1 << (self.nqubits - 1 - index);
+        let size = self.size;
+        let p1 = (0..size)
+            .filter(|i| i & bit != 0)
+            .map(|i| self.data.data[i * size + i].re)
+            .sum::<f64>() / self.trace().re;
+        let outcome = u8::from(rng.gen::<f64>() < p1);
+        for (k, c) in self.data.data.iter_mut().enumerate() {
+            let (row, col) = (k / size, k % size);
+            if u8::from(row & bit != 0) != outcome || u8::from(col & bit != 0) != outcome {
+                *c = Complex::ZERO;
+            }
+        }
+        self.normalize();
+        Ok(outcome)
+    }
+
+    // Same as measure, the reported outcome going through the confusion matrix of the qubit while
+    // rho collapses onto the ideal one.
+    pub fn measure_with_readout(&mut self, index: usize, readout: &ReadoutError, rng: &mut dyn RngCore) -> Result<ReadoutOutcome<u8>, SimulatorError> {
+        let ideal = self.measure(index, rng)?;
+        Ok(ReadoutOutcome { recorded: readout.confusion(index).apply(ideal, rng), ideal })
+    }
+
+    // Append a fresh qubit in the given state after the existing ones.
+    pub fn add_qubit(&mut self, state: State) {
+        *self = DensityMatrix::tensor(self, &DensityMatrix::new(1, state));
+    }
+
+    // Same as add_qubit, the enlarged buffer coming from the allocator.
+    pub fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        *self = self.tensor_with(&DensityMatrix::new(1, state), allocator)?;
+        Ok(())
+    }
+
+    // Measure a qubit in the computational basis and trace it out of the register.
+    pub fn remove_qubit(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.measure_and_remove(index, rng)
+    }
+
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.measure_and_remove_with(index, rng, &SystemAllocator)
+    }
+
+    // Same as measure_and_remove, the reduced buffer coming from the allocator.
+    pub fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        // After the collapse only the block where the qubit equals the outcome is non zero.
+        let bit = 1 << (self.nqubits - 1 - index);
+        let kept = (0..self.size)
+            .filter(|i| u8::from(i & bit != 0) == outcome)
+            .collect::<Vec<usize>>();
+        let mut data = config::allocate(allocator, kept.len() * kept.len())?;
+        let pairs = kept.iter().flat_map(|&i| kept.iter().map(move |&j| (i, j)));
+        data.iter_mut().zip(pairs).for_each(|(x, (i, j))| *x = self.data.data[i * self.size + j]);
+        self.nqubits -= 1;
+        self.size = kept.len();
+        self.data = Tensor::from_vec(data, vec![2; 2 * self.nqubits]);
+        Ok(outcome)
+    }
+
     pub fn equals(&self, other: DensityMatrix, tol: f64) -> bool {
         if self.data.shape.iter().product::<usize>() == other.data.shape.iter().product::<usize>() {
             for i in 0..self.data.data.len() {
-                if complex_approx_eq(self.data.data[i], other.data.data[i], tol) == false {
+                if !complex_approx_eq(self.data.data[i], other.data.data[i], tol) {
                     return false;
                 }
             }
@@ -217,15 +669,49 @@ impl DensityMatrix {
         }
     }
 
-    pub fn tensor(&mut self, other: &DensityMatrix) {
-        self.data = self.data.tensor_product(&other.data);
-        self.nqubits += other.nqubits;
+    // Elementwise comparison with the equality tolerance of the policy.
+    pub fn approx_eq(&self, other: &DensityMatrix, tol: &TolerancePolicy) -> bool {
+        self.nqubits == other.nqubits && linalg::max_abs_diff(&self.data.data, &other.data.data) <= tol.equality
+    }
+
+    // Kronecker product rho x sigma, the qubits of other coming after the qubits of self.
+    pub fn tensor(&self, other: &DensityMatrix) -> DensityMatrix {
+        let size = self.size * other.size;
+        self.tensor_into(other, vec![Complex::ZERO; size * size])
     }
 
-    pub fn ptrace(&mut self, qargs: &[usize]) -> Result<(), &str> {
+    // Same as tensor, the product buffer coming from the allocator.
+    pub fn tensor_with(&self, other: &DensityMatrix, allocator: &dyn BufferAllocator) -> Result<DensityMatrix, SimulatorError> {
+        let size = self.size * other.size;
+        Ok(self.tensor_into(other, config::allocate(allocator, size * size)?))
+    }
+
+    // Kronecker product written into data, which holds (self.size * other.size)^2 entries.
+    fn tensor_into(&self, other: &DensityMatrix, mut data: Vec<Complex<f64>>) -> DensityMatrix {
+        // (A x B)[(i, k), (j, l)] = A[i, j] B[k, l].
+        let size = self.size * other.size;
+        for (a_idx, a) in self.data.data.iter().enumerate() {
+            let (i, j) = (a_idx / self.size, a_idx % self.size);
+            for (b_idx, b) in other.data.data.iter().enumerate() {
+                let (k, l) = (b_idx / other.size, b_idx % other.size);
+                data[(i * other.size + k) * size + j * other.size + l] = a * b;
+            }
+        }
+        let nqubits = self.nqubits + other.nqubits;
+        DensityMatrix {
+            data: Tensor::from_vec(data, vec![2; 2 * nqubits]),
+            size,
+            nqubits
+        }
+    }
+
+    pub fn ptrace(&mut self, qargs: &[usize]) -> Result<(), SimulatorError> {
         let n = self.nqubits;
-        if !qargs.iter().all(|&e| e < n) {
-            return Err("Wrong qubit argument for partial trace");
+        if let Some(&index) = qargs.iter().find(|&&e| e >= n) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: n });
+        }
+        if !are_elements_unique(qargs) {
+            return Err(SimulatorError::DuplicateIndices(qargs.to_vec()));
         }
         let nqubit_after = n - qargs.len();
         let second_trace_axe = qargs.iter().map(|e| e + n).collect::<Vec<_>>();
@@ -234,36 +720,82 @@ impl DensityMatrix {
         // Build identity tensor
         let id_tensor_size = 2_i32.pow(qargs.len() as u32) as usize;
         let mut id_tensor = Tensor::new(&vec![2; qargs.len() * 2]);
-        for i in 0..id_tensor_size * id_tensor_size {
+        for i in 0..id_tensor_size {
             let index = bitwise_int_to_bin_vec(i * id_tensor_size + i, qargs.len() * 2);
             id_tensor.set(&index, Complex::ONE);
         }
 
         let tensordot_first_axe = (0..qargs.len() * 2).collect::<Vec<usize>>();
-        let rho_res = id_tensor.tensordot(&self.data, (&tensordot_first_axe, &trace_axes)).unwrap();
+        let rho_res = id_tensor.tensordot(&self.data, (&tensordot_first_axe, &trace_axes))?;
         self.data = rho_res;
         self.nqubits = nqubit_after;
+        self.size = 1 << nqubit_after;
         Ok(())
     }
 
-    pub fn entangle(&mut self, edge: &(usize, usize)) {
+    pub fn entangle(&mut self, edge: &(usize, usize)) -> Result<(), SimulatorError> {
         self.evolve(
             &Operator::two_qubits(TwoQubitsOp::CZ),
             &[edge.0, edge.1]
-        );
+        )
     }
 
-    pub fn swap(&mut self, edge: &(usize, usize)) {
+    pub fn swap(&mut self, edge: &(usize, usize)) -> Result<(), SimulatorError> {
         self.evolve(
             &Operator::two_qubits(TwoQubitsOp::SWAP),
             &[edge.0, edge.1]
-        );
+        )
     }
 
-    pub fn cnot(&mut self, edge: &(usize, usize)) {
+    pub fn cnot(&mut self, edge: &(usize, usize)) -> Result<(), SimulatorError> {
         self.evolve(
             &Operator::two_qubits(TwoQubitsOp::CX),
             &[edge.0, edge.1]
-        );
+        )
     }
-}
\ No newline at end of file
+}
+impl QuantumBackend for DensityMatrix {
+    fn nqubits(&self) -> usize {
+        self.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        DensityMatrix::evolve_single(self, op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        DensityMatrix::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        DensityMatrix::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        DensityMatrix::expectation(self, pauli_string)
+    }
+
+    fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        DensityMatrix::apply_channel(self, kraus, indices)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        *self = DensityMatrix::tensor(self, other);
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        DensityMatrix::add_qubit(self, state)
+    }
+
+    fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        DensityMatrix::add_qubit_with(self, state, allocator)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        DensityMatrix::measure_and_remove(self, index, rng)
+    }
+
+    fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        DensityMatrix::measure_and_remove_with(self, index, rng, allocator)
+    }
+}
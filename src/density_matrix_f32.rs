@@ -0,0 +1,295 @@
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::config::{self, BufferAllocator, SimulationConfig, SystemAllocator, TolerancePolicy};
+use crate::density_matrix::{DensityMatrix, State};
+use crate::error::SimulatorError;
+use crate::operators::Operator;
+use crate::pauli::PauliString;
+use crate::tensor::Tensor;
+use crate::tools::{are_elements_unique, apply_left, apply_right_adjoint};
+
+// Single precision density matrix, halving the memory of DensityMatrix. Same row-major layout,
+// qubit 0 being the most significant bit. Operators stay in double precision and are rounded
+// when applied; reductions such as the trace and expectation values are accumulated in f64.
+#[derive(Debug, Clone)]
+pub struct DensityMatrixF32 {
+    pub data: Vec<Complex<f32>>,
+    pub size: usize,    // 2 ** nqubits
+    pub nqubits: usize
+}
+
+impl DensityMatrixF32 {
+    pub const TOLERANCE: TolerancePolicy = TolerancePolicy::SINGLE;
+
+    pub fn new(nqubits: usize, initial_state: State) -> Self {
+        let size = 1 << nqubits;
+        let data = match initial_state {
+            State::PLUS => vec![Complex::new(1. / size as f32, 0.); size * size],
+            State::ZERO => {
+                let mut data = vec![Complex::ZERO; size * size];
+                data[0] = Complex::ONE;
+                data
+            }
+        };
+        DensityMatrixF32 { data, size, nqubits }
+    }
+
+    // Same as new, with the buffer taken from the allocator of the config.
+    pub fn with_config(nqubits: usize, initial_state: State, config: &SimulationConfig) -> Result<Self, SimulatorError> {
+        let size = 1 << nqubits;
+        let mut data = config::allocate_f32(config.allocator.as_ref(), size * size)?;
+        match initial_state {
+            State::PLUS => data.iter_mut().for_each(|x| *x = Complex::new(1. / size as f32, 0.)),
+            State::ZERO => data[0] = Complex::ONE
+        }
+        Ok(DensityMatrixF32 { data, size, nqubits })
+    }
+
+    pub fn from_density_matrix(rho: &DensityMatrix) -> Self {
+        DensityMatrixF32 {
+            data: rho.data.data.iter().map(|c| Complex::new(c.re as f32, c.im as f32)).collect(),
+            size: rho.size,
+            nqubits: rho.nqubits
+        }
+    }
+
+    pub fn to_density_matrix(&self) -> DensityMatrix {
+        DensityMatrix {
+            data: Tensor::from_vec(widen(&self.data), vec![2; 2 * self.nqubits]),
+            size: self.size,
+            nqubits: self.nqubits
+        }
+    }
+
+    pub fn trace(&self) -> Complex<f64> {
+        (0..self.size).map(|i| {
+            let c = self.data[i * self.size + i];
+            Complex::new(c.re as f64, c.im as f64)
+        }).sum()
+    }
+
+    pub fn normalize(&mut self) {
+        let trace = self.trace();
+        let trace = Complex::new(trace.re as f32, trace.im as f32);
+        self.data.iter_mut().for_each(|c| *c /= trace);
+    }
+
+    // Same as normalize, refusing states whose trace is too small to be rescaled meaningfully.
+    pub fn normalize_checked(&mut self) -> Result<(), SimulatorError> {
+        let trace = self.trace();
+        if trace.norm() < Self::TOLERANCE.probability {
+            return Err(SimulatorError::NotNormalized(trace.re));
+        }
+        self.normalize();
+        Ok(())
+    }
+
+    // Hermitian and of unit trace up to the single precision tolerances, as the rounding of every
+    // operation leaves errors far above the double precision ones.
+    pub fn check_invariants(&self) -> Result<(), SimulatorError> {
+        let tol = Self::TOLERANCE;
+        let hermitian = (0..self.size).all(|i| (0..self.size).all(|j| {
+            (self.data[i * self.size + j] - self.data[j * self.size + i].conj()).norm() as f64 <= tol.equality
+        }));
+        if !hermitian {
+            return Err(SimulatorError::NotHermitian);
+        }
+        let trace = self.trace();
+        if (trace - 1.).norm() > tol.trace {
+            return Err(SimulatorError::NotNormalized(trace.re));
+        }
+        Ok(())
+    }
+
+    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != 1 {
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
+        }
+        self.evolve(op, &[index])
+    }
+
+    // Apply rho -> U rho U^dagger in place, the i-th qubit of the operator acting on indices[i].
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        let op = narrow(&op.data.data);
+        apply_left(&mut self.data, self.size, &op, indices, self.nqubits);
+        apply_right_adjoint(&mut self.data, self.size, &op, indices, self.nqubits);
+        Ok(())
+    }
+
+    // Apply the channel rho -> sum_k K_k rho K_k^dagger given by its Kraus operators.
+    pub fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        if kraus.is_empty() {
+            return Err(SimulatorError::InvalidArgument("A channel needs at least one Kraus operator.".to_string()));
+        }
+        let mut result = vec![Complex::ZERO; self.data.len()];
+        for k in kraus {
+            let mut branch = self.clone();
+            branch.evolve(k, indices)?;
+            result.iter_mut()
+                .zip(branch.data.iter())
+                .for_each(|(r, b)| *r += b);
+        }
+        self.data = result;
+        Ok(())
+    }
+
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let bit = 1 << (self.nqubits - 1 - index);
+        let size = self.size;
+        let p1 = (0..size)
+            .filter(|i| i & bit != 0)
+            .map(|i| self.data[i * size + i].re as f64)
+            .sum::<f64>() / self.trace().re;
+        let outcome = u8::from(rng.gen::<f64>() < p1);
+        for (k, c) in self.data.iter_mut().enumerate() {
+            let (row, col) = (k / size, k % size);
+            if u8::from(row & bit != 0) != outcome || u8::from(col & bit != 0) != outcome {
+                *c = Complex::ZERO;
+            }
+        }
+        self.normalize_checked()?;
+        Ok(outcome)
+    }
+
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.measure_and_remove_with(index, rng, &SystemAllocator)
+    }
+
+    // Same as measure_and_remove, the reduced buffer coming from the allocator.
+    pub fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        let bit = 1 << (self.nqubits - 1 - index);
+        let kept = (0..self.size)
+            .filter(|i| u8::from(i & bit != 0) == outcome)
+            .collect::<Vec<usize>>();
+        let mut data = config::allocate_f32(allocator, kept.len() * kept.len())?;
+        let pairs = kept.iter().flat_map(|&i| kept.iter().map(move |&j| (i, j)));
+        data.iter_mut().zip(pairs).for_each(|(x, (i, j))| *x = self.data[i * self.size + j]);
+        self.data = data;
+        self.nqubits -= 1;
+        self.size = kept.len();
+        Ok(outcome)
+    }
+
+    // Compute Tr(rho P) for a Pauli string P covering every qubit.
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: pauli_string.nqubits() });
+        }
+        let x_mask = pauli_string.x_mask();
+        let value = (0..self.size)
+            .map(|i| {
+                let c = self.data[i * self.size + (i ^ x_mask)];
+                Complex::new(c.re as f64, c.im as f64) * pauli_string.phase(i)
+            })
+            .sum::<Complex<f64>>();
+        Ok(value.re)
+    }
+
+    // Kronecker product rho x sigma, the qubits of other coming after the qubits of self.
+    pub fn tensor(&self, other: &DensityMatrixF32) -> DensityMatrixF32 {
+        let size = self.size * other.size;
+        self.tensor_into(other, vec![Complex::ZERO; size * size])
+    }
+
+    // Same as tensor, the product buffer coming from the allocator.
+    pub fn tensor_with(&self, other: &DensityMatrixF32, allocator: &dyn BufferAllocator) -> Result<DensityMatrixF32, SimulatorError> {
+        let size = self.size * other.size;
+        Ok(self.tensor_into(other, config::allocate_f32(allocator, size * size)?))
+    }
+
+    // Append a fresh qubit in the given state after the existing ones.
+    pub fn add_qubit(&mut self, state: State) {
+        *self = DensityMatrixF32::tensor(self, &DensityMatrixF32::new(1, state));
+    }
+
+    // Same as add_qubit, the enlarged buffer coming from the allocator.
+    pub fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        *self = self.tensor_with(&DensityMatrixF32::new(1, state), allocator)?;
+        Ok(())
+    }
+
+    // Kronecker product written into data, which holds (self.size * other.size)^2 entries.
+    fn tensor_into(&self, other: &DensityMatrixF32, mut data: Vec<Complex<f32>>) -> DensityMatrixF32 {
+        let size = self.size * other.size;
+        for (a_idx, a) in self.data.iter().enumerate() {
+            let (i, j) = (a_idx / self.size, a_idx % self.size);
+            for (b_idx, b) in other.data.iter().enumerate() {
+                let (k, l) = (b_idx / other.size, b_idx % other.size);
+                data[(i * other.size + k) * size + j * other.size + l] = a * b;
+            }
+        }
+        DensityMatrixF32 { data, size, nqubits: self.nqubits + other.nqubits }
+    }
+}
+
+impl QuantumBackend for DensityMatrixF32 {
+    fn nqubits(&self) -> usize {
+        self.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        DensityMatrixF32::evolve_single(self, op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        DensityMatrixF32::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        DensityMatrixF32::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        DensityMatrixF32::expectation(self, pauli_string)
+    }
+
+    fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        DensityMatrixF32::apply_channel(self, kraus, indices)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        *self = DensityMatrixF32::tensor(self, other);
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        DensityMatrixF32::add_qubit(self, state)
+    }
+
+    fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        DensityMatrixF32::add_qubit_with(self, state, allocator)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        DensityMatrixF32::measure_and_remove(self, index, rng)
+    }
+
+    fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        DensityMatrixF32::measure_and_remove_with(self, index, rng, allocator)
+    }
+}
+
+fn narrow(data: &[Complex<f64>]) -> Vec<Complex<f32>> {
+    data.iter().map(|c| Complex::new(c.re as f32, c.im as f32)).collect()
+}
+
+fn widen(data: &[Complex<f32>]) -> Vec<Complex<f64>> {
+    data.iter().map(|c| Complex::new(c.re as f64, c.im as f64)).collect()
+}
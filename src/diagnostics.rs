@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+
+use num_complex::Complex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+
+use crate::clifford::Clifford;
+use crate::config::TolerancePolicy;
+use crate::density_matrix::State;
+use crate::open_graph::OpenGraph;
+use crate::operators::Operator;
+use crate::pattern::{Command, Pattern};
+use crate::statevector::StateVector;
+
+// Static and numerical checks of a pattern, run before long simulations. Every check reports
+// at least one diagnostic, so the output also lists what passed.
+
+// Largest register for which determinism is checked by simulation.
+const DETERMINISM_MAX_WIDTH: usize = 12;
+const DETERMINISM_RUNS: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Diagnostic {
+    fn new(check: &'static str, severity: Severity, message: String) -> Self {
+        Diagnostic { check, severity, message }
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+// {"valid": bool, "diagnostics": [{"check", "severity", "message"}, ...]}
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let entries = diagnostics.iter()
+        .map(|d| json!({ "check": d.check, "severity": d.severity.name(), "message": d.message }))
+        .collect::<Vec<Value>>();
+    json!({ "valid": !has_errors(diagnostics), "diagnostics": entries }).to_string()
+}
+
+impl Pattern {
+    pub fn diagnostics(&self, tol: &TolerancePolicy) -> Vec<Diagnostic> {
+        let mut diagnostics = command_diagnostics(self);
+        diagnostics.extend(physicality_diagnostics(self, tol));
+        let runnable = !has_errors(&diagnostics);
+
+        match self.standardize() {
+            Ok(standard) => diagnostics.push(Diagnostic::new("standardization", Severity::Info,
+                format!("Pattern standardizes to {} commands.", standard.seq().len()))),
+            Err(e) => diagnostics.push(Diagnostic::new("standardization", Severity::Error, e))
+        }
+
+        let flow = OpenGraph::from_pattern(self).map(|graph| {
+            if graph.find_flow().is_ok() {
+                Some("causal flow")
+            } else if graph.find_gflow().is_ok() {
+                Some("generalized flow")
+            } else {
+                None
+            }
+        });
+        match flow {
+            Ok(Some(kind)) => diagnostics.push(Diagnostic::new("flow", Severity::Info, format!("Open graph has a {}.", kind))),
+            Ok(None) => diagnostics.push(Diagnostic::new("flow", Severity::Warning,
+                "Open graph has no generalized flow, so no choice of corrections makes it deterministic.".to_string())),
+            Err(e) => diagnostics.push(Diagnostic::new("flow", Severity::Error, e))
+        }
+
+        if runnable {
+            diagnostics.push(determinism_diagnostic(self, tol));
+        } else {
+            diagnostics.push(Diagnostic::new("determinism", Severity::Info,
+                "Skipped because the pattern cannot be run.".to_string()));
+        }
+        diagnostics
+    }
+}
+
+// Nodes are prepared before use, measured once, and domains only hold measured nodes.
+fn command_diagnostics(pattern: &Pattern) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut error = |message: String| diagnostics.push(Diagnostic::new("commands", Severity::Error, message));
+    let mut live = pattern.input_nodes().iter().copied().collect::<BTreeSet<_>>();
+    let mut measured = BTreeSet::new();
+    for (i, command) in pattern.seq().iter().enumerate() {
+        let (targets, dependencies): (Vec<usize>, Vec<usize>) = match command {
+            Command::N(node) | Command::NState(node, _) => {
+                if live.contains(node) || measured.contains(node) {
+                    error(format!("Command {}: node {} is prepared twice.", i, node));
+                }
+                live.insert(*node);
+                continue;
+            },
+            Command::E((a, b)) => {
+                if a == b {
+                    error(format!("Command {}: edge ({}, {}) is a self loop.", i, a, b));
+                }
+                (vec![*a, *b], vec![])
+            },
+            Command::M(node, _, _, s_domain, t_domain, _) => (vec![*node], [s_domain.as_slice(), t_domain].concat()),
+            Command::X(node, domain) | Command::Z(node, domain) | Command::S(node, domain) => (vec![*node], domain.clone()),
+            Command::XIf(node, condition) | Command::ZIf(node, condition) => (vec![*node], condition.nodes().into_iter().collect()),
+            Command::C(node, _) => (vec![*node], vec![]),
+            Command::T => continue
+        };
+        for node in targets.iter().filter(|node| !live.contains(node)) {
+            let state = if measured.contains(node) { "already measured" } else { "not prepared" };
+            error(format!("Command {}: node {} is {}.", i, node, state));
+        }
+        for node in dependencies.iter().filter(|node| !measured.contains(*node)) {
+            error(format!("Command {}: domain uses node {} before its measurement.", i, node));
+        }
+        if let Command::M(node, ..) = command {
+            live.remove(node);
+            measured.insert(*node);
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::new("commands", Severity::Info,
+            format!("{} commands on {} nodes are well formed.", pattern.seq().len(), pattern.n_nodes())));
+    }
+    diagnostics
+}
+
+// Angles are finite and every Clifford index, including vertex operators, exists.
+fn physicality_diagnostics(pattern: &Pattern, tol: &TolerancePolicy) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (i, command) in pattern.seq().iter().enumerate() {
+        match command {
+            Command::M(node, _, angle, _, _, vop) => {
+                if !angle.is_finite() {
+                    diagnostics.push(Diagnostic::new("physicality", Severity::Error,
+                        format!("Command {}: measurement angle {} of node {} is not finite.", i, angle, node)));
+                }
+                if let Err(e) = Clifford::new(*vop) {
+                    diagnostics.push(Diagnostic::new("physicality", Severity::Error, format!("Command {}: {}", i, e)));
+                } else if *vop != 0 {
+                    diagnostics.push(Diagnostic::new("physicality", Severity::Error,
+                        format!("Command {}: measurement of node {} has a vertex operator, which the runner does not support.", i, node)));
+                }
+            },
+            Command::NState(node, [alpha, beta]) => {
+                let norm = (alpha.norm_sqr() + beta.norm_sqr()).sqrt();
+                if !norm.is_finite() || (norm - 1.).abs() > tol.trace {
+                    diagnostics.push(Diagnostic::new("physicality", Severity::Error,
+                        format!("Command {}: state of node {} has norm {} instead of 1.", i, node, norm)));
+                }
+            },
+            Command::C(_, index) => {
+                if let Err(e) = Clifford::new(*index) {
+                    diagnostics.push(Diagnostic::new("physicality", Severity::Error, format!("Command {}: {}", i, e)));
+                }
+            },
+            Command::S(node, _) => diagnostics.push(Diagnostic::new("physicality", Severity::Error,
+                format!("Command {}: signal shifting on node {} is not supported by the runner.", i, node))),
+            _ => {}
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::new("physicality", Severity::Info, "Preparations, measurements and Clifford commands are valid.".to_string()));
+    }
+    diagnostics
+}
+
+// Run the pattern on a generic product input with different outcomes and compare the outputs up
+// to a global phase.
+fn determinism_diagnostic(pattern: &Pattern, tol: &TolerancePolicy) -> Diagnostic {
+    let width = pattern.resources(tol).max_width;
+    if width > DETERMINISM_MAX_WIDTH {
+        return Diagnostic::new("determinism", Severity::Info,
+            format!("Skipped because the pattern holds up to {} qubits, more than {}.", width, DETERMINISM_MAX_WIDTH));
+    }
+    let mut input = StateVector::new(pattern.input_nodes().len(), State::ZERO);
+    for q in 0..input.nqubits {
+        let result = input.evolve_single(&Operator::ry(0.7 + 0.3 * q as f64), q)
+            .and_then(|_| input.evolve_single(&Operator::rz(0.4 + 0.2 * q as f64), q));
+        if let Err(e) = result {
+            return Diagnostic::new("determinism", Severity::Error, e.to_string());
+        }
+    }
+    let mut outputs = Vec::new();
+    for seed in 0..DETERMINISM_RUNS {
+        match pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(seed)) {
+            Ok(result) => outputs.push(result.state),
+            Err(e) => return Diagnostic::new("determinism", Severity::Error, e)
+        }
+    }
+    let reference = &outputs[0];
+    let worst = outputs.iter()
+        .map(|output| output.data.iter().zip(reference.data.iter()).map(|(a, b)| a.conj() * b).sum::<Complex<f64>>().norm_sqr())
+        .fold(1., f64::min);
+    if 1. - worst > tol.equality {
+        Diagnostic::new("determinism", Severity::Error,
+            format!("Outputs of {} runs differ, with a fidelity down to {}.", DETERMINISM_RUNS, worst))
+    } else {
+        Diagnostic::new("determinism", Severity::Info,
+            format!("Outputs of {} runs with different outcomes agree.", DETERMINISM_RUNS))
+    }
+}
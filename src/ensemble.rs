@@ -0,0 +1,77 @@
+use rand::distributions::Distribution;
+use rand::{Rng, RngCore};
+
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+
+// Ensemble-averaged simulation: each shot draws a fresh set of parameters,
+// prepares the corresponding state and samples one +/-1 outcome per observable.
+
+pub type ParameterDistribution = Box<dyn Fn(&mut dyn RngCore) -> f64>;
+pub type Observable<'a> = &'a dyn Fn(&DensityMatrix) -> Result<f64, String>;
+
+pub fn from_distribution<D: Distribution<f64> + 'static>(distribution: D) -> ParameterDistribution {
+    Box::new(move |rng: &mut dyn RngCore| distribution.sample(rng))
+}
+
+#[derive(Debug, Clone)]
+pub struct ObservableEstimate {
+    pub mean: f64,                  // Average of the sampled outcomes.
+    pub exact_mean: f64,            // Average of the exact expectation values over the parameter samples.
+    pub total_variance: f64,        // Sample variance of the outcomes.
+    pub shot_variance: f64,         // E_theta[Var(O | theta)], the part due to projective measurement.
+    pub parameter_variance: f64,    // Var_theta(E[O | theta]), the part due to parameter fluctuations.
+}
+
+fn variance(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+// Observables must have eigenvalues +/-1 (e.g. Pauli strings) and return the exact expectation value.
+pub fn run_ensemble<F>(
+    distributions: &[ParameterDistribution],
+    shots: usize,
+    mut simulate: F,
+    observables: &[Observable],
+    tol: &TolerancePolicy,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<ObservableEstimate>, String>
+where
+    F: FnMut(&[f64]) -> Result<DensityMatrix, String>,
+{
+    if shots == 0 {
+        return Err("At least one shot is needed.".to_string());
+    }
+
+    let mut expectations = vec![Vec::with_capacity(shots); observables.len()];
+    let mut outcomes = vec![Vec::with_capacity(shots); observables.len()];
+    for _ in 0..shots {
+        let params = distributions.iter().map(|d| d(rng)).collect::<Vec<f64>>();
+        let rho = simulate(&params)?;
+        for (i, observable) in observables.iter().enumerate() {
+            // Rounding may push the exact value of a +/-1 eigenstate slightly past 1.
+            let expectation = observable(&rho)?;
+            if !(-1. - tol.probability..=1. + tol.probability).contains(&expectation) {
+                return Err(format!("Expectation value {} is outside of [-1, 1].", expectation));
+            }
+            let expectation = expectation.clamp(-1., 1.);
+            let outcome = if rng.gen::<f64>() < (1. + expectation) / 2. { 1. } else { -1. };
+            expectations[i].push(expectation);
+            outcomes[i].push(outcome);
+        }
+    }
+
+    Ok(expectations.iter().zip(outcomes.iter()).map(|(exp, out)| {
+        ObservableEstimate {
+            mean: out.iter().sum::<f64>() / shots as f64,
+            exact_mean: exp.iter().sum::<f64>() / shots as f64,
+            total_variance: variance(out),
+            shot_variance: exp.iter().map(|e| 1. - e * e).sum::<f64>() / shots as f64,
+            parameter_variance: variance(exp),
+        }
+    }).collect())
+}
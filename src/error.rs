@@ -0,0 +1,78 @@
+use std::fmt;
+
+use thiserror::Error;
+
+// Errors raised by the simulators. Modules whose errors are plain messages convert these
+// into String, so `?` works across both.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SimulatorError {
+    #[error("Expected dimension {expected} but got {actual}.")]
+    DimensionMismatch { expected: usize, actual: usize },
+    #[error("Target qubit {index} is not in the range [0-{nqubits}].")]
+    IndexOutOfRange { index: usize, nqubits: usize },
+    #[error("Target qubits must be unique, got {0:?}.")]
+    DuplicateIndices(Vec<usize>),
+    #[error("Size {0} is not a power of two.")]
+    NotPowerOfTwo(usize),
+    #[error("State has trace {0} instead of 1.")]
+    NotNormalized(f64),
+    #[error("Matrix is not Hermitian.")]
+    NotHermitian,
+    #[error("Density matrix has a negative eigenvalue {0}.")]
+    NotPositive(f64),
+    #[error("Matrix is not unitary.")]
+    NotUnitary,
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{context}: {source}")]
+    Context { context: String, source: Box<SimulatorError> }
+}
+
+impl From<SimulatorError> for String {
+    fn from(error: SimulatorError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<SimulatorError> for pyo3::PyErr {
+    fn from(error: SimulatorError) -> Self {
+        pyo3::exceptions::PyValueError::new_err(error.to_string())
+    }
+}
+
+// Prefix an error with where it happened, e.g. "Command 4 E((1, 2)) on DensityMatrix with
+// register [0, 1, 2]: Target qubit 5 is not in the range [0-3].", so that failures deep in the
+// simulators point at the operation that caused them. Contexts chain from the outermost in.
+// Simulator errors keep their kind under SimulatorError::Context, plain messages stay Strings.
+pub trait Context<T> {
+    type Error;
+
+    fn context<C: fmt::Display>(self, context: C) -> Result<T, Self::Error>;
+
+    // Same as context, building the message only on failure.
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T, Self::Error>;
+}
+
+impl<T> Context<T> for Result<T, SimulatorError> {
+    type Error = SimulatorError;
+
+    fn context<C: fmt::Display>(self, context: C) -> Result<T, SimulatorError> {
+        self.with_context(|| context)
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T, SimulatorError> {
+        self.map_err(|error| SimulatorError::Context { context: context().to_string(), source: Box::new(error) })
+    }
+}
+
+impl<T> Context<T> for Result<T, String> {
+    type Error = String;
+
+    fn context<C: fmt::Display>(self, context: C) -> Result<T, String> {
+        self.with_context(|| context)
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T, String> {
+        self.map_err(|error| format!("{}: {}", context(), error))
+    }
+}
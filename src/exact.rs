@@ -0,0 +1,388 @@
+use std::f64::consts::SQRT_2;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::config::{SimulationConfig, TolerancePolicy};
+use crate::density_matrix::State;
+use crate::error::SimulatorError;
+use crate::operators::Operator;
+use crate::pauli::PauliString;
+use crate::statevector::StateVector;
+use crate::tools::{are_elements_unique, target_offsets};
+
+// Exact arithmetic for small Clifford (and Clifford + T) computations. Amplitudes live in the ring
+// Z[w, 1/sqrt(2)], w = e^{i pi / 4}, which holds every entry of H, S, T, CZ and the Paulis, so
+// states can be compared with == instead of a tolerance.
+
+// Largest power of sqrt(2) tried when reading a floating point number as an exact one.
+const MAX_DENOMINATOR: u32 = 8;
+
+// (a0 + a1 w + a2 w^2 + a3 w^3) / sqrt(2)^k, kept reduced so that equal numbers have equal fields:
+// k is as small as possible and 0 has k = 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExactComplex {
+    coeffs: [i64; 4],
+    k: u32
+}
+
+// Product of polynomials in w, using w^4 = -1.
+fn poly_mul(a: &[i64; 4], b: &[i64; 4]) -> [i64; 4] {
+    let mut c = [0; 4];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            if i + j < 4 {
+                c[i + j] += x * y;
+            } else {
+                c[i + j - 4] -= x * y;
+            }
+        }
+    }
+    c
+}
+
+// sqrt(2) = w - w^3.
+const SQRT_2_POLY: [i64; 4] = [0, 1, 0, -1];
+
+impl ExactComplex {
+    pub const ZERO: ExactComplex = ExactComplex { coeffs: [0; 4], k: 0 };
+    pub const ONE: ExactComplex = ExactComplex { coeffs: [1, 0, 0, 0], k: 0 };
+    pub const I: ExactComplex = ExactComplex { coeffs: [0, 0, 1, 0], k: 0 };
+
+    pub fn new(coeffs: [i64; 4], k: u32) -> Self {
+        ExactComplex { coeffs, k }.reduced()
+    }
+
+    // w^j = e^{i j pi / 4}.
+    pub fn omega(j: i64) -> Self {
+        let j = j.rem_euclid(8) as usize;
+        let mut coeffs = [0; 4];
+        coeffs[j % 4] = if j < 4 { 1 } else { -1 };
+        ExactComplex { coeffs, k: 0 }
+    }
+
+    // 1 / sqrt(2)^k.
+    pub fn inv_sqrt2_pow(k: u32) -> Self {
+        ExactComplex::new([1, 0, 0, 0], k)
+    }
+
+    pub fn coeffs(&self) -> [i64; 4] {
+        self.coeffs
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs == [0; 4]
+    }
+
+    fn reduced(mut self) -> Self {
+        if self.is_zero() {
+            return ExactComplex::ZERO;
+        }
+        while self.k > 0 {
+            let doubled = poly_mul(&self.coeffs, &SQRT_2_POLY);
+            if doubled.iter().any(|c| c % 2 != 0) {
+                break;
+            }
+            self.coeffs = doubled.map(|c| c / 2);
+            self.k -= 1;
+        }
+        self
+    }
+
+    // Same number written over sqrt(2)^k, k being at least the current one.
+    fn raised(&self, k: u32) -> [i64; 4] {
+        (self.k..k).fold(self.coeffs, |coeffs, _| poly_mul(&coeffs, &SQRT_2_POLY))
+    }
+
+    // Multiply by sqrt(2)^m.
+    pub fn mul_sqrt2_pow(&self, m: u32) -> Self {
+        if m <= self.k {
+            ExactComplex::new(self.coeffs, self.k - m)
+        } else {
+            ExactComplex::new(ExactComplex { coeffs: self.coeffs, k: 0 }.raised(m - self.k), 0)
+        }
+    }
+
+    pub fn conj(&self) -> Self {
+        let [a0, a1, a2, a3] = self.coeffs;
+        ExactComplex { coeffs: [a0, -a3, -a2, -a1], k: self.k }
+    }
+
+    pub fn norm_sqr(&self) -> Self {
+        *self * self.conj()
+    }
+
+    pub fn to_complex(&self) -> Complex<f64> {
+        let [a0, a1, a2, a3] = self.coeffs.map(|c| c as f64);
+        let scale = SQRT_2.powi(-(self.k as i32));
+        Complex::new(a0 + (a1 - a3) / SQRT_2, a2 + (a1 + a3) / SQRT_2) * scale
+    }
+
+    // Exact number equal to c up to the equality tolerance, with the smallest power of sqrt(2) in
+    // the denominator, if there is one with at most MAX_DENOMINATOR.
+    pub fn from_complex(c: Complex<f64>, tol: &TolerancePolicy) -> Option<Self> {
+        // Integers a, b with a + b / sqrt(2) = x.
+        let split = |x: f64, parity: Option<i64>| {
+            let bound = (x.abs() * 2.) as i64 + 4;
+            (-bound..=bound)
+                .filter(|b| parity.is_none_or(|p| (b - p).rem_euclid(2) == 0))
+                .map(|b| (x - b as f64 / SQRT_2, b))
+                .find(|(a, _)| (a - a.round()).abs() < tol.equality)
+                .map(|(a, b)| (a.round() as i64, b))
+        };
+        (0..=MAX_DENOMINATOR).find_map(|k| {
+            let scaled = c * SQRT_2.powi(k as i32);
+            // a1 - a3 and a1 + a3 have the same parity.
+            let (a0, difference) = split(scaled.re, None)?;
+            let (a2, sum) = split(scaled.im, Some(difference))?;
+            Some(ExactComplex::new([a0, (sum + difference) / 2, a2, (sum - difference) / 2], k))
+        })
+    }
+}
+
+impl Add for ExactComplex {
+    type Output = ExactComplex;
+
+    fn add(self, other: ExactComplex) -> ExactComplex {
+        let k = self.k.max(other.k);
+        let (a, b) = (self.raised(k), other.raised(k));
+        ExactComplex::new([a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]], k)
+    }
+}
+
+impl Neg for ExactComplex {
+    type Output = ExactComplex;
+
+    fn neg(self) -> ExactComplex {
+        ExactComplex { coeffs: self.coeffs.map(|c| -c), k: self.k }
+    }
+}
+
+impl Sub for ExactComplex {
+    type Output = ExactComplex;
+
+    fn sub(self, other: ExactComplex) -> ExactComplex {
+        self + -other
+    }
+}
+
+impl Mul for ExactComplex {
+    type Output = ExactComplex;
+
+    fn mul(self, other: ExactComplex) -> ExactComplex {
+        // Denominators multiply, so their powers of sqrt(2) add up.
+        let k = [self.k, other.k].iter().sum();
+        ExactComplex::new(poly_mul(&self.coeffs, &other.coeffs), k)
+    }
+}
+
+// Entries of the operator as exact numbers, row-major.
+pub fn exact_matrix(op: &Operator, tol: &TolerancePolicy) -> Result<Vec<ExactComplex>, SimulatorError> {
+    op.data.data.iter()
+        .map(|&c| ExactComplex::from_complex(c, tol).ok_or_else(|| {
+            SimulatorError::InvalidArgument(format!("Operator entry {} is not in Z[w, 1/sqrt(2)].", c))
+        }))
+        .collect()
+}
+
+// Pure state with exact amplitudes, qubit 0 being the most significant bit. Operators given as
+// floating point matrices are read exactly and rejected when one of their entries is not in the
+// ring, and measurements are only possible when the outcome probability is a power of 1/2 so that
+// the renormalized state stays exact, which always holds for stabilizer states. Operators are read
+// with the tolerances of the state, which do not take part in comparisons.
+#[derive(Debug, Clone)]
+pub struct ExactStateVector {
+    pub amplitudes: Vec<ExactComplex>,
+    pub nqubits: usize,
+    pub tolerance: TolerancePolicy
+}
+
+impl PartialEq for ExactStateVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.nqubits == other.nqubits && self.amplitudes == other.amplitudes
+    }
+}
+
+impl Eq for ExactStateVector {}
+
+impl ExactStateVector {
+    pub fn new(nqubits: usize, initial_state: State) -> Self {
+        let size = 1 << nqubits;
+        let amplitudes = match initial_state {
+            State::PLUS => vec![ExactComplex::inv_sqrt2_pow(nqubits as u32); size],
+            State::ZERO => {
+                let mut amplitudes = vec![ExactComplex::ZERO; size];
+                amplitudes[0] = ExactComplex::ONE;
+                amplitudes
+            }
+        };
+        ExactStateVector { amplitudes, nqubits, tolerance: TolerancePolicy::default() }
+    }
+
+    // Same as new, operators being read with the tolerances of the config.
+    pub fn with_config(nqubits: usize, initial_state: State, config: &SimulationConfig) -> Self {
+        ExactStateVector { tolerance: config.tolerance, ..ExactStateVector::new(nqubits, initial_state) }
+    }
+
+    pub fn from_statevector(psi: &StateVector, tol: &TolerancePolicy) -> Result<Self, SimulatorError> {
+        let amplitudes = psi.data.iter()
+            .map(|&c| ExactComplex::from_complex(c, tol).ok_or_else(|| {
+                SimulatorError::InvalidArgument(format!("Amplitude {} is not in Z[w, 1/sqrt(2)].", c))
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ExactStateVector { amplitudes, nqubits: psi.nqubits, tolerance: *tol })
+    }
+
+    pub fn to_statevector(&self) -> StateVector {
+        StateVector { data: self.amplitudes.iter().map(ExactComplex::to_complex).collect(), nqubits: self.nqubits }
+    }
+
+    // Whether other is self times w^j for some j, the only global phases of states reached
+    // from |0> or |+> by gates with entries in the ring and exact measurements.
+    pub fn equals_up_to_phase(&self, other: &ExactStateVector) -> bool {
+        (0..8).any(|j| {
+            let phase = ExactComplex::omega(j);
+            self.amplitudes.len() == other.amplitudes.len()
+                && self.amplitudes.iter().zip(&other.amplitudes).all(|(a, b)| *a * phase == *b)
+        })
+    }
+
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        let matrix = exact_matrix(op, &self.tolerance)?;
+        let offsets = target_offsets(indices, self.nqubits);
+        let mask = offsets.iter().fold(0, |mask, offset| mask | offset);
+        let dim = offsets.len();
+        for base in (0..self.amplitudes.len()).filter(|i| i & mask == 0) {
+            let local = offsets.iter().map(|offset| self.amplitudes[base | offset]).collect::<Vec<_>>();
+            for (row, offset) in offsets.iter().enumerate() {
+                self.amplitudes[base | offset] = (0..dim)
+                    .fold(ExactComplex::ZERO, |sum, col| sum + matrix[row * dim + col] * local[col]);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        if op.nqubits != 1 {
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
+        }
+        self.evolve(op, &[index])
+    }
+
+    // Exact probability of reading outcome 1 on the qubit.
+    pub fn probability_one(&self, index: usize) -> Result<ExactComplex, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let bit = 1 << (self.nqubits - 1 - index);
+        Ok(self.amplitudes.iter().enumerate()
+            .filter(|(i, _)| i & bit != 0)
+            .fold(ExactComplex::ZERO, |sum, (_, a)| sum + a.norm_sqr()))
+    }
+
+    // Collapse onto the outcome, which must have probability 1 / 2^m.
+    pub fn project(&mut self, index: usize, outcome: u8) -> Result<(), SimulatorError> {
+        let p1 = self.probability_one(index)?;
+        let p = if outcome == 1 { p1 } else { ExactComplex::ONE - p1 };
+        if p.coeffs != [1, 0, 0, 0] || p.k % 2 != 0 {
+            return Err(SimulatorError::InvalidArgument(format!(
+                "Outcome {} of qubit {} has probability {}, which is not a power of 1/2.", outcome, index, p.to_complex().re)));
+        }
+        let bit = 1 << (self.nqubits - 1 - index);
+        for (i, a) in self.amplitudes.iter_mut().enumerate() {
+            *a = if u8::from(i & bit != 0) == outcome { a.mul_sqrt2_pow(p.k / 2) } else { ExactComplex::ZERO };
+        }
+        Ok(())
+    }
+
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        let p1 = self.probability_one(index)?.to_complex().re;
+        let outcome = u8::from(rng.gen::<f64>() < p1);
+        self.project(index, outcome)?;
+        Ok(outcome)
+    }
+
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        let bit = 1 << (self.nqubits - 1 - index);
+        self.amplitudes = self.amplitudes.iter().enumerate()
+            .filter(|(i, _)| u8::from(i & bit != 0) == outcome)
+            .map(|(_, a)| *a)
+            .collect();
+        self.nqubits -= 1;
+        Ok(outcome)
+    }
+
+    // Exact <psi|P|psi> for a Pauli string P covering every qubit.
+    pub fn expectation_exact(&self, pauli_string: &PauliString) -> Result<ExactComplex, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: pauli_string.nqubits() });
+        }
+        let x_mask = pauli_string.x_mask();
+        Ok(self.amplitudes.iter().enumerate().fold(ExactComplex::ZERO, |sum, (i, a)| {
+            // Pauli phases are powers of i, so they are always read exactly.
+            let phase = ExactComplex::from_complex(pauli_string.phase(i), &self.tolerance).unwrap();
+            sum + self.amplitudes[i ^ x_mask].conj() * phase * *a
+        }))
+    }
+
+    pub fn tensor(&mut self, other: &ExactStateVector) {
+        self.amplitudes = self.amplitudes.iter()
+            .flat_map(|a| other.amplitudes.iter().map(move |b| *a * *b))
+            .collect();
+        self.nqubits += other.nqubits;
+    }
+
+    pub fn add_qubit(&mut self, state: State) {
+        self.tensor(&ExactStateVector::new(1, state));
+    }
+}
+
+impl QuantumBackend for ExactStateVector {
+    fn nqubits(&self) -> usize {
+        self.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        ExactStateVector::evolve_single(self, op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        ExactStateVector::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        ExactStateVector::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        Ok(self.expectation_exact(pauli_string)?.to_complex().re)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        ExactStateVector::tensor(self, other)
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        ExactStateVector::add_qubit(self, state)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        ExactStateVector::measure_and_remove(self, index, rng)
+    }
+}
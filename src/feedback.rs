@@ -0,0 +1,197 @@
+use core::fmt;
+use std::collections::{BTreeSet, HashMap};
+
+// Deepest nesting of parentheses and negations accepted by Condition::parse, which recurses once
+// per level. Chains of one operator are a single level whatever their length.
+const MAX_DEPTH: usize = 256;
+
+// Boolean conditions over measurement outcomes deciding whether a correction is applied, e.g.
+// "s[3] ^ s[7] & !s[1]" where s[i] is the outcome of node i. Precedence follows Rust and C:
+// ! binds tighter than &, then ^, then |. Plain XOR domains are the special case
+// s[a] ^ s[b] ^ ... Binary operators hold the terms of a whole chain, so that long domains are
+// neither nested nor walked recursively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    Constant(bool),
+    Outcome(usize),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Xor(Vec<Condition>),
+    Or(Vec<Condition>)
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0, depth: 0 };
+        let condition = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error());
+        }
+        Ok(condition)
+    }
+
+    // XOR of the outcomes of a domain, false for an empty one.
+    pub fn parity(domain: &[usize]) -> Condition {
+        match domain {
+            [] => Condition::Constant(false),
+            [node] => Condition::Outcome(*node),
+            _ => Condition::Xor(domain.iter().map(|&node| Condition::Outcome(node)).collect())
+        }
+    }
+
+    // Nodes whose outcomes the condition depends on.
+    pub fn nodes(&self) -> BTreeSet<usize> {
+        let mut nodes = BTreeSet::new();
+        self.collect_nodes(&mut nodes);
+        nodes
+    }
+
+    fn collect_nodes(&self, nodes: &mut BTreeSet<usize>) {
+        match self {
+            Condition::Constant(_) => {},
+            Condition::Outcome(node) => { nodes.insert(*node); },
+            Condition::Not(a) => a.collect_nodes(nodes),
+            Condition::And(terms) | Condition::Xor(terms) | Condition::Or(terms) => {
+                terms.iter().for_each(|term| term.collect_nodes(nodes));
+            }
+        }
+    }
+
+    pub fn evaluate(&self, outcomes: &HashMap<usize, u8>) -> Result<bool, String> {
+        Ok(match self {
+            Condition::Constant(value) => *value,
+            Condition::Outcome(node) => *outcomes.get(node)
+                .ok_or_else(|| format!("Node {} is used in a condition before being measured.", node))? == 1,
+            Condition::Not(a) => !a.evaluate(outcomes)?,
+            Condition::And(terms) => Condition::fold(terms, true, |a, b| a & b, outcomes)?,
+            Condition::Xor(terms) => Condition::fold(terms, false, |a, b| a ^ b, outcomes)?,
+            Condition::Or(terms) => Condition::fold(terms, false, |a, b| a | b, outcomes)?
+        })
+    }
+
+    // Every term is evaluated, so that unmeasured nodes are reported whatever the other outcomes.
+    fn fold(terms: &[Condition], init: bool, op: fn(bool, bool) -> bool, outcomes: &HashMap<usize, u8>) -> Result<bool, String> {
+        terms.iter().try_fold(init, |acc, term| Ok(op(acc, term.evaluate(outcomes)?)))
+    }
+}
+
+impl fmt::Display for Condition {
+    // Every chain in parentheses, which parses back to the same condition.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chain = |f: &mut fmt::Formatter<'_>, terms: &[Condition], op: &str| {
+            write!(f, "(")?;
+            for (i, term) in terms.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                write!(f, "{}", term)?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Condition::Constant(value) => write!(f, "{}", u8::from(*value)),
+            Condition::Outcome(node) => write!(f, "s[{}]", node),
+            Condition::Not(a) => write!(f, "!{}", a),
+            Condition::And(terms) => chain(f, terms, "&"),
+            Condition::Xor(terms) => chain(f, terms, "^"),
+            Condition::Or(terms) => chain(f, terms, "|")
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    depth: usize
+}
+
+impl Parser<'_> {
+    fn error(&self) -> String {
+        format!("Invalid condition {:?} at position {}.", String::from_utf8_lossy(self.text), self.pos)
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).copied()
+    }
+
+    fn binary(&mut self, op: u8, operand: fn(&mut Self) -> Result<Condition, String>, node: fn(Vec<Condition>) -> Condition) -> Result<Condition, String> {
+        let mut terms = vec![operand(self)?];
+        while self.peek() == Some(op) {
+            self.pos += 1;
+            terms.push(operand(self)?);
+        }
+        Ok(if terms.len() == 1 { terms.pop().unwrap() } else { node(terms) })
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        self.binary(b'|', Self::xor, Condition::Or)
+    }
+
+    fn xor(&mut self) -> Result<Condition, String> {
+        self.binary(b'^', Self::and, Condition::Xor)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        self.binary(b'&', Self::unary, Condition::And)
+    }
+
+    // Parse a nested operand, refusing inputs deep enough to overflow the stack.
+    fn nested(&mut self, operand: fn(&mut Self) -> Result<Condition, String>) -> Result<Condition, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("Condition is nested more than {} levels deep at position {}.", MAX_DEPTH, self.pos));
+        }
+        self.depth += 1;
+        let condition = operand(self);
+        self.depth -= 1;
+        condition
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        match self.peek() {
+            Some(b'!') => {
+                self.pos += 1;
+                Ok(Condition::Not(Box::new(self.nested(Self::unary)?)))
+            },
+            Some(b'(') => {
+                self.pos += 1;
+                let condition = self.nested(Self::or)?;
+                if self.peek() != Some(b')') {
+                    return Err(self.error());
+                }
+                self.pos += 1;
+                Ok(condition)
+            },
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(Condition::Constant(false))
+            },
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(Condition::Constant(true))
+            },
+            Some(b's') => {
+                self.pos += 1;
+                if self.peek() != Some(b'[') {
+                    return Err(self.error());
+                }
+                self.pos += 1;
+                self.peek();
+                let start = self.pos;
+                while self.pos < self.text.len() && self.text[self.pos].is_ascii_digit() {
+                    self.pos += 1;
+                }
+                let node = std::str::from_utf8(&self.text[start..self.pos]).unwrap().parse().map_err(|_| self.error())?;
+                if self.peek() != Some(b']') {
+                    return Err(self.error());
+                }
+                self.pos += 1;
+                Ok(Condition::Outcome(node))
+            },
+            _ => Err(self.error())
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::channels::{self, Channel};
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+use crate::noise::{CommandKind, NoiseModel};
+use crate::pauli::{Pauli, PauliString};
+use crate::tensor::Tensor;
+use crate::tomography;
+
+// Least squares fit of a single qubit noise model to process tomography data given as a Pauli
+// transfer matrix, ordered I, X, Y, Z as in `Channel::ptm`. The model applies amplitude damping,
+// then phase damping, then depolarizing noise, whose transfer matrix is
+//
+//     1                0                0                0
+//     0                (1 - p) a        0                0
+//     0                0                (1 - p) a        0
+//     (1 - p) gamma    0                0                (1 - p) (1 - gamma)
+//
+// with a = sqrt((1 - gamma) (1 - lambda)).
+
+const MAX_ITERATIONS: usize = 200;
+const STEP_TOLERANCE: f64 = 1e-14;
+const FINITE_DIFFERENCE: f64 = 1e-7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseFit {
+    pub depolarizing: f64,  // p
+    pub damping: f64,       // gamma
+    pub dephasing: f64,     // lambda
+    pub residual: f64       // Root mean square deviation from the data over the 16 entries.
+}
+
+fn model_ptm(params: &[f64; 3]) -> Vec<f64> {
+    let [p, gamma, lambda] = *params;
+    let shrink = 1. - p;
+    let transverse = shrink * ((1. - gamma) * (1. - lambda)).sqrt();
+    vec![
+        1., 0., 0., 0.,
+        0., transverse, 0., 0.,
+        0., 0., transverse, 0.,
+        shrink * gamma, 0., 0., shrink * (1. - gamma)
+    ]
+}
+
+fn residuals(params: &[f64; 3], data: &[f64]) -> Vec<f64> {
+    model_ptm(params).iter().zip(data).map(|(m, d)| m - d).collect()
+}
+
+fn cost(params: &[f64; 3], data: &[f64]) -> f64 {
+    residuals(params, data).iter().map(|r| r * r).sum()
+}
+
+// Solve the 3 x 3 system a x = b by Gaussian elimination with partial pivoting.
+fn solve3(mut a: [[f64; 3]; 3], mut b: [f64; 3]) -> Option<[f64; 3]> {
+    for col in 0..3 {
+        let pivot = (col..3).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..3 {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            a[row].iter_mut().zip(pivot_row).skip(col).for_each(|(x, p)| *x -= factor * p);
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.; 3];
+    for row in (0..3).rev() {
+        x[row] = (b[row] - (row + 1..3).map(|k| a[row][k] * x[k]).sum::<f64>()) / a[row][row];
+    }
+    Some(x)
+}
+
+// Levenberg-Marquardt iterations from the closed form estimate read off the Z row and the
+// transverse diagonal, each parameter kept in [0, 1].
+pub fn fit_noise(ptm: &[f64]) -> Result<NoiseFit, String> {
+    if ptm.len() != 16 {
+        return Err(format!("Pauli transfer matrix of one qubit should have 16 entries, got {}.", ptm.len()));
+    }
+    if ptm.iter().any(|x| !x.is_finite()) {
+        return Err("Pauli transfer matrix has non finite entries.".to_string());
+    }
+    let shrink = (ptm[12] + ptm[15]).clamp(0., 1.);
+    let gamma = if shrink > 0. { (ptm[12] / shrink).clamp(0., 1.) } else { 0. };
+    let transverse = (ptm[5] + ptm[10]) / 2.;
+    let lambda = if shrink > 0. && gamma < 1. {
+        (1. - (transverse / shrink).powi(2) / (1. - gamma)).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let mut params = [1. - shrink, gamma, lambda];
+    let mut current = cost(&params, ptm);
+    let mut mu = 1e-3;
+    for _ in 0..MAX_ITERATIONS {
+        let r = residuals(&params, ptm);
+        let mut jacobian = [[0.; 3]; 16];
+        for k in 0..3 {
+            // One-sided differences pointing inside [0, 1].
+            let h = if params[k] + FINITE_DIFFERENCE <= 1. { FINITE_DIFFERENCE } else { -FINITE_DIFFERENCE };
+            let mut shifted = params;
+            shifted[k] += h;
+            residuals(&shifted, ptm).iter().zip(&r).enumerate().for_each(|(i, (s, r))| jacobian[i][k] = (s - r) / h);
+        }
+        let mut normal = [[0.; 3]; 3];
+        let mut gradient = [0.; 3];
+        for (row, r) in jacobian.iter().zip(&r) {
+            for a in 0..3 {
+                gradient[a] -= row[a] * r;
+                for b in 0..3 {
+                    normal[a][b] += row[a] * row[b];
+                }
+            }
+        }
+        (0..3).for_each(|k| normal[k][k] *= 1. + mu);
+        let Some(step) = solve3(normal, gradient) else { break };
+        let mut candidate = params;
+        (0..3).for_each(|k| candidate[k] = (params[k] + step[k]).clamp(0., 1.));
+        let moved = (0..3).map(|k| (candidate[k] - params[k]).abs()).fold(0., f64::max);
+        let candidate_cost = cost(&candidate, ptm);
+        if candidate_cost <= current {
+            params = candidate;
+            current = candidate_cost;
+            mu /= 10.;
+            if moved < STEP_TOLERANCE {
+                break;
+            }
+        } else {
+            mu *= 10.;
+            if mu > 1e12 {
+                break;
+            }
+        }
+    }
+    let [depolarizing, damping, dephasing] = params;
+    Ok(NoiseFit { depolarizing, damping, dephasing, residual: (current / 16.).sqrt() })
+}
+
+impl NoiseFit {
+    pub fn ptm(&self) -> Vec<f64> {
+        model_ptm(&[self.depolarizing, self.damping, self.dephasing])
+    }
+
+    pub fn channel(&self) -> Result<Channel, String> {
+        Channel::new(channels::amplitude_damping(self.damping)?)?
+            .compose(&Channel::new(channels::phase_damping(self.dephasing)?)?)?
+            .compose(&Channel::new(channels::depolarizing(self.depolarizing)?)?)
+    }
+
+    // Noise model applying the fitted channel after every single qubit command and to both
+    // qubits of every edge.
+    pub fn noise_model(&self) -> Result<NoiseModel, String> {
+        let channel = self.channel()?;
+        let mut model = NoiseModel::default();
+        for kind in [CommandKind::N, CommandKind::M, CommandKind::X, CommandKind::Z, CommandKind::C] {
+            model.channels.insert(kind, channel.clone());
+        }
+        model.channels.insert(CommandKind::E, channel.tensor(&channel));
+        Ok(model)
+    }
+}
+
+// Simulated process tomography of a single qubit channel: the outputs of the inputs |0>, |1>, |+>
+// and |+i> of `tomography::tomography_inputs` are each measured `shots` times in the X, Y and Z
+// bases, rebuilt from the estimated Bloch vectors and inverted by `tomography::reconstruct_choi`.
+pub fn simulate_tomography(channel: &Channel, shots: usize, tol: &TolerancePolicy, rng: &mut dyn RngCore) -> Result<Vec<f64>, String> {
+    if channel.nqubits != 1 {
+        return Err(format!("Process tomography is implemented for one qubit channels, got {} qubits.", channel.nqubits));
+    }
+    if shots == 0 {
+        return Err("At least one shot is needed.".to_string());
+    }
+    let inputs = tomography::tomography_inputs(1);
+    let outputs = inputs.iter().map(|input| {
+        let mut exact = input.clone();
+        exact.apply_channel(&channel.kraus, &[0])?;
+        let mut bloch = [0.; 3];
+        for (pauli, value) in [Pauli::X, Pauli::Y, Pauli::Z].into_iter().zip(bloch.iter_mut()) {
+            let expectation = exact.expectation(&PauliString::new(vec![pauli]))?.clamp(-1., 1.);
+            let ups = (0..shots).filter(|_| rng.gen::<f64>() < (1. + expectation) / 2.).count();
+            *value = (2. * ups as f64 - shots as f64) / shots as f64;
+        }
+        // (I + x X + y Y + z Z) / 2, which sampling may leave slightly outside the Bloch ball.
+        let [x, y, z] = bloch;
+        let data = vec![
+            Complex::new((1. + z) / 2., 0.), Complex::new(x / 2., -y / 2.),
+            Complex::new(x / 2., y / 2.), Complex::new((1. - z) / 2., 0.)
+        ];
+        Ok(DensityMatrix { data: Tensor::from_vec(data, vec![2, 2]), size: 2, nqubits: 1 })
+    }).collect::<Result<Vec<_>, String>>()?;
+    channels::ptm_from_choi(&tomography::reconstruct_choi(&inputs, &outputs, tol)?, 1)
+}
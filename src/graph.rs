@@ -0,0 +1,61 @@
+use num_complex::Complex;
+
+use crate::density_matrix::DensityMatrix;
+use crate::statevector::StateVector;
+
+// Graph state |G> = prod_{(i, j) in E} CZ_ij |+>^n.
+// Every amplitude of |G> is (-1)^{number of edges with both ends set} / sqrt(2^n), so the state
+// is written directly instead of applying the CZ gates one by one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphState {
+    pub nqubits: usize,
+    pub edges: Vec<(usize, usize)>
+}
+
+impl GraphState {
+    pub fn new(nqubits: usize, edges: &[(usize, usize)]) -> Result<Self, String> {
+        for &(a, b) in edges {
+            if a >= nqubits || b >= nqubits {
+                return Err(format!("Edge ({}, {}) is not in the range [0-{}].", a, b, nqubits));
+            }
+            if a == b {
+                return Err(format!("Self loop on qubit {} is not allowed.", a));
+            }
+        }
+        Ok(GraphState { nqubits, edges: edges.to_vec() })
+    }
+
+    pub fn neighbors(&self, node: usize) -> Vec<usize> {
+        self.edges.iter()
+            .filter_map(|&(a, b)| if a == node { Some(b) } else if b == node { Some(a) } else { None })
+            .collect()
+    }
+
+    // Sign of the amplitude of the basis state |index>, qubit 0 being the most significant bit.
+    fn sign(&self, index: usize) -> f64 {
+        let bit = |q: usize| (index >> (self.nqubits - 1 - q)) & 1;
+        let parity = self.edges.iter().fold(0, |p, &(a, b)| p ^ (bit(a) & bit(b)));
+        if parity == 0 { 1. } else { -1. }
+    }
+
+    pub fn amplitudes(&self) -> Vec<Complex<f64>> {
+        let norm = ((1 << self.nqubits) as f64).sqrt();
+        (0..1 << self.nqubits)
+            .map(|i| Complex::new(self.sign(i) / norm, 0.))
+            .collect()
+    }
+
+    pub fn to_statevector(&self) -> StateVector {
+        StateVector { data: self.amplitudes(), nqubits: self.nqubits }
+    }
+
+    pub fn to_density_matrix(&self) -> DensityMatrix {
+        DensityMatrix::from_statevec(&self.amplitudes()).unwrap()
+    }
+}
+
+impl DensityMatrix {
+    pub fn from_graph(edges: &[(usize, usize)], n: usize) -> Result<Self, String> {
+        Ok(GraphState::new(n, edges)?.to_density_matrix())
+    }
+}
@@ -0,0 +1,93 @@
+use num_complex::Complex;
+
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+use crate::linalg;
+use crate::tensor::Tensor;
+
+// Isometry V from input_qubits to output_qubits (V^dagger V = I), e.g. the encoding map of a code.
+// data is the row-major 2^output_qubits x 2^input_qubits matrix.
+#[derive(Debug, Clone)]
+pub struct Isometry {
+    pub input_qubits: usize,
+    pub output_qubits: usize,
+    pub data: Vec<Complex<f64>>
+}
+
+impl Isometry {
+    pub fn new(data: Vec<Complex<f64>>, input_qubits: usize, output_qubits: usize, tol: &TolerancePolicy) -> Result<Self, String> {
+        if input_qubits > output_qubits {
+            return Err(format!("An isometry cannot map {} qubits to {} qubits.", input_qubits, output_qubits));
+        }
+        if data.len() != 1 << (input_qubits + output_qubits) {
+            return Err(format!("Expected a {}x{} matrix but got {} elements.", 1 << output_qubits, 1 << input_qubits, data.len()));
+        }
+        let isometry = Isometry { input_qubits, output_qubits, data };
+        let (rows, cols) = (isometry.rows(), isometry.cols());
+        let gram = linalg::matmul_rect(&linalg::adjoint_rect(&isometry.data, rows, cols), &isometry.data, cols, rows, cols);
+        if linalg::max_abs_diff(&gram, &linalg::identity(cols)) > tol.unitarity {
+            return Err("Matrix is not an isometry, V^dagger V != I.".to_string());
+        }
+        Ok(isometry)
+    }
+
+    // Build V from its columns, the images of the input basis states (e.g. the codewords |0_L>, |1_L>).
+    pub fn from_codewords(codewords: &[Vec<Complex<f64>>], tol: &TolerancePolicy) -> Result<Self, String> {
+        if !codewords.len().is_power_of_two() {
+            return Err("The number of codewords should be a power of two.".to_string());
+        }
+        let rows = codewords[0].len();
+        if !rows.is_power_of_two() || codewords.iter().any(|c| c.len() != rows) {
+            return Err("Codewords should all have the same size, a power of two.".to_string());
+        }
+        let cols = codewords.len();
+        let data = (0..rows * cols).map(|idx| codewords[idx % cols][idx / cols]).collect();
+        Isometry::new(data, cols.ilog2() as usize, rows.ilog2() as usize, tol)
+    }
+
+    pub fn rows(&self) -> usize {
+        1 << self.output_qubits
+    }
+
+    pub fn cols(&self) -> usize {
+        1 << self.input_qubits
+    }
+
+    // Projector V V^dagger onto the image of V, i.e. the code subspace.
+    pub fn projector(&self) -> Vec<Complex<f64>> {
+        let (rows, cols) = (self.rows(), self.cols());
+        linalg::matmul_rect(&self.data, &linalg::adjoint_rect(&self.data, rows, cols), rows, cols, rows)
+    }
+}
+
+impl DensityMatrix {
+    // Encoded state V rho V^dagger on the output qubits of the isometry.
+    pub fn encode(&self, isometry: &Isometry) -> Result<DensityMatrix, String> {
+        if isometry.input_qubits != self.nqubits {
+            return Err(format!("Isometry takes {} qubits but the state has {}.", isometry.input_qubits, self.nqubits));
+        }
+        let (rows, cols) = (isometry.rows(), isometry.cols());
+        let v_rho = linalg::matmul_rect(&isometry.data, &self.data.data, rows, cols, cols);
+        let data = linalg::matmul_rect(&v_rho, &linalg::adjoint_rect(&isometry.data, rows, cols), rows, cols, rows);
+        Ok(DensityMatrix {
+            data: Tensor::from_vec(data, vec![2; 2 * isometry.output_qubits]),
+            size: rows,
+            nqubits: isometry.output_qubits
+        })
+    }
+
+    // Project onto the image of the isometry, rho -> P rho P / Tr(P rho), and return Tr(P rho).
+    pub fn project(&mut self, isometry: &Isometry, tol: &TolerancePolicy) -> Result<f64, String> {
+        if isometry.output_qubits != self.nqubits {
+            return Err(format!("Isometry maps onto {} qubits but the state has {}.", isometry.output_qubits, self.nqubits));
+        }
+        let projector = isometry.projector();
+        let projected = linalg::matmul(&linalg::matmul(&projector, &self.data.data, self.size), &projector, self.size);
+        let probability = (0..self.size).map(|i| projected[i * self.size + i].re).sum::<f64>();
+        if probability < tol.probability {
+            return Err("The state has no support on the subspace.".to_string());
+        }
+        self.data.data = projected.iter().map(|c| c / probability).collect();
+        Ok(probability)
+    }
+}
@@ -0,0 +1,141 @@
+use num_complex::Complex;
+
+use crate::density_matrix::DensityMatrix;
+use crate::operators::Operator;
+use crate::statevector::StateVector;
+
+// LaTeX export of small operators and states, as a pmatrix of rounded entries or, in Dirac form,
+// as a sum of |i><j| (or |i> for state vectors) over the entries that do not round to zero. Basis
+// labels are bit strings with qubit 0 first.
+
+#[derive(Debug, Clone)]
+pub struct LatexOptions {
+    pub digits: usize,      // Decimal places kept, trailing zeros being dropped.
+    pub dirac: bool,
+    pub max_qubits: usize   // Larger objects are refused, their matrices being unreadable anyway.
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        LatexOptions { digits: 3, dirac: false, max_qubits: 4 }
+    }
+}
+
+fn number(x: f64, digits: usize) -> String {
+    let text = format!("{:.*}", digits, x);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+// Rounded entry, "0" when both parts round to zero.
+fn entry(c: Complex<f64>, digits: usize) -> String {
+    let (re, im) = (number(c.re, digits), number(c.im, digits));
+    match (re.as_str(), im.as_str()) {
+        (_, "0") => re,
+        ("0", "1") => "i".to_string(),
+        ("0", "-1") => "-i".to_string(),
+        ("0", _) => format!("{}i", im),
+        (_, _) => match im.strip_prefix('-') {
+            Some(abs) => format!("{} - {}i", re, if abs == "1" { "" } else { abs }),
+            None => format!("{} + {}i", re, if im == "1" { "" } else { &im })
+        }
+    }
+}
+
+fn check_size(nqubits: usize, options: &LatexOptions) -> Result<(), String> {
+    if nqubits > options.max_qubits {
+        return Err(format!("{} qubits is more than the {} allowed for LaTeX export.", nqubits, options.max_qubits));
+    }
+    Ok(())
+}
+
+fn pmatrix(data: &[Complex<f64>], cols: usize, digits: usize) -> String {
+    let rows = data.chunks(cols)
+        .map(|row| row.iter().map(|&c| entry(c, digits)).collect::<Vec<_>>().join(" & "))
+        .collect::<Vec<_>>();
+    format!("\\begin{{pmatrix}}\n{}\n\\end{{pmatrix}}", rows.join(" \\\\\n"))
+}
+
+fn label(index: usize, nqubits: usize) -> String {
+    (0..nqubits).map(|q| if (index >> (nqubits - 1 - q)) & 1 == 1 { '1' } else { '0' }).collect()
+}
+
+// Sum of coefficient times basis element, the coefficients 1 and -1 being left implicit and the
+// ones with both a real and an imaginary part being parenthesized.
+fn dirac(terms: Vec<(Complex<f64>, String)>, digits: usize) -> String {
+    let mut sum = String::new();
+    for (c, basis) in terms {
+        let coefficient = entry(c, digits);
+        if coefficient == "0" {
+            continue;
+        }
+        let (negative, magnitude) = match coefficient.strip_prefix('-') {
+            Some(rest) if !rest.contains(' ') => (true, rest.to_string()),
+            _ => (false, coefficient)
+        };
+        let magnitude = match magnitude.as_str() {
+            "1" => String::new(),
+            m if m.contains(' ') => format!("({})", m),
+            m => m.to_string()
+        };
+        sum.push_str(match (sum.is_empty(), negative) {
+            (true, true) => "-",
+            (true, false) => "",
+            (false, true) => " - ",
+            (false, false) => " + "
+        });
+        sum.push_str(&magnitude);
+        sum.push_str(&basis);
+    }
+    if sum.is_empty() { "0".to_string() } else { sum }
+}
+
+fn matrix_latex(data: &[Complex<f64>], nqubits: usize, options: &LatexOptions) -> Result<String, String> {
+    check_size(nqubits, options)?;
+    let size = 1 << nqubits;
+    if !options.dirac {
+        return Ok(pmatrix(data, size, options.digits));
+    }
+    let terms = data.iter().enumerate()
+        .map(|(idx, &c)| (c, format!("|{}\\rangle\\langle {}|", label(idx / size, nqubits), label(idx % size, nqubits))))
+        .collect();
+    Ok(dirac(terms, options.digits))
+}
+
+impl Operator {
+    pub fn to_latex(&self) -> Result<String, String> {
+        self.to_latex_with(&LatexOptions::default())
+    }
+
+    pub fn to_latex_with(&self, options: &LatexOptions) -> Result<String, String> {
+        matrix_latex(&self.data.data, self.nqubits, options)
+    }
+}
+
+impl DensityMatrix {
+    pub fn to_latex(&self) -> Result<String, String> {
+        self.to_latex_with(&LatexOptions::default())
+    }
+
+    pub fn to_latex_with(&self, options: &LatexOptions) -> Result<String, String> {
+        matrix_latex(&self.data.data, self.nqubits, options)
+    }
+}
+
+impl StateVector {
+    pub fn to_latex(&self) -> Result<String, String> {
+        self.to_latex_with(&LatexOptions::default())
+    }
+
+    // Column vector, or sum of kets in Dirac form.
+    pub fn to_latex_with(&self, options: &LatexOptions) -> Result<String, String> {
+        check_size(self.nqubits, options)?;
+        if !options.dirac {
+            return Ok(pmatrix(&self.data, 1, options.digits));
+        }
+        let terms = self.data.iter().enumerate()
+            .map(|(idx, &c)| (c, format!("|{}\\rangle", label(idx, self.nqubits))))
+            .collect();
+        Ok(dirac(terms, options.digits))
+    }
+}
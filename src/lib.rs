@@ -1,7 +1,61 @@
 pub mod tensor;
+pub mod config;
+pub mod rng;
+pub mod error;
 pub mod density_matrix;
+pub mod density_matrix_f32;
 pub mod operators;
 pub mod tools;
+pub mod latex;
+pub mod channels;
+pub mod noise;
+pub mod fitting;
+pub mod tomography;
+pub mod ensemble;
+pub mod batch;
+pub mod pauli;
+pub mod linalg;
+pub mod backend;
+pub mod statevector;
+pub mod stabilizer;
+#[cfg(feature = "exact")]
+pub mod exact;
+pub mod trajectory;
+pub mod mps;
+pub mod graph;
+pub mod verification;
+pub mod css;
+pub mod open_graph;
+pub mod pattern_library;
+pub mod loss;
+pub mod isometry;
+pub mod mitigation;
+pub mod metrics;
+pub mod randomized;
+pub mod npy;
+pub mod validation;
+pub mod checkpoint;
+pub mod records;
+pub mod postselection;
+pub mod mapped;
+pub mod shards;
+pub mod pattern;
+pub mod feedback;
+pub mod resources;
+pub mod diagnostics;
+pub mod circuit;
+pub mod dag;
+pub mod state_preparation;
+pub mod multi_controlled;
+pub mod qasm;
+pub mod clifford;
+pub mod decoder;
+pub mod runner;
+pub mod preprocessing;
+pub mod audit;
+pub mod demos;
+pub mod simulator;
+pub mod prelude;
 
 use num_complex::Complex;
 use pyo3::prelude::*;
@@ -13,8 +67,8 @@ fn dm_simu_rs<'py>(
     _py: pyo3::prelude::Python<'py>,
     m: &pyo3::prelude::Bound<'py, pyo3::types::PyModule>,
 ) -> pyo3::prelude::PyResult<()> {
-    m.add("Zero", State::ZERO);
-    m.add("Plus", State::PLUS);
+    m.add("Zero", State::ZERO)?;
+    m.add("Plus", State::PLUS)?;
 
     type PyVec<'py> = Bound<'py, pyo3::types::PyCapsule>;
 
@@ -34,15 +88,15 @@ fn dm_simu_rs<'py>(
         pyo3::types::PyCapsule::new_bound(py, op, Some(capsule_name))
     }
 
-    fn get_op_ref<'py>(op: PyVec<'py>) -> &Operator {
+    fn get_op_ref<'py>(op: PyVec<'py>) -> &'py Operator {
         unsafe { op.reference::<Operator>() }
     }
 
-    fn get_dm_ref<'py>(dm: PyVec<'py>) -> &DensityMatrix {
+    fn get_dm_ref<'py>(dm: PyVec<'py>) -> &'py DensityMatrix {
         unsafe { dm.reference::<DensityMatrix>() }
     }
 
-    fn get_dm_mut_ref<'py>(dm: PyVec<'py>) -> &mut DensityMatrix {
+    fn get_dm_mut_ref<'py>(dm: PyVec<'py>) -> &'py mut DensityMatrix {
         unsafe { &mut *dm.pointer().cast() }
     }
 
@@ -63,8 +117,7 @@ fn dm_simu_rs<'py>(
     ) -> pyo3::prelude::PyResult<PyVec<'py>> {
         make_dm_pyvec(
             py,
-            DensityMatrix::from_statevec(&vec.as_slice()?)
-                .map_err(pyo3::exceptions::PyValueError::new_err)?,
+            DensityMatrix::from_statevec(vec.as_slice()?)?,
         )
     }
     m.add_function(pyo3::wrap_pyfunction!(new_dm_from_vec, m)?)?;
@@ -86,8 +139,7 @@ fn dm_simu_rs<'py>(
     ) -> pyo3::prelude::PyResult<PyVec<'py>> {
         make_op_pyvec(
             py,
-            Operator::new(data.as_slice()?.to_vec())
-                .map_err(pyo3::exceptions::PyValueError::new_err)?,
+            Operator::new(data.as_slice()?.to_vec())?,
         )
     }
     m.add_function(pyo3::wrap_pyfunction!(new_op, m)?)?;
@@ -110,42 +162,51 @@ fn dm_simu_rs<'py>(
     m.add_function(pyo3::wrap_pyfunction!(get_nqubits, m)?)?;
 
     #[pyo3::pyfunction]
-    fn evolve_single<'py>(py: pyo3::prelude::Python<'py>, py_dm: PyVec<'py>, py_op: PyVec<'py>, qubit: usize) -> pyo3::prelude::PyResult<()> {
+    fn evolve_single<'py>(py_dm: PyVec<'py>, py_op: PyVec<'py>, qubit: usize) -> pyo3::prelude::PyResult<()> {
         let dm = get_dm_mut_ref(py_dm);
         let op = get_op_ref(py_op);
-        Ok(dm.evolve_single(op, qubit).unwrap())
+        Ok(dm.evolve_single(op, qubit)?)
     }
     m.add_function(pyo3::wrap_pyfunction!(evolve_single, m)?)?;
 
     #[pyo3::pyfunction]
-    fn evolve<'py>(py: pyo3::prelude::Python<'py>, py_dm: PyVec<'py>, py_op: PyVec<'py>, qubits: Vec<usize>) -> pyo3::prelude::PyResult<()> {
+    fn evolve<'py>(py_dm: PyVec<'py>, py_op: PyVec<'py>, qubits: Vec<usize>) -> pyo3::prelude::PyResult<()> {
         let dm = get_dm_mut_ref(py_dm);
         let op = get_op_ref(py_op);
-        Ok(dm.evolve(op, &qubits).unwrap())
+        Ok(dm.evolve(op, &qubits)?)
     }
     m.add_function(pyo3::wrap_pyfunction!(evolve, m)?)?;
 
     #[pyo3::pyfunction]
     fn entangle<'py>(py_vec: PyVec<'py>, qubits: (usize, usize)) -> pyo3::prelude::PyResult<()> {
         let dm = get_dm_mut_ref(py_vec);
-        Ok(dm.entangle(&qubits))
+        Ok(dm.entangle(&qubits)?)
     }
     m.add_function(pyo3::wrap_pyfunction!(entangle, m)?)?;
 
     #[pyo3::pyfunction]
     fn swap<'py>(py_vec: PyVec<'py>, qubits: (usize, usize)) -> pyo3::prelude::PyResult<()> {
         let dm = get_dm_mut_ref(py_vec);
-        Ok(dm.swap(&qubits))
+        Ok(dm.swap(&qubits)?)
     }
     m.add_function(pyo3::wrap_pyfunction!(swap, m)?)?;
 
     #[pyo3::pyfunction]
     fn tensor_dm<'py>(dm: PyVec<'py>, other: PyVec<'py>) -> pyo3::prelude::PyResult<()> {
         let dm = get_dm_mut_ref(dm);
-        let other_dm = get_dm_mut_ref(other);
-        Ok(dm.tensor(other_dm))
+        let other_dm = get_dm_ref(other);
+        *dm = dm.tensor(other_dm);
+        Ok(())
     }
     m.add_function(pyo3::wrap_pyfunction!(tensor_dm, m)?)?;
 
+    #[pyo3::pyfunction]
+    fn run_demo<'py>(py: pyo3::prelude::Python<'py>, name: &str) -> pyo3::prelude::PyResult<PyVec<'py>> {
+        let result = demos::run_demo(name, &mut rand::thread_rng())
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        make_dm_pyvec(py, result.state)
+    }
+    m.add_function(pyo3::wrap_pyfunction!(run_demo, m)?)?;
+
     Ok(())
 }
@@ -0,0 +1,220 @@
+use num_complex::Complex;
+
+// Dense linear algebra on row-major n x n complex matrices stored as flat slices, and on
+// rows x cols matrices for the _rect variants and svd.
+
+const MAX_SWEEPS: usize = 100;
+
+// Mixing coefficient c of eig_normal, which diagonalizes H + c K with H and K the Hermitian and
+// anti-Hermitian parts of A (over i). Two distinct eigenvalues h + i k of A merge into one of
+// H + c K only when c = -(h_1 - h_2) / (k_1 - k_2), which would mix their eigenvectors. Gates have
+// eigenvalues built from 0, 1, 1/sqrt(2), cos and sin of rational multiples of pi, so simple
+// values such as c = 1 collide (1 and i for S). Every c has colliding pairs, but the
+// Euler-Mascheroni constant is an arbitrary number unrelated to those built from gates, so they
+// do not come up in practice.
+const NORMAL_MIXING: f64 = 0.577_215_664_901_532_9;
+
+pub fn identity(n: usize) -> Vec<Complex<f64>> {
+    let mut id = vec![Complex::ZERO; n * n];
+    for i in 0..n {
+        id[i * n + i] = Complex::ONE;
+    }
+    id
+}
+
+pub fn matmul(a: &[Complex<f64>], b: &[Complex<f64>], n: usize) -> Vec<Complex<f64>> {
+    let mut result = vec![Complex::ZERO; n * n];
+    for i in 0..n {
+        for k in 0..n {
+            let a_ik = a[i * n + k];
+            if a_ik == Complex::ZERO {
+                continue;
+            }
+            for j in 0..n {
+                result[i * n + j] += a_ik * b[k * n + j];
+            }
+        }
+    }
+    result
+}
+
+pub fn adjoint(a: &[Complex<f64>], n: usize) -> Vec<Complex<f64>> {
+    let mut result = vec![Complex::ZERO; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            result[j * n + i] = a[i * n + j].conj();
+        }
+    }
+    result
+}
+
+// Product of a rows x inner matrix with an inner x cols matrix.
+pub(crate) fn matmul_rect(a: &[Complex<f64>], b: &[Complex<f64>], rows: usize, inner: usize, cols: usize) -> Vec<Complex<f64>> {
+    let mut result = vec![Complex::ZERO; rows * cols];
+    for i in 0..rows {
+        for k in 0..inner {
+            let a_ik = a[i * inner + k];
+            if a_ik == Complex::ZERO {
+                continue;
+            }
+            for j in 0..cols {
+                result[i * cols + j] += a_ik * b[k * cols + j];
+            }
+        }
+    }
+    result
+}
+
+pub(crate) fn adjoint_rect(a: &[Complex<f64>], rows: usize, cols: usize) -> Vec<Complex<f64>> {
+    let mut result = vec![Complex::ZERO; rows * cols];
+    for i in 0..rows {
+        for j in 0..cols {
+            result[j * rows + i] = a[i * cols + j].conj();
+        }
+    }
+    result
+}
+
+// Largest absolute difference between the entries of two matrices.
+pub fn max_abs_diff(a: &[Complex<f64>], b: &[Complex<f64>]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).norm()).fold(0., f64::max)
+}
+
+// Eigendecomposition of a Hermitian matrix with the cyclic Jacobi method.
+// Returns the eigenvalues in ascending order and the matrix V whose columns are the
+// corresponding eigenvectors, so that A = V diag(eigenvalues) V^dagger.
+pub fn eigh(a: &[Complex<f64>], n: usize) -> (Vec<f64>, Vec<Complex<f64>>) {
+    let mut a = a.to_vec();
+    let mut v = identity(n);
+    let norm = a.iter().map(|x| x.norm_sqr()).sum::<f64>().sqrt();
+    let threshold = f64::EPSILON * norm.max(f64::MIN_POSITIVE);
+
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = (0..n)
+            .flat_map(|p| (0..n).filter(move |&q| q != p).map(move |q| (p, q)))
+            .map(|(p, q)| a[p * n + q].norm_sqr())
+            .sum::<f64>()
+            .sqrt();
+        if off_diagonal <= threshold {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let b = a[p * n + q];
+                if b.norm() <= threshold / n as f64 {
+                    continue;
+                }
+                // J = diag(1, e^{-i phi}) R(theta) zeroes the (p, q) element of J^dagger A J.
+                let phase = Complex::from_polar(1., -b.arg());
+                let theta = 0.5 * (2. * b.norm()).atan2(a[q * n + q].re - a[p * n + p].re);
+                let (c, s) = (theta.cos(), theta.sin());
+                let j_pp = Complex::new(c, 0.);
+                let j_pq = Complex::new(s, 0.);
+                let j_qp = -phase * s;
+                let j_qq = phase * c;
+
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = akp * j_pp + akq * j_qp;
+                    a[k * n + q] = akp * j_pq + akq * j_qq;
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = vkp * j_pp + vkq * j_qp;
+                    v[k * n + q] = vkp * j_pq + vkq * j_qq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = j_pp.conj() * apk + j_qp.conj() * aqk;
+                    a[q * n + k] = j_pq.conj() * apk + j_qq.conj() * aqk;
+                }
+            }
+        }
+    }
+
+    let mut order = (0..n).collect::<Vec<usize>>();
+    order.sort_by(|&i, &j| a[i * n + i].re.total_cmp(&a[j * n + j].re));
+    let eigenvalues = order.iter().map(|&i| a[i * n + i].re).collect();
+    let mut vectors = vec![Complex::ZERO; n * n];
+    for (new_col, &old_col) in order.iter().enumerate() {
+        for k in 0..n {
+            vectors[k * n + new_col] = v[k * n + old_col];
+        }
+    }
+    (eigenvalues, vectors)
+}
+
+// Eigendecomposition of a normal matrix (e.g. a unitary), A = V diag(eigenvalues) V^dagger.
+// The Hermitian and anti-Hermitian parts of a normal matrix commute, so a generic real
+// combination of them shares its eigenvectors with A.
+pub fn eig_normal(a: &[Complex<f64>], n: usize) -> (Vec<Complex<f64>>, Vec<Complex<f64>>) {
+    let a_dag = adjoint(a, n);
+    let mixed = a.iter().zip(a_dag.iter())
+        .map(|(x, y)| (x + y) / 2. + (x - y) / Complex::new(0., 2.) * NORMAL_MIXING)
+        .collect::<Vec<_>>();
+    let (_, v) = eigh(&mixed, n);
+    let diagonal = matmul(&adjoint(&v, n), &matmul(a, &v, n), n);
+    let eigenvalues = (0..n).map(|i| diagonal[i * n + i]).collect();
+    (eigenvalues, v)
+}
+
+// Rebuild V diag(values) V^dagger.
+pub fn from_eigen(values: &[Complex<f64>], v: &[Complex<f64>], n: usize) -> Vec<Complex<f64>> {
+    let mut scaled = v.to_vec();
+    for k in 0..n {
+        for j in 0..n {
+            scaled[k * n + j] *= values[j];
+        }
+    }
+    matmul(&scaled, &adjoint(v, n), n)
+}
+
+// Singular value decomposition of a rows x cols matrix with the one-sided Jacobi method, which
+// rotates pairs of columns of A V until they are orthogonal. Unlike the eigenvalues of A A^dagger,
+// the singular values come out with a relative accuracy close to machine precision even when they
+// span many orders of magnitude. Returns the singular values in descending order and the columns
+// of A V in the same order, column j being u_j times the j-th singular value.
+pub(crate) fn svd(a: &[Complex<f64>], rows: usize, cols: usize) -> (Vec<f64>, Vec<Complex<f64>>) {
+    let mut a = a.to_vec();
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+        for p in 0..cols {
+            for q in (p + 1)..cols {
+                let (mut alpha, mut beta, mut gamma) = (0_f64, 0_f64, Complex::<f64>::ZERO);
+                for k in 0..rows {
+                    let (akp, akq) = (a[k * cols + p], a[k * cols + q]);
+                    alpha += akp.norm_sqr();
+                    beta += akq.norm_sqr();
+                    gamma += akp.conj() * akq;
+                }
+                if gamma.norm() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == Complex::ZERO {
+                    continue;
+                }
+                rotated = true;
+                // The phase makes a_p^dagger a_q real, then a real rotation zeroes it.
+                let phase = gamma.conj() / gamma.norm();
+                let zeta = (beta - alpha) / (2. * gamma.norm());
+                let t = zeta.signum() / (zeta.abs() + (1. + zeta * zeta).sqrt());
+                let c = 1. / (1. + t * t).sqrt();
+                let s = c * t;
+                for k in 0..rows {
+                    let (akp, akq) = (a[k * cols + p], a[k * cols + q] * phase);
+                    a[k * cols + p] = akp * c - akq * s;
+                    a[k * cols + q] = akp * s + akq * c;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let norms = (0..cols).map(|j| (0..rows).map(|k| a[k * cols + j].norm_sqr()).sum::<f64>().sqrt()).collect::<Vec<_>>();
+    let mut order = (0..cols).collect::<Vec<usize>>();
+    order.sort_by(|&i, &j| norms[j].total_cmp(&norms[i]));
+    let mut columns = vec![Complex::ZERO; rows * cols];
+    for (new_col, &old_col) in order.iter().enumerate() {
+        for k in 0..rows {
+            columns[k * cols + new_col] = a[k * cols + old_col];
+        }
+    }
+    (order.iter().map(|&j| norms[j]).collect(), columns)
+}
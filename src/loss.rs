@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::graph::GraphState;
+use crate::open_graph::OpenGraph;
+use crate::pattern::{Command, Pattern, Plane};
+use crate::runner::RunResult;
+
+// Heralded qubit loss: every node of a resource state is lost with some probability before being
+// entangled, e.g. when a photon source fails, so a lost node is simply missing from the graph.
+
+#[derive(Debug, Clone, Default)]
+pub struct LossModel {
+    pub probability: f64,
+    pub node_probabilities: HashMap<usize, f64>    // Replace the default for the listed nodes.
+}
+
+impl LossModel {
+    pub fn uniform(probability: f64) -> Result<Self, String> {
+        if !(0. ..=1.).contains(&probability) {
+            return Err(format!("Loss probability should be in [0, 1], got {}.", probability));
+        }
+        Ok(LossModel { probability, node_probabilities: HashMap::new() })
+    }
+
+    pub fn probability(&self, node: usize) -> f64 {
+        self.node_probabilities.get(&node).copied().unwrap_or(self.probability)
+    }
+
+    pub fn sample(&self, nodes: &BTreeSet<usize>, rng: &mut dyn RngCore) -> BTreeSet<usize> {
+        nodes.iter().copied().filter(|&node| rng.gen::<f64>() < self.probability(node)).collect()
+    }
+}
+
+// Computation along a wire of a bipartite resource graph: the state of the input node is
+// teleported to the output node along a path whose measured nodes take the angles in order, in
+// the XY plane and in units of pi, and every other node is measured in Z, which cuts it out of
+// the graph. When nodes are lost the wire is rerouted along the shortest surviving path. Paths
+// between two nodes of a bipartite graph all have the same parity, so a longer path has an even
+// number of extra nodes, measured at angle 0 after the given angles: each pair applies H H = I.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireComputation {
+    pub nodes: BTreeSet<usize>,
+    pub edges: Vec<(usize, usize)>,
+    pub input: usize,
+    pub output: usize,
+    pub angles: Vec<f64>
+}
+
+// Outcome of the adaptation to the losses of one or several trials.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdaptationReport {
+    pub trials: usize,
+    pub successes: usize,
+    pub lost_nodes: usize,      // Over all trials.
+    pub extra_nodes: usize      // Measured nodes added to the nominal route, over the successful trials.
+}
+
+impl AdaptationReport {
+    pub fn success_rate(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.;
+        }
+        self.successes as f64 / self.trials as f64
+    }
+}
+
+// Run of a wire computation under loss: the lost nodes, and the result unless no route survived.
+pub struct LossRun<B> {
+    pub lost: BTreeSet<usize>,
+    pub result: Option<RunResult<B>>
+}
+
+impl WireComputation {
+    pub fn new(edges: &[(usize, usize)], input: usize, output: usize, angles: Vec<f64>) -> Result<Self, String> {
+        let nodes = edges.iter().flat_map(|&(a, b)| [a, b]).collect::<BTreeSet<_>>();
+        let size = nodes.last().map_or(0, |&n| n + 1);
+        if GraphState::new(size, edges)?.two_coloring().is_none() {
+            return Err("The resource graph should be bipartite.".to_string());
+        }
+        let computation = WireComputation { nodes, edges: edges.to_vec(), input, output, angles };
+        let route = computation.route(&BTreeSet::new())?;
+        let measured = route.len() - 1;
+        if measured < computation.angles.len() || !(measured - computation.angles.len()).is_multiple_of(2) {
+            return Err(format!("The shortest wire has {} measured nodes, which cannot carry {} angles.", measured, computation.angles.len()));
+        }
+        Ok(computation)
+    }
+
+    fn neighbors(&self, node: usize) -> BTreeSet<usize> {
+        self.edges.iter()
+            .filter_map(|&(a, b)| if a == node { Some(b) } else if b == node { Some(a) } else { None })
+            .collect()
+    }
+
+    // Shortest path from the input to the output avoiding the lost nodes. It is an induced path,
+    // since a chord would give a shorter one.
+    pub fn route(&self, lost: &BTreeSet<usize>) -> Result<Vec<usize>, String> {
+        if lost.contains(&self.input) || lost.contains(&self.output) {
+            return Err("The input or the output node is lost.".to_string());
+        }
+        let mut previous = BTreeMap::from([(self.input, self.input)]);
+        let mut queue = VecDeque::from([self.input]);
+        while let Some(node) = queue.pop_front() {
+            if node == self.output {
+                let mut path = vec![node];
+                while path[path.len() - 1] != self.input {
+                    path.push(previous[&path[path.len() - 1]]);
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for neighbor in self.neighbors(node) {
+                if !lost.contains(&neighbor) && !previous.contains_key(&neighbor) {
+                    previous.insert(neighbor, node);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        Err(format!("No path from node {} to node {} avoids the lost nodes.", self.input, self.output))
+    }
+
+    // Pattern on the surviving nodes, the input node being the only input. The Z measurements go
+    // first, and the outcome of each flips the path nodes next to it with Z, which is absorbed in
+    // their t domains.
+    pub fn adapt(&self, lost: &BTreeSet<usize>) -> Result<Pattern, String> {
+        let route = self.route(lost)?;
+        let path_edges = route.windows(2).map(|pair| (pair[0], pair[1])).collect::<Vec<_>>();
+        let planes = route[..route.len() - 1].iter().map(|&node| (node, Plane::XY)).collect();
+        let angles = route[..route.len() - 1].iter().enumerate()
+            .map(|(i, &node)| (node, self.angles.get(i).copied().unwrap_or(0.)))
+            .collect();
+        let wire = OpenGraph::new(&path_edges, vec![self.input], vec![self.output], planes)?.to_pattern(&angles)?;
+
+        let cut = self.nodes.iter().copied().filter(|node| !lost.contains(node) && !route.contains(node)).collect::<BTreeSet<_>>();
+        let z_domain = |node: usize| self.neighbors(node).intersection(&cut).copied().collect::<Vec<_>>();
+        let mut pattern = Pattern::new(vec![self.input]);
+        for &node in self.nodes.iter().filter(|&&node| node != self.input && !lost.contains(&node)) {
+            pattern.add(Command::N(node));
+        }
+        for &(a, b) in self.edges.iter().filter(|(a, b)| !lost.contains(a) && !lost.contains(b)) {
+            pattern.add(Command::E((a, b)));
+        }
+        for &node in &cut {
+            pattern.add(Command::M(node, Plane::YZ, 0., vec![], vec![], 0));
+        }
+        let mut output_z = z_domain(self.output);
+        for command in wire.seq() {
+            match command {
+                Command::M(node, plane, angle, s_domain, t_domain, vop) => {
+                    let mut t_domain = t_domain.clone();
+                    t_domain.extend(z_domain(*node));
+                    pattern.add(Command::M(*node, *plane, *angle, s_domain.clone(), t_domain, *vop));
+                },
+                Command::Z(node, domain) if *node == self.output => output_z.extend(domain),
+                Command::X(..) => pattern.add(command.clone()),
+                _ => {}
+            }
+        }
+        if !output_z.is_empty() {
+            pattern.add(Command::Z(self.output, output_z));
+        }
+        Ok(pattern)
+    }
+
+    // Sample losses and count the trials in which a route survives.
+    pub fn estimate_adaptation(&self, loss: &LossModel, trials: usize, rng: &mut dyn RngCore) -> AdaptationReport {
+        let nominal = self.route(&BTreeSet::new()).map_or(0, |route| route.len());
+        let mut report = AdaptationReport { trials, ..AdaptationReport::default() };
+        for _ in 0..trials {
+            let lost = loss.sample(&self.nodes, rng);
+            report.lost_nodes += lost.len();
+            if let Ok(route) = self.route(&lost) {
+                report.successes += 1;
+                report.extra_nodes += route.len() - nominal;
+            }
+        }
+        report
+    }
+
+    // Sample the losses, adapt the pattern and run it on the input state of the input node.
+    pub fn run<B: QuantumBackend>(&self, input: B, loss: &LossModel, rng: &mut dyn RngCore) -> Result<LossRun<B>, String> {
+        let lost = loss.sample(&self.nodes, rng);
+        let result = match self.adapt(&lost) {
+            Ok(pattern) => Some(pattern.simulate(input, rng)?),
+            Err(_) => None
+        };
+        Ok(LossRun { lost, result })
+    }
+}
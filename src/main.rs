@@ -1,7 +1,50 @@
-mod density_matrix;
-mod tensor;
-mod tools;
-mod operators;
+use std::fs;
+use std::process::ExitCode;
 
-fn main() {
-}
\ No newline at end of file
+use dm_simu_rs::config::TolerancePolicy;
+use dm_simu_rs::diagnostics::{self, Diagnostic, Severity};
+use dm_simu_rs::pattern::Pattern;
+
+const USAGE: &str = "Usage: mbqc resources <pattern file>\n       mbqc validate <pattern file>";
+
+fn load_pattern(path: &str) -> Result<Pattern, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}.", path, e))?;
+    Pattern::from_json(&json)
+}
+
+// JSON diagnostics, always printed on stdout, the exit code failing when any of them is an error
+// so that scripts can stop early.
+fn validate(path: &str) -> (String, ExitCode) {
+    let diagnostics = match load_pattern(path) {
+        Ok(pattern) => pattern.diagnostics(&TolerancePolicy::default()),
+        Err(e) => vec![Diagnostic { check: "parse", severity: Severity::Error, message: e }]
+    };
+    let report = diagnostics::to_json(&diagnostics) + "\n";
+    if diagnostics::has_errors(&diagnostics) {
+        (report, ExitCode::FAILURE)
+    } else {
+        (report, ExitCode::SUCCESS)
+    }
+}
+
+fn run(args: &[String]) -> Result<(String, ExitCode), String> {
+    match args {
+        [command, path] if command == "resources" => Ok((load_pattern(path)?.resources(&TolerancePolicy::default()).to_string(), ExitCode::SUCCESS)),
+        [command, path] if command == "validate" => Ok(validate(path)),
+        _ => Err(USAGE.to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok((output, code)) => {
+            print!("{}", output);
+            code
+        },
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
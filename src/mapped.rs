@@ -0,0 +1,89 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+use num_complex::Complex;
+
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+use crate::npy;
+use crate::pauli::PauliString;
+use crate::tensor::Tensor;
+
+// Read-only view of a density matrix saved as a 2^n x 2^n complex128 npy file (see npy::write_npy).
+// Elements are read from the mapped file on demand, so analyses that only need a few passes over
+// the matrix do not copy it into memory.
+pub struct MappedDensityMatrix {
+    mmap: Mmap,
+    data_start: usize,
+    pub size: usize,
+    pub nqubits: usize
+}
+
+impl MappedDensityMatrix {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let file = File::open(path.as_ref()).map_err(|e| format!("Cannot open {}: {}.", path.as_ref().display(), e))?;
+        // Safety: the map is only read, and checkpoints are not expected to be modified while analysed.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Cannot map {}: {}.", path.as_ref().display(), e))?;
+        let header = npy::parse_header(&mmap)?;
+        if header.descr != "<c16" {
+            return Err(format!("Mapped density matrices must be complex128, got {}.", header.descr));
+        }
+        let size = match header.shape[..] {
+            [rows, cols] if rows == cols && rows.is_power_of_two() => rows,
+            _ => return Err(format!("Expected a 2^n x 2^n matrix, got shape {:?}.", header.shape))
+        };
+        let len = size.checked_mul(size).and_then(|n| n.checked_mul(16)).and_then(|n| n.checked_add(header.data_start));
+        if len != Some(mmap.len()) {
+            return Err("File length does not match the npy header.".to_string());
+        }
+        Ok(MappedDensityMatrix { data_start: header.data_start, size, nqubits: size.ilog2() as usize, mmap })
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Complex<f64> {
+        let offset = self.data_start + 16 * (i * self.size + j);
+        let bytes = &self.mmap[offset..offset + 16];
+        Complex::new(
+            f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            f64::from_le_bytes(bytes[8..].try_into().unwrap()),
+        )
+    }
+
+    pub fn trace(&self) -> Complex<f64> {
+        (0..self.size).map(|i| self.get(i, i)).sum()
+    }
+
+    pub fn purity(&self) -> f64 {
+        (0..self.size * self.size)
+            .map(|k| self.get(k / self.size, k % self.size).norm_sqr())
+            .sum()
+    }
+
+    // Same as DensityMatrix::expectation, reading one element per row.
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, String> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(format!("Pauli string acts on {} qubits but the state has {}.", pauli_string.nqubits(), self.nqubits));
+        }
+        let x_mask = pauli_string.x_mask();
+        let value = (0..self.size)
+            .map(|i| self.get(i, i ^ x_mask) * pauli_string.phase(i))
+            .sum::<Complex<f64>>();
+        Ok(value.re)
+    }
+
+    // Entropies need an eigendecomposition, so they go through a full in-memory copy.
+    pub fn entropy(&self, tol: &TolerancePolicy) -> f64 {
+        self.to_density_matrix().entropy(tol)
+    }
+
+    pub fn to_density_matrix(&self) -> DensityMatrix {
+        let data = (0..self.size * self.size)
+            .map(|k| self.get(k / self.size, k % self.size))
+            .collect();
+        DensityMatrix {
+            data: Tensor::from_vec(data, vec![2; 2 * self.nqubits]),
+            size: self.size,
+            nqubits: self.nqubits
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use num_complex::Complex;
+
+use crate::config::{SimulationConfig, TolerancePolicy};
+use crate::density_matrix::{DensityMatrix, State};
+use crate::error::SimulatorError;
+use crate::linalg;
+use crate::noise::NoiseModel;
+use crate::pattern::Pattern;
+use crate::pauli::PauliString;
+use crate::simulator::{Simulator, StateAverage};
+use crate::stabilizer::Stabilizer;
+use crate::statevector::StateVector;
+
+// Distance measures between density matrices, used to compare noisy runs against ideal outputs.
+
+// Eigenvalues below the eigenvalue tolerance are rounding noise of a zero eigenvalue. Their
+// square roots would otherwise be much larger than the noise itself.
+fn clamped_sqrt(x: f64, tol: &TolerancePolicy) -> f64 {
+    if x < tol.eigenvalue { 0. } else { x.sqrt() }
+}
+
+fn check_sizes(rho: &DensityMatrix, sigma: &DensityMatrix) -> Result<(), String> {
+    if rho.nqubits != sigma.nqubits {
+        return Err(format!("Cannot compare states on {} and {} qubits.", rho.nqubits, sigma.nqubits));
+    }
+    Ok(())
+}
+
+fn difference(rho: &DensityMatrix, sigma: &DensityMatrix) -> Vec<Complex<f64>> {
+    rho.data.data.iter().zip(sigma.data.data.iter()).map(|(a, b)| a - b).collect()
+}
+
+// Uhlmann fidelity F = (Tr sqrt(sqrt(rho) sigma sqrt(rho)))^2.
+pub fn fidelity(rho: &DensityMatrix, sigma: &DensityMatrix, tol: &TolerancePolicy) -> Result<f64, String> {
+    check_sizes(rho, sigma)?;
+    let n = rho.size;
+    let sqrt_rho = rho.sqrtm(tol)?.data.data;
+    let inner = linalg::matmul(&sqrt_rho, &linalg::matmul(&sigma.data.data, &sqrt_rho, n), n);
+    let (values, _) = linalg::eigh(&inner, n);
+    Ok(values.iter().map(|&x| clamped_sqrt(x, tol)).sum::<f64>().powi(2))
+}
+
+// T = ||rho - sigma||_1 / 2, half the sum of the absolute eigenvalues of the difference.
+pub fn trace_distance(rho: &DensityMatrix, sigma: &DensityMatrix) -> Result<f64, String> {
+    check_sizes(rho, sigma)?;
+    let (values, _) = linalg::eigh(&difference(rho, sigma), rho.size);
+    Ok(values.iter().map(|x| x.abs()).sum::<f64>() / 2.)
+}
+
+// Frobenius norm of the difference, sqrt(Tr((rho - sigma)^2)).
+pub fn hilbert_schmidt_distance(rho: &DensityMatrix, sigma: &DensityMatrix) -> Result<f64, String> {
+    check_sizes(rho, sigma)?;
+    Ok(difference(rho, sigma).iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt())
+}
+
+// Expectation of an observable on the ideal and noisy outputs of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObservableDelta {
+    pub ideal: f64,
+    pub noisy: f64
+}
+
+impl ObservableDelta {
+    pub fn delta(&self) -> f64 {
+        self.noisy - self.ideal
+    }
+}
+
+#[derive(Clone)]
+pub struct IdealComparison {
+    pub ideal: DensityMatrix,
+    pub noisy: DensityMatrix,           // Output averaged over the noisy shots.
+    pub fidelity: f64,
+    pub observables: Vec<ObservableDelta>
+}
+
+// Compare the output of a pattern run with the noise model against its noiseless output, the
+// input nodes starting in the given state. The ideal run is a single shot on the stabilizer backend when the
+// pattern is Clifford and on state vectors otherwise, so the pattern should be deterministic, and
+// the noisy shots run on density matrices with the RNG configuration of the config.
+pub fn fidelity_vs_ideal(pattern: &Pattern, input: State, noise: &NoiseModel, shots: usize, observables: &[PauliString], config: &SimulationConfig) -> Result<IdealComparison, String> {
+    let n = pattern.input_nodes().len();
+    let mut rng = config.rng.rng("ideal", 0);
+    let ideal = if pattern.is_clifford(&config.tolerance) {
+        pattern.simulate_with_config(Stabilizer::with_config(n, input, config), config, &mut *rng)?.state.to_statevector()?
+    } else {
+        pattern.simulate_with_config(StateVector::with_config(n, input, config)?, config, &mut *rng)?.state
+    }.to_density_matrix();
+
+    let mut average = StateAverage::default();
+    Simulator::new(DensityMatrix::with_config(n, input, config)?)
+        .load(pattern.clone())
+        .with_noise(noise.clone())
+        .with_config(config.clone())
+        .for_each_shot(shots, |_, result| {
+            average.add(&result.state)?;
+            Ok(())
+        })?;
+    let noisy = average.average()?;
+
+    let observables = observables.iter()
+        .map(|observable| Ok(ObservableDelta { ideal: ideal.expectation(observable)?, noisy: noisy.expectation(observable)? }))
+        .collect::<Result<Vec<_>, SimulatorError>>()?;
+    Ok(IdealComparison { fidelity: fidelity(&ideal, &noisy, &config.tolerance)?, ideal, noisy, observables })
+}
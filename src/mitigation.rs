@@ -0,0 +1,172 @@
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+use crate::linalg;
+use crate::noise::{Confusion, ReadoutError};
+use crate::pauli::{Pauli, PauliString};
+use crate::tensor::Tensor;
+
+// Error mitigation post-processing of simulated states and shots.
+
+#[derive(Debug, Clone)]
+pub struct MitigatedEstimate {
+    pub value: f64,                 // Mitigated expectation value.
+    pub raw_value: f64,             // Expectation value without mitigation.
+    pub discarded_fraction: f64,    // Fraction of the shots (or probability) outside of the symmetry sector.
+}
+
+fn check_sector(sector: i8) -> Result<f64, String> {
+    match sector {
+        1 => Ok(1.),
+        -1 => Ok(-1.),
+        _ => Err(format!("Symmetry sector should be +1 or -1, got {}.", sector))
+    }
+}
+
+// Project rho onto the eigenspace of the symmetry with eigenvalue sector, P = (I + sector * S) / 2,
+// and return the renormalized state with the probability Tr(P rho) of being in the sector.
+pub fn project_symmetry(rho: &DensityMatrix, symmetry: &PauliString, sector: i8, tol: &TolerancePolicy) -> Result<(DensityMatrix, f64), String> {
+    let sign = check_sector(sector)?;
+    if symmetry.nqubits() != rho.nqubits {
+        return Err(format!("Symmetry acts on {} qubits but the state has {}.", symmetry.nqubits(), rho.nqubits));
+    }
+    let size = rho.size;
+    let projector = symmetry.matrix().iter().zip(linalg::identity(size).iter())
+        .map(|(s, i)| (i + s * sign) / 2.)
+        .collect::<Vec<_>>();
+    let projected = linalg::matmul(&linalg::matmul(&projector, &rho.data.data, size), &projector, size);
+    let probability = (0..size).map(|i| projected[i * size + i].re).sum::<f64>();
+    if probability < tol.probability {
+        return Err("The state has no support on the symmetry sector.".to_string());
+    }
+    let data = projected.iter().map(|c| c / probability).collect();
+    let projected = DensityMatrix {
+        data: Tensor::from_vec(data, vec![2; 2 * rho.nqubits]),
+        size,
+        nqubits: rho.nqubits
+    };
+    Ok((projected, probability))
+}
+
+// Exact symmetry verified expectation value Tr(P rho P O) / Tr(P rho).
+pub fn symmetry_verified_expectation(
+    rho: &DensityMatrix,
+    observable: &PauliString,
+    symmetry: &PauliString,
+    sector: i8,
+    tol: &TolerancePolicy,
+) -> Result<MitigatedEstimate, String> {
+    let (projected, probability) = project_symmetry(rho, symmetry, sector, tol)?;
+    Ok(MitigatedEstimate {
+        value: projected.expectation(observable)?,
+        raw_value: rho.expectation(observable)?,
+        discarded_fraction: 1. - probability,
+    })
+}
+
+// Sample shots measuring the observable together with the symmetry and keep only the shots
+// found in the expected sector. Both must commute to be measured jointly.
+pub fn postselect_shots(
+    rho: &DensityMatrix,
+    observable: &PauliString,
+    symmetry: &PauliString,
+    sector: i8,
+    shots: usize,
+    rng: &mut dyn RngCore,
+) -> Result<MitigatedEstimate, String> {
+    let sign = check_sector(sector)?;
+    if shots == 0 {
+        return Err("At least one shot is needed.".to_string());
+    }
+    if !observable.commutes_with(symmetry) {
+        return Err(format!("Observable {} does not commute with symmetry {}.", observable, symmetry));
+    }
+    let (phase, joint) = observable.product(symmetry)?;
+    let o = rho.expectation(observable)?;
+    let s = rho.expectation(symmetry)?;
+    let os = (phase * Complex::new(rho.expectation(&joint)?, 0.)).re;
+
+    // Joint outcome distribution p(o, s) = Tr(rho (I + o O) (I + s S)) / 4 for o, s = +/-1.
+    let outcomes = [(1., 1.), (1., -1.), (-1., 1.), (-1., -1.)];
+    let probabilities = outcomes.map(|(a, b)| ((1. + a * o + b * s + a * b * os) / 4.).max(0.));
+    let norm = probabilities.iter().sum::<f64>();
+    let (mut kept, mut total, mut raw_total) = (0usize, 0., 0.);
+    for _ in 0..shots {
+        let mut u = rng.gen::<f64>() * norm;
+        let (outcome_o, outcome_s) = *outcomes.iter().zip(probabilities.iter())
+            .find(|(_, &p)| { u -= p; u < 0. })
+            .map(|(outcome, _)| outcome)
+            .unwrap_or(&outcomes[3]);
+        raw_total += outcome_o;
+        if outcome_s == sign {
+            kept += 1;
+            total += outcome_o;
+        }
+    }
+    if kept == 0 {
+        return Err("All shots were discarded by the symmetry check.".to_string());
+    }
+    Ok(MitigatedEstimate {
+        value: total / kept as f64,
+        raw_value: raw_total / shots as f64,
+        discarded_fraction: 1. - kept as f64 / shots as f64,
+    })
+}
+
+// Symmetric readout errors, qubit q reporting the wrong bit with probability confusion[q].p01,
+// which equals confusion[q].p10.
+#[derive(Debug, Clone)]
+pub struct ReadoutModel {
+    pub confusion: Vec<Confusion>
+}
+
+impl ReadoutModel {
+    pub fn new(flip_probabilities: Vec<f64>) -> Result<Self, String> {
+        ReadoutModel::from_confusion(flip_probabilities.into_iter().map(Confusion::symmetric).collect::<Result<_, _>>()?)
+    }
+
+    // Asymmetric confusions shift expectation values on top of damping them, so they are refused.
+    pub fn from_confusion(confusion: Vec<Confusion>) -> Result<Self, String> {
+        if let Some(c) = confusion.iter().find(|c| c.p01 != c.p10) {
+            return Err(format!("Readout correction needs symmetric errors, got p01 = {} and p10 = {}.", c.p01, c.p10));
+        }
+        if let Some(c) = confusion.iter().find(|c| c.p01 >= 0.5) {
+            return Err(format!("Readout flip probability should be in [0, 0.5), got {}.", c.p01));
+        }
+        Ok(ReadoutModel { confusion })
+    }
+
+    // Model of the first nqubits qubits of a register.
+    pub fn from_readout(readout: &ReadoutError, nqubits: usize) -> Result<Self, String> {
+        ReadoutModel::from_confusion((0..nqubits).map(|q| readout.confusion(q)).collect())
+    }
+
+    // Each flip multiplies the parity of the measured qubits by -1, so the expectation value of
+    // a Pauli string is damped by prod_{q in support} (1 - 2 p_q).
+    pub fn damping(&self, pauli_string: &PauliString) -> Result<f64, String> {
+        if pauli_string.nqubits() != self.confusion.len() {
+            return Err(format!("Pauli string acts on {} qubits but the readout model has {}.", pauli_string.nqubits(), self.confusion.len()));
+        }
+        Ok(pauli_string.paulis.iter().zip(self.confusion.iter())
+            .filter(|(pauli, _)| **pauli != Pauli::I)
+            .map(|(_, c)| 1. - 2. * c.p01)
+            .product())
+    }
+
+    // Expectation value observed through the noisy readout.
+    pub fn noisy_expectation(&self, ideal: f64, pauli_string: &PauliString) -> Result<f64, String> {
+        Ok(ideal * self.damping(pauli_string)?)
+    }
+
+    // Undo the damping of a measured expectation value. The result is not clipped to [-1, 1]
+    // so that estimates stay unbiased.
+    pub fn correct_expectation(&self, measured: f64, pauli_string: &PauliString) -> Result<MitigatedEstimate, String> {
+        Ok(MitigatedEstimate {
+            value: measured / self.damping(pauli_string)?,
+            raw_value: measured,
+            discarded_fraction: 0.,
+        })
+    }
+}
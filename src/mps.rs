@@ -0,0 +1,338 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::density_matrix::State;
+use crate::error::SimulatorError;
+use crate::linalg;
+use crate::operators::{Operator, TwoQubitsOp};
+use crate::pauli::PauliString;
+use crate::statevector::StateVector;
+use crate::tensor::Tensor;
+use crate::tools::are_elements_unique;
+
+// Pure state as a matrix product state: site i holds a tensor A_i[left, physical, right] and the
+// amplitude of |s_0 ... s_{n-1}> is the product A_0[s_0] ... A_{n-1}[s_{n-1}]. Memory grows with
+// the bond dimensions instead of 2^n, so low-entanglement states such as 1D cluster states run on
+// hundreds of qubits.
+//
+// The state is kept in mixed canonical form around a center site: sites on its left are left
+// isometries and sites on its right are right isometries, so singular values cut at the center
+// are the Schmidt coefficients and truncation is optimal. Two-qubit gates on distant qubits go
+// through swaps of neighboring sites.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MpsOptions {
+    pub max_bond: usize,    // Largest bond dimension kept after a two-site update.
+    pub cutoff: f64         // Schmidt weights below cutoff times the total are dropped.
+}
+
+impl Default for MpsOptions {
+    fn default() -> Self {
+        MpsOptions { max_bond: 64, cutoff: 1e-12 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mps {
+    sites: Vec<Tensor<Complex<f64>>>,
+    center: usize,
+    pub options: MpsOptions,
+    pub truncation_error: f64   // Sum of the relative Schmidt weights dropped so far.
+}
+
+impl Mps {
+    pub fn new(nqubits: usize, state: State, options: MpsOptions) -> Self {
+        let mut mps = Mps { sites: Vec::new(), center: 0, options, truncation_error: 0. };
+        (0..nqubits).for_each(|_| mps.add_qubit(state));
+        mps
+    }
+
+    pub fn nqubits(&self) -> usize {
+        self.sites.len()
+    }
+
+    // Dimensions of the n - 1 bonds between neighboring sites.
+    pub fn bond_dimensions(&self) -> Vec<usize> {
+        self.sites.iter().skip(1).map(|site| site.shape[0]).collect()
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), SimulatorError> {
+        if index >= self.nqubits() {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits() });
+        }
+        Ok(())
+    }
+
+    // Split the rows x cols matrix m into u v with u an isometry of at most max_bond columns, from
+    // the singular value decomposition of m. Returns u, v and the number of columns kept.
+    fn split(&mut self, m: &[Complex<f64>], rows: usize, cols: usize) -> (Vec<Complex<f64>>, Vec<Complex<f64>>, usize) {
+        let (singular, columns) = linalg::svd(m, rows, cols);
+        let values = singular.iter().map(|s| s * s).collect::<Vec<_>>();
+        let total = values.iter().sum::<f64>();
+        let kept = (0..rows.min(cols))
+            .take(self.options.max_bond.max(1))
+            .take_while(|&i| values[i] > self.options.cutoff * total)
+            .count()
+            .max(1);
+        let kept_weight = values[..kept].iter().sum::<f64>();
+        if total > 0. {
+            self.truncation_error += values[kept..].iter().sum::<f64>() / total;
+        }
+        // A zero matrix has no left singular vector, any unit vector keeps u an isometry.
+        let u = (0..rows).flat_map(|r| (0..kept).map(move |i| (r, i)))
+            .map(|(r, i)| if singular[i] > 0. { columns[r * cols + i] / singular[i] } else if r == i { Complex::ONE } else { Complex::ZERO })
+            .collect::<Vec<_>>();
+        let mut v = linalg::matmul_rect(&linalg::adjoint_rect(&u, rows, kept), m, kept, rows, cols);
+        // Restore the norm lost to the dropped weights.
+        if kept_weight > 0. {
+            let scale = (total / kept_weight).sqrt();
+            v.iter_mut().for_each(|a| *a *= scale);
+        }
+        (u, v, kept)
+    }
+
+    // Make site i a left isometry, pushing the remainder into site i + 1.
+    fn left_orthogonalize(&mut self, i: usize) {
+        let (l, r) = (self.sites[i].shape[0], self.sites[i].shape[2]);
+        let data = std::mem::take(&mut self.sites[i].data);
+        let (u, v, k) = self.split(&data, 2 * l, r);
+        self.sites[i] = Tensor::from_vec(u, vec![l, 2, k]);
+        let next = &self.sites[i + 1];
+        let r_next = next.shape[2];
+        let merged = linalg::matmul_rect(&v, &next.data, k, r, 2 * r_next);
+        self.sites[i + 1] = Tensor::from_vec(merged, vec![k, 2, r_next]);
+    }
+
+    // Make site i a right isometry, pushing the remainder into site i - 1.
+    fn right_orthogonalize(&mut self, i: usize) {
+        let (l, r) = (self.sites[i].shape[0], self.sites[i].shape[2]);
+        // m = v^dagger u^dagger with m^dagger = u v.
+        let m_adjoint = linalg::adjoint_rect(&self.sites[i].data, l, 2 * r);
+        let (u, v, k) = self.split(&m_adjoint, 2 * r, l);
+        self.sites[i] = Tensor::from_vec(linalg::adjoint_rect(&u, 2 * r, k), vec![k, 2, r]);
+        let previous = &self.sites[i - 1];
+        let l_previous = previous.shape[0];
+        let merged = linalg::matmul_rect(&previous.data, &linalg::adjoint_rect(&v, k, l), 2 * l_previous, l, k);
+        self.sites[i - 1] = Tensor::from_vec(merged, vec![l_previous, 2, k]);
+    }
+
+    fn move_center(&mut self, to: usize) {
+        while self.center < to {
+            self.left_orthogonalize(self.center);
+            self.center += 1;
+        }
+        while self.center > to {
+            self.right_orthogonalize(self.center);
+            self.center -= 1;
+        }
+    }
+
+    fn apply_one(&mut self, op: &[Complex<f64>], i: usize) {
+        let site = &mut self.sites[i];
+        let (l, r) = (site.shape[0], site.shape[2]);
+        for a in 0..l {
+            for b in 0..r {
+                let (s0, s1) = (site.data[(a * 2) * r + b], site.data[(a * 2 + 1) * r + b]);
+                site.data[(a * 2) * r + b] = op[0] * s0 + op[1] * s1;
+                site.data[(a * 2 + 1) * r + b] = op[2] * s0 + op[3] * s1;
+            }
+        }
+    }
+
+    // Apply a two-qubit operator to sites i and i + 1, its first qubit acting on site i.
+    fn apply_adjacent(&mut self, op: &[Complex<f64>], i: usize) {
+        self.move_center(i);
+        let (l, m, r) = (self.sites[i].shape[0], self.sites[i].shape[2], self.sites[i + 1].shape[2]);
+        let theta = linalg::matmul_rect(&self.sites[i].data, &self.sites[i + 1].data, 2 * l, m, 2 * r);
+        let mut updated = vec![Complex::ZERO; theta.len()];
+        for a in 0..l {
+            for b in 0..r {
+                let at = |s: usize| ((a * 2 + (s >> 1)) * 2 + (s & 1)) * r + b;
+                for t in 0..4 {
+                    updated[at(t)] = (0..4).map(|s| op[t * 4 + s] * theta[at(s)]).sum();
+                }
+            }
+        }
+        let (u, v, k) = self.split(&updated, 2 * l, 2 * r);
+        self.sites[i] = Tensor::from_vec(u, vec![l, 2, k]);
+        self.sites[i + 1] = Tensor::from_vec(v, vec![k, 2, r]);
+        self.center = i + 1;
+    }
+
+    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        if op.nqubits != 1 {
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
+        }
+        self.check_index(index)?;
+        self.apply_one(&op.data.data, index);
+        Ok(())
+    }
+
+    // Operators act on one or two qubits, the second target being brought next to the first
+    // with swaps and moved back afterwards.
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits()) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits() });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        match *indices {
+            [index] => self.apply_one(&op.data.data, index),
+            [a, b] => {
+                let (op, a, b) = if a < b {
+                    (op.data.data.clone(), a, b)
+                } else {
+                    // Exchange the roles of the two qubits of the operator.
+                    let swapped = (0..16).map(|k| {
+                        let (t, s) = (k / 4, k % 4);
+                        let flip = |x: usize| ((x & 1) << 1) | (x >> 1);
+                        op.data.data[flip(t) * 4 + flip(s)]
+                    }).collect();
+                    (swapped, b, a)
+                };
+                let swap = Operator::two_qubits(TwoQubitsOp::SWAP).data.data;
+                (a + 1..b).rev().for_each(|j| self.apply_adjacent(&swap, j));
+                self.apply_adjacent(&op, a);
+                (a + 1..b).for_each(|j| self.apply_adjacent(&swap, j));
+            },
+            _ => return Err(SimulatorError::InvalidArgument("MPS operators act on one or two qubits.".to_string()))
+        }
+        Ok(())
+    }
+
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.check_index(index)?;
+        self.move_center(index);
+        let site = &mut self.sites[index];
+        let (l, r) = (site.shape[0], site.shape[2]);
+        let weight = |data: &[Complex<f64>], s: usize| {
+            (0..l).flat_map(|a| (0..r).map(move |b| (a * 2 + s) * r + b)).map(|k| data[k].norm_sqr()).sum::<f64>()
+        };
+        let (p0, p1) = (weight(&site.data, 0), weight(&site.data, 1));
+        let outcome = u8::from(rng.gen::<f64>() * (p0 + p1) < p1);
+        let norm = if outcome == 1 { p1 } else { p0 }.sqrt();
+        for (k, a) in site.data.iter_mut().enumerate() {
+            *a = if (k / r) % 2 == outcome as usize { *a / norm } else { Complex::ZERO };
+        }
+        Ok(outcome)
+    }
+
+    // Measure the qubit and contract the projected site into a neighbor.
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        let site = self.sites.remove(index);
+        let (l, r) = (site.shape[0], site.shape[2]);
+        let projected = (0..l).flat_map(|a| (0..r).map(move |b| (a * 2 + outcome as usize) * r + b))
+            .map(|k| site.data[k])
+            .collect::<Vec<_>>();
+        if index < self.sites.len() {
+            let next = &self.sites[index];
+            let r_next = next.shape[2];
+            self.sites[index] = Tensor::from_vec(linalg::matmul_rect(&projected, &next.data, l, r, 2 * r_next), vec![l, 2, r_next]);
+            self.center = index;
+        } else if index > 0 {
+            let previous = &self.sites[index - 1];
+            let l_previous = previous.shape[0];
+            self.sites[index - 1] = Tensor::from_vec(linalg::matmul_rect(&previous.data, &projected, 2 * l_previous, l, r), vec![l_previous, 2, r]);
+            self.center = index - 1;
+        } else {
+            self.center = 0;
+        }
+        Ok(outcome)
+    }
+
+    // <psi|P|psi> / <psi|psi>, contracting the transfer matrices from the left.
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits() {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits(), actual: pauli_string.nqubits() });
+        }
+        let contract = |ops: &mut dyn Iterator<Item = Vec<Complex<f64>>>| {
+            let mut env = vec![Complex::ONE];
+            for (site, op) in self.sites.iter().zip(ops) {
+                let (l, r) = (site.shape[0], site.shape[2]);
+                let mut applied = site.clone();
+                for a in 0..l {
+                    for b in 0..r {
+                        let (s0, s1) = (site.data[(a * 2) * r + b], site.data[(a * 2 + 1) * r + b]);
+                        applied.data[(a * 2) * r + b] = op[0] * s0 + op[1] * s1;
+                        applied.data[(a * 2 + 1) * r + b] = op[2] * s0 + op[3] * s1;
+                    }
+                }
+                let half = linalg::matmul_rect(&env, &applied.data, l, l, 2 * r);
+                env = linalg::matmul_rect(&linalg::adjoint_rect(&site.data, 2 * l, r), &half, r, 2 * l, r);
+            }
+            env[0]
+        };
+        let paulis = &mut pauli_string.paulis.iter().map(|p| (0..4).map(|k| p.element(k / 2, k % 2)).collect());
+        let identities = &mut std::iter::repeat_with(|| linalg::identity(2));
+        Ok(contract(paulis).re / contract(identities).re)
+    }
+
+    // Append the sites of other and restore the canonical form.
+    pub fn tensor(&mut self, other: &Mps) {
+        self.sites.extend(other.sites.iter().cloned());
+        self.center = self.sites.len().saturating_sub(1);
+        self.move_center(0);
+    }
+
+    pub fn add_qubit(&mut self, state: State) {
+        let amplitudes = match state {
+            State::ZERO => vec![Complex::ONE, Complex::ZERO],
+            State::PLUS => vec![Complex::new(FRAC_1_SQRT_2, 0.); 2]
+        };
+        self.sites.push(Tensor::from_vec(amplitudes, vec![1, 2, 1]));
+    }
+
+    // Dense amplitudes, qubit 0 being the most significant bit, for small registers.
+    pub fn to_statevector(&self) -> StateVector {
+        let mut amplitudes = vec![Complex::ONE];
+        let mut rows = 1;
+        for site in &self.sites {
+            let (l, r) = (site.shape[0], site.shape[2]);
+            amplitudes = linalg::matmul_rect(&amplitudes, &site.data, rows, l, 2 * r);
+            rows *= 2;
+        }
+        StateVector { data: amplitudes, nqubits: self.nqubits() }
+    }
+}
+
+impl QuantumBackend for Mps {
+    fn nqubits(&self) -> usize {
+        Mps::nqubits(self)
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        Mps::evolve_single(self, op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        Mps::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        Mps::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        Mps::expectation(self, pauli_string)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        Mps::tensor(self, other)
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        Mps::add_qubit(self, state)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        Mps::measure_and_remove(self, index, rng)
+    }
+}
@@ -0,0 +1,103 @@
+use std::f64::consts::PI;
+
+use crate::circuit::Circuit;
+
+// Multi-controlled gates written with the Circuit gate set, so that oracles can be transpiled to
+// patterns. Without ancillas, the gate is the phase polynomial of its controls: only CNOT and RZ
+// are used, with 2^n terms for n qubits, exact up to a global phase. With clean ancillas (at
+// least n_controls - 2 qubits in |0>, returned to |0>), a chain of 2 n_controls - 3 Toffoli
+// gates computes the AND of the controls instead, which scales linearly.
+
+impl Circuit {
+    // X on target when every control is |1>. Ancillas may be empty for the ancilla-free form.
+    pub fn mcx(&mut self, controls: &[usize], target: usize, ancillas: &[usize]) {
+        self.check_multi_controlled(controls, target, ancillas);
+        match controls {
+            [] => self.x(target),
+            [c] => self.cnot(*c, target),
+            [c1, c2] => self.ccx(*c1, *c2, target),
+            _ if ancillas.is_empty() => {
+                self.h(target);
+                self.mc_phase(&[controls, &[target]].concat(), PI);
+                self.h(target);
+            },
+            _ => {
+                assert!(ancillas.len() >= controls.len() - 2, "C^{}X needs {} ancillas, got {}.", controls.len(), controls.len() - 2, ancillas.len());
+                // ancillas[k] holds the AND of controls[0..k + 2].
+                let n = controls.len();
+                let compute = |circuit: &mut Circuit| {
+                    circuit.ccx(controls[0], controls[1], ancillas[0]);
+                    for k in 2..n - 1 {
+                        circuit.ccx(controls[k], ancillas[k - 2], ancillas[k - 1]);
+                    }
+                };
+                compute(self);
+                self.ccx(controls[n - 1], ancillas[n - 3], target);
+                // The chain is its own inverse once reversed.
+                for k in (2..n - 1).rev() {
+                    self.ccx(controls[k], ancillas[k - 2], ancillas[k - 1]);
+                }
+                self.ccx(controls[0], controls[1], ancillas[0]);
+            }
+        }
+    }
+
+    pub fn mcz(&mut self, controls: &[usize], target: usize, ancillas: &[usize]) {
+        self.check_multi_controlled(controls, target, ancillas);
+        if ancillas.is_empty() {
+            self.mc_phase(&[controls, &[target]].concat(), PI);
+            return;
+        }
+        self.h(target);
+        self.mcx(controls, target, ancillas);
+        self.h(target);
+    }
+
+    // rz(angle) on target when every control is |1>, ancilla-free.
+    pub fn mcrz(&mut self, controls: &[usize], target: usize, angle: f64) {
+        self.check_multi_controlled(controls, target, &[]);
+        if controls.is_empty() {
+            self.rz(target, angle);
+            return;
+        }
+        // e^{-i angle / 2} on the controls, then e^{i angle} when the target is |1> too.
+        self.mc_phase(controls, -angle / 2.);
+        self.mc_phase(&[controls, &[target]].concat(), angle);
+    }
+
+    // rx = H rz H.
+    pub fn mcrx(&mut self, controls: &[usize], target: usize, angle: f64) {
+        self.h(target);
+        self.mcrz(controls, target, angle);
+        self.h(target);
+    }
+
+    // ry = S rx S^dagger.
+    pub fn mcry(&mut self, controls: &[usize], target: usize, angle: f64) {
+        self.rz(target, -PI / 2.);
+        self.mcrx(controls, target, angle);
+        self.s(target);
+    }
+
+    // |x> -> e^{i lambda x_1 ... x_m} |x>, using x_1 ... x_m = sum_S (-1)^{|S| - 1} parity_S(x) / 2^{m - 1}
+    // over the non-empty subsets S of the qubits. Each parity phase is a CNOT ladder into the last
+    // qubit of S around a RZ, which applies it up to a global phase.
+    fn mc_phase(&mut self, qubits: &[usize], lambda: f64) {
+        let m = qubits.len();
+        let scale = lambda / (1u64 << (m - 1)) as f64;
+        for subset in 1usize..1 << m {
+            let members = (0..m).filter(|i| subset >> i & 1 == 1).map(|i| qubits[i]).collect::<Vec<_>>();
+            let (last, rest) = members.split_last().unwrap();
+            let sign = if members.len() % 2 == 1 { 1. } else { -1. };
+            rest.iter().for_each(|&q| self.cnot(q, *last));
+            self.rz(*last, sign * scale);
+            rest.iter().rev().for_each(|&q| self.cnot(q, *last));
+        }
+    }
+
+    fn check_multi_controlled(&self, controls: &[usize], target: usize, ancillas: &[usize]) {
+        let qubits = [controls, &[target], ancillas].concat();
+        assert!(qubits.iter().all(|&q| q < self.width()), "Qubits {:?} exceed the circuit width {}.", qubits, self.width());
+        assert!((1..qubits.len()).all(|i| !qubits[..i].contains(&qubits[i])), "Controls, target and ancillas must be distinct, got {:?}.", qubits);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
+
+use crate::channels::{self, Channel};
+use crate::error::Context;
+use crate::operators::Operator;
+
+// Readout crosstalk: measuring a node dephases each of its graph neighbors that are still
+// unmeasured, with a probability given per edge (in either orientation) or a default for the
+// edges that are not listed.
+#[derive(Debug, Clone, Default)]
+pub struct MeasurementCrosstalk {
+    pub dephasing: HashMap<(usize, usize), f64>,
+    pub default: f64
+}
+
+impl MeasurementCrosstalk {
+    // Same dephasing probability on every edge.
+    pub fn uniform(p: f64) -> Self {
+        MeasurementCrosstalk { dephasing: HashMap::new(), default: p }
+    }
+
+    pub fn probability(&self, measured: usize, neighbor: usize) -> f64 {
+        self.dephasing.get(&(measured, neighbor))
+            .or_else(|| self.dephasing.get(&(neighbor, measured)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    // Kraus operators hitting the neighbor when measured is read out, None when the edge is noiseless.
+    pub fn channel(&self, measured: usize, neighbor: usize) -> Result<Option<Vec<Operator>>, String> {
+        let p = self.probability(measured, neighbor);
+        if p == 0. {
+            return Ok(None);
+        }
+        channels::dephasing(p).map(Some)
+    }
+}
+
+// Confusion matrix of the readout of one qubit: the ideal outcome 0 is reported as 1 with
+// probability p01 and the ideal outcome 1 as 0 with probability p10.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Confusion {
+    pub p01: f64,
+    pub p10: f64
+}
+
+impl Confusion {
+    pub fn new(p01: f64, p10: f64) -> Result<Self, String> {
+        for p in [p01, p10] {
+            if !(0. ..=1.).contains(&p) {
+                return Err(format!("Readout error should be a probability in [0, 1], got {}.", p));
+            }
+        }
+        Ok(Confusion { p01, p10 })
+    }
+
+    pub fn symmetric(p: f64) -> Result<Self, String> {
+        Confusion::new(p, p)
+    }
+
+    // Row-stochastic matrix [[P(0 | 0), P(1 | 0)], [P(0 | 1), P(1 | 1)]].
+    pub fn matrix(&self) -> [[f64; 2]; 2] {
+        [[1. - self.p01, self.p01], [self.p10, 1. - self.p10]]
+    }
+
+    pub fn apply(&self, ideal: u8, rng: &mut dyn RngCore) -> u8 {
+        let p = if ideal == 0 { self.p01 } else { self.p10 };
+        if p > 0. && rng.gen::<f64>() < p { ideal ^ 1 } else { ideal }
+    }
+}
+
+// Readout errors of a register, qubit q using confusion[q] and the qubits beyond the list using
+// the default, which reads perfectly unless set.
+#[derive(Debug, Clone, Default)]
+pub struct ReadoutError {
+    pub confusion: Vec<Confusion>,
+    pub default: Confusion
+}
+
+// Outcome reported by a noisy readout along with the one the ideal measurement gave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadoutOutcome<T> {
+    pub recorded: T,
+    pub ideal: T
+}
+
+impl ReadoutError {
+    pub fn uniform(nqubits: usize, confusion: Confusion) -> Self {
+        ReadoutError { confusion: vec![confusion; nqubits], default: Confusion::default() }
+    }
+
+    pub fn confusion(&self, qubit: usize) -> Confusion {
+        self.confusion.get(qubit).copied().unwrap_or(self.default)
+    }
+
+    // Read the bits of an outcome of n qubits, qubit 0 being the most significant bit.
+    pub fn apply(&self, ideal: u64, nqubits: usize, rng: &mut dyn RngCore) -> u64 {
+        (0..nqubits).fold(ideal, |recorded, q| {
+            let shift = nqubits - 1 - q;
+            let bit = ((ideal >> shift) & 1) as u8;
+            recorded ^ (((self.confusion(q).apply(bit, rng) ^ bit) as u64) << shift)
+        })
+    }
+}
+
+// Kind of pattern command a channel of a NoiseModel is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandKind {
+    N,
+    E,
+    M,
+    X,
+    Z,
+    C
+}
+
+// Relaxation times of a qubit, in the unit of the command durations of IdleNoise. Coherences
+// decay as exp(-t / t2), which requires t2 <= 2 t1, and infinite times disable the process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelaxationTimes {
+    pub t1: f64,
+    pub t2: f64
+}
+
+impl RelaxationTimes {
+    pub fn new(t1: f64, t2: f64) -> Result<Self, String> {
+        if t1.is_nan() || t2.is_nan() || t1 <= 0. || t2 <= 0. {
+            return Err(format!("Relaxation times should be positive, got T1 = {} and T2 = {}.", t1, t2));
+        }
+        if t2 > 2. * t1 {
+            return Err(format!("T2 = {} cannot exceed 2 T1 = {}.", t2, 2. * t1));
+        }
+        Ok(RelaxationTimes { t1, t2 })
+    }
+
+    // Amplitude damping with gamma = 1 - exp(-t / t1) followed by the phase damping that brings the
+    // decay of the coherences, sqrt((1 - gamma) (1 - lambda)), to exp(-t / t2).
+    pub fn idle_channel(&self, duration: f64) -> Result<Channel, String> {
+        if duration.is_nan() || duration < 0. {
+            return Err(format!("Idle duration should be non negative, got {}.", duration));
+        }
+        let gamma = 1. - (-duration / self.t1).exp();
+        let lambda = (1. - (duration / self.t1 - 2. * duration / self.t2).exp()).clamp(0., 1.);
+        Channel::new(channels::amplitude_damping(gamma)?)?.compose(&Channel::new(channels::phase_damping(lambda)?)?)
+    }
+}
+
+// Time-aware noise: every command kind lasts a given duration, during which the qubits it does not
+// act on sit idle and relax with their T1 and T2. Nodes without times of their own use the
+// default ones, and nodes with neither are noiseless.
+#[derive(Debug, Clone, Default)]
+pub struct IdleNoise {
+    pub durations: HashMap<CommandKind, f64>,   // Missing kinds take no time.
+    pub times: HashMap<usize, RelaxationTimes>,
+    pub default_times: Option<RelaxationTimes>
+}
+
+impl IdleNoise {
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty() && self.default_times.is_none()
+    }
+
+    pub fn duration(&self, kind: CommandKind) -> f64 {
+        self.durations.get(&kind).copied().unwrap_or(0.)
+    }
+
+    pub fn times(&self, node: usize) -> Option<RelaxationTimes> {
+        self.times.get(&node).copied().or(self.default_times)
+    }
+}
+
+// Clock of a run under IdleNoise. Commands are executed one after the other, and the time a node
+// spends waiting between two commands acting on it is turned into one relaxation channel, applied
+// right before the second command, or at the end of the run for the output nodes. Input nodes
+// exist from time 0 and prepared nodes from their N command.
+#[derive(Debug, Clone)]
+pub struct IdleScheduler {
+    pub noise: IdleNoise,
+    pub clock: f64,
+    pub last_active: HashMap<usize, f64>    // End of the last command acting on each node.
+}
+
+impl IdleScheduler {
+    pub fn new(noise: IdleNoise) -> Self {
+        IdleScheduler { noise, clock: 0., last_active: HashMap::new() }
+    }
+
+    // Idle time of each of the nodes since they were last acted upon, the nodes being busy until
+    // the end of the command, which advances the clock by its duration.
+    pub fn start(&mut self, kind: CommandKind, nodes: &[usize]) -> Vec<(usize, f64)> {
+        let idle = if kind == CommandKind::N {
+            Vec::new()
+        } else {
+            self.idle(nodes)
+        };
+        self.clock += self.noise.duration(kind);
+        nodes.iter().for_each(|&node| {
+            self.last_active.insert(node, self.clock);
+        });
+        idle
+    }
+
+    // Idle time of each of the nodes up to the current clock.
+    pub fn idle(&self, nodes: &[usize]) -> Vec<(usize, f64)> {
+        nodes.iter()
+            .map(|&node| (node, self.clock - self.last_active.get(&node).copied().unwrap_or(0.)))
+            .filter(|&(_, time)| time > 0.)
+            .collect()
+    }
+
+    // Relaxation channel of the node over the duration, None when the node is noiseless.
+    pub fn channel(&self, node: usize, duration: f64) -> Result<Option<Channel>, String> {
+        self.noise.times(node).map(|times| times.idle_channel(duration)).transpose()
+    }
+}
+
+// Contents of a noise model file, every key being checked against these fields.
+#[cfg(feature = "cli")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseFile {
+    depolarizing: Option<f64>,
+    readout_error: Option<f64>,
+    idle: Option<IdleSection>,
+    #[serde(rename = "N")]
+    n: Option<ChannelSection>,
+    #[serde(rename = "E")]
+    e: Option<ChannelSection>,
+    #[serde(rename = "M")]
+    m: Option<ChannelSection>,
+    #[serde(rename = "X")]
+    x: Option<ChannelSection>,
+    #[serde(rename = "Z")]
+    z: Option<ChannelSection>,
+    #[serde(rename = "C")]
+    c: Option<ChannelSection>
+}
+
+#[cfg(feature = "cli")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdleSection {
+    t1: f64,
+    t2: f64,
+    #[serde(rename = "N")]
+    n: Option<f64>,
+    #[serde(rename = "E")]
+    e: Option<f64>,
+    #[serde(rename = "M")]
+    m: Option<f64>,
+    #[serde(rename = "X")]
+    x: Option<f64>,
+    #[serde(rename = "Z")]
+    z: Option<f64>,
+    #[serde(rename = "C")]
+    c: Option<f64>
+}
+
+// Function of the channels module named by the channel key, with its arguments.
+#[cfg(feature = "cli")]
+#[derive(serde::Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case", deny_unknown_fields)]
+enum ChannelSection {
+    Depolarizing { p: f64 },
+    TwoQubitDepolarizing { p: f64 },
+    ZzCrosstalk { p: f64 },
+    Dephasing { p: f64 },
+    BitFlip { p: f64 },
+    PhaseFlip { p: f64 },
+    AmplitudeDamping { gamma: f64 },
+    PhaseDamping { lambda: f64 },
+    GeneralizedAmplitudeDamping { gamma: f64, excited_population: f64 }
+}
+
+#[cfg(feature = "cli")]
+impl ChannelSection {
+    fn kraus(&self) -> Result<Vec<Operator>, String> {
+        match *self {
+            ChannelSection::Depolarizing { p } => channels::depolarizing(p),
+            ChannelSection::TwoQubitDepolarizing { p } => channels::two_qubit_depolarizing(p),
+            ChannelSection::ZzCrosstalk { p } => channels::zz_crosstalk(p),
+            ChannelSection::Dephasing { p } => channels::dephasing(p),
+            ChannelSection::BitFlip { p } => channels::bit_flip(p),
+            ChannelSection::PhaseFlip { p } => channels::phase_flip(p),
+            ChannelSection::AmplitudeDamping { gamma } => channels::amplitude_damping(gamma),
+            ChannelSection::PhaseDamping { lambda } => channels::phase_damping(lambda),
+            ChannelSection::GeneralizedAmplitudeDamping { gamma, excited_population } => channels::generalized_amplitude_damping(gamma, excited_population)
+        }
+    }
+}
+
+// Noise model of each shot of a run, shot i using the model returned for i, e.g. to emulate the
+// calibration drift of a long campaign by letting t2 or the gate errors depend on the shot.
+pub type NoiseSchedule = Arc<dyn Fn(u64) -> Result<NoiseModel, String> + Send + Sync>;
+
+// Noise injected by Pattern::simulate_with_noise, following graphix's noise models. Channels
+// hit the prepared qubit after N, the entangled pair after E, the measured qubit just before M,
+// and the corrected qubit after C and after X and Z corrections that are actually applied. E
+// channels act on both nodes when they have two qubits and on each node separately otherwise,
+// the other kinds need one qubit channels. Channels given for a node, or for an edge in either
+// orientation, replace the default of their command kind. Idle noise comes on top of them.
+// Measurement outcomes are then recorded through the readout error of the measured node.
+#[derive(Clone, Default)]
+pub struct NoiseModel {
+    pub channels: HashMap<CommandKind, Channel>,
+    pub node_channels: HashMap<(CommandKind, usize), Channel>,
+    pub edge_channels: HashMap<(usize, usize), Channel>,
+    pub readout: ReadoutError,
+    pub idle: IdleNoise
+}
+
+impl NoiseModel {
+    // Depolarizing noise with probability p after every command, two qubit depolarizing on edges.
+    pub fn depolarizing(p: f64) -> Result<Self, String> {
+        let one_qubit = Channel::new(channels::depolarizing(p)?)?;
+        let mut model = NoiseModel::default();
+        for kind in [CommandKind::N, CommandKind::M, CommandKind::X, CommandKind::Z, CommandKind::C] {
+            model.channels.insert(kind, one_qubit.clone());
+        }
+        model.channels.insert(CommandKind::E, Channel::new(channels::two_qubit_depolarizing(p)?)?);
+        Ok(model)
+    }
+
+    // Model read from a TOML file such as
+    //
+    //     readout_error = 0.01
+    //     depolarizing = 0.001    # Depolarizing model, overridden by the sections below.
+    //
+    //     [E]
+    //     channel = "two_qubit_depolarizing"
+    //     p = 0.02
+    //
+    // with one section per command kind (N, E, M, X, Z or C), naming a function of the channels
+    // module and its arguments, and an optional [idle] section giving t1, t2 and the duration of
+    // each command kind for IdleNoise. Unknown keys and keys defined twice are refused.
+    #[cfg(feature = "cli")]
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let file = toml::from_str::<NoiseFile>(source).map_err(|e| format!("Invalid noise model: {}", e.to_string().trim_end()))?;
+        let mut model = match file.depolarizing {
+            Some(p) => NoiseModel::depolarizing(p)?,
+            None => NoiseModel::default()
+        };
+        if let Some(p) = file.readout_error {
+            model.readout.default = Confusion::symmetric(p)?;
+        }
+        let sections = [(CommandKind::N, file.n), (CommandKind::E, file.e), (CommandKind::M, file.m), (CommandKind::X, file.x), (CommandKind::Z, file.z), (CommandKind::C, file.c)];
+        for (kind, section) in sections {
+            if let Some(section) = section {
+                model.channels.insert(kind, Channel::new(section.kraus().with_context(|| format!("Section [{:?}]", kind))?)?);
+            }
+        }
+        if let Some(idle) = file.idle {
+            model.idle.default_times = Some(RelaxationTimes::new(idle.t1, idle.t2)?);
+            let durations = [(CommandKind::N, idle.n), (CommandKind::E, idle.e), (CommandKind::M, idle.m), (CommandKind::X, idle.x), (CommandKind::Z, idle.z), (CommandKind::C, idle.c)];
+            model.idle.durations.extend(durations.into_iter().filter_map(|(kind, duration)| Some((kind, duration?))));
+        }
+        Ok(model)
+    }
+
+    // ZZ crosstalk on entangling commands, strengths[a][b] being the probability of a correlated
+    // Z error on nodes a and b after E(a, b). The matrix must be symmetric with a zero diagonal.
+    // The crosstalk follows the channel E(a, b) already had, a one qubit channel acting on both
+    // nodes, and zero strengths leave the pair untouched.
+    pub fn add_zz_crosstalk(&mut self, strengths: &[Vec<f64>]) -> Result<(), String> {
+        let n = strengths.len();
+        if let Some(row) = strengths.iter().position(|row| row.len() != n) {
+            return Err(format!("Crosstalk matrix should be {} x {}, row {} has {} entries.", n, n, row, strengths[row].len()));
+        }
+        for (a, row) in strengths.iter().enumerate() {
+            if row[a] != 0. {
+                return Err(format!("Crosstalk of node {} with itself should be 0, got {}.", a, row[a]));
+            }
+            for (b, &p) in row.iter().enumerate().skip(a + 1) {
+                if p != strengths[b][a] {
+                    return Err(format!("Crosstalk matrix is not symmetric for nodes {} and {}.", a, b));
+                }
+                if p == 0. {
+                    continue;
+                }
+                let crosstalk = Channel::new(channels::zz_crosstalk(p)?)?;
+                let channel = match self.edge_channel(a, b) {
+                    Some(existing) if existing.nqubits == 1 => existing.tensor(existing).compose(&crosstalk)?,
+                    Some(existing) => existing.compose(&crosstalk)?,
+                    None => crosstalk
+                };
+                self.edge_channels.remove(&(b, a));
+                self.edge_channels.insert((a, b), channel);
+            }
+        }
+        Ok(())
+    }
+
+    // Check that every channel of the model is completely positive and trace preserving.
+    pub fn validate(&self, tol: f64) -> Result<(), String> {
+        let channels = self.channels.iter().map(|(kind, channel)| (format!("{:?} channel", kind), channel))
+            .chain(self.node_channels.iter().map(|((kind, node), channel)| (format!("{:?} channel of node {}", kind, node), channel)))
+            .chain(self.edge_channels.iter().map(|((a, b), channel)| (format!("E channel of nodes {} and {}", a, b), channel)));
+        for (name, channel) in channels {
+            channel.is_cptp(tol).map_err(|violation| violation.to_string()).with_context(|| name)?;
+        }
+        Ok(())
+    }
+
+    pub fn channel(&self, kind: CommandKind, node: usize) -> Option<&Channel> {
+        self.node_channels.get(&(kind, node)).or_else(|| self.channels.get(&kind))
+    }
+
+    pub fn edge_channel(&self, a: usize, b: usize) -> Option<&Channel> {
+        self.edge_channels.get(&(a, b))
+            .or_else(|| self.edge_channels.get(&(b, a)))
+            .or_else(|| self.channels.get(&CommandKind::E))
+    }
+}
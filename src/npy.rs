@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+
+use num_complex::Complex;
+
+// Minimal reader and writer for NumPy .npy files holding little-endian complex128 or float64
+// arrays in C order, the format produced by numpy.save on QuTiP's Qobj.full().
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+// Value of a key in the header dictionary, e.g. 'descr': '<c16'.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, String> {
+    let pattern = format!("'{}':", key);
+    let start = header.find(&pattern)
+        .ok_or_else(|| format!("Missing {} in npy header.", key))? + pattern.len();
+    let rest = header[start..].trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }.ok_or_else(|| format!("Malformed {} in npy header.", key))?;
+    Ok(rest[..end].trim())
+}
+
+fn parse_shape(value: &str) -> Result<Vec<usize>, String> {
+    value.trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().map_err(|e| format!("Invalid npy shape {}: {}.", value, e)))
+        .collect()
+}
+
+// Layout of an npy file: shape, dtype and offset of the first data byte.
+#[derive(Debug, Clone)]
+pub struct NpyHeader {
+    pub shape: Vec<usize>,
+    pub descr: String,
+    pub data_start: usize
+}
+
+impl NpyHeader {
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub fn parse_header(bytes: &[u8]) -> Result<NpyHeader, String> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err("Not an npy file.".to_string());
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12),
+        v => return Err(format!("Unsupported npy version {}.", v))
+    };
+    let data_start = header_start + header_len;
+    if bytes.len() < data_start {
+        return Err("Truncated npy header.".to_string());
+    }
+    let header = std::str::from_utf8(&bytes[header_start..data_start]).map_err(|e| e.to_string())?;
+
+    if header_value(header, "fortran_order")? != "False" {
+        return Err("Fortran ordered arrays are not supported.".to_string());
+    }
+    let shape = parse_shape(header_value(header, "shape")?)?;
+    let descr = header_value(header, "descr")?.trim_matches('\'').to_string();
+    if descr != "<c16" && descr != "<f8" {
+        return Err(format!("Unsupported npy dtype {}, expected <c16 or <f8.", descr));
+    }
+    Ok(NpyHeader { shape, descr, data_start })
+}
+
+pub fn parse_npy(bytes: &[u8]) -> Result<(Vec<usize>, Vec<Complex<f64>>), String> {
+    let header = parse_header(bytes)?;
+    let len = header.len();
+    let words = bytes[header.data_start..].chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect::<Vec<f64>>();
+    let data = match header.descr.as_str() {
+        "<c16" if words.len() == 2 * len => words.chunks_exact(2).map(|c| Complex::new(c[0], c[1])).collect(),
+        "<f8" if words.len() == len => words.iter().map(|&x| Complex::new(x, 0.)).collect(),
+        _ => return Err(format!("Expected {} elements for shape {:?}.", len, header.shape))
+    };
+    Ok((header.shape, data))
+}
+
+pub fn to_npy(shape: &[usize], data: &[Complex<f64>]) -> Result<Vec<u8>, String> {
+    if shape.iter().product::<usize>() != data.len() {
+        return Err(format!("Shape {:?} does not match {} elements.", shape, data.len()));
+    }
+    let shape_str = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(", "))
+    };
+    let mut header = format!("{{'descr': '<c16', 'fortran_order': False, 'shape': {}, }}", shape_str);
+    // The data starts on a 64 bytes boundary and the header ends with a newline.
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(10 + header.len() + 16 * data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for c in data {
+        bytes.extend_from_slice(&c.re.to_le_bytes());
+        bytes.extend_from_slice(&c.im.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+pub fn read_npy<P: AsRef<Path>>(path: P) -> Result<(Vec<usize>, Vec<Complex<f64>>), String> {
+    let bytes = fs::read(path.as_ref()).map_err(|e| format!("Cannot read {}: {}.", path.as_ref().display(), e))?;
+    parse_npy(&bytes)
+}
+
+pub fn write_npy<P: AsRef<Path>>(path: P, shape: &[usize], data: &[Complex<f64>]) -> Result<(), String> {
+    fs::write(path.as_ref(), to_npy(shape, data)?).map_err(|e| format!("Cannot write {}: {}.", path.as_ref().display(), e))
+}
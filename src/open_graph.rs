@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::pattern::{Command, Pattern, Plane};
+
+// Graph of a measurement pattern with its input and output nodes and the measurement plane of
+// every non-output node. A (generalized) flow certifies that the pattern can be made
+// deterministic, and gives an order in which to measure and correct the nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenGraph {
+    pub nodes: BTreeSet<usize>,
+    pub edges: Vec<(usize, usize)>,
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+    pub planes: BTreeMap<usize, Plane>
+}
+
+// Correction function f and layers of a causal flow. Node u is corrected through f(u), and
+// nodes of higher layers are measured first, outputs being in layer 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flow {
+    pub correction: BTreeMap<usize, usize>,
+    pub layers: BTreeMap<usize, usize>
+}
+
+// Same as Flow with a correction set g(u) for each measured node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GFlow {
+    pub correction: BTreeMap<usize, BTreeSet<usize>>,
+    pub layers: BTreeMap<usize, usize>
+}
+
+// Nodes from the highest layer to the lowest, i.e. a valid measurement order followed by the outputs.
+fn order(layers: &BTreeMap<usize, usize>) -> Vec<usize> {
+    let mut nodes = layers.keys().copied().collect::<Vec<_>>();
+    nodes.sort_by_key(|node| std::cmp::Reverse(layers[node]));
+    nodes
+}
+
+impl Flow {
+    pub fn measurement_order(&self) -> Vec<usize> {
+        order(&self.layers)
+    }
+}
+
+impl GFlow {
+    pub fn measurement_order(&self) -> Vec<usize> {
+        order(&self.layers)
+    }
+}
+
+impl OpenGraph {
+    pub fn new(edges: &[(usize, usize)], inputs: Vec<usize>, outputs: Vec<usize>, planes: BTreeMap<usize, Plane>) -> Result<Self, String> {
+        let mut nodes = BTreeSet::new();
+        for &(a, b) in edges {
+            if a == b {
+                return Err(format!("Self loop on node {} is not allowed.", a));
+            }
+            nodes.insert(a);
+            nodes.insert(b);
+        }
+        nodes.extend(inputs.iter().chain(outputs.iter()).chain(planes.keys()));
+        if let Some(node) = nodes.iter().find(|node| !outputs.contains(node) && !planes.contains_key(node)) {
+            return Err(format!("Node {} is neither an output nor measured.", node));
+        }
+        if let Some(node) = outputs.iter().find(|node| planes.contains_key(node)) {
+            return Err(format!("Output node {} cannot be measured.", node));
+        }
+        Ok(OpenGraph { nodes, edges: edges.to_vec(), inputs, outputs, planes })
+    }
+
+    // Graph of the N, E and M commands of a pattern.
+    pub fn from_pattern(pattern: &Pattern) -> Result<Self, String> {
+        let mut edges = Vec::new();
+        let mut planes = BTreeMap::new();
+        for command in pattern.seq() {
+            match command {
+                Command::E(edge) => edges.push(*edge),
+                Command::M(node, plane, ..) => { planes.insert(*node, *plane); },
+                _ => {}
+            }
+        }
+        OpenGraph::new(&edges, pattern.input_nodes().to_vec(), pattern.output_nodes().to_vec(), planes)
+    }
+
+    pub fn neighbors(&self, node: usize) -> BTreeSet<usize> {
+        self.edges.iter()
+            .filter_map(|&(a, b)| if a == node { Some(b) } else if b == node { Some(a) } else { None })
+            .collect()
+    }
+
+    // Nodes with an odd number of neighbors in the set.
+    pub fn odd_neighborhood(&self, set: &BTreeSet<usize>) -> BTreeSet<usize> {
+        self.nodes.iter().copied()
+            .filter(|&w| set.iter().filter(|&&v| self.neighbors(v).contains(&w)).count() % 2 == 1)
+            .collect()
+    }
+
+    // Deterministic pattern measuring each node at the given angle (in units of pi), ordered and
+    // corrected with the generalized flow: the outcome of u flips the nodes of g(u) with X and
+    // those of Odd(g(u)) with Z, both being measured after u.
+    pub fn to_pattern(&self, angles: &BTreeMap<usize, f64>) -> Result<Pattern, String> {
+        if let Some(node) = self.planes.keys().find(|node| !angles.contains_key(node)) {
+            return Err(format!("Missing measurement angle for node {}.", node));
+        }
+        let gflow = self.find_gflow()?;
+        let odd = gflow.correction.iter()
+            .map(|(&u, set)| (u, self.odd_neighborhood(set)))
+            .collect::<BTreeMap<_, _>>();
+        let domains = |node: usize| {
+            let s_domain = gflow.correction.iter().filter(|(&u, set)| u != node && set.contains(&node)).map(|(&u, _)| u).collect::<Vec<_>>();
+            let t_domain = odd.iter().filter(|(&u, set)| u != node && set.contains(&node)).map(|(&u, _)| u).collect::<Vec<_>>();
+            (s_domain, t_domain)
+        };
+
+        let mut pattern = Pattern::new(self.inputs.clone());
+        for &node in self.nodes.iter().filter(|node| !self.inputs.contains(node)) {
+            pattern.add(Command::N(node));
+        }
+        for &edge in &self.edges {
+            pattern.add(Command::E(edge));
+        }
+        for node in gflow.measurement_order().into_iter().filter(|node| self.planes.contains_key(node)) {
+            let (s_domain, t_domain) = domains(node);
+            pattern.add(Command::M(node, self.planes[&node], angles[&node], s_domain, t_domain, 0));
+        }
+        for &node in &self.outputs {
+            let (s_domain, t_domain) = domains(node);
+            if !s_domain.is_empty() {
+                pattern.add(Command::X(node, s_domain));
+            }
+            if !t_domain.is_empty() {
+                pattern.add(Command::Z(node, t_domain));
+            }
+        }
+        pattern.reorder_output_nodes(self.outputs.clone())?;
+        Ok(pattern)
+    }
+
+    // Causal flow with the layer by layer algorithm of Mhalla and Perdrix, which only applies
+    // when every node is measured in the XY plane.
+    pub fn find_flow(&self) -> Result<Flow, String> {
+        if let Some((node, _)) = self.planes.iter().find(|(_, &plane)| plane != Plane::XY) {
+            return Err(format!("Node {} is not measured in the XY plane, which causal flow requires.", node));
+        }
+        let mut correction = BTreeMap::new();
+        let mut layers = self.outputs.iter().map(|&node| (node, 0)).collect::<BTreeMap<_, _>>();
+        let mut processed = self.outputs.iter().copied().collect::<BTreeSet<_>>();
+        let mut correctors = self.outputs.iter().copied().filter(|node| !self.inputs.contains(node)).collect::<BTreeSet<_>>();
+        let mut layer = 1;
+        loop {
+            let mut found = BTreeSet::new();
+            let mut used = BTreeSet::new();
+            for &v in &correctors {
+                let unprocessed = self.neighbors(v).into_iter().filter(|n| !processed.contains(n)).collect::<Vec<_>>();
+                if let [u] = unprocessed[..] {
+                    correction.insert(u, v);
+                    layers.insert(u, layer);
+                    found.insert(u);
+                    used.insert(v);
+                }
+            }
+            if found.is_empty() {
+                if processed.len() == self.nodes.len() {
+                    return Ok(Flow { correction, layers });
+                }
+                return Err("The open graph has no causal flow.".to_string());
+            }
+            processed.extend(found.iter());
+            correctors = correctors.difference(&used).copied()
+                .chain(found.into_iter().filter(|node| !self.inputs.contains(node)))
+                .collect();
+            layer += 1;
+        }
+    }
+
+    // Generalized flow, built layer by layer from the outputs as in Backens et al. Each layer
+    // takes every node u whose correction set can be chosen among the already processed
+    // non-input nodes (plus u itself outside the XY plane) such that the odd neighborhood of
+    // the set hits the unprocessed nodes exactly on u for XY and XZ, and nowhere for YZ.
+    pub fn find_gflow(&self) -> Result<GFlow, String> {
+        let mut correction = BTreeMap::new();
+        let mut layers = self.outputs.iter().map(|&node| (node, 0)).collect::<BTreeMap<_, _>>();
+        let mut processed = self.outputs.iter().copied().collect::<BTreeSet<_>>();
+        let mut layer = 1;
+        loop {
+            let unprocessed = self.nodes.difference(&processed).copied().collect::<Vec<_>>();
+            if unprocessed.is_empty() {
+                return Ok(GFlow { correction, layers });
+            }
+            let candidates = processed.iter().copied().filter(|node| !self.inputs.contains(node)).collect::<Vec<_>>();
+            let adjacency = unprocessed.iter()
+                .map(|&w| {
+                    let neighbors = self.neighbors(w);
+                    candidates.iter().map(|c| neighbors.contains(c)).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let mut found = Vec::new();
+            for &u in &unprocessed {
+                let plane = self.planes[&u];
+                if plane != Plane::XY && self.inputs.contains(&u) {
+                    continue;
+                }
+                // Odd(K + u) = Odd(K) + N(u), so outside the XY plane the target is shifted by N(u).
+                let neighbors = self.neighbors(u);
+                let target = unprocessed.iter()
+                    .map(|&w| match plane {
+                        Plane::XY => w == u,
+                        Plane::ZX => (w == u) != neighbors.contains(&w),
+                        Plane::YZ => neighbors.contains(&w)
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(solution) = solve_gf2(&adjacency, &target, candidates.len()) {
+                    let mut set = candidates.iter().zip(solution).filter(|(_, x)| *x).map(|(&c, _)| c).collect::<BTreeSet<_>>();
+                    if plane != Plane::XY {
+                        set.insert(u);
+                    }
+                    correction.insert(u, set);
+                    layers.insert(u, layer);
+                    found.push(u);
+                }
+            }
+            if found.is_empty() {
+                return Err("The open graph has no generalized flow.".to_string());
+            }
+            processed.extend(found);
+            layer += 1;
+        }
+    }
+}
+
+// Solve A x = b over GF(2) by Gauss-Jordan elimination, A having one row per equation.
+fn solve_gf2(a: &[Vec<bool>], b: &[bool], nvars: usize) -> Option<Vec<bool>> {
+    let mut rows = a.iter().zip(b).map(|(row, &rhs)| {
+        let mut row = row.clone();
+        row.push(rhs);
+        row
+    }).collect::<Vec<_>>();
+    let mut pivots = Vec::new();
+    let mut rank = 0;
+    for col in 0..nvars {
+        let Some(pivot) = (rank..rows.len()).find(|&r| rows[r][col]) else { continue };
+        rows.swap(rank, pivot);
+        for r in 0..rows.len() {
+            if r != rank && rows[r][col] {
+                let pivot_row = rows[rank].clone();
+                rows[r].iter_mut().zip(pivot_row).for_each(|(x, p)| *x ^= p);
+            }
+        }
+        pivots.push(col);
+        rank += 1;
+    }
+    if rows[rank..].iter().any(|row| row[nvars]) {
+        return None;
+    }
+    let mut x = vec![false; nvars];
+    for (r, &col) in pivots.iter().enumerate() {
+        x[col] = rows[r][nvars];
+    }
+    Some(x)
+}
@@ -4,13 +4,20 @@ use num_complex::Complex;
 use num_traits::pow;
 use crate::tensor::Tensor;
 use crate::tools::bitwise_int_to_bin_vec;
+use crate::linalg;
+use crate::config::TolerancePolicy;
+use crate::error::SimulatorError;
 
 pub enum OneQubitOp {
     I,
     H,
     X,
     Y,
-    Z
+    Z,
+    S,
+    SDG,
+    T,
+    TDG
 }
 
 pub enum TwoQubitsOp {
@@ -19,7 +26,22 @@ pub enum TwoQubitsOp {
     SWAP
 }
 
+pub enum ThreeQubitsOp {
+    CCX,
+    CCZ
+}
+
+// Memory order of the entries of a matrix. Operators always store theirs row-major, entry (i, j)
+// at i * 2^n + j, and act on kets from the left. Matrices from column-major sources (Fortran
+// ordered numpy arrays, Eigen, Julia) read as row-major would be silently transposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixLayout {
+    RowMajor,
+    ColumnMajor
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Operator {
     pub nqubits: usize,
     pub data: Tensor<Complex<f64>>
@@ -32,10 +54,10 @@ impl fmt::Display for Operator {
 }
 
 impl Operator {
-    pub fn new(data: Vec<Complex<f64>>) -> Result<Self, String> {
+    pub fn new(data: Vec<Complex<f64>>) -> Result<Self, SimulatorError> {
         let size = (data.len() as f64).sqrt();
-        if !(size as usize).is_power_of_two() {
-            return Err("Operator should be a squared matrix with size 2^nqubits * 2^nqubits".to_string());
+        if !(size as usize).is_power_of_two() || (size as usize).pow(2) != data.len() {
+            return Err(SimulatorError::NotPowerOfTwo(data.len()));
         }
         let nqubits = size.log2() as usize;
         let shape = vec![2; 2 * nqubits];
@@ -43,50 +65,132 @@ impl Operator {
         Ok(Operator { nqubits, data: Tensor::from_vec(data, shape) })
     }
 
+    // Operator on nqubits from its row-major 2^nqubits x 2^nqubits matrix.
+    pub fn from_matrix(data: &[Complex<f64>], nqubits: usize) -> Result<Self, SimulatorError> {
+        let size = 1 << nqubits;
+        if data.len() != size * size {
+            return Err(SimulatorError::DimensionMismatch { expected: size * size, actual: data.len() });
+        }
+        Ok(Operator { nqubits, data: Tensor::from_vec(data.to_vec(), vec![2; 2 * nqubits]) })
+    }
+
+    // Same as from_matrix, for entries stored in the given layout.
+    pub fn from_matrix_layout(data: &[Complex<f64>], nqubits: usize, layout: MatrixLayout) -> Result<Self, SimulatorError> {
+        let op = Operator::from_matrix(data, nqubits)?;
+        Ok(match layout {
+            MatrixLayout::RowMajor => op,
+            MatrixLayout::ColumnMajor => op.transpose()
+        })
+    }
+
+    // Entries of the matrix in the given layout.
+    pub fn to_matrix(&self, layout: MatrixLayout) -> Vec<Complex<f64>> {
+        match layout {
+            MatrixLayout::RowMajor => self.data.data.clone(),
+            MatrixLayout::ColumnMajor => self.transpose().data.data
+        }
+    }
+
+    // Same as from_matrix, rejecting matrices that are not unitary.
+    pub fn from_unitary(data: &[Complex<f64>], nqubits: usize, tol: &TolerancePolicy) -> Result<Self, SimulatorError> {
+        let op = Operator::from_matrix(data, nqubits)?;
+        if !op.is_unitary(tol.unitarity) {
+            return Err(SimulatorError::NotUnitary);
+        }
+        Ok(op)
+    }
+
     pub fn one_qubit(gate: OneQubitOp) -> Self {
-        let data;
         let nqubits = 1;
-        match gate {
+        let data = match gate {
             OneQubitOp::H => {
-                data = vec![Complex::new(FRAC_1_SQRT_2, 0.); 4];
+                let h = Complex::new(FRAC_1_SQRT_2, 0.);
+                vec![h, h, h, -h]
             }
             OneQubitOp::X => {
-                data = vec![Complex::ZERO, Complex::ONE, Complex::ONE, Complex::ZERO];
+                vec![Complex::ZERO, Complex::ONE, Complex::ONE, Complex::ZERO]
             },
             OneQubitOp::Y => {
-                data = vec![Complex::ZERO, Complex::new(0., -1.), Complex::new(0., 1.), Complex::ZERO];
+                vec![Complex::ZERO, Complex::new(0., -1.), Complex::new(0., 1.), Complex::ZERO]
             },
             OneQubitOp::Z => {
-                data = vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::new(-1., 0.)];
+                vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::new(-1., 0.)]
             },
             OneQubitOp::I => {
-                data = vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ONE];
+                vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ONE]
             },
-        }
+            OneQubitOp::S => return Operator::phase(f64::consts::FRAC_PI_2),
+            OneQubitOp::SDG => return Operator::phase(-f64::consts::FRAC_PI_2),
+            OneQubitOp::T => return Operator::phase(f64::consts::FRAC_PI_4),
+            OneQubitOp::TDG => return Operator::phase(-f64::consts::FRAC_PI_4),
+        };
         Self {
             nqubits,
             data: Tensor::from_vec(data, vec![2, 2])
         }
     }
 
+    // Phase gate diag(1, e^{i theta}), equal to rz(theta) up to a global phase.
+    pub fn phase(theta: f64) -> Self {
+        Self {
+            nqubits: 1,
+            data: Tensor::from_vec(vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::from_polar(1., theta)], vec![2, 2])
+        }
+    }
+
+    // Rotation exp(-i theta X / 2) around the X axis.
+    pub fn rx(theta: f64) -> Self {
+        let (c, s) = ((theta / 2.).cos(), (theta / 2.).sin());
+        Self {
+            nqubits: 1,
+            data: Tensor::from_vec(vec![
+                Complex::new(c, 0.), Complex::new(0., -s),
+                Complex::new(0., -s), Complex::new(c, 0.)
+            ], vec![2, 2])
+        }
+    }
+
+    // Rotation exp(-i theta Y / 2) around the Y axis.
+    pub fn ry(theta: f64) -> Self {
+        let (c, s) = ((theta / 2.).cos(), (theta / 2.).sin());
+        Self {
+            nqubits: 1,
+            data: Tensor::from_vec(vec![
+                Complex::new(c, 0.), Complex::new(-s, 0.),
+                Complex::new(s, 0.), Complex::new(c, 0.)
+            ], vec![2, 2])
+        }
+    }
+
+    // Rotation exp(-i theta Z / 2) around the Z axis.
+    pub fn rz(theta: f64) -> Self {
+        Self {
+            nqubits: 1,
+            data: Tensor::from_vec(vec![
+                Complex::from_polar(1., -theta / 2.), Complex::ZERO,
+                Complex::ZERO, Complex::from_polar(1., theta / 2.)
+            ], vec![2, 2])
+        }
+    }
+
     pub fn two_qubits(gate: TwoQubitsOp) -> Self {
         let nqubits = 2;
         let mut data = vec![Complex::ZERO; 16];
-        data[0 * 4 + 0] = Complex::ONE;
+        data[0] = Complex::ONE;
         match gate {
             TwoQubitsOp::CX => {
                 data[2 * 4 + 3] = Complex::ONE;
                 data[3 * 4 + 2] = Complex::ONE;
-                data[1 * 4 + 1] = Complex::ONE;
+                data[4 + 1] = Complex::ONE;
             },
             TwoQubitsOp::CZ => {
                 data[2 * 4 + 2] = Complex::ONE;
                 data[3 * 4 + 3] = Complex::new(-1., 0.);
-                data[1 * 4 + 1] = Complex::ONE;
+                data[4 + 1] = Complex::ONE;
             },
             TwoQubitsOp::SWAP => {
                 data[2 * 4 + 1] = Complex::ONE;
-                data[1 * 4 + 2] = Complex::ONE;
+                data[4 + 2] = Complex::ONE;
                 data[3 * 4 + 3] = Complex::ONE;
             },
         }
@@ -96,6 +200,35 @@ impl Operator {
         }
     }
 
+    // Gates with their two controls on the first two target qubits.
+    pub fn three_qubits(gate: ThreeQubitsOp) -> Self {
+        match gate {
+            ThreeQubitsOp::CCX => Operator::one_qubit(OneQubitOp::X).controlled(2),
+            ThreeQubitsOp::CCZ => Operator::one_qubit(OneQubitOp::Z).controlled(2),
+        }
+    }
+
+    // Same as u.controlled(n_controls), e.g. Operator::multi_controlled(&x, 3) for C^3 X.
+    pub fn multi_controlled(u: &Operator, n_controls: usize) -> Operator {
+        u.controlled(n_controls)
+    }
+
+    // Operator applying self when all num_controls control qubits are in |1>. The controls come
+    // first, so the targets of self are the last ones when evolving.
+    pub fn controlled(&self, num_controls: usize) -> Operator {
+        let nqubits = self.nqubits + num_controls;
+        let size = 1 << nqubits;
+        let base = 1 << self.nqubits;
+        let offset = size - base;
+        let mut data = linalg::identity(size);
+        for i in 0..base {
+            for j in 0..base {
+                data[(offset + i) * size + offset + j] = self.data.data[i * base + j];
+            }
+        }
+        Operator { nqubits, data: Tensor::from_vec(data, vec![2; 2 * nqubits]) }
+    }
+
     pub fn conj(&self) -> Operator {
         let new_data = self.data.data.iter().map(|e| e.conj()).collect::<Vec<Complex<f64>>>();
         Operator { nqubits: self.nqubits, data: Tensor::from_vec(new_data, self.data.shape.clone()) }
@@ -124,4 +257,34 @@ impl Operator {
         }
         Operator { nqubits: self.nqubits, data: Tensor::from_vec(new_data, self.data.shape.clone()) }        
     }
+
+    pub fn is_unitary(&self, tol: f64) -> bool {
+        let size = 1 << self.nqubits;
+        let product = linalg::matmul(&self.data.data, &linalg::adjoint(&self.data.data, size), size);
+        linalg::max_abs_diff(&product, &linalg::identity(size)) < tol
+    }
+
+    // Fractional power U^alpha of a unitary, taking the principal branch of each eigenphase in (-pi, pi].
+    pub fn powf(&self, alpha: f64, tol: &TolerancePolicy) -> Result<Operator, SimulatorError> {
+        if !self.is_unitary(tol.unitarity) {
+            return Err(SimulatorError::NotUnitary);
+        }
+        let size = 1 << self.nqubits;
+        let (eigenvalues, v) = linalg::eig_normal(&self.data.data, size);
+        let powers = eigenvalues.iter().map(|lambda| {
+            let mut phase = lambda.arg();
+            if phase <= -f64::consts::PI + tol.unitarity {
+                phase = f64::consts::PI;
+            }
+            Complex::from_polar(1., alpha * phase)
+        }).collect::<Vec<_>>();
+        Ok(Operator {
+            nqubits: self.nqubits,
+            data: Tensor::from_vec(linalg::from_eigen(&powers, &v, size), self.data.shape.clone())
+        })
+    }
+
+    pub fn sqrt(&self, tol: &TolerancePolicy) -> Result<Operator, SimulatorError> {
+        self.powf(0.5, tol)
+    }
 }
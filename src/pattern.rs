@@ -1,23 +1,57 @@
-#[derive(Debug)]
+use std::collections::{BTreeMap, BTreeSet};
+
+use num_complex::Complex;
+use serde_json::{json, Value};
+
+use crate::feedback::Condition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Plane {
     XY,
     YZ,
     ZX
 }
 
-#[derive(Debug)]
+impl Plane {
+    // graphix names the ZX plane "XZ".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Plane::XY => "XY",
+            Plane::YZ => "YZ",
+            Plane::ZX => "XZ"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "XY" => Ok(Plane::XY),
+            "YZ" => Ok(Plane::YZ),
+            "XZ" | "ZX" => Ok(Plane::ZX),
+            _ => Err(format!("Unknown measurement plane {}.", name))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
     N(usize), // N(node)
+    NState(usize, [Complex<f64>; 2]), // NState(node, [alpha, beta]), preparing alpha |0> + beta |1>
     M(usize, Plane, f64, Vec<usize>, Vec<usize>, usize),    // M(node, plane, angle, s_domain, t_domain, vop)
     E((usize, usize)),  // E(node1, node2)
     C(usize, usize),    // C(node, cliff_index)
     X(usize, Vec<usize>),  // X(node, domain)
     Z(usize, Vec<usize>),  // Z(node, domain)
     T,  // T
-    S(usize, Vec<usize>)   // S(node, domain)
+    S(usize, Vec<usize>),  // S(node, domain)
+    XIf(usize, Condition), // XIf(node, condition)
+    ZIf(usize, Condition)  // ZIf(node, condition)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PatternRepr"))]
 pub struct Pattern {
     input_nodes: Vec<usize>,
     output_nodes: Vec<usize>,
@@ -25,18 +59,41 @@ pub struct Pattern {
     seq: Vec<Command>,
 }
 
+// Fields of a deserialized Pattern, replayed command by command like a JSON export.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PatternRepr {
+    input_nodes: Vec<usize>,
+    output_nodes: Vec<usize>,
+    n_nodes: usize,
+    seq: Vec<Command>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PatternRepr> for Pattern {
+    type Error = String;
+
+    fn try_from(repr: PatternRepr) -> Result<Self, String> {
+        let pattern = Pattern::from_parts(repr.input_nodes, repr.seq, Some(repr.output_nodes))?;
+        if pattern.n_nodes != repr.n_nodes {
+            return Err(format!("Pattern has {} nodes but declares {}.", pattern.n_nodes, repr.n_nodes));
+        }
+        Ok(pattern)
+    }
+}
+
 impl Pattern {
     pub fn new(input_nodes: Vec<usize>) -> Self {
         Pattern { 
-            input_nodes: input_nodes.iter().map(|e| *e).collect(),
-            output_nodes: input_nodes.iter().map(|e| *e).collect(),
+            input_nodes: input_nodes.clone(),
+            output_nodes: input_nodes.clone(),
             n_nodes: input_nodes.len(),
             seq: Vec::new()
         }
     }
 
     pub fn add(&mut self, command: Command) {
-        if let Command::N(node) = command {
+        if let Command::N(node) | Command::NState(node, _) = command {
             if self.output_nodes.contains(&node) {
                 panic!("Node already prepared!");
             }
@@ -55,8 +112,234 @@ impl Pattern {
     }
 
     pub fn extend(&mut self, commands: Vec<Command>) {
-        self.seq.extend(commands);
+        commands.into_iter().for_each(|command| self.add(command));
+    }
+
+    // Set the order of the output nodes, which must be a permutation of the current ones.
+    pub fn reorder_output_nodes(&mut self, output_nodes: Vec<usize>) -> Result<(), String> {
+        let mut current = self.output_nodes.clone();
+        let mut given = output_nodes.clone();
+        current.sort_unstable();
+        given.sort_unstable();
+        if current != given {
+            return Err(format!("{:?} is not a permutation of the output nodes {:?}.", output_nodes, self.output_nodes));
+        }
+        self.output_nodes = output_nodes;
+        Ok(())
+    }
+
+    pub fn input_nodes(&self) -> &[usize] {
+        &self.input_nodes
+    }
+
+    pub fn output_nodes(&self) -> &[usize] {
+        &self.output_nodes
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.n_nodes
     }
+
+    pub fn seq(&self) -> &[Command] {
+        &self.seq
+    }
+
+    // Equivalent pattern in standard form: preparations, then entanglement, then measurements,
+    // then corrections of the output nodes. Corrections of measured nodes are absorbed into the
+    // measurement domains, an X moved past an E leaving a Z on the other node of the edge.
+    pub fn standardize(&self) -> Result<Pattern, String> {
+        let mut preparations = Vec::new();
+        let mut edges = Vec::new();
+        let mut measurements = Vec::new();
+        let mut x_domains: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        let mut z_domains: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        let mut measured = BTreeSet::new();
+        for command in &self.seq {
+            match command {
+                Command::N(_) | Command::NState(..) => preparations.push(command.clone()),
+                Command::E((a, b)) => {
+                    if measured.contains(a) || measured.contains(b) {
+                        return Err(format!("Edge ({}, {}) is created after a measurement of one of its nodes.", a, b));
+                    }
+                    for (from, to) in [(a, b), (b, a)] {
+                        if let Some(domain) = x_domains.get(from).cloned() {
+                            let z = z_domains.entry(*to).or_default();
+                            *z = z.symmetric_difference(&domain).copied().collect();
+                        }
+                    }
+                    edges.push(command.clone());
+                },
+                Command::M(node, plane, angle, s_domain, t_domain, vop) => {
+                    let merge = |domain: &[usize], pending: Option<BTreeSet<usize>>| {
+                        let domain = domain.iter().copied().collect::<BTreeSet<_>>();
+                        domain.symmetric_difference(&pending.unwrap_or_default()).copied().collect::<Vec<_>>()
+                    };
+                    let s_domain = merge(s_domain, x_domains.remove(node));
+                    let t_domain = merge(t_domain, z_domains.remove(node));
+                    measured.insert(*node);
+                    measurements.push(Command::M(*node, *plane, *angle, s_domain, t_domain, *vop));
+                },
+                Command::X(node, domain) | Command::Z(node, domain) => {
+                    let domains = if matches!(command, Command::X(..)) { &mut x_domains } else { &mut z_domains };
+                    let pending = domains.entry(*node).or_default();
+                    for d in domain {
+                        if !pending.remove(d) {
+                            pending.insert(*d);
+                        }
+                    }
+                },
+                Command::T => {},
+                Command::C(node, _) | Command::S(node, _) | Command::XIf(node, _) | Command::ZIf(node, _) => return Err(format!("Cannot standardize the pattern: command on node {} is not supported.", node))
+            }
+        }
+        let mut seq = [preparations, edges, measurements].concat();
+        for node in &self.output_nodes {
+            for (domains, correction) in [(&x_domains, Command::X as fn(usize, Vec<usize>) -> Command), (&z_domains, Command::Z)] {
+                if let Some(domain) = domains.get(node).filter(|d| !d.is_empty()) {
+                    seq.push(correction(*node, domain.iter().copied().collect()));
+                }
+            }
+        }
+        Ok(Pattern {
+            input_nodes: self.input_nodes.clone(),
+            output_nodes: self.output_nodes.clone(),
+            n_nodes: self.n_nodes,
+            seq
+        })
+    }
+
+    // Serialize as graphix does, each command being a list starting with its name, e.g.
+    // ["M", node, "XY", angle, s_domain, t_domain, vop] with the angle in units of pi.
+    pub fn to_json(&self) -> String {
+        let seq = self.seq.iter().map(|command| match command {
+            Command::N(node) => json!(["N", node]),
+            Command::NState(node, [alpha, beta]) => json!(["N", node, [[alpha.re, alpha.im], [beta.re, beta.im]]]),
+            Command::M(node, plane, angle, s_domain, t_domain, vop) => json!(["M", node, plane.name(), angle, s_domain, t_domain, vop]),
+            Command::E((a, b)) => json!(["E", [a, b]]),
+            Command::C(node, cliff_index) => json!(["C", node, cliff_index]),
+            Command::X(node, domain) => json!(["X", node, domain]),
+            Command::Z(node, domain) => json!(["Z", node, domain]),
+            Command::T => json!(["T"]),
+            Command::S(node, domain) => json!(["S", node, domain]),
+            Command::XIf(node, condition) => json!(["X", node, condition.to_string()]),
+            Command::ZIf(node, condition) => json!(["Z", node, condition.to_string()])
+        }).collect::<Vec<Value>>();
+        json!({
+            "input_nodes": self.input_nodes,
+            "output_nodes": self.output_nodes,
+            "Nnode": self.n_nodes,
+            "seq": seq
+        }).to_string()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid pattern JSON: {}.", e))?;
+        let input_nodes = nodes(&value["input_nodes"], "input_nodes")?;
+        let seq = value["seq"].as_array()
+            .ok_or("Pattern JSON has no seq list.")?
+            .iter()
+            .map(parse_command)
+            .collect::<Result<Vec<Command>, String>>()?;
+        // graphix may reorder the output nodes, which matters for the final state.
+        let output_nodes = (!value["output_nodes"].is_null()).then(|| nodes(&value["output_nodes"], "output_nodes")).transpose()?;
+        Pattern::from_parts(input_nodes, seq, output_nodes)
+    }
+
+    // Replay the commands from the input nodes, refusing the sequences add would panic on, and
+    // reorder the outputs if given.
+    fn from_parts(input_nodes: Vec<usize>, seq: Vec<Command>, output_nodes: Option<Vec<usize>>) -> Result<Self, String> {
+        let mut pattern = Pattern::new(input_nodes);
+        for command in seq {
+            if let Command::M(node, ..) = command {
+                if !pattern.output_nodes.contains(&node) {
+                    return Err(format!("Node {} is measured before being prepared.", node));
+                }
+            }
+            if let Command::N(node) | Command::NState(node, _) = command {
+                if pattern.output_nodes.contains(&node) {
+                    return Err(format!("Node {} is prepared twice.", node));
+                }
+            }
+            pattern.add(command);
+        }
+        if let Some(output_nodes) = output_nodes {
+            let (mut expected, mut given) = (pattern.output_nodes.clone(), output_nodes.clone());
+            expected.sort_unstable();
+            given.sort_unstable();
+            if expected != given {
+                return Err("output_nodes do not match the unmeasured nodes of the pattern.".to_string());
+            }
+            pattern.output_nodes = output_nodes;
+        }
+        Ok(pattern)
+    }
+}
+
+fn node(value: &Value) -> Result<usize, String> {
+    value.as_u64().map(|n| n as usize).ok_or_else(|| format!("Expected a node index, got {}.", value))
+}
+
+fn nodes(value: &Value, name: &str) -> Result<Vec<usize>, String> {
+    value.as_array()
+        .ok_or_else(|| format!("Expected a list of nodes for {}.", name))?
+        .iter()
+        .map(node)
+        .collect()
+}
+
+fn amplitudes(value: &Value) -> Result<[Complex<f64>; 2], String> {
+    let error = || format!("Expected the amplitudes [[re, im], [re, im]] of a state, got {}.", value);
+    let amplitude = |value: &Value| match value.as_array().map(|parts| parts.iter().map(Value::as_f64).collect::<Vec<_>>()).as_deref() {
+        Some([Some(re), Some(im)]) => Ok(Complex::new(*re, *im)),
+        _ => Err(error())
+    };
+    match value.as_array().map(Vec::as_slice) {
+        Some([alpha, beta]) => Ok([amplitude(alpha)?, amplitude(beta)?]),
+        _ => Err(error())
+    }
+}
+
+fn parse_command(value: &Value) -> Result<Command, String> {
+    let items = value.as_array().ok_or_else(|| format!("Expected a command list, got {}.", value))?;
+    let name = items.first().and_then(Value::as_str).ok_or_else(|| format!("Command without a name: {}.", value))?;
+    let arg = |i: usize| items.get(i).ok_or_else(|| format!("Missing argument {} in command {}.", i, value));
+    let command = match name {
+        // Nodes are prepared in |+> unless the amplitudes [[re, im], [re, im]] are given.
+        "N" => match items.get(2) {
+            Some(state) => Command::NState(node(arg(1)?)?, amplitudes(state)?),
+            None => Command::N(node(arg(1)?)?)
+        },
+        "M" => Command::M(
+            node(arg(1)?)?,
+            Plane::from_name(arg(2)?.as_str().ok_or("Measurement plane should be a string.")?)?,
+            arg(3)?.as_f64().ok_or("Measurement angle should be a number.")?,
+            nodes(arg(4)?, "s_domain")?,
+            nodes(arg(5)?, "t_domain")?,
+            // Older exports omit the vertex operator.
+            items.get(6).map(node).transpose()?.unwrap_or(0),
+        ),
+        "E" => {
+            let edge = nodes(arg(1)?, "edge")?;
+            match edge[..] {
+                [a, b] => Command::E((a, b)),
+                _ => return Err(format!("An edge links two nodes, got {}.", value))
+            }
+        },
+        "C" => Command::C(node(arg(1)?)?, node(arg(2)?)?),
+        // Corrections are conditioned on an expression when it is given instead of a domain.
+        "X" => match arg(2)?.as_str() {
+            Some(condition) => Command::XIf(node(arg(1)?)?, Condition::parse(condition)?),
+            None => Command::X(node(arg(1)?)?, nodes(arg(2)?, "domain")?)
+        },
+        "Z" => match arg(2)?.as_str() {
+            Some(condition) => Command::ZIf(node(arg(1)?)?, Condition::parse(condition)?),
+            None => Command::Z(node(arg(1)?)?, nodes(arg(2)?, "domain")?)
+        },
+        "T" => Command::T,
+        "S" => Command::S(node(arg(1)?)?, nodes(arg(2)?, "domain")?),
+        _ => return Err(format!("Unknown command {}.", name))
+    };
+    Ok(command)
 }
 
 #[cfg(test)]
@@ -81,7 +364,7 @@ mod pattern_tests {
             Test for initializing empty pattern.
          */
         let input_nodes: [usize; 5] = [1, 2, 3, 4, 5];
-        let _pattern = Pattern::new(input_nodes.iter().map(|n| *n).collect::<Vec<usize>>());
+        let _pattern = Pattern::new(input_nodes.to_vec());
         assert!(_pattern.input_nodes.len() == 5);
         assert!(_pattern.output_nodes.len() == 5);
         assert!(_pattern.n_nodes == 5);
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::checkpoint::write_atomic;
+use crate::open_graph::OpenGraph;
+use crate::pattern::Pattern;
+
+// Content-addressed directory of compiled patterns, so that sweeps reusing the same sub-patterns
+// (CNOTs, T gadgets, ...) compile each of them once. An entry <key>.json holds the JSON of the
+// pattern compiled from an open graph and its angles, the key being a hash of both:
+//
+//     key = fnv1a128(nodes, edges, inputs, outputs, planes, angles)
+//
+// over a canonical encoding, with edges as sorted (min, max) pairs since CZs commute, and only
+// the angles of measured nodes. The hash is fixed here rather than taken from std, whose hashers
+// may change between releases, so a library stays valid across versions of this crate.
+#[derive(Debug, Clone)]
+pub struct PatternLibrary {
+    dir: PathBuf
+}
+
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+fn fnv1a128(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &b| (hash ^ b as u128).wrapping_mul(FNV_PRIME))
+}
+
+// Length-prefixed so that consecutive lists cannot be confused.
+fn push_nodes(bytes: &mut Vec<u8>, nodes: &[usize]) {
+    bytes.extend((nodes.len() as u64).to_le_bytes());
+    nodes.iter().for_each(|&node| bytes.extend((node as u64).to_le_bytes()));
+}
+
+impl PatternLibrary {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}.", dir.display(), e))?;
+        Ok(PatternLibrary { dir: dir.to_path_buf() })
+    }
+
+    // Hash of the canonical open graph and the angles of its measured nodes, as 32 hex digits.
+    pub fn key(graph: &OpenGraph, angles: &BTreeMap<usize, f64>) -> Result<String, String> {
+        let mut bytes = Vec::new();
+        push_nodes(&mut bytes, &graph.nodes.iter().copied().collect::<Vec<_>>());
+        let mut edges = graph.edges.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect::<Vec<_>>();
+        edges.sort_unstable();
+        push_nodes(&mut bytes, &edges.iter().flat_map(|&(a, b)| [a, b]).collect::<Vec<_>>());
+        push_nodes(&mut bytes, &graph.inputs);
+        push_nodes(&mut bytes, &graph.outputs);
+        bytes.extend((graph.planes.len() as u64).to_le_bytes());
+        for (&node, plane) in &graph.planes {
+            let angle = angles.get(&node).ok_or_else(|| format!("Missing measurement angle for node {}.", node))?;
+            bytes.extend((node as u64).to_le_bytes());
+            bytes.extend(plane.name().as_bytes());
+            // -0 and 0 are the same angle.
+            bytes.extend((angle + 0.).to_bits().to_le_bytes());
+        }
+        Ok(format!("{:032x}", fnv1a128(&bytes)))
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    // Stored pattern of the graph and angles, if any.
+    pub fn get(&self, graph: &OpenGraph, angles: &BTreeMap<usize, f64>) -> Result<Option<Pattern>, String> {
+        let path = self.path(&PatternLibrary::key(graph, angles)?);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}.", path.display(), e))?;
+        Pattern::from_json(&json).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Load the stored pattern of the graph and angles, or compile it with OpenGraph::to_pattern
+    // and store it. Entries that cannot be read back are compiled and written again, since they
+    // can always be rebuilt.
+    pub fn get_or_compile(&self, graph: &OpenGraph, angles: &BTreeMap<usize, f64>) -> Result<Pattern, String> {
+        if let Ok(Some(pattern)) = self.get(graph, angles) {
+            return Ok(pattern);
+        }
+        let pattern = graph.to_pattern(angles)?;
+        let path = self.path(&PatternLibrary::key(graph, angles)?);
+        write_atomic(&path, pattern.to_json().as_bytes())?;
+        Ok(pattern)
+    }
+}
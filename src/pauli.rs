@@ -0,0 +1,283 @@
+use core::fmt;
+use std::str::FromStr;
+
+use num_complex::Complex;
+
+use crate::error::SimulatorError;
+use crate::operators::Operator;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Pauli {
+    I,
+    X,
+    Y,
+    Z
+}
+
+impl Pauli {
+    // Matrix element <row|P|col> for single qubit basis states.
+    pub fn element(&self, row: usize, col: usize) -> Complex<f64> {
+        match (self, row, col) {
+            (Pauli::I, r, c) if r == c => Complex::ONE,
+            (Pauli::X, r, c) if r != c => Complex::ONE,
+            (Pauli::Y, 0, 1) => Complex::new(0., -1.),
+            (Pauli::Y, 1, 0) => Complex::new(0., 1.),
+            (Pauli::Z, 0, 0) => Complex::ONE,
+            (Pauli::Z, 1, 1) => Complex::new(-1., 0.),
+            _ => Complex::ZERO
+        }
+    }
+
+    // Whether the Pauli flips the computational basis state it acts on.
+    pub fn flips(&self) -> bool {
+        matches!(self, Pauli::X | Pauli::Y)
+    }
+
+    // Product self * other = phase * P.
+    pub fn product(&self, other: &Pauli) -> (Complex<f64>, Pauli) {
+        let i = Complex::I;
+        match (self, other) {
+            (Pauli::I, p) | (p, Pauli::I) => (Complex::ONE, *p),
+            (a, b) if a == b => (Complex::ONE, Pauli::I),
+            (Pauli::X, Pauli::Y) => (i, Pauli::Z),
+            (Pauli::Y, Pauli::X) => (-i, Pauli::Z),
+            (Pauli::Y, Pauli::Z) => (i, Pauli::X),
+            (Pauli::Z, Pauli::Y) => (-i, Pauli::X),
+            (Pauli::Z, Pauli::X) => (i, Pauli::Y),
+            _ => (-i, Pauli::Y)  // X * Z
+        }
+    }
+}
+
+// Tensor product of single qubit Paulis, the i-th character acting on qubit i.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PauliString {
+    pub paulis: Vec<Pauli>
+}
+
+impl PauliString {
+    pub fn new(paulis: Vec<Pauli>) -> Self {
+        PauliString { paulis }
+    }
+
+    pub fn nqubits(&self) -> usize {
+        self.paulis.len()
+    }
+
+    // Bitmask of the qubits flipped by the string, qubit 0 being the most significant bit.
+    pub fn x_mask(&self) -> usize {
+        let n = self.nqubits();
+        self.paulis.iter().enumerate()
+            .filter(|(_, p)| p.flips())
+            .fold(0, |mask, (q, _)| mask | (1 << (n - 1 - q)))
+    }
+
+    // Product self * other = phase * P, qubit by qubit.
+    pub fn product(&self, other: &PauliString) -> Result<(Complex<f64>, PauliString), String> {
+        if self.nqubits() != other.nqubits() {
+            return Err(format!("Cannot multiply Pauli strings on {} and {} qubits.", self.nqubits(), other.nqubits()));
+        }
+        let (phase, paulis) = self.paulis.iter().zip(other.paulis.iter())
+            .fold((Complex::ONE, Vec::with_capacity(self.nqubits())), |(phase, mut paulis), (a, b)| {
+                let (p, pauli) = a.product(b);
+                paulis.push(pauli);
+                (phase * p, paulis)
+            });
+        Ok((phase, PauliString { paulis }))
+    }
+
+    // Two Pauli strings commute when they anticommute on an even number of qubits.
+    pub fn commutes_with(&self, other: &PauliString) -> bool {
+        self.paulis.iter().zip(other.paulis.iter())
+            .filter(|(a, b)| **a != Pauli::I && **b != Pauli::I && a != b)
+            .count() % 2 == 0
+    }
+
+    // Dense 2^n x 2^n matrix of the string.
+    pub fn matrix(&self) -> Vec<Complex<f64>> {
+        let d = 1 << self.nqubits();
+        let x_mask = self.x_mask();
+        let mut matrix = vec![Complex::ZERO; d * d];
+        for col in 0..d {
+            matrix[(col ^ x_mask) * d + col] = self.phase(col);
+        }
+        matrix
+    }
+
+    // Phase picked up by the basis state |col> when the string is applied to it.
+    pub fn phase(&self, col: usize) -> Complex<f64> {
+        let n = self.nqubits();
+        self.paulis.iter().enumerate().fold(Complex::ONE, |phase, (q, p)| {
+            let bit = (col >> (n - 1 - q)) & 1;
+            let flipped = if p.flips() { bit ^ 1 } else { bit };
+            phase * p.element(flipped, bit)
+        })
+    }
+}
+
+impl FromStr for PauliString {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paulis = s.chars().map(|c| match c.to_ascii_uppercase() {
+            'I' => Ok(Pauli::I),
+            'X' => Ok(Pauli::X),
+            'Y' => Ok(Pauli::Y),
+            'Z' => Ok(Pauli::Z),
+            _ => Err(format!("Invalid Pauli character '{}' in \"{}\".", c, s))
+        }).collect::<Result<Vec<_>, _>>()?;
+        if paulis.is_empty() {
+            return Err("A Pauli string acts on at least one qubit.".to_string());
+        }
+        Ok(PauliString { paulis })
+    }
+}
+
+impl fmt::Display for PauliString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for p in &self.paulis {
+            write!(f, "{:?}", p)?;
+        }
+        Ok(())
+    }
+}
+
+// Pauli operator i^phase P_0 x ... x P_{n-1} stored as X and Z bitmasks, qubit q being bit q % 64
+// of word q / 64. (x, z) = (1, 0) is X, (0, 1) is Z and (1, 1) is Y, so that products and
+// commutation checks are a few bitwise operations per 64 qubits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SparsePauliOp {
+    pub nqubits: usize,
+    pub x: Vec<u64>,
+    pub z: Vec<u64>,
+    pub phase: u8   // Power of i, in 0..4.
+}
+
+impl SparsePauliOp {
+    // Identity on nqubits.
+    pub fn new(nqubits: usize) -> Self {
+        let words = nqubits.div_ceil(64);
+        SparsePauliOp { nqubits, x: vec![0; words], z: vec![0; words], phase: 0 }
+    }
+
+    // Single qubit Pauli acting on qubit q of nqubits.
+    pub fn single(nqubits: usize, q: usize, pauli: Pauli) -> Result<Self, SimulatorError> {
+        let mut op = SparsePauliOp::new(nqubits);
+        op.set(q, pauli)?;
+        Ok(op)
+    }
+
+    pub fn from_pauli_string(pauli_string: &PauliString) -> Self {
+        let mut op = SparsePauliOp::new(pauli_string.nqubits());
+        for (q, p) in pauli_string.paulis.iter().enumerate() {
+            op.set(q, *p).unwrap();
+        }
+        op
+    }
+
+    // Split into its phase and the Pauli string without phase.
+    pub fn to_pauli_string(&self) -> (Complex<f64>, PauliString) {
+        (self.phase(), PauliString::new((0..self.nqubits).map(|q| self.pauli(q)).collect()))
+    }
+
+    pub fn pauli(&self, q: usize) -> Pauli {
+        let (word, bit) = (q / 64, 1 << (q % 64));
+        match (self.x[word] & bit != 0, self.z[word] & bit != 0) {
+            (false, false) => Pauli::I,
+            (true, false) => Pauli::X,
+            (true, true) => Pauli::Y,
+            (false, true) => Pauli::Z
+        }
+    }
+
+    pub fn set(&mut self, q: usize, pauli: Pauli) -> Result<(), SimulatorError> {
+        if q >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index: q, nqubits: self.nqubits });
+        }
+        let (word, bit) = (q / 64, 1 << (q % 64));
+        let (x, z) = match pauli {
+            Pauli::I => (false, false),
+            Pauli::X => (true, false),
+            Pauli::Y => (true, true),
+            Pauli::Z => (false, true)
+        };
+        self.x[word] = if x { self.x[word] | bit } else { self.x[word] & !bit };
+        self.z[word] = if z { self.z[word] | bit } else { self.z[word] & !bit };
+        Ok(())
+    }
+
+    pub fn phase(&self) -> Complex<f64> {
+        Complex::I.powu(self.phase as u32)
+    }
+
+    // Number of qubits on which the operator is not the identity.
+    pub fn weight(&self) -> usize {
+        self.x.iter().zip(self.z.iter()).map(|(x, z)| (x | z).count_ones() as usize).sum()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.weight() == 0
+    }
+
+    // Two Paulis commute when their symplectic product x1.z2 + z1.x2 is even.
+    pub fn commutes_with(&self, other: &SparsePauliOp) -> bool {
+        self.x.iter().zip(self.z.iter()).zip(other.x.iter().zip(other.z.iter()))
+            .map(|((x1, z1), (x2, z2))| ((x1 & z2) ^ (z1 & x2)).count_ones())
+            .sum::<u32>() % 2 == 0
+    }
+
+    // Product self * other, qubit by qubit.
+    pub fn product(&self, other: &SparsePauliOp) -> Result<SparsePauliOp, SimulatorError> {
+        if self.nqubits != other.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: other.nqubits });
+        }
+        // Qubits where the single qubit product picks up +i (XY, YZ, ZX) or -i (YX, ZY, XZ).
+        let (mut plus, mut minus) = (0, 0);
+        for ((x1, z1), (x2, z2)) in self.x.iter().zip(self.z.iter()).zip(other.x.iter().zip(other.z.iter())) {
+            let (y1, only_x1, only_z1) = (x1 & z1, x1 & !z1, z1 & !x1);
+            let (y2, only_x2, only_z2) = (x2 & z2, x2 & !z2, z2 & !x2);
+            plus += ((only_x1 & y2) | (y1 & only_z2) | (only_z1 & only_x2)).count_ones();
+            minus += ((y1 & only_x2) | (only_z1 & y2) | (only_x1 & only_z2)).count_ones();
+        }
+        Ok(SparsePauliOp {
+            nqubits: self.nqubits,
+            x: self.x.iter().zip(other.x.iter()).map(|(a, b)| a ^ b).collect(),
+            z: self.z.iter().zip(other.z.iter()).map(|(a, b)| a ^ b).collect(),
+            phase: ((self.phase as u32 + other.phase as u32 + plus + 3 * minus) % 4) as u8
+        })
+    }
+
+    // Dense operator, qubit 0 being the most significant bit as everywhere else.
+    pub fn to_operator(&self) -> Operator {
+        let (phase, pauli_string) = self.to_pauli_string();
+        let matrix = pauli_string.matrix().iter().map(|c| c * phase).collect::<Vec<_>>();
+        Operator::from_matrix(&matrix, self.nqubits).unwrap()
+    }
+}
+
+// Strings such as "XZIY", optionally preceded by a sign and a factor i, e.g. "-iXY". A leading
+// lowercase i is always read as the phase, the identity being written I.
+impl FromStr for SparsePauliOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s))
+        };
+        let (imaginary, rest) = match rest.strip_prefix('i') {
+            Some(rest) => (true, rest),
+            None => (false, rest)
+        };
+        let mut op = SparsePauliOp::from_pauli_string(&rest.parse()?);
+        op.phase = 2 * u8::from(negative) + u8::from(imaginary);
+        Ok(op)
+    }
+}
+
+impl fmt::Display for SparsePauliOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", ["", "i", "-", "-i"][self.phase as usize])?;
+        write!(f, "{}", self.to_pauli_string().1)
+    }
+}
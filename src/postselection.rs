@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use crate::density_matrix::DensityMatrix;
+use crate::pauli::PauliString;
+use crate::simulator::SimulationResult;
+
+// Estimates over post-selected shots: the shots whose measured nodes gave the outcomes of a
+// selection, e.g. [(3, 1), (7, 0)] for s[3] = 1 and s[7] = 0. Bins kept by fewer shots than the
+// requested minimum are still estimated, with a warning since their error bars are unreliable.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalEstimate {
+    pub mean: f64,
+    pub standard_error: f64,        // Standard error of the mean over the selected shots.
+    pub shots: usize,               // Number of selected shots.
+    pub warning: Option<String>
+}
+
+fn selected(outcomes: &HashMap<usize, u8>, selection: &[(usize, u8)]) -> Result<bool, String> {
+    selection.iter().try_fold(true, |acc, (node, outcome)| {
+        let measured = outcomes.get(node).ok_or_else(|| format!("Node {} is not measured.", node))?;
+        Ok(acc && measured == outcome)
+    })
+}
+
+// (-1)^(s_a + s_b + ...) of the outcomes of the correlated nodes.
+fn sign(outcomes: &HashMap<usize, u8>, correlated: &[usize]) -> Result<f64, String> {
+    let parity = correlated.iter().try_fold(0, |acc, node| {
+        outcomes.get(node).map(|outcome| acc ^ outcome).ok_or_else(|| format!("Node {} is not measured.", node))
+    })?;
+    Ok(if parity == 0 { 1. } else { -1. })
+}
+
+fn estimate(values: &[f64], total: usize, min_shots: usize) -> Result<ConditionalEstimate, String> {
+    let n = values.len();
+    if n == 0 {
+        return Err(format!("None of the {} shots matches the selection.", total));
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let standard_error = if n > 1 {
+        (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64 / n as f64).sqrt()
+    } else {
+        f64::INFINITY
+    };
+    let warning = (n < min_shots).then(|| format!("Only {} of {} shots match the selection, fewer than the {} requested.", n, total, min_shots));
+    Ok(ConditionalEstimate { mean, standard_error, shots: n, warning })
+}
+
+impl<B> SimulationResult<B> {
+    // Correlator of the outcomes of the correlated nodes among the selected shots.
+    pub fn conditional_correlator(&self, selection: &[(usize, u8)], correlated: &[usize], min_shots: usize) -> Result<ConditionalEstimate, String> {
+        let mut values = Vec::new();
+        for shot in &self.shots {
+            if selected(&shot.outcomes, selection)? {
+                values.push(sign(&shot.outcomes, correlated)?);
+            }
+        }
+        estimate(&values, self.shots.len(), min_shots)
+    }
+}
+
+impl SimulationResult<DensityMatrix> {
+    // Expectation of the observable on the output states of the selected shots.
+    pub fn conditional_expectation(&self, selection: &[(usize, u8)], observable: &PauliString, min_shots: usize) -> Result<ConditionalEstimate, String> {
+        let mut values = Vec::new();
+        for shot in &self.shots {
+            if selected(&shot.outcomes, selection)? {
+                values.push(shot.state.expectation(observable)?);
+            }
+        }
+        estimate(&values, self.shots.len(), min_shots)
+    }
+}
+
+// Same as SimulationResult::conditional_correlator, from shots stored as measurement records of
+// the given nodes, e.g. loaded with records::load_records.
+pub fn records_correlator(nodes: &[usize], shots: &[Vec<u8>], selection: &[(usize, u8)], correlated: &[usize], min_shots: usize) -> Result<ConditionalEstimate, String> {
+    let mut values = Vec::new();
+    for shot in shots {
+        if shot.len() != nodes.len() {
+            return Err(format!("Shot has {} outcomes but the records have {} nodes.", shot.len(), nodes.len()));
+        }
+        let outcomes = nodes.iter().copied().zip(shot.iter().copied()).collect::<HashMap<_, _>>();
+        if selected(&outcomes, selection)? {
+            values.push(sign(&outcomes, correlated)?);
+        }
+    }
+    estimate(&values, shots.len(), min_shots)
+}
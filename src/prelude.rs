@@ -0,0 +1,16 @@
+// Types needed by most simulations, to be glob imported with `use dm_simu_rs::prelude::*`.
+
+pub use crate::backend::QuantumBackend;
+pub use crate::channels::Channel;
+pub use crate::circuit::Circuit;
+pub use crate::density_matrix::{Basis, DensityMatrix, State};
+pub use crate::mps::Mps;
+pub use crate::noise::{CommandKind, NoiseModel};
+pub use crate::operators::{OneQubitOp, Operator, TwoQubitsOp};
+pub use crate::pattern::{Command, Pattern, Plane};
+pub use crate::pauli::{Pauli, PauliString};
+pub use crate::rng::{RngConfig, RngKind};
+pub use crate::runner::RunResult;
+pub use crate::simulator::{SimulationResult, Simulator};
+pub use crate::stabilizer::Stabilizer;
+pub use crate::statevector::StateVector;
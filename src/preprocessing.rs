@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+
+use crate::clifford::Clifford;
+use crate::config::TolerancePolicy;
+use crate::pattern::{Command, Pattern, Plane};
+use crate::pauli::Pauli;
+use crate::runner::measurement_vector;
+
+// Pauli measurement preprocessing. Once standardized, a pattern prepares a graph state and
+// measures it, and a Pauli measurement of a graph state leaves a graph state on the other nodes,
+// up to local Clifford gates (Hein et al., Entanglement in graph states, 2006). These
+// measurements are simulated here on the graph, and the reduced pattern only measures the
+// remaining nodes, the local Cliffords being absorbed into their measurement bases.
+
+// Outcome of a simulated measurement, equal to the constant XOR the outcomes of the listed
+// nodes, which are measured when running the reduced pattern.
+type Signal = (bool, BTreeSet<usize>);
+
+fn toggle(set: &mut BTreeSet<usize>, node: usize) {
+    if !set.remove(&node) {
+        set.insert(node);
+    }
+}
+
+fn xor(a: &Signal, b: &Signal) -> Signal {
+    (a.0 != b.0, a.1.symmetric_difference(&b.1).copied().collect())
+}
+
+// Pauli axis and sign of a Bloch vector lying on an axis.
+fn pauli_axis(n: [f64; 3], tol: &TolerancePolicy) -> Option<(f64, Pauli)> {
+    [Pauli::X, Pauli::Y, Pauli::Z].into_iter().zip(n)
+        .find(|(_, x)| (x.abs() - 1.).abs() < tol.equality)
+        .map(|(pauli, x)| (x.signum(), pauli))
+}
+
+// Bloch vector n' with C^dagger (n.sigma) C = n'.sigma.
+fn conjugate_vector(clifford: &Clifford, n: [f64; 3]) -> [f64; 3] {
+    let mut result = [0.; 3];
+    for (pauli, component) in [Pauli::X, Pauli::Y, Pauli::Z].into_iter().zip(n) {
+        let (sign, image) = clifford.conjugate(pauli);
+        let axis = match image { Pauli::X => 0, Pauli::Y => 1, _ => 2 };
+        result[axis] += sign * component;
+    }
+    result
+}
+
+// Plane and angle (in units of pi) of a Bloch vector, inverting runner::measurement_vector.
+fn plane_angle(n: [f64; 3], tol: &TolerancePolicy) -> (Plane, f64) {
+    if n[2].abs() < tol.equality {
+        (Plane::XY, n[1].atan2(n[0]) / PI)
+    } else if n[1].abs() < tol.equality {
+        (Plane::ZX, n[0].atan2(n[2]) / PI)
+    } else {
+        (Plane::YZ, n[1].atan2(n[2]) / PI)
+    }
+}
+
+struct GraphWithCliffords {
+    adjacency: BTreeMap<usize, BTreeSet<usize>>,
+    vops: BTreeMap<usize, Clifford>   // The state is prod_v vops[v] |G>.
+}
+
+impl GraphWithCliffords {
+    fn neighbors(&self, node: usize) -> BTreeSet<usize> {
+        self.adjacency[&node].clone()
+    }
+
+    fn toggle_edge(&mut self, a: usize, b: usize) {
+        toggle(self.adjacency.get_mut(&a).unwrap(), b);
+        toggle(self.adjacency.get_mut(&b).unwrap(), a);
+    }
+
+    fn local_complement(&mut self, node: usize) {
+        let neighbors = self.neighbors(node).into_iter().collect::<Vec<_>>();
+        for (i, &a) in neighbors.iter().enumerate() {
+            for &b in &neighbors[i + 1..] {
+                self.toggle_edge(a, b);
+            }
+        }
+    }
+
+    fn remove(&mut self, node: usize) {
+        for neighbor in self.neighbors(node) {
+            self.adjacency.get_mut(&neighbor).unwrap().remove(&node);
+        }
+        self.adjacency.remove(&node);
+        self.vops.remove(&node);
+    }
+
+    // Apply a local Clifford before the current vertex operator.
+    fn apply(&mut self, node: usize, clifford: Clifford) {
+        let vop = self.vops.get_mut(&node).unwrap();
+        *vop = clifford.then(vop);
+    }
+
+    // Project the graph state on the outcome of the Pauli measurement of node. For X, b0 is a
+    // neighbor of node prepared in |+>.
+    fn measure(&mut self, node: usize, pauli: Pauli, outcome: bool, b0: Option<usize>) {
+        let neighbors = self.neighbors(node);
+        match pauli {
+            Pauli::Z => {
+                self.remove(node);
+                if outcome {
+                    neighbors.iter().for_each(|&b| self.apply(b, Clifford::pauli(Pauli::Z)));
+                }
+            },
+            Pauli::Y => {
+                self.local_complement(node);
+                self.remove(node);
+                let sign = if outcome { 1. } else { -1. };
+                neighbors.iter().for_each(|&b| self.apply(b, Clifford::sqrt_pauli(Pauli::Z, sign)));
+            },
+            _ => {
+                let Some(b0) = b0 else {
+                    // An isolated |+> always gives the outcome 0.
+                    self.remove(node);
+                    return;
+                };
+                let b0_neighbors = self.neighbors(b0);
+                self.local_complement(b0);
+                self.local_complement(node);
+                self.local_complement(b0);
+                self.remove(node);
+                let (sign, flipped) = if outcome {
+                    (-1., b0_neighbors.iter().filter(|b| !neighbors.contains(b) && **b != node).copied().collect::<Vec<_>>())
+                } else {
+                    (1., neighbors.iter().filter(|b| !b0_neighbors.contains(b) && **b != b0).copied().collect::<Vec<_>>())
+                };
+                self.apply(b0, Clifford::sqrt_pauli(Pauli::Y, sign));
+                flipped.iter().for_each(|&b| self.apply(b, Clifford::pauli(Pauli::Z)));
+            }
+        }
+    }
+}
+
+impl Pattern {
+    // Simulate every measurement along a Pauli axis, sampling its outcome, and return the
+    // pattern measuring the remaining nodes. Input nodes are kept, since their state is only
+    // known when running the pattern, and so is a node measured along X whose neighbors are all
+    // inputs. Measurements within the equality tolerance of a Pauli axis count as Pauli ones.
+    pub fn perform_pauli_measurements(&self, tol: &TolerancePolicy, rng: &mut dyn RngCore) -> Result<Pattern, String> {
+        let standard = self.standardize()?;
+        let inputs = standard.input_nodes().to_vec();
+        let mut graph = GraphWithCliffords { adjacency: BTreeMap::new(), vops: BTreeMap::new() };
+        for &node in &inputs {
+            graph.adjacency.insert(node, BTreeSet::new());
+            graph.vops.insert(node, Clifford::identity());
+        }
+        let mut signals: BTreeMap<usize, Signal> = BTreeMap::new();
+        let expand = |signals: &BTreeMap<usize, Signal>, domain: &[usize]| {
+            domain.iter().fold((false, BTreeSet::new()), |acc, node| match signals.get(node) {
+                Some(signal) => xor(&acc, signal),
+                None => xor(&acc, &(false, BTreeSet::from([*node])))
+            })
+        };
+
+        // Remaining measurements, as Bloch vectors with the constant part of their domains applied.
+        let mut remaining = Vec::new();
+        let mut corrections = Vec::new();
+        for command in standard.seq() {
+            match command {
+                Command::N(node) => {
+                    graph.adjacency.insert(*node, BTreeSet::new());
+                    graph.vops.insert(*node, Clifford::identity());
+                },
+                Command::NState(node, _) => return Err(format!("Node {} is not prepared in |+>, so the pattern does not act on a graph state.", node)),
+                Command::E((a, b)) => graph.toggle_edge(*a, *b),
+                Command::M(node, plane, angle, s_domain, t_domain, vop) => {
+                    if *vop != 0 {
+                        return Err(format!("Measurement of node {} has a vertex operator, which is not supported.", node));
+                    }
+                    let (s_const, s_nodes) = expand(&signals, s_domain);
+                    let (t_const, t_nodes) = expand(&signals, t_domain);
+                    let mut n = measurement_vector(*plane, *angle);
+                    if t_const {
+                        n = [-n[0], -n[1], n[2]];
+                    }
+                    if s_const {
+                        n = [n[0], -n[1], -n[2]];
+                    }
+                    let b0 = graph.neighbors(*node).into_iter().find(|b| !inputs.contains(b));
+                    let measurable = match pauli_axis(n, tol) {
+                        Some((sign, axis)) if !inputs.contains(node) => {
+                            let (vop_sign, pauli) = graph.vops[node].conjugate(axis);
+                            (pauli != Pauli::X || b0.is_some() || graph.neighbors(*node).is_empty())
+                                .then_some((sign * vop_sign, axis, pauli))
+                        },
+                        _ => None
+                    };
+                    match measurable {
+                        Some((sign, axis, pauli)) => {
+                            let isolated_x = pauli == Pauli::X && b0.is_none();
+                            let outcome = !isolated_x && rng.gen::<bool>();
+                            graph.measure(*node, pauli, outcome, b0);
+                            // Runtime X corrections flip the Y and Z axes, Z corrections the X and Y axes.
+                            let mut flips = BTreeSet::new();
+                            if axis != Pauli::X {
+                                flips = flips.symmetric_difference(&s_nodes).copied().collect();
+                            }
+                            if axis != Pauli::Z {
+                                flips = flips.symmetric_difference(&t_nodes).copied().collect();
+                            }
+                            signals.insert(*node, (outcome != (sign < 0.), flips));
+                        },
+                        None => remaining.push((*node, n, s_nodes, t_nodes))
+                    }
+                },
+                Command::X(node, domain) | Command::Z(node, domain) => {
+                    corrections.push((*node, matches!(command, Command::X(..)), expand(&signals, domain)));
+                },
+                _ => {}
+            }
+        }
+
+        let mut pattern = Pattern::new(inputs.clone());
+        for node in graph.adjacency.keys().filter(|node| !inputs.contains(node)) {
+            pattern.add(Command::N(*node));
+        }
+        for (&a, neighbors) in &graph.adjacency {
+            for &b in neighbors.iter().filter(|&&b| a < b) {
+                pattern.add(Command::E((a, b)));
+            }
+        }
+        for (node, n, s_nodes, t_nodes) in remaining {
+            // Measuring n on vop |G> is measuring vop^dagger n vop on |G>, and the byproducts
+            // are conjugated the same way.
+            let vop = graph.vops[&node];
+            let (plane, angle) = plane_angle(conjugate_vector(&vop, n), tol);
+            let (mut s_domain, mut t_domain) = (BTreeSet::new(), BTreeSet::new());
+            for (pauli, nodes) in [(Pauli::X, &s_nodes), (Pauli::Z, &t_nodes)] {
+                let (_, image) = vop.conjugate(pauli);
+                if image != Pauli::Z {
+                    s_domain = s_domain.symmetric_difference(nodes).copied().collect();
+                }
+                if image != Pauli::X {
+                    t_domain = t_domain.symmetric_difference(nodes).copied().collect();
+                }
+            }
+            pattern.add(Command::M(node, plane, angle, s_domain.into_iter().collect(), t_domain.into_iter().collect(), 0));
+        }
+        for &node in standard.output_nodes() {
+            // Constant corrections join the vertex operator, the other ones are kept.
+            let mut fixed = graph.vops[&node];
+            let mut runtime = Vec::new();
+            for (_, is_x, (constant, nodes)) in corrections.iter().filter(|(n, _, _)| *n == node) {
+                let pauli = if *is_x { Pauli::X } else { Pauli::Z };
+                if *constant {
+                    fixed = fixed.then(&Clifford::pauli(pauli));
+                }
+                if !nodes.is_empty() {
+                    runtime.push((pauli, nodes.iter().copied().collect::<Vec<_>>()));
+                }
+            }
+            if fixed != Clifford::identity() {
+                pattern.add(Command::C(node, fixed.index()));
+            }
+            for (pauli, domain) in runtime {
+                pattern.add(if pauli == Pauli::X { Command::X(node, domain) } else { Command::Z(node, domain) });
+            }
+        }
+        pattern.reorder_output_nodes(standard.output_nodes().to_vec())?;
+        Ok(pattern)
+    }
+}
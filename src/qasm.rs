@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::circuit::Circuit;
+
+// OpenQASM 2.0 import for the gates of qelib1.inc. Gates without a Circuit counterpart are
+// rewritten with H, S, RX, RY, RZ and CNOT, up to a global phase. Measurements are only accepted
+// after the last gate on their qubits and are dropped, so the circuit gives the state before
+// readout. Classical control, resets and custom gate definitions are rejected.
+
+impl Circuit {
+    pub fn from_qasm(source: &str) -> Result<Circuit, String> {
+        let statements = strip_comments(source);
+        let mut statements = statements.split(';').map(str::trim).filter(|s| !s.is_empty());
+        match statements.next() {
+            Some(header) if header.split_whitespace().collect::<Vec<_>>() == ["OPENQASM", "2.0"] => {},
+            _ => return Err("QASM source should start with OPENQASM 2.0;.".to_string())
+        }
+
+        // Registers are laid out one after the other in declaration order.
+        let mut registers: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut width = 0;
+        let mut gates: Vec<GateCall> = Vec::new();
+        let mut measured = Vec::new();
+        for statement in statements {
+            let (keyword, rest) = split_keyword(statement);
+            match keyword {
+                "include" => {
+                    if rest.trim() != "\"qelib1.inc\"" {
+                        return Err(format!("Only qelib1.inc can be included, got {}.", rest.trim()));
+                    }
+                },
+                "qreg" => {
+                    let (name, size) = parse_register(rest)?;
+                    if registers.insert(name.clone(), (width, size)).is_some() {
+                        return Err(format!("Register {} is declared twice.", name));
+                    }
+                    width += size;
+                },
+                "creg" | "barrier" => {},
+                "measure" => {
+                    let (qubits, _) = rest.split_once("->").ok_or_else(|| format!("Invalid measurement: {}.", statement))?;
+                    measured.extend(parse_argument(qubits, &registers)?);
+                },
+                "gate" | "opaque" => return Err("Custom gate definitions are not supported.".to_string()),
+                "reset" | "if" => return Err(format!("{} statements are not supported.", keyword)),
+                _ => {
+                    let gate = parse_gate(statement, &registers)?;
+                    if let Some(q) = gate.args.iter().flatten().find(|q| measured.contains(q)) {
+                        return Err(format!("Gate {} acts on qubit {} after its measurement.", gate.name, q));
+                    }
+                    gates.push(gate);
+                }
+            }
+        }
+
+        let mut circuit = Circuit::new(width);
+        for GateCall { name, params, args } in gates {
+            // Registers given as arguments broadcast the gate over their qubits.
+            let repeat = args.iter().map(Vec::len).filter(|&l| l > 1).max().unwrap_or(1);
+            if args.iter().any(|a| a.len() != 1 && a.len() != repeat) {
+                return Err(format!("Registers given to {} have different sizes.", name));
+            }
+            for k in 0..repeat {
+                let qubits = args.iter().map(|a| if a.len() == 1 { a[0] } else { a[k] }).collect::<Vec<_>>();
+                apply_gate(&mut circuit, &name, &params, &qubits)?;
+            }
+        }
+        Ok(circuit)
+    }
+}
+
+fn strip_comments(source: &str) -> String {
+    source.lines().map(|line| line.split("//").next().unwrap_or("")).collect::<Vec<_>>().join("\n")
+}
+
+fn split_keyword(statement: &str) -> (&str, &str) {
+    let end = statement.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(statement.len());
+    (&statement[..end], &statement[end..])
+}
+
+// name[size]
+fn parse_register(declaration: &str) -> Result<(String, usize), String> {
+    let (name, size) = declaration.trim().strip_suffix(']')
+        .and_then(|d| d.split_once('['))
+        .ok_or_else(|| format!("Invalid register declaration: {}.", declaration.trim()))?;
+    let size = size.trim().parse().map_err(|_| format!("Invalid register size: {}.", size))?;
+    Ok((name.trim().to_string(), size))
+}
+
+// Qubits of q[i] or of a whole register q.
+fn parse_argument(argument: &str, registers: &HashMap<String, (usize, usize)>) -> Result<Vec<usize>, String> {
+    let argument = argument.trim();
+    let (name, index) = match argument.strip_suffix(']').and_then(|a| a.split_once('[')) {
+        Some((name, index)) => (name.trim(), Some(index.trim())),
+        None => (argument, None)
+    };
+    let &(offset, size) = registers.get(name).ok_or_else(|| format!("Unknown register {}.", name))?;
+    match index {
+        None => Ok((offset..offset + size).collect()),
+        Some(index) => {
+            let index = index.parse::<usize>().map_err(|_| format!("Invalid qubit index: {}.", argument))?;
+            if index >= size {
+                return Err(format!("Qubit {} is out of register {} of size {}.", index, name, size));
+            }
+            Ok(vec![offset + index])
+        }
+    }
+}
+
+// Gate application, each argument being one qubit or a whole register.
+struct GateCall {
+    name: String,
+    params: Vec<f64>,
+    args: Vec<Vec<usize>>
+}
+
+// name(params) args
+fn parse_gate(statement: &str, registers: &HashMap<String, (usize, usize)>) -> Result<GateCall, String> {
+    let (name, rest) = split_keyword(statement);
+    if name.is_empty() {
+        return Err(format!("Invalid statement: {}.", statement));
+    }
+    let rest = rest.trim_start();
+    let (params, args) = match rest.strip_prefix('(') {
+        Some(rest) => {
+            let close = matching_parenthesis(rest).ok_or_else(|| format!("Unbalanced parentheses in {}.", statement))?;
+            let params = split_top_level(&rest[..close])
+                .iter()
+                .map(|p| Expression::new(p).parse())
+                .collect::<Result<Vec<f64>, String>>()?;
+            (params, &rest[close + 1..])
+        },
+        None => (Vec::new(), rest)
+    };
+    let args = args.split(',')
+        .map(|a| parse_argument(a, registers))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(GateCall { name: name.to_string(), params, args })
+}
+
+// Index of the parenthesis closing the one just before text.
+fn matching_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Split on the commas outside parentheses.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn apply_gate(circuit: &mut Circuit, name: &str, params: &[f64], qubits: &[usize]) -> Result<(), String> {
+    let (nparams, nqubits) = match name {
+        "id" | "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "sx" | "sxdg" => (0, 1),
+        "rx" | "ry" | "rz" | "p" | "u1" => (1, 1),
+        "u2" => (2, 1),
+        "u3" | "u" | "U" => (3, 1),
+        "cx" | "CX" | "cy" | "cz" | "ch" | "swap" => (0, 2),
+        "crx" | "cry" | "crz" | "cp" | "cu1" | "rzz" => (1, 2),
+        "ccx" => (0, 3),
+        _ => return Err(format!("Unsupported gate {}.", name))
+    };
+    if params.len() != nparams || qubits.len() != nqubits {
+        return Err(format!("Gate {} takes {} parameters and {} qubits, got {} and {}.", name, nparams, nqubits, params.len(), qubits.len()));
+    }
+    if (1..qubits.len()).any(|i| qubits[..i].contains(&qubits[i])) {
+        return Err(format!("Gate {} is applied on repeated qubits {:?}.", name, qubits));
+    }
+    let q = qubits;
+    match name {
+        "id" => circuit.i(q[0]),
+        "x" => circuit.x(q[0]),
+        "y" => circuit.y(q[0]),
+        "z" => circuit.z(q[0]),
+        "h" => circuit.h(q[0]),
+        "s" => circuit.s(q[0]),
+        "sdg" => circuit.rz(q[0], -PI / 2.),
+        "t" => circuit.rz(q[0], PI / 4.),
+        "tdg" => circuit.rz(q[0], -PI / 4.),
+        "sx" => circuit.rx(q[0], PI / 2.),
+        "sxdg" => circuit.rx(q[0], -PI / 2.),
+        "rx" => circuit.rx(q[0], params[0]),
+        "ry" => circuit.ry(q[0], params[0]),
+        "rz" | "p" | "u1" => circuit.rz(q[0], params[0]),
+        "u2" => u3(circuit, q[0], PI / 2., params[0], params[1]),
+        "u3" | "u" | "U" => u3(circuit, q[0], params[0], params[1], params[2]),
+        "cx" | "CX" => circuit.cnot(q[0], q[1]),
+        "cy" => {
+            circuit.rz(q[1], -PI / 2.);
+            circuit.cnot(q[0], q[1]);
+            circuit.s(q[1]);
+        },
+        "cz" => {
+            circuit.h(q[1]);
+            circuit.cnot(q[0], q[1]);
+            circuit.h(q[1]);
+        },
+        // As defined in qelib1.inc.
+        "ch" => {
+            circuit.h(q[1]);
+            circuit.rz(q[1], -PI / 2.);
+            circuit.cnot(q[0], q[1]);
+            circuit.h(q[1]);
+            circuit.rz(q[1], PI / 4.);
+            circuit.cnot(q[0], q[1]);
+            circuit.rz(q[1], PI / 4.);
+            circuit.h(q[1]);
+            circuit.s(q[1]);
+            circuit.x(q[1]);
+            circuit.s(q[0]);
+        },
+        "swap" => circuit.swap(q[0], q[1]),
+        "crx" => {
+            circuit.rz(q[1], PI / 2.);
+            controlled_ry(circuit, q[0], q[1], params[0]);
+            circuit.rz(q[1], -PI / 2.);
+        },
+        "cry" => controlled_ry(circuit, q[0], q[1], params[0]),
+        "crz" => {
+            circuit.rz(q[1], params[0] / 2.);
+            circuit.cnot(q[0], q[1]);
+            circuit.rz(q[1], -params[0] / 2.);
+            circuit.cnot(q[0], q[1]);
+        },
+        // diag(1, 1, 1, e^{i lambda}) = e^{i lambda / 4} (rz(lambda / 2) x I) crz(lambda).
+        "cp" | "cu1" => {
+            circuit.rz(q[0], params[0] / 2.);
+            circuit.rz(q[1], params[0] / 2.);
+            circuit.cnot(q[0], q[1]);
+            circuit.rz(q[1], -params[0] / 2.);
+            circuit.cnot(q[0], q[1]);
+        },
+        "rzz" => circuit.rzz(q[0], q[1], params[0]),
+        "ccx" => circuit.ccx(q[0], q[1], q[2]),
+        _ => unreachable!()
+    }
+    Ok(())
+}
+
+// u3(theta, phi, lambda) = rz(phi) ry(theta) rz(lambda) up to a global phase.
+fn u3(circuit: &mut Circuit, target: usize, theta: f64, phi: f64, lambda: f64) {
+    circuit.rz(target, lambda);
+    circuit.ry(target, theta);
+    circuit.rz(target, phi);
+}
+
+fn controlled_ry(circuit: &mut Circuit, control: usize, target: usize, theta: f64) {
+    circuit.ry(target, theta / 2.);
+    circuit.cnot(control, target);
+    circuit.ry(target, -theta / 2.);
+    circuit.cnot(control, target);
+}
+
+// Recursive descent parser of gate parameters: numbers, pi, + - * / ^, parentheses and the
+// functions sin, cos, tan, exp, ln and sqrt.
+struct Expression<'a> {
+    text: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Expression<'a> {
+    fn new(text: &'a str) -> Self {
+        Expression { text: text.as_bytes(), pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<f64, String> {
+        let value = self.sum()?;
+        self.skip_whitespace();
+        if self.pos != self.text.len() {
+            return Err(format!("Invalid parameter expression: {}.", String::from_utf8_lossy(self.text).trim()));
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        while let Some(op @ (b'+' | b'-')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = if op == b'+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+        while let Some(op @ (b'*' | b'/')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.power()?;
+            value = if op == b'*' { value * rhs } else { value / rhs };
+        }
+        Ok(value)
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.unary()?;
+        if self.peek() == Some(b'^') {
+            self.pos += 1;
+            return Ok(base.powf(self.power()?));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            },
+            Some(b'+') => {
+                self.pos += 1;
+                self.unary()
+            },
+            _ => self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        let invalid = || format!("Invalid parameter expression: {}.", String::from_utf8_lossy(self.text).trim());
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let value = self.sum()?;
+                if self.peek() != Some(b')') {
+                    return Err(invalid());
+                }
+                self.pos += 1;
+                Ok(value)
+            },
+            Some(c) if c.is_ascii_digit() || c == b'.' => {
+                let start = self.pos;
+                while self.pos < self.text.len() && (self.text[self.pos].is_ascii_digit() || self.text[self.pos] == b'.') {
+                    self.pos += 1;
+                }
+                // Exponent, e.g. 1e-3.
+                if self.pos < self.text.len() && matches!(self.text[self.pos], b'e' | b'E') {
+                    self.pos += 1;
+                    if self.pos < self.text.len() && matches!(self.text[self.pos], b'+' | b'-') {
+                        self.pos += 1;
+                    }
+                    while self.pos < self.text.len() && self.text[self.pos].is_ascii_digit() {
+                        self.pos += 1;
+                    }
+                }
+                std::str::from_utf8(&self.text[start..self.pos]).unwrap().parse().map_err(|_| invalid())
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.pos < self.text.len() && self.text[self.pos].is_ascii_alphanumeric() {
+                    self.pos += 1;
+                }
+                let name = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                if name == "pi" {
+                    return Ok(PI);
+                }
+                let function: fn(f64) -> f64 = match name {
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "exp" => f64::exp,
+                    "ln" => f64::ln,
+                    "sqrt" => f64::sqrt,
+                    _ => return Err(format!("Unknown identifier {} in a parameter.", name))
+                };
+                if self.peek() != Some(b'(') {
+                    return Err(invalid());
+                }
+                Ok(function(self.atom()?))
+            },
+            _ => Err(invalid())
+        }
+    }
+}
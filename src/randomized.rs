@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use rand::{Rng, RngCore};
+
+use crate::density_matrix::{Basis, DensityMatrix};
+use crate::tools::are_elements_unique;
+
+// Randomized measurements: every setting measures each qubit in a uniformly random Pauli basis,
+// repeated for a number of shots. Subsystem purities follow from the outcome statistics alone
+// (Brydges et al., Science 364, 260 (2019)), so simulated and lab data go through the same
+// estimators:
+//
+//     tr(rho_A^2) = 2^|A| E_u[ sum_{s, s'} (-2)^-D(s, s') P_u(s) P_u(s') ]
+//
+// with D the Hamming distance on the qubits of A. The sum is estimated without bias from the
+// pairs of distinct shots of a setting.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomizedSetting {
+    pub bases: Vec<Basis>,      // Basis of every qubit.
+    pub outcomes: Vec<u64>      // Bitstring of every shot, qubit 0 being the most significant bit.
+}
+
+// Outcome histogram of shots measurements of every qubit q in bases[q].
+pub(crate) fn measure_in_bases(rho: &DensityMatrix, bases: &[Basis], shots: usize, rng: &mut dyn RngCore) -> Result<HashMap<u64, usize>, String> {
+    let mut rotated = rho.clone();
+    rotated.rotate_to_bases(bases)?;
+    Ok(rotated.sample(shots, rng))
+}
+
+// The same measurements as a list of bitstrings in increasing order.
+pub(crate) fn sample_in_bases(rho: &DensityMatrix, bases: &[Basis], shots: usize, rng: &mut dyn RngCore) -> Result<Vec<u64>, String> {
+    let mut histogram = measure_in_bases(rho, bases, shots, rng)?.into_iter().collect::<Vec<_>>();
+    histogram.sort_unstable();
+    Ok(histogram.into_iter().flat_map(|(outcome, count)| std::iter::repeat_n(outcome, count)).collect())
+}
+
+// Measure rho in settings random bases with shots shots each.
+pub fn sample(rho: &DensityMatrix, settings: usize, shots: usize, rng: &mut dyn RngCore) -> Result<Vec<RandomizedSetting>, String> {
+    (0..settings).map(|_| {
+        let bases = (0..rho.nqubits)
+            .map(|_| [Basis::X, Basis::Y, Basis::Z][rng.gen_range(0..3)])
+            .collect::<Vec<_>>();
+        let outcomes = sample_in_bases(rho, &bases, shots, rng)?;
+        Ok(RandomizedSetting { bases, outcomes })
+    }).collect()
+}
+
+// Estimate of tr(rho_A^2) for the subsystem A. Every setting needs at least two shots.
+pub fn estimate_purity(settings: &[RandomizedSetting], subsystem: &[usize]) -> Result<f64, String> {
+    let nqubits = settings.first().ok_or("At least one setting is needed.")?.bases.len();
+    if let Some(&q) = subsystem.iter().find(|&&q| q >= nqubits) {
+        return Err(format!("Target qubit {} is not in the range [0-{}].", q, nqubits));
+    }
+    if !are_elements_unique(subsystem) {
+        return Err("Subsystem qubits must be unique.".to_string());
+    }
+    let k = subsystem.len();
+    let mut total = 0.;
+    for setting in settings {
+        if setting.bases.len() != nqubits {
+            return Err(format!("Settings measure {} qubits but one measures {}.", nqubits, setting.bases.len()));
+        }
+        let n = setting.outcomes.len();
+        if n < 2 {
+            return Err(format!("Every setting needs at least two shots, got {}.", n));
+        }
+        // Counts of the outcomes restricted to A, subsystem[0] being the most significant bit.
+        let mut counts = vec![0.; 1 << k];
+        for outcome in &setting.outcomes {
+            let restricted = subsystem.iter().fold(0, |acc, &q| (acc << 1) | ((outcome >> (nqubits - 1 - q)) & 1) as usize);
+            counts[restricted] += 1.;
+        }
+        let mut pairs = 0.;
+        for (s, &cs) in counts.iter().enumerate().filter(|(_, &c)| c > 0.) {
+            for (t, &ct) in counts.iter().enumerate().filter(|(_, &c)| c > 0.) {
+                pairs += cs * ct * (-0.5f64).powi((s ^ t).count_ones() as i32);
+            }
+        }
+        // Drop the n pairs of a shot with itself, each weighing 1.
+        total += (1 << k) as f64 * (pairs - n as f64) / (n * (n - 1)) as f64;
+    }
+    Ok(total / settings.len() as f64)
+}
+
+// Second Renyi entropy -log2 tr(rho_A^2) of the subsystem A.
+pub fn estimate_renyi2(settings: &[RandomizedSetting], subsystem: &[usize]) -> Result<f64, String> {
+    let purity = estimate_purity(settings, subsystem)?;
+    if purity <= 0. {
+        return Err(format!("Estimated purity {} is not positive, more settings or shots are needed.", purity));
+    }
+    Ok(-purity.log2())
+}
+
+// Frame potentials of state ensembles, e.g. the outputs of random patterns or circuits:
+//
+//     F_t = E_{rho, sigma} tr(rho sigma)^t
+//
+// over independent draws, which is at least the Haar value t! (d - 1)! / (t + d - 1)! with
+// equality exactly when the ensemble of pure states is a t-design. F_t is estimated from all the
+// pairs of distinct samples.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FramePotential {
+    pub t: usize,
+    pub value: f64,
+    pub standard_error: f64,    // Spread of the pair overlaps over the number of disjoint pairs.
+    pub haar: f64
+}
+
+impl FramePotential {
+    // Excess over the Haar value in units of the standard error.
+    pub fn deviation(&self) -> f64 {
+        (self.value - self.haar) / self.standard_error.max(f64::MIN_POSITIVE)
+    }
+}
+
+// 1 / binomial(d + t - 1, t).
+pub fn haar_frame_potential(dim: usize, t: usize) -> f64 {
+    (1..=t).map(|k| k as f64 / (dim + k - 1) as f64).product()
+}
+
+pub fn frame_potential(states: &[DensityMatrix], t: usize) -> Result<FramePotential, String> {
+    let m = states.len();
+    if m < 2 {
+        return Err(format!("At least two states are needed, got {}.", m));
+    }
+    let nqubits = states[0].nqubits;
+    if states.iter().any(|rho| rho.nqubits != nqubits) {
+        return Err("All states must have the same number of qubits.".to_string());
+    }
+    // tr(rho sigma) = sum_ij rho_ij conj(sigma_ij) for Hermitian sigma.
+    let overlaps = (0..m)
+        .flat_map(|i| (i + 1..m).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let overlap = states[i].data.data.iter().zip(states[j].data.data.iter()).map(|(a, b)| (a * b.conj()).re).sum::<f64>();
+            overlap.max(0.).powi(t as i32)
+        })
+        .collect::<Vec<_>>();
+    let value = overlaps.iter().sum::<f64>() / overlaps.len() as f64;
+    let variance = overlaps.iter().map(|x| (x - value).powi(2)).sum::<f64>() / overlaps.len() as f64;
+    Ok(FramePotential { t, value, standard_error: (variance / (m / 2) as f64).sqrt(), haar: haar_frame_potential(1 << nqubits, t) })
+}
+
+// Frame potential of the states drawn by generate, called samples times.
+pub fn sample_frame_potential<F>(mut generate: F, samples: usize, t: usize, rng: &mut dyn RngCore) -> Result<FramePotential, String>
+where
+    F: FnMut(&mut dyn RngCore) -> Result<DensityMatrix, String>
+{
+    let states = (0..samples).map(|_| generate(rng)).collect::<Result<Vec<_>, _>>()?;
+    frame_potential(&states, t)
+}
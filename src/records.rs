@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+// Compact binary files of shot-level measurement records, one bit per measured node, for
+// syndrome data consumed offline by decoders.
+//
+// Layout: magic, version, node count (u32), the node indices (u64 each), then one frame of
+// ceil(nodes / 8) bytes per shot until the end of the file, all little-endian. Bit k of a frame
+// is the outcome of nodes[k], bit 0 being the least significant bit of the first byte. Frames
+// have a fixed size, so shots can be appended while streaming and read back one at a time.
+
+const MAGIC: &[u8; 4] = b"MREC";
+const VERSION: u8 = 1;
+
+fn frame_bytes(nnodes: usize) -> usize {
+    nnodes.div_ceil(8)
+}
+
+pub struct RecordWriter<W: Write> {
+    writer: W,
+    nodes: Vec<usize>,
+    shots: usize
+}
+
+impl<W: Write> RecordWriter<W> {
+    // Write the header; every shot then gives the outcomes of nodes, in this order.
+    pub fn new(mut writer: W, nodes: Vec<usize>) -> Result<Self, String> {
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        header.extend_from_slice(&(nodes.len() as u32).to_le_bytes());
+        for node in &nodes {
+            header.extend_from_slice(&(*node as u64).to_le_bytes());
+        }
+        writer.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(RecordWriter { writer, nodes, shots: 0 })
+    }
+
+    pub fn nodes(&self) -> &[usize] {
+        &self.nodes
+    }
+
+    pub fn shots(&self) -> usize {
+        self.shots
+    }
+
+    pub fn write_shot(&mut self, outcomes: &[u8]) -> Result<(), String> {
+        if outcomes.len() != self.nodes.len() {
+            return Err(format!("Shot has {} outcomes but the record has {} nodes.", outcomes.len(), self.nodes.len()));
+        }
+        let mut frame = vec![0u8; frame_bytes(self.nodes.len())];
+        for (k, &outcome) in outcomes.iter().enumerate() {
+            if outcome > 1 {
+                return Err(format!("Measurement outcome should be 0 or 1, got {}.", outcome));
+            }
+            frame[k / 8] |= outcome << (k % 8);
+        }
+        self.writer.write_all(&frame).map_err(|e| e.to_string())?;
+        self.shots += 1;
+        Ok(())
+    }
+
+    // Shot from the outcomes of a pattern run, e.g. RunResult::outcomes.
+    pub fn write_outcomes(&mut self, outcomes: &HashMap<usize, u8>) -> Result<(), String> {
+        let shot = self.nodes.iter()
+            .map(|node| outcomes.get(node).copied().ok_or_else(|| format!("Node {} has no measurement outcome.", node)))
+            .collect::<Result<Vec<u8>, String>>()?;
+        self.write_shot(&shot)
+    }
+
+    pub fn finish(mut self) -> Result<W, String> {
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok(self.writer)
+    }
+}
+
+pub struct RecordReader<R: Read> {
+    reader: R,
+    nodes: Vec<usize>
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header).map_err(|_| "Not a measurement record.".to_string())?;
+        if &header[..4] != MAGIC {
+            return Err("Not a measurement record.".to_string());
+        }
+        if header[4] != VERSION {
+            return Err(format!("Unsupported measurement record version {}.", header[4]));
+        }
+        let nnodes = u32::from_le_bytes(header[5..].try_into().unwrap()) as usize;
+        // The count is not trusted, so the nodes are pushed as they are read rather than preallocated.
+        let mut nodes = Vec::new();
+        let mut node = [0u8; 8];
+        for _ in 0..nnodes {
+            reader.read_exact(&mut node).map_err(|_| "Truncated measurement record header.".to_string())?;
+            nodes.push(u64::from_le_bytes(node) as usize);
+        }
+        Ok(RecordReader { reader, nodes })
+    }
+
+    pub fn nodes(&self) -> &[usize] {
+        &self.nodes
+    }
+
+    // Outcomes of the next shot, in the order of nodes, or None at the end of the record.
+    pub fn read_shot(&mut self) -> Result<Option<Vec<u8>>, String> {
+        let mut frame = vec![0u8; frame_bytes(self.nodes.len())];
+        let mut read = 0;
+        while read < frame.len() {
+            match self.reader.read(&mut frame[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.to_string())
+            }
+        }
+        // Records without nodes have empty frames and hold no shots.
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < frame.len() {
+            return Err("Truncated measurement record frame.".to_string());
+        }
+        Ok(Some((0..self.nodes.len()).map(|k| (frame[k / 8] >> (k % 8)) & 1).collect()))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<Vec<u8>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_shot().transpose()
+    }
+}
+
+pub fn save_records<P: AsRef<Path>>(path: P, nodes: Vec<usize>, shots: &[Vec<u8>]) -> Result<(), String> {
+    let file = File::create(path.as_ref()).map_err(|e| format!("Cannot write {}: {}.", path.as_ref().display(), e))?;
+    let mut writer = RecordWriter::new(BufWriter::new(file), nodes)?;
+    for shot in shots {
+        writer.write_shot(shot)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+// Nodes and every shot of a record file.
+pub fn load_records<P: AsRef<Path>>(path: P) -> Result<(Vec<usize>, Vec<Vec<u8>>), String> {
+    let file = File::open(path.as_ref()).map_err(|e| format!("Cannot read {}: {}.", path.as_ref().display(), e))?;
+    let reader = RecordReader::new(BufReader::new(file))?;
+    let nodes = reader.nodes().to_vec();
+    let shots = reader.collect::<Result<Vec<_>, String>>()?;
+    Ok((nodes, shots))
+}
@@ -0,0 +1,96 @@
+use core::fmt;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::config::TolerancePolicy;
+use crate::pattern::{Command, Pattern};
+
+// Cost of running a pattern, computed from its commands alone so that feasibility can be checked
+// before simulating anything.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceReport {
+    pub nodes: usize,
+    pub edges: usize,
+    pub measurements: usize,
+    pub depth: usize,               // Longest chain of measurements depending on earlier outcomes, counting layers.
+    pub t_count: usize,             // Measurements whose angle is not a multiple of pi / 2.
+    pub max_width: usize,           // Most qubits held at once by the runner, which frees measured nodes.
+    pub memory: Vec<(&'static str, f64)>    // Bytes needed for a state of max_width qubits, per backend.
+}
+
+impl Pattern {
+    // Angles within the equality tolerance of a multiple of pi / 2 do not count as T measurements.
+    pub fn resources(&self, tol: &TolerancePolicy) -> ResourceReport {
+        let mut live = self.input_nodes().len();
+        let mut max_width = live;
+        let (mut edges, mut measurements, mut t_count) = (0, 0, 0);
+        // Layer of every measured node, one more than the deepest node its domains depend on.
+        let mut layers: HashMap<usize, usize> = HashMap::new();
+        let mut depth = 0;
+        for command in self.seq() {
+            match command {
+                Command::N(_) | Command::NState(..) => {
+                    live += 1;
+                    max_width = max_width.max(live);
+                },
+                Command::E(_) => edges += 1,
+                Command::M(node, _, angle, s_domain, t_domain, _) => {
+                    live -= 1;
+                    measurements += 1;
+                    // Angles are in units of pi, rounding errors on either side of a multiple of
+                    // pi / 2 do not make a T measurement.
+                    let quarters = angle * 2.;
+                    if (quarters - quarters.round()).abs() > tol.equality {
+                        t_count += 1;
+                    }
+                    let dependencies = s_domain.iter().chain(t_domain.iter()).collect::<BTreeSet<_>>();
+                    let layer = dependencies.iter().filter_map(|d| layers.get(d)).max().map_or(1, |l| l + 1);
+                    layers.insert(*node, layer);
+                    depth = depth.max(layer);
+                },
+                _ => {}
+            }
+        }
+        let (amplitudes, entries) = (2f64.powi(max_width as i32), 4f64.powi(max_width as i32));
+        ResourceReport {
+            nodes: self.n_nodes(),
+            edges,
+            measurements,
+            depth,
+            t_count,
+            max_width,
+            memory: vec![
+                ("statevector", 16. * amplitudes),
+                ("density_matrix", 16. * entries),
+                ("density_matrix_f32", 8. * entries),
+            ]
+        }
+    }
+}
+
+// Byte counts with a binary unit, e.g. 4.00 GiB.
+fn human_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024. && unit < units.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, units[unit])
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nodes: {}", self.nodes)?;
+        writeln!(f, "edges: {}", self.edges)?;
+        writeln!(f, "measurements: {}", self.measurements)?;
+        writeln!(f, "depth: {}", self.depth)?;
+        writeln!(f, "t_count: {}", self.t_count)?;
+        writeln!(f, "max_width: {}", self.max_width)?;
+        writeln!(f, "memory:")?;
+        for (backend, bytes) in &self.memory {
+            writeln!(f, "  {}: {}", backend, human_bytes(*bytes))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+// Random number generators selected through SimulationConfig.
+//
+// Every component drawing random numbers (a shot, a trajectory, a client of a blind computation,
+// ...) gets its own generator seeded from the master seed, a component label and an index:
+//
+//     seed = splitmix64(splitmix64(master ^ fnv1a64(label)) ^ index)
+//
+// with the 64 bit FNV-1a hash and the SplitMix64 finalizer. Both are fixed here rather than
+// taken from std or rand, whose hashers and StdRng are allowed to change between releases, so
+// a given (kind, master, label, index) gives the same stream in every version of this crate.
+// Generators are then seeded with seed_from_u64, which rand_core specifies as PCG32 expansion.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RngKind {
+    #[default]
+    Pcg64,      // Fast, for Monte Carlo sampling.
+    ChaCha20,   // Cryptographic quality and reproducible, e.g. for UBQC secrets.
+    Os          // Operating system entropy, ignores the seed.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RngConfig {
+    pub kind: RngKind,
+    pub seed: u64
+}
+
+impl RngConfig {
+    // Generator of the index-th instance of a component.
+    pub fn rng(&self, label: &str, index: u64) -> Box<dyn RngCore> {
+        let seed = derive_seed(self.seed, label, index);
+        match self.kind {
+            RngKind::Pcg64 => Box::new(Pcg64::seed_from_u64(seed)),
+            RngKind::ChaCha20 => Box::new(ChaCha20Rng::seed_from_u64(seed)),
+            RngKind::Os => Box::new(OsRng)
+        }
+    }
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+pub fn derive_seed(master: u64, label: &str, index: u64) -> u64 {
+    splitmix64(splitmix64(master ^ fnv1a64(label.as_bytes())) ^ index)
+}
+
+// PCG XSL RR 128/64, the generator numpy and rand_pcg call PCG64, with a 128 bit LCG state and
+// an odd increment selecting the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcg64 {
+    state: u128,
+    increment: u128
+}
+
+const PCG_MULTIPLIER: u128 = 0x2360ed051fc65da44385df649fccf645;
+
+impl Pcg64 {
+    pub fn new(state: u128, stream: u128) -> Self {
+        Pcg64::from_state_increment(state, (stream << 1) | 1)
+    }
+
+    fn from_state_increment(state: u128, increment: u128) -> Self {
+        let mut rng = Pcg64 { state, increment };
+        rng.state = rng.state.wrapping_add(rng.increment);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.increment);
+    }
+}
+
+impl RngCore for Pcg64 {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.step();
+        let state = self.state;
+        let rotation = (state >> 122) as u32;
+        (((state >> 64) as u64) ^ (state as u64)).rotate_right(rotation)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Pcg64 {
+    type Seed = [u8; 32];
+
+    // State from the first 16 bytes and increment from the last 16, little-endian, its lowest bit
+    // being forced to 1 as in rand_pcg rather than shifted in like the stream of new.
+    fn from_seed(seed: Self::Seed) -> Self {
+        let state = u128::from_le_bytes(seed[..16].try_into().unwrap());
+        let increment = u128::from_le_bytes(seed[16..].try_into().unwrap());
+        Pcg64::from_state_increment(state, increment | 1)
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::PI;
+
+use num_complex::Complex;
+use rand::RngCore;
+
+use crate::backend::QuantumBackend;
+use crate::clifford::Clifford;
+use crate::config::SimulationConfig;
+use crate::decoder::Decoder;
+use crate::error::Context;
+use crate::noise::{CommandKind, IdleScheduler, MeasurementCrosstalk, NoiseModel};
+use crate::density_matrix::State;
+use crate::operators::{OneQubitOp, Operator, TwoQubitsOp};
+use crate::pattern::{Command, Pattern, Plane};
+use crate::pauli::Pauli;
+
+// Execution of measurement patterns on any backend. Qubits are allocated by N commands and
+// dropped as soon as they are measured, so the register only holds the live nodes.
+
+pub struct RunResult<B> {
+    pub state: B,                       // Output nodes, in the order of Pattern::output_nodes.
+    pub outcomes: HashMap<usize, u8>,   // Measurement outcome of every measured node.
+}
+
+// Bloch vector of the measurement basis, the outcome 0 projecting onto its +1 eigenstate.
+// XY: cos(a) X + sin(a) Y, YZ: cos(a) Z + sin(a) Y, XZ: cos(a) Z + sin(a) X, with a in units of pi.
+pub fn measurement_vector(plane: Plane, angle: f64) -> [f64; 3] {
+    let (c, s) = ((angle * PI).cos(), (angle * PI).sin());
+    match plane {
+        Plane::XY => [c, s, 0.],
+        Plane::YZ => [0., s, c],
+        Plane::ZX => [s, 0., c]
+    }
+}
+
+// Unitary mapping the +1 and -1 eigenstates of n.sigma to |0> and |1>.
+pub fn basis_change(n: [f64; 3]) -> Operator {
+    let theta = n[2].clamp(-1., 1.).acos();
+    let phi = n[1].atan2(n[0]);
+    let (c, s) = ((theta / 2.).cos(), (theta / 2.).sin());
+    let e = Complex::from_polar(1., -phi);
+    Operator::from_matrix(&[
+        Complex::new(c, 0.), e * s,
+        Complex::new(s, 0.), -e * c,
+    ], 1).unwrap()
+}
+
+// Operators built by the runner, kept across commands: patterns reuse the same few measurement
+// angles and Clifford corrections thousands of times. Backends apply the adjoint while
+// contracting, so only the operators themselves are stored.
+pub struct OperatorCache {
+    basis_changes: HashMap<[u64; 3], Operator>,     // Keyed by the bits of the Bloch vector.
+    cliffords: HashMap<usize, Operator>,
+    paulis: [Operator; 3],
+    cz: Operator,
+    pub hits: usize,
+    pub misses: usize
+}
+
+impl Default for OperatorCache {
+    fn default() -> Self {
+        OperatorCache {
+            basis_changes: HashMap::new(),
+            cliffords: HashMap::new(),
+            paulis: [OneQubitOp::X, OneQubitOp::Y, OneQubitOp::Z].map(Operator::one_qubit),
+            cz: Operator::two_qubits(TwoQubitsOp::CZ),
+            hits: 0,
+            misses: 0
+        }
+    }
+}
+
+impl OperatorCache {
+    pub fn basis_change(&mut self, n: [f64; 3]) -> &Operator {
+        let key = n.map(|x| (x + 0.).to_bits());
+        if self.basis_changes.contains_key(&key) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        self.basis_changes.entry(key).or_insert_with(|| basis_change(n))
+    }
+
+    pub fn clifford(&mut self, index: usize) -> Result<&Operator, String> {
+        if self.cliffords.contains_key(&index) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            self.cliffords.insert(index, Clifford::new(index)?.operator());
+        }
+        Ok(&self.cliffords[&index])
+    }
+
+    // X, Y or Z, None for the identity.
+    pub fn pauli(&self, pauli: Pauli) -> Option<&Operator> {
+        match pauli {
+            Pauli::I => None,
+            Pauli::X => Some(&self.paulis[0]),
+            Pauli::Y => Some(&self.paulis[1]),
+            Pauli::Z => Some(&self.paulis[2])
+        }
+    }
+
+    pub fn cz(&self) -> &Operator {
+        &self.cz
+    }
+
+    pub fn len(&self) -> usize {
+        self.basis_changes.len() + self.cliffords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Type name of the backend without its module path, e.g. DensityMatrix.
+fn backend_name<B>() -> &'static str {
+    let name = std::any::type_name::<B>();
+    name.split('<').next().and_then(|path| path.rsplit("::").next()).unwrap_or(name)
+}
+
+// Called after the measurement of a node with the node, the outcomes so far and the next commands
+// to run, at most the lookahead it was registered with. It may edit, remove or add commands, which
+// then run in place of the ones it was given.
+pub type MeasurementCallback = Box<dyn FnMut(usize, &HashMap<usize, u8>, &mut Vec<Command>) -> Result<(), String>>;
+
+// State of a partially executed pattern. It owns the backend, so a run can be stopped after any
+// command, inspected, and resumed later with the same or modified remaining commands.
+pub struct ExecutionCursor<B: QuantumBackend> {
+    pub backend: B,
+    pub nodes: Vec<usize>,              // Node held by each qubit of the backend.
+    pub outcomes: HashMap<usize, u8>,
+    pub executed: usize,                // Number of commands applied so far.
+    decoders: Vec<(Box<dyn Decoder>, bool)>,    // Each decoder with whether it already ran.
+    callbacks: HashMap<usize, (usize, MeasurementCallback)>,   // Lookahead and callback of each node.
+    crosstalk: Option<MeasurementCrosstalk>,
+    noise: Option<NoiseModel>,
+    idle: Option<IdleScheduler>,
+    edges: Vec<(usize, usize)>,         // Edges entangled so far, to find the neighbors of measured nodes.
+    config: SimulationConfig,
+    pub cache: OperatorCache
+}
+
+impl<B: QuantumBackend> ExecutionCursor<B> {
+    // The backend holds the input nodes, qubit i being input_nodes[i].
+    pub fn new(pattern: &Pattern, input: B) -> Result<Self, String> {
+        if input.nqubits() != pattern.input_nodes().len() {
+            return Err(format!("Pattern has {} input nodes but the input state has {} qubits.", pattern.input_nodes().len(), input.nqubits()));
+        }
+        Ok(ExecutionCursor { backend: input, nodes: pattern.input_nodes().to_vec(), outcomes: HashMap::new(), executed: 0, decoders: Vec::new(), callbacks: HashMap::new(), crosstalk: None, noise: None, idle: None, edges: Vec::new(), config: SimulationConfig::default(), cache: OperatorCache::default() })
+    }
+
+    // Cursor of a run interrupted after executed commands, for instance loaded from a checkpoint.
+    pub fn restore(backend: B, nodes: Vec<usize>, outcomes: HashMap<usize, u8>, executed: usize) -> Result<Self, String> {
+        if backend.nqubits() != nodes.len() {
+            return Err(format!("Register holds {} nodes but the state has {} qubits.", nodes.len(), backend.nqubits()));
+        }
+        Ok(ExecutionCursor { backend, nodes, outcomes, executed, decoders: Vec::new(), callbacks: HashMap::new(), crosstalk: None, noise: None, idle: None, edges: Vec::new(), config: SimulationConfig::default(), cache: OperatorCache::default() })
+    }
+
+    // Tolerances and allocator used from now on, the defaults being the double precision ones.
+    pub fn set_config(&mut self, config: SimulationConfig) {
+        self.config = config;
+    }
+
+    // Edges entangled so far and the clock of the idle noise, which a checkpoint needs besides the
+    // register to resume a run with crosstalk or idle noise.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn idle_scheduler(&self) -> Option<&IdleScheduler> {
+        self.idle.as_ref()
+    }
+
+    // Restore the edges and the idle clock saved from an interrupted run. The clock is only kept
+    // when the cursor has idle noise, so the noise model has to be set first.
+    pub fn restore_history(&mut self, edges: Vec<(usize, usize)>, clock: f64, last_active: HashMap<usize, f64>) {
+        self.edges = edges;
+        if let Some(scheduler) = self.idle.as_mut() {
+            scheduler.clock = clock;
+            scheduler.last_active = last_active;
+        }
+    }
+
+    pub fn add_decoder(&mut self, decoder: Box<dyn Decoder>) {
+        self.decoders.push((decoder, false));
+    }
+
+    // Call back into user code after the measurement of the node, handing it the next lookahead
+    // commands, e.g. to choose the following measurement angles from the outcomes. Runs with
+    // callbacks may apply other commands than the ones they were given, and executed then counts
+    // the commands actually applied.
+    pub fn add_callback(&mut self, node: usize, lookahead: usize, callback: MeasurementCallback) {
+        self.callbacks.insert(node, (lookahead, callback));
+    }
+
+    // Dephase the unmeasured neighbors of every measured node from now on. The backend has to
+    // support noise channels.
+    pub fn set_crosstalk(&mut self, crosstalk: MeasurementCrosstalk) {
+        self.crosstalk = Some(crosstalk);
+    }
+
+    // Inject the channels of the noise model around every command from now on. The backend has
+    // to support noise channels unless the model is empty.
+    pub fn set_noise(&mut self, noise: NoiseModel) {
+        self.idle = (!noise.idle.is_empty()).then(|| IdleScheduler::new(noise.idle.clone()));
+        self.noise = Some(noise);
+    }
+
+    // Relax the nodes a command of the given kind acts on for the time they waited since their
+    // last command, and advance the clock of the idle noise.
+    fn apply_idle(&mut self, kind: CommandKind, nodes: &[usize]) -> Result<(), String> {
+        let Some(scheduler) = self.idle.as_mut() else {
+            return Ok(());
+        };
+        let idle = scheduler.start(kind, nodes);
+        self.relax(idle)
+    }
+
+    fn relax(&mut self, idle: Vec<(usize, f64)>) -> Result<(), String> {
+        for (node, duration) in idle {
+            let Some(channel) = self.idle.as_ref().map(|scheduler| scheduler.channel(node, duration)).transpose()?.flatten() else {
+                continue;
+            };
+            let index = self.position(node)?;
+            self.backend.apply_channel(&channel.kraus, &[index]).with_context(|| format!("Idle noise on node {}", node))?;
+        }
+        Ok(())
+    }
+
+    fn apply_noise(&mut self, kind: CommandKind, node: usize) -> Result<(), String> {
+        let Some(channel) = self.noise.as_ref().and_then(|noise| noise.channel(kind, node)) else {
+            return Ok(());
+        };
+        if channel.nqubits != 1 {
+            return Err(format!("Noise channels of {:?} commands should act on one qubit, got {}.", kind, channel.nqubits));
+        }
+        let index = self.position(node)?;
+        Ok(self.backend.apply_channel(&channel.kraus, &[index]).with_context(|| format!("{:?} noise on node {}", kind, node))?)
+    }
+
+    fn apply_edge_noise(&mut self, a: usize, b: usize) -> Result<(), String> {
+        let Some(channel) = self.noise.as_ref().and_then(|noise| noise.edge_channel(a, b)) else {
+            return Ok(());
+        };
+        let targets = [self.position(a)?, self.position(b)?];
+        let result = match channel.nqubits {
+            1 => self.backend.apply_channel(&channel.kraus, &targets[..1])
+                .and_then(|_| self.backend.apply_channel(&channel.kraus, &targets[1..])),
+            2 => self.backend.apply_channel(&channel.kraus, &targets),
+            n => return Err(format!("Noise channels of E commands should act on one or two qubits, got {}.", n))
+        };
+        Ok(result.with_context(|| format!("E noise on nodes {} and {}", a, b))?)
+    }
+
+    fn apply_crosstalk(&mut self, measured: usize) -> Result<(), String> {
+        let Some(crosstalk) = &self.crosstalk else {
+            return Ok(());
+        };
+        let neighbors = self.edges.iter()
+            .filter_map(|&(a, b)| if a == measured { Some(b) } else if b == measured { Some(a) } else { None })
+            .filter(|n| self.nodes.contains(n))
+            .collect::<Vec<_>>();
+        for neighbor in neighbors {
+            if let Some(kraus) = crosstalk.channel(measured, neighbor)? {
+                let index = self.position(neighbor)?;
+                self.backend.apply_channel(&kraus, &[index])?;
+            }
+        }
+        Ok(())
+    }
+
+    // Run the decoders whose syndrome is complete and apply their corrections.
+    fn run_decoders(&mut self) -> Result<(), String> {
+        for i in 0..self.decoders.len() {
+            let (decoder, done) = &mut self.decoders[i];
+            if *done || !decoder.syndrome_nodes().iter().all(|node| self.outcomes.contains_key(node)) {
+                continue;
+            }
+            *done = true;
+            let syndrome = decoder.syndrome_nodes().iter().map(|node| self.outcomes[node]).collect::<Vec<_>>();
+            for (node, pauli) in decoder.decode(&syndrome)? {
+                let Some(op) = self.cache.pauli(pauli) else {
+                    continue;
+                };
+                let index = self.position(node)?;
+                self.backend.evolve_single(op, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn position(&self, node: usize) -> Result<usize, String> {
+        self.nodes.iter().position(|&n| n == node).ok_or_else(|| format!("Node {} is not in the register.", node))
+    }
+
+    fn parity(&self, domain: &[usize]) -> Result<u8, String> {
+        domain.iter().try_fold(0, |acc, node| {
+            self.outcomes.get(node)
+                .map(|outcome| acc ^ outcome)
+                .ok_or_else(|| format!("Node {} is used in a domain before being measured.", node))
+        })
+    }
+
+    pub fn apply(&mut self, command: &Command, rng: &mut dyn RngCore) -> Result<(), String> {
+        match command {
+            Command::N(node) => {
+                if self.nodes.contains(node) {
+                    return Err(format!("Node {} is already prepared.", node));
+                }
+                self.backend.add_qubit_with(State::PLUS, self.config.allocator.as_ref())?;
+                self.nodes.push(*node);
+                self.apply_idle(CommandKind::N, &[*node])?;
+                self.apply_noise(CommandKind::N, *node)?;
+            },
+            Command::NState(node, [alpha, beta]) => {
+                if self.nodes.contains(node) {
+                    return Err(format!("Node {} is already prepared.", node));
+                }
+                let norm = (alpha.norm_sqr() + beta.norm_sqr()).sqrt();
+                if !norm.is_finite() || (norm - 1.).abs() > self.config.tolerance.trace {
+                    return Err(format!("State of node {} has norm {} instead of 1.", node, norm));
+                }
+                // Unitary mapping |0> to alpha |0> + beta |1>.
+                let prepare = Operator::from_matrix(&[*alpha, -beta.conj(), *beta, alpha.conj()], 1)?;
+                self.backend.add_qubit_with(State::ZERO, self.config.allocator.as_ref())?;
+                self.backend.evolve_single(&prepare, self.nodes.len())?;
+                self.nodes.push(*node);
+                self.apply_idle(CommandKind::N, &[*node])?;
+                self.apply_noise(CommandKind::N, *node)?;
+            },
+            Command::E((a, b)) => {
+                self.apply_idle(CommandKind::E, &[*a, *b])?;
+                let targets = [self.position(*a)?, self.position(*b)?];
+                self.backend.evolve(self.cache.cz(), &targets)?;
+                self.edges.push((*a, *b));
+                self.apply_edge_noise(*a, *b)?;
+            },
+            Command::M(node, plane, angle, s_domain, t_domain, vop) => {
+                if *vop != 0 {
+                    return Err(format!("Measurement of node {} has a vertex operator, which is not supported.", node));
+                }
+                let mut n = measurement_vector(*plane, *angle);
+                // X^s Z^t byproducts conjugate the measured observable: Z flips the X and Y
+                // components and X flips the Y and Z components.
+                if self.parity(t_domain)? == 1 {
+                    n = [-n[0], -n[1], n[2]];
+                }
+                if self.parity(s_domain)? == 1 {
+                    n = [n[0], -n[1], -n[2]];
+                }
+                self.apply_idle(CommandKind::M, &[*node])?;
+                self.apply_noise(CommandKind::M, *node)?;
+                let index = self.position(*node)?;
+                self.backend.evolve_single(self.cache.basis_change(n), index)?;
+                let mut outcome = self.backend.measure_and_remove_with(index, rng, self.config.allocator.as_ref())?;
+                if let Some(noise) = &self.noise {
+                    outcome = noise.readout.confusion(*node).apply(outcome, rng);
+                }
+                self.nodes.remove(index);
+                self.outcomes.insert(*node, outcome);
+                self.apply_crosstalk(*node)?;
+                self.run_decoders()?;
+            },
+            Command::X(node, domain) | Command::Z(node, domain) => {
+                if self.parity(domain)? == 1 {
+                    let (gate, kind) = if matches!(command, Command::X(..)) {
+                        (Pauli::X, CommandKind::X)
+                    } else {
+                        (Pauli::Z, CommandKind::Z)
+                    };
+                    self.apply_idle(kind, &[*node])?;
+                    let index = self.position(*node)?;
+                    self.backend.evolve_single(self.cache.pauli(gate).unwrap(), index)?;
+                    self.apply_noise(kind, *node)?;
+                }
+            },
+            Command::XIf(node, condition) | Command::ZIf(node, condition) => {
+                if condition.evaluate(&self.outcomes)? {
+                    let (gate, kind) = if matches!(command, Command::XIf(..)) {
+                        (Pauli::X, CommandKind::X)
+                    } else {
+                        (Pauli::Z, CommandKind::Z)
+                    };
+                    self.apply_idle(kind, &[*node])?;
+                    let index = self.position(*node)?;
+                    self.backend.evolve_single(self.cache.pauli(gate).unwrap(), index)?;
+                    self.apply_noise(kind, *node)?;
+                }
+            },
+            Command::T => {},
+            Command::C(node, index) => {
+                self.apply_idle(CommandKind::C, &[*node])?;
+                let index_in_register = self.position(*node)?;
+                self.backend.evolve_single(self.cache.clifford(*index)?, index_in_register)?;
+                self.apply_noise(CommandKind::C, *node)?;
+            },
+            Command::S(node, _) => return Err(format!("Signal shifting on node {} is not supported.", node))
+        }
+        self.executed += 1;
+        Ok(())
+    }
+
+    // Errors name the failing command, its index in the run, the backend and the node held by
+    // each qubit when it failed.
+    pub fn run(&mut self, commands: &[Command], rng: &mut dyn RngCore) -> Result<(), String> {
+        if self.callbacks.is_empty() {
+            return commands.iter().try_for_each(|command| self.apply_in_run(command, rng));
+        }
+        let mut remaining = commands.iter().cloned().collect::<VecDeque<_>>();
+        while let Some(command) = remaining.pop_front() {
+            self.apply_in_run(&command, rng)?;
+            if let Command::M(node, ..) = command {
+                self.call_back(node, &mut remaining)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_in_run(&mut self, command: &Command, rng: &mut dyn RngCore) -> Result<(), String> {
+        self.apply(command, rng).with_context(|| {
+            format!("Command {} {:?} on {} with register {:?}", self.executed, command, backend_name::<B>(), self.nodes)
+        })
+    }
+
+    // Hand the next commands to the callback of the measured node and queue the edited ones.
+    fn call_back(&mut self, node: usize, remaining: &mut VecDeque<Command>) -> Result<(), String> {
+        let Some((lookahead, callback)) = self.callbacks.get_mut(&node) else {
+            return Ok(());
+        };
+        let mut window = remaining.drain(..(*lookahead).min(remaining.len())).collect::<Vec<_>>();
+        callback(node, &self.outcomes, &mut window).with_context(|| format!("Callback of node {}", node))?;
+        window.into_iter().rev().for_each(|command| remaining.push_front(command));
+        Ok(())
+    }
+
+    // Run the remaining commands, which may differ from the ones of the original pattern, and
+    // return the output nodes in the given order.
+    pub fn resume(mut self, remaining: &[Command], output_nodes: &[usize], rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        self.run(remaining, rng)?;
+        self.finish(output_nodes)
+    }
+
+    // Relax the nodes left for the time they waited, reorder the register to match the output
+    // nodes and return the result.
+    pub fn finish(mut self, output_nodes: &[usize]) -> Result<RunResult<B>, String> {
+        if self.nodes.len() != output_nodes.len() {
+            return Err(format!("{} nodes are left but the pattern has {} output nodes.", self.nodes.len(), output_nodes.len()));
+        }
+        if let Some(scheduler) = &self.idle {
+            let idle = scheduler.idle(&self.nodes);
+            self.relax(idle).context("Idle noise at the end of the run")?;
+        }
+        let swap = Operator::two_qubits(TwoQubitsOp::SWAP);
+        for (target, node) in output_nodes.iter().enumerate() {
+            let current = self.position(*node)?;
+            if current != target {
+                self.backend.evolve(&swap, &[current, target]).with_context(|| {
+                    format!("Moving output node {} on {} with register {:?}", node, backend_name::<B>(), self.nodes)
+                })?;
+                self.nodes.swap(current, target);
+            }
+        }
+        Ok(RunResult { state: self.backend, outcomes: self.outcomes })
+    }
+}
+
+impl Pattern {
+    pub fn simulate<B: QuantumBackend>(&self, input: B, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        self.run_until(input, self.seq().len(), rng)?.resume(&[], self.output_nodes(), rng)
+    }
+
+    // Same as simulate, running the decoders as soon as their syndrome is measured.
+    pub fn simulate_with_decoders<B: QuantumBackend>(&self, input: B, decoders: Vec<Box<dyn Decoder>>, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        let mut cursor = ExecutionCursor::new(self, input)?;
+        decoders.into_iter().for_each(|decoder| cursor.add_decoder(decoder));
+        cursor.resume(self.seq(), self.output_nodes(), rng)
+    }
+
+    // Same as simulate, with readout crosstalk on the graph neighbors of every measured node.
+    pub fn simulate_with_crosstalk<B: QuantumBackend>(&self, input: B, crosstalk: MeasurementCrosstalk, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        let mut cursor = ExecutionCursor::new(self, input)?;
+        cursor.set_crosstalk(crosstalk);
+        cursor.resume(self.seq(), self.output_nodes(), rng)
+    }
+
+    // Run the pattern with the channels of the noise model injected around every command.
+    pub fn simulate_with_noise<B: QuantumBackend>(&self, input: B, noise: &NoiseModel, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        let mut cursor = ExecutionCursor::new(self, input)?;
+        cursor.set_noise(noise.clone());
+        cursor.resume(self.seq(), self.output_nodes(), rng)
+    }
+
+    // Same as simulate, with the tolerances and allocator of the config.
+    pub fn simulate_with_config<B: QuantumBackend>(&self, input: B, config: &SimulationConfig, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        let mut cursor = ExecutionCursor::new(self, input)?;
+        cursor.set_config(config.clone());
+        cursor.resume(self.seq(), self.output_nodes(), rng)
+    }
+
+    // Execute the first end commands and return the cursor to resume from.
+    pub fn run_until<B: QuantumBackend>(&self, input: B, end: usize, rng: &mut dyn RngCore) -> Result<ExecutionCursor<B>, String> {
+        if end > self.seq().len() {
+            return Err(format!("Pattern has {} commands, cannot run up to {}.", self.seq().len(), end));
+        }
+        let mut cursor = ExecutionCursor::new(self, input)?;
+        cursor.run(&self.seq()[..end], rng)?;
+        Ok(cursor)
+    }
+
+    // Continue a cursor of this pattern with its remaining commands.
+    pub fn resume<B: QuantumBackend>(&self, cursor: ExecutionCursor<B>, rng: &mut dyn RngCore) -> Result<RunResult<B>, String> {
+        let start = cursor.executed.min(self.seq().len());
+        cursor.resume(&self.seq()[start..], self.output_nodes(), rng)
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+use num_complex::Complex;
+
+use crate::density_matrix::DensityMatrix;
+use crate::npy;
+use crate::pauli::PauliString;
+
+// Out-of-core storage of a density matrix as consecutive blocks of rows, each saved as a
+// complex128 npy file shard_<k>.npy of shape (rows, 2^n) in a directory. Traces and Pauli
+// expectations are streamed shard by shard, mapping a single shard at a time.
+
+fn shard_path(dir: &Path, k: usize) -> PathBuf {
+    dir.join(format!("shard_{}.npy", k))
+}
+
+pub fn write_shards<P: AsRef<Path>>(rho: &DensityMatrix, dir: P, rows_per_shard: usize) -> Result<Vec<PathBuf>, String> {
+    if rows_per_shard == 0 {
+        return Err("A shard holds at least one row.".to_string());
+    }
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}.", dir.display(), e))?;
+    rho.data.data.chunks(rows_per_shard * rho.size).enumerate().map(|(k, rows)| {
+        let path = shard_path(dir, k);
+        npy::write_npy(&path, &[rows.len() / rho.size, rho.size], rows)?;
+        Ok(path)
+    }).collect()
+}
+
+// Shards of a directory in row order.
+pub fn list_shards<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, String> {
+    let dir = dir.as_ref();
+    let count = (0..).take_while(|&k| shard_path(dir, k).exists()).count();
+    if count == 0 {
+        return Err(format!("No shard found in {}.", dir.display()));
+    }
+    Ok((0..count).map(|k| shard_path(dir, k)).collect())
+}
+
+// Visit every shard with its first row index, its number of rows and an element accessor,
+// after checking that the shards fit together into a size x size matrix.
+fn for_each_shard<F>(paths: &[PathBuf], size: usize, mut visit: F) -> Result<(), String>
+where
+    F: FnMut(usize, usize, &dyn Fn(usize, usize) -> Complex<f64>),
+{
+    let mut row = 0usize;
+    for path in paths {
+        let file = File::open(path).map_err(|e| format!("Cannot open {}: {}.", path.display(), e))?;
+        // Safety: shards are only read while they are mapped.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Cannot map {}: {}.", path.display(), e))?;
+        let header = npy::parse_header(&mmap)?;
+        let (rows, cols) = match header.shape[..] {
+            [rows, cols] if header.descr == "<c16" => (rows, cols),
+            _ => return Err(format!("{} is not a complex128 block of rows.", path.display()))
+        };
+        let len = rows.checked_mul(cols).and_then(|n| n.checked_mul(16)).and_then(|n| n.checked_add(header.data_start));
+        if cols != size || row.checked_add(rows).is_none_or(|end| end > size) || len != Some(mmap.len()) {
+            return Err(format!("{} is not a block of rows of a {}x{} matrix.", path.display(), size, size));
+        }
+        let get = |i: usize, j: usize| {
+            let offset = header.data_start + 16 * (i * cols + j);
+            Complex::new(
+                f64::from_le_bytes(mmap[offset..offset + 8].try_into().unwrap()),
+                f64::from_le_bytes(mmap[offset + 8..offset + 16].try_into().unwrap()),
+            )
+        };
+        visit(row, rows, &get);
+        row += rows;
+    }
+    if row != size {
+        return Err(format!("Shards hold {} rows of a {}x{} matrix.", row, size, size));
+    }
+    Ok(())
+}
+
+// Matrix size read from the header of the first shard.
+pub fn matrix_size(paths: &[PathBuf]) -> Result<usize, String> {
+    let path = paths.first().ok_or("No shard given.")?;
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}.", path.display(), e))?;
+    // Safety: the shard is only read while it is mapped.
+    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Cannot map {}: {}.", path.display(), e))?;
+    match npy::parse_header(&mmap)?.shape[..] {
+        [_, cols] if cols.is_power_of_two() => Ok(cols),
+        _ => Err(format!("{} is not a block of rows of a 2^n x 2^n matrix.", path.display()))
+    }
+}
+
+pub fn trace(paths: &[PathBuf]) -> Result<Complex<f64>, String> {
+    let mut trace = Complex::ZERO;
+    for_each_shard(paths, matrix_size(paths)?, |start, rows, get| {
+        trace += (0..rows).map(|i| get(i, start + i)).sum::<Complex<f64>>();
+    })?;
+    Ok(trace)
+}
+
+pub fn expectation(paths: &[PathBuf], pauli_string: &PauliString) -> Result<f64, String> {
+    let size = matrix_size(paths)?;
+    if size != 1 << pauli_string.nqubits() {
+        return Err(format!("Pauli string acts on {} qubits but the sharded state has {}.", pauli_string.nqubits(), size.ilog2()));
+    }
+    let x_mask = pauli_string.x_mask();
+    let mut value = Complex::ZERO;
+    for_each_shard(paths, size, |start, rows, get| {
+        value += (0..rows)
+            .map(|i| get(i, (start + i) ^ x_mask) * pauli_string.phase(start + i))
+            .sum::<Complex<f64>>();
+    })?;
+    Ok(value.re)
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use crate::backend::QuantumBackend;
+use crate::config::SimulationConfig;
+use crate::error::{Context, SimulatorError};
+use crate::density_matrix::DensityMatrix;
+use crate::noise::{NoiseModel, NoiseSchedule};
+use crate::pattern::Pattern;
+use crate::runner::{ExecutionCursor, RunResult};
+
+// High-level entry point for the usual workflow: pick an input state on some backend, load a
+// pattern, optionally add noise, and run it for a number of shots.
+//
+//     let result = Simulator::new(DensityMatrix::new(1, State::PLUS))
+//         .load(pattern)
+//         .with_noise(NoiseModel::depolarizing(0.01)?)
+//         .run(1000)?;
+//
+// Shot i draws its randomness from the generator ("shot", i) of the RNG configuration, so runs
+// are reproducible with a seeded configuration. A noise schedule picks the model of every shot
+// instead of a fixed one. The tolerances and allocator of the simulation
+// configuration apply to every shot.
+#[derive(Clone)]
+pub struct Simulator<B: QuantumBackend + Clone> {
+    input: B,
+    pattern: Option<Pattern>,
+    noise: Option<ShotNoise>,
+    config: SimulationConfig
+}
+
+#[derive(Clone)]
+enum ShotNoise {
+    Fixed(Box<NoiseModel>),
+    Scheduled(NoiseSchedule)
+}
+
+pub struct SimulationResult<B> {
+    pub shots: Vec<RunResult<B>>
+}
+
+impl<B: QuantumBackend + Clone> Simulator<B> {
+    // The backend holds the input state, qubit i being the i-th input node of the pattern.
+    pub fn new(input: B) -> Self {
+        Simulator { input, pattern: None, noise: None, config: SimulationConfig::default() }
+    }
+
+    pub fn load(mut self, pattern: Pattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_noise(mut self, noise: NoiseModel) -> Self {
+        self.noise = Some(ShotNoise::Fixed(Box::new(noise)));
+        self
+    }
+
+    // Replaces the noise model, if any, as the model of a shot is only known when it runs.
+    pub fn with_noise_schedule(mut self, schedule: NoiseSchedule) -> Self {
+        self.noise = Some(ShotNoise::Scheduled(schedule));
+        self
+    }
+
+    pub fn with_config(mut self, config: SimulationConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.config.rng.seed = seed;
+        self
+    }
+
+    pub fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    pub fn run(&self, shots: usize) -> Result<SimulationResult<B>, String> {
+        let mut results = Vec::new();
+        self.for_each_shot(shots, |_, result| {
+            results.push(result);
+            Ok(())
+        })?;
+        Ok(SimulationResult { shots: results })
+    }
+
+    // Run the shots one after the other, handing each result to f with its index instead of
+    // keeping them all in memory.
+    pub fn for_each_shot<F>(&self, shots: usize, mut f: F) -> Result<(), String>
+    where
+        F: FnMut(u64, RunResult<B>) -> Result<(), String>,
+    {
+        let pattern = self.pattern.as_ref().ok_or("No pattern loaded in the simulator.")?;
+        for shot in 0..shots as u64 {
+            let mut rng = self.config.rng.rng("shot", shot);
+            let mut cursor = ExecutionCursor::new(pattern, self.input.clone())?;
+            cursor.set_config(self.config.clone());
+            match &self.noise {
+                Some(ShotNoise::Fixed(noise)) => cursor.set_noise((**noise).clone()),
+                Some(ShotNoise::Scheduled(schedule)) => cursor.set_noise(schedule(shot).with_context(|| format!("Noise schedule of shot {}", shot))?),
+                None => {}
+            }
+            f(shot, cursor.resume(pattern.seq(), pattern.output_nodes(), &mut *rng)?)?;
+        }
+        Ok(())
+    }
+}
+
+// Sum of density matrices of the same size, e.g. the output states of the shots of a run.
+#[derive(Default)]
+pub struct StateAverage {
+    sum: Option<DensityMatrix>,
+    count: usize
+}
+
+impl StateAverage {
+    pub fn add(&mut self, state: &DensityMatrix) -> Result<(), SimulatorError> {
+        self.sum = Some(match self.sum.take() {
+            Some(sum) => DensityMatrix { data: sum.data.add(&state.data)?, ..sum },
+            None => state.clone()
+        });
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn average(self) -> Result<DensityMatrix, String> {
+        let mut sum = self.sum.ok_or("No shots to average.")?;
+        sum.data.data.iter_mut().for_each(|x| *x /= self.count as f64);
+        Ok(sum)
+    }
+}
+
+impl<B> SimulationResult<B> {
+    // Histogram of the outcomes of the given measured nodes, in the given order.
+    pub fn counts(&self, nodes: &[usize]) -> Result<HashMap<Vec<u8>, usize>, String> {
+        let mut counts = HashMap::new();
+        for shot in &self.shots {
+            let bits = nodes.iter()
+                .map(|node| shot.outcomes.get(node).copied().ok_or_else(|| format!("Node {} is not measured.", node)))
+                .collect::<Result<Vec<_>, _>>()?;
+            *counts.entry(bits).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = &B> {
+        self.shots.iter().map(|shot| &shot.state)
+    }
+}
+
+impl SimulationResult<DensityMatrix> {
+    // Output state averaged over the shots, i.e. with the measurement outcomes forgotten.
+    pub fn average_state(&self) -> Result<DensityMatrix, String> {
+        let mut average = StateAverage::default();
+        self.states().try_for_each(|state| average.add(state))?;
+        average.average()
+    }
+}
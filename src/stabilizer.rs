@@ -0,0 +1,414 @@
+use std::fmt;
+use std::str::FromStr;
+
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::config::{SimulationConfig, TolerancePolicy};
+use crate::density_matrix::{DensityMatrix, State};
+use crate::error::SimulatorError;
+use crate::linalg;
+use crate::operators::Operator;
+use crate::pattern::{Command, Pattern};
+use crate::pauli::{Pauli, PauliString, SparsePauliOp};
+use crate::runner::RunResult;
+use crate::statevector::StateVector;
+use crate::tools::are_elements_unique;
+
+// Stabilizer states in the tableau form of Aaronson and Gottesman (PRA 70, 052328 (2004)):
+// n stabilizer generators and n destabilizers, destabilizer i anticommuting with stabilizer i
+// only. Clifford gates and Pauli measurements cost O(n) and O(n^2) instead of the 4^n entries of
+// a density matrix, so Clifford patterns run on thousands of qubits.
+//
+// Gates are given as dense operators like on the other backends. Their action on the Pauli
+// strings of the targets is read off the matrix with the unitarity tolerance of the tableau, which
+// fails for non-Clifford operators.
+
+#[derive(Debug, Clone)]
+pub struct Stabilizer {
+    pub nqubits: usize,
+    pub destabilizers: Vec<SparsePauliOp>,
+    pub stabilizers: Vec<SparsePauliOp>,
+    pub tolerance: TolerancePolicy
+}
+
+// Tableaux are compared row by row, whatever tolerances they read operators with.
+impl PartialEq for Stabilizer {
+    fn eq(&self, other: &Self) -> bool {
+        self.nqubits == other.nqubits && self.destabilizers == other.destabilizers && self.stabilizers == other.stabilizers
+    }
+}
+
+impl Stabilizer {
+    pub fn new(nqubits: usize, state: State) -> Self {
+        let mut tableau = Stabilizer::empty(0);
+        (0..nqubits).for_each(|_| tableau.add_qubit(state));
+        tableau
+    }
+
+    // Same as new, operators being read with the tolerances of the config.
+    pub fn with_config(nqubits: usize, state: State, config: &SimulationConfig) -> Self {
+        Stabilizer { tolerance: config.tolerance, ..Stabilizer::new(nqubits, state) }
+    }
+
+    fn empty(nqubits: usize) -> Self {
+        Stabilizer { nqubits, destabilizers: Vec::with_capacity(nqubits), stabilizers: Vec::with_capacity(nqubits), tolerance: TolerancePolicy::default() }
+    }
+
+    pub fn add_qubit(&mut self, state: State) {
+        let n = self.nqubits + 1;
+        let widen = |row: &mut SparsePauliOp| {
+            row.nqubits = n;
+            row.x.resize(n.div_ceil(64), 0);
+            row.z.resize(n.div_ceil(64), 0);
+        };
+        self.destabilizers.iter_mut().chain(self.stabilizers.iter_mut()).for_each(widen);
+        let (destabilizer, stabilizer) = match state {
+            State::ZERO => (Pauli::X, Pauli::Z),
+            State::PLUS => (Pauli::Z, Pauli::X)
+        };
+        self.destabilizers.push(SparsePauliOp::single(n, n - 1, destabilizer).unwrap());
+        self.stabilizers.push(SparsePauliOp::single(n, n - 1, stabilizer).unwrap());
+        self.nqubits = n;
+    }
+
+    // Image U P U^dagger = sign Q of every Pauli string P on the targets, indexed by the base 4
+    // digits of P (I, X, Y, Z), the first target being the most significant digit.
+    fn conjugation_table(op: &Operator, tol: &TolerancePolicy) -> Result<Vec<SparsePauliOp>, SimulatorError> {
+        let k = op.nqubits;
+        let d = 1 << k;
+        let strings = (0..1usize << (2 * k)).map(|index| {
+            PauliString::new((0..k).map(|q| [Pauli::I, Pauli::X, Pauli::Y, Pauli::Z][(index >> (2 * (k - 1 - q))) & 3]).collect())
+        }).collect::<Vec<_>>();
+        let adjoint = linalg::adjoint(&op.data.data, d);
+        strings.iter().map(|p| {
+            let image = linalg::matmul(&linalg::matmul(&op.data.data, &p.matrix(), d), &adjoint, d);
+            strings.iter().find_map(|q| {
+                // tr(Q M) / d is +-1 when M = +-Q, Pauli strings being Hermitian and orthogonal.
+                let q_matrix = q.matrix();
+                let overlap = (0..d * d).map(|k| q_matrix[k] * image[(k % d) * d + k / d]).sum::<Complex<f64>>() / d as f64;
+                ((overlap.norm() - 1.).abs() < tol.unitarity && overlap.im.abs() < tol.unitarity).then(|| {
+                    let mut row = SparsePauliOp::from_pauli_string(q);
+                    row.phase = if overlap.re > 0. { 0 } else { 2 };
+                    row
+                })
+            }).ok_or_else(|| SimulatorError::InvalidArgument("Operator is not a Clifford gate.".to_string()))
+        }).collect()
+    }
+
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        let table = Stabilizer::conjugation_table(op, &self.tolerance)?;
+        let digit = |p: Pauli| match p { Pauli::I => 0, Pauli::X => 1, Pauli::Y => 2, Pauli::Z => 3 };
+        for row in self.destabilizers.iter_mut().chain(self.stabilizers.iter_mut()) {
+            let index = indices.iter().fold(0, |acc, &q| (acc << 2) | digit(row.pauli(q)));
+            let image = &table[index];
+            for (t, &q) in indices.iter().enumerate() {
+                row.set(q, image.pauli(t))?;
+            }
+            row.phase = (row.phase + image.phase) % 4;
+        }
+        Ok(())
+    }
+
+    // Sign of the Pauli string in the stabilizer group, or None if it anticommutes with it and
+    // its expectation value is 0. P is then the product of the stabilizers whose destabilizers
+    // anticommute with it.
+    fn stabilizer_sign(&self, pauli: &SparsePauliOp) -> Option<f64> {
+        if self.stabilizers.iter().any(|s| !s.commutes_with(pauli)) {
+            return None;
+        }
+        let product = self.destabilizers.iter().zip(&self.stabilizers)
+            .filter(|(d, _)| !d.commutes_with(pauli))
+            .fold(SparsePauliOp::new(self.nqubits), |acc, (_, s)| acc.product(s).unwrap());
+        Some(if product.phase == pauli.phase { 1. } else { -1. })
+    }
+
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: pauli_string.nqubits() });
+        }
+        Ok(self.stabilizer_sign(&SparsePauliOp::from_pauli_string(pauli_string)).unwrap_or(0.))
+    }
+
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let z = SparsePauliOp::single(self.nqubits, index, Pauli::Z)?;
+        let flips = |row: &SparsePauliOp| matches!(row.pauli(index), Pauli::X | Pauli::Y);
+        let Some(p) = self.stabilizers.iter().position(flips) else {
+            // Z is in the stabilizer group, the outcome is given by its sign.
+            return Ok(u8::from(self.stabilizer_sign(&z) == Some(-1.)));
+        };
+        // Random outcome: only stabilizer p anticommutes with Z once the others are multiplied by it.
+        let pivot = self.stabilizers[p].clone();
+        for row in self.destabilizers.iter_mut().filter(|row| flips(row)) {
+            *row = row.product(&pivot)?;
+        }
+        for (i, row) in self.stabilizers.iter_mut().enumerate() {
+            if i != p && flips(row) {
+                *row = row.product(&pivot)?;
+            }
+        }
+        let outcome = rng.gen::<bool>();
+        self.destabilizers[p] = pivot;
+        self.stabilizers[p] = SparsePauliOp { phase: 2 * u8::from(outcome), ..z };
+        Ok(u8::from(outcome))
+    }
+
+    // Measure the qubit, then rewrite the tableau so that the stabilizer of a pair p is the
+    // measured +-Z and every other row commutes with it, and drop that pair and the qubit.
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        // Z is the product of the stabilizers whose destabilizers anticommute with it.
+        let anticommuting = (0..self.nqubits)
+            .filter(|&i| matches!(self.destabilizers[i].pauli(index), Pauli::X | Pauli::Y))
+            .collect::<Vec<_>>();
+        let p = anticommuting[0];
+        for &j in &anticommuting[1..] {
+            self.stabilizers[p] = self.stabilizers[p].product(&self.stabilizers[j])?;
+            self.destabilizers[j] = self.destabilizers[j].product(&self.destabilizers[p])?;
+        }
+        self.destabilizers.remove(p);
+        self.stabilizers.remove(p);
+        // The remaining rows are I or Z on the qubit, and Z is worth (-1)^outcome on the state.
+        let n = self.nqubits - 1;
+        let narrow = |row: &mut SparsePauliOp, sign: u8| {
+            if row.pauli(index) == Pauli::Z {
+                row.phase = (row.phase + sign) % 4;
+            }
+            row.nqubits = n;
+            remove_bit(&mut row.x, index, n);
+            remove_bit(&mut row.z, index, n);
+        };
+        self.destabilizers.iter_mut().for_each(|row| narrow(row, 0));
+        self.stabilizers.iter_mut().for_each(|row| narrow(row, 2 * outcome));
+        self.nqubits = n;
+        Ok(outcome)
+    }
+
+    pub fn tensor(&mut self, other: &Stabilizer) {
+        let n = self.nqubits + other.nqubits;
+        let shift = |row: &SparsePauliOp, offset: usize| {
+            let mut shifted = SparsePauliOp::new(n);
+            (0..row.nqubits).for_each(|q| shifted.set(q + offset, row.pauli(q)).unwrap());
+            shifted.phase = row.phase;
+            shifted
+        };
+        let destabilizers = self.destabilizers.iter().map(|r| shift(r, 0)).chain(other.destabilizers.iter().map(|r| shift(r, self.nqubits))).collect();
+        let stabilizers = self.stabilizers.iter().map(|r| shift(r, 0)).chain(other.stabilizers.iter().map(|r| shift(r, self.nqubits))).collect();
+        *self = Stabilizer { nqubits: n, destabilizers, stabilizers, tolerance: self.tolerance };
+    }
+
+    // Dense state prod_i (I + S_i) / 2 applied to a basis state, normalized, for small registers.
+    // The projector keeps some basis state with a probability above the probability tolerance
+    // unless the rows were edited into an inconsistent set of stabilizers.
+    pub fn to_statevector(&self) -> Result<StateVector, SimulatorError> {
+        let qubits = (0..self.nqubits).collect::<Vec<_>>();
+        let d = 1 << self.nqubits;
+        (0..d).find_map(|start| {
+            let mut data = vec![Complex::ZERO; d];
+            data[start] = Complex::ONE;
+            let mut state = StateVector { data, nqubits: self.nqubits };
+            for s in &self.stabilizers {
+                let mut applied = state.clone();
+                applied.evolve(&s.to_operator(), &qubits).unwrap();
+                state.data.iter_mut().zip(&applied.data).for_each(|(a, b)| *a = (*a + b) / 2.);
+            }
+            (state.norm().powi(2) > self.tolerance.probability).then(|| {
+                state.normalize();
+                state
+            })
+        }).ok_or_else(|| SimulatorError::InvalidArgument("Stabilizers have no common +1 eigenstate.".to_string()))
+    }
+}
+
+// Drop the bit of a qubit from a bitset, shifting the bits of the following qubits down.
+fn remove_bit(words: &mut Vec<u64>, index: usize, nqubits: usize) {
+    let (word, bit) = (index / 64, index % 64);
+    let low = words[word] & ((1 << bit) - 1);
+    let high = if bit == 63 { 0 } else { (words[word] >> (bit + 1)) << bit };
+    words[word] = low | high;
+    for w in word..words.len() - 1 {
+        words[w] |= (words[w + 1] & 1) << 63;
+        words[w + 1] >>= 1;
+    }
+    words.truncate(nqubits.div_ceil(64));
+}
+
+// Tableau text format shared with CHP and stim: one signed Pauli string per row, e.g. "+XZ_" or
+// "-Y_X", the identity being written _ or I. A full tableau lists the n destabilizers, a line of
+// dashes and the n stabilizers. A list of n stabilizers alone is completed with destabilizers,
+// the generators possibly being recombined without changing the state.
+impl fmt::Display for Stabilizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_row = |f: &mut fmt::Formatter<'_>, row: &SparsePauliOp| {
+            let paulis = (0..row.nqubits).map(|q| match row.pauli(q) {
+                Pauli::I => '_',
+                Pauli::X => 'X',
+                Pauli::Y => 'Y',
+                Pauli::Z => 'Z'
+            }).collect::<String>();
+            writeln!(f, "{}{}", if row.phase == 2 { '-' } else { '+' }, paulis)
+        };
+        self.destabilizers.iter().try_for_each(|row| write_row(f, row))?;
+        writeln!(f, "{}", "-".repeat(self.nqubits + 1))?;
+        self.stabilizers.iter().try_for_each(|row| write_row(f, row))
+    }
+}
+
+impl FromStr for Stabilizer {
+    type Err = SimulatorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+        let parse_rows = |lines: &[&str]| lines.iter().map(|line| {
+            let row = line.replace('_', "I").parse::<SparsePauliOp>().map_err(SimulatorError::InvalidArgument)?;
+            if row.phase % 2 == 1 {
+                return Err(SimulatorError::InvalidArgument(format!("Tableau row \"{}\" is not Hermitian.", line)));
+            }
+            Ok(row)
+        }).collect::<Result<Vec<_>, SimulatorError>>();
+        let separator = lines.iter().position(|line| line.chars().all(|c| c == '-'));
+        let tableau = match separator {
+            Some(i) => Stabilizer {
+                destabilizers: parse_rows(&lines[..i])?,
+                stabilizers: parse_rows(&lines[i + 1..])?,
+                ..Stabilizer::empty(i)
+            },
+            None => return Stabilizer::from_stabilizers(parse_rows(&lines)?)
+        };
+        let n = tableau.nqubits;
+        if tableau.stabilizers.len() != n || tableau.destabilizers.iter().chain(&tableau.stabilizers).any(|row| row.nqubits != n) {
+            return Err(SimulatorError::InvalidArgument(format!("A tableau of {} qubits needs {} destabilizers and {} stabilizers on {} qubits.", n, n, n, n)));
+        }
+        for i in 0..n {
+            for j in 0..n {
+                let (d, s) = (&tableau.destabilizers[i], &tableau.stabilizers[i]);
+                if !d.commutes_with(&tableau.destabilizers[j]) || !s.commutes_with(&tableau.stabilizers[j])
+                    || d.commutes_with(&tableau.stabilizers[j]) == (i == j) {
+                    return Err(SimulatorError::InvalidArgument(format!("Rows {} and {} break the commutation relations of a tableau.", i, j)));
+                }
+            }
+        }
+        Ok(tableau)
+    }
+}
+
+impl Stabilizer {
+    // Complete n independent commuting stabilizers with destabilizers by symplectic
+    // Gram-Schmidt over the single qubit X and Z operators.
+    pub fn from_stabilizers(stabilizers: Vec<SparsePauliOp>) -> Result<Self, SimulatorError> {
+        let n = stabilizers.len();
+        if let Some(row) = stabilizers.iter().find(|row| row.nqubits != n) {
+            return Err(SimulatorError::InvalidArgument(format!("Stabilizer {} acts on {} qubits but {} stabilizers are given.", row, row.nqubits, n)));
+        }
+        if let Some(row) = stabilizers.iter().find(|row| row.phase % 2 == 1) {
+            return Err(SimulatorError::InvalidArgument(format!("Stabilizer {} is not Hermitian.", row)));
+        }
+        if stabilizers.iter().any(|a| stabilizers.iter().any(|b| !a.commutes_with(b))) {
+            return Err(SimulatorError::InvalidArgument("Stabilizers do not commute.".to_string()));
+        }
+        let mut pool = (0..n).flat_map(|q| [Pauli::X, Pauli::Z].map(|p| SparsePauliOp::single(n, q, p).unwrap())).collect::<Vec<_>>();
+        let mut tableau = Stabilizer::empty(n);
+        for mut s in stabilizers {
+            // Commute with the destabilizers found so far, keeping the stabilizer group.
+            for (d, previous) in tableau.destabilizers.iter().zip(&tableau.stabilizers) {
+                if !s.commutes_with(d) {
+                    s = s.product(previous)?;
+                }
+            }
+            let position = pool.iter().position(|p| !p.commutes_with(&s)).ok_or_else(|| SimulatorError::InvalidArgument("Stabilizers are not independent.".to_string()))?;
+            let mut d = pool.swap_remove(position);
+            d.phase = 0;
+            for p in pool.iter_mut() {
+                let (flip_d, flip_s) = (!p.commutes_with(&d), !p.commutes_with(&s));
+                if flip_d {
+                    *p = p.product(&s)?;
+                }
+                if flip_s {
+                    *p = p.product(&d)?;
+                }
+            }
+            tableau.destabilizers.push(d);
+            tableau.stabilizers.push(s);
+        }
+        Ok(tableau)
+    }
+}
+
+impl QuantumBackend for Stabilizer {
+    fn nqubits(&self) -> usize {
+        self.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        if op.nqubits != 1 {
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
+        }
+        Stabilizer::evolve(self, op, &[index])
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        Stabilizer::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        Stabilizer::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        Stabilizer::expectation(self, pauli_string)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        Stabilizer::tensor(self, other)
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        Stabilizer::add_qubit(self, state)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        Stabilizer::measure_and_remove(self, index, rng)
+    }
+}
+
+// Output of Pattern::simulate_auto, on the backend it picked.
+pub enum AutoResult {
+    Stabilizer(RunResult<Stabilizer>),
+    DensityMatrix(RunResult<DensityMatrix>)
+}
+
+impl Pattern {
+    // Whether the pattern only uses Clifford operations: every measurement angle is a multiple
+    // of pi / 2, within the equality tolerance, and every node is prepared in |+>.
+    pub fn is_clifford(&self, tol: &TolerancePolicy) -> bool {
+        self.seq().iter().all(|command| match command {
+            Command::M(_, _, angle, ..) => ((angle * 2.) - (angle * 2.).round()).abs() < tol.equality,
+            Command::NState(..) => false,
+            _ => true
+        })
+    }
+
+    // Run on the stabilizer backend if the pattern is Clifford and on density matrices otherwise,
+    // the input nodes being prepared in the given state.
+    pub fn simulate_auto(&self, input: State, config: &SimulationConfig, rng: &mut dyn RngCore) -> Result<AutoResult, String> {
+        let n = self.input_nodes().len();
+        if self.is_clifford(&config.tolerance) {
+            Ok(AutoResult::Stabilizer(self.simulate_with_config(Stabilizer::with_config(n, input, config), config, rng)?))
+        } else {
+            Ok(AutoResult::DensityMatrix(self.simulate_with_config(DensityMatrix::with_config(n, input, config)?, config, rng)?))
+        }
+    }
+}
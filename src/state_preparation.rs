@@ -0,0 +1,95 @@
+use num_complex::Complex;
+
+use crate::circuit::Circuit;
+use crate::config::TolerancePolicy;
+use crate::error::SimulatorError;
+
+// Rotation axis of a uniformly controlled rotation.
+#[derive(Clone, Copy)]
+enum Axis {
+    Y,
+    Z
+}
+
+impl Circuit {
+    // Circuit preparing the normalized target from |0...0>, up to a global phase, with the
+    // recursive decomposition of Mottonen et al. (quant-ph/0407010): qubit k gets a RY controlled
+    // by qubits 0..k to set the magnitudes, then RZ controlled the same way to set the phases.
+    // Only RY, RZ and CNOT are used, so the circuit also translates to a pattern.
+    // Rotations below the equality tolerance are left out.
+    pub fn prepare_state(target: &[Complex<f64>], tol: &TolerancePolicy) -> Result<Circuit, SimulatorError> {
+        let len = target.len();
+        if len < 2 || !len.is_power_of_two() {
+            return Err(SimulatorError::NotPowerOfTwo(len));
+        }
+        let norm = target.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        if norm < tol.probability {
+            return Err(SimulatorError::NotNormalized(norm));
+        }
+        let n = len.ilog2() as usize;
+        let mut circuit = Circuit::new(n);
+
+        // probabilities[k][c] is the weight of the basis states whose first k qubits read c.
+        let mut probabilities = vec![target.iter().map(|a| a.norm_sqr()).collect::<Vec<_>>()];
+        for _ in 0..n {
+            let last = probabilities.last().unwrap();
+            let parent = last.chunks(2).map(|p| p[0] + p[1]).collect::<Vec<_>>();
+            probabilities.push(parent);
+        }
+        probabilities.reverse();
+        for k in 0..n {
+            let angles = probabilities[k + 1].chunks(2)
+                .map(|p| 2. * p[1].sqrt().atan2(p[0].sqrt()))
+                .collect::<Vec<_>>();
+            uniformly_controlled_rotation(&mut circuit, Axis::Y, k, &angles, tol);
+        }
+
+        // The phases are diagonal, so they can all be set once the magnitudes are in place.
+        // Each level fixes the relative phase of the two children of a prefix and passes their
+        // mean up to the parent prefix.
+        let mut phases = target.iter().map(|a| a.arg()).collect::<Vec<_>>();
+        let mut levels = Vec::new();
+        for _ in 0..n {
+            levels.push(phases.chunks(2).map(|p| p[1] - p[0]).collect::<Vec<_>>());
+            phases = phases.chunks(2).map(|p| (p[0] + p[1]) / 2.).collect();
+        }
+        for (k, angles) in levels.iter().rev().enumerate() {
+            uniformly_controlled_rotation(&mut circuit, Axis::Z, k, angles, tol);
+        }
+        Ok(circuit)
+    }
+}
+
+// Rotation of qubit target by angles[c], c being the value of the qubits 0..target (qubit 0 as
+// the most significant bit), decomposed into 2^target rotations interleaved with CNOTs. The CNOT
+// after rotation i is controlled by the bit changing between the Gray codes of i and i + 1, so
+// that the target sees angle sum_i (-1)^(c . gray(i)) theta_i.
+fn uniformly_controlled_rotation(circuit: &mut Circuit, axis: Axis, target: usize, angles: &[f64], tol: &TolerancePolicy) {
+    if angles.iter().all(|a| a.abs() < tol.equality) {
+        return;
+    }
+    let rotate = |circuit: &mut Circuit, angle: f64| {
+        if angle.abs() >= tol.equality {
+            match axis {
+                Axis::Y => circuit.ry(target, angle),
+                Axis::Z => circuit.rz(target, angle)
+            }
+        }
+    };
+    let k = target;
+    if k == 0 {
+        rotate(circuit, angles[0]);
+        return;
+    }
+    let gray = |i: usize| i ^ (i >> 1);
+    let count = 1 << k;
+    for i in 0..count {
+        let theta = (0..count)
+            .map(|c| if (c & gray(i)).count_ones() % 2 == 0 { angles[c] } else { -angles[c] })
+            .sum::<f64>() / count as f64;
+        rotate(circuit, theta);
+        // Bit j of c is the value of qubit k - 1 - j.
+        let changed = (gray(i) ^ gray((i + 1) % count)).trailing_zeros() as usize;
+        circuit.cnot(k - 1 - changed, target);
+    }
+}
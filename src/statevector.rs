@@ -0,0 +1,206 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use num_complex::Complex;
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::config::{self, BufferAllocator, SimulationConfig, SystemAllocator};
+use crate::density_matrix::{DensityMatrix, State};
+use crate::operators::Operator;
+use crate::pauli::PauliString;
+use crate::error::SimulatorError;
+use crate::tools::{are_elements_unique, apply_left};
+
+// Pure state of nqubits stored as its 2^nqubits amplitudes, qubit 0 being the most significant bit.
+#[derive(Debug, Clone)]
+pub struct StateVector {
+    pub data: Vec<Complex<f64>>,
+    pub nqubits: usize
+}
+
+impl StateVector {
+    pub fn new(nqubits: usize, initial_state: State) -> Self {
+        let size = 1 << nqubits;
+        let data = match initial_state {
+            State::PLUS => vec![Complex::new(FRAC_1_SQRT_2.powi(nqubits as i32), 0.); size],
+            State::ZERO => {
+                let mut data = vec![Complex::ZERO; size];
+                data[0] = Complex::ONE;
+                data
+            }
+        };
+        StateVector { data, nqubits }
+    }
+
+    // Same as new, with the buffer taken from the allocator of the config.
+    pub fn with_config(nqubits: usize, initial_state: State, config: &SimulationConfig) -> Result<Self, SimulatorError> {
+        let size = 1 << nqubits;
+        let mut data = config::allocate(config.allocator.as_ref(), size)?;
+        match initial_state {
+            State::PLUS => data.iter_mut().for_each(|a| *a = Complex::new(FRAC_1_SQRT_2.powi(nqubits as i32), 0.)),
+            State::ZERO => data[0] = Complex::ONE
+        }
+        Ok(StateVector { data, nqubits })
+    }
+
+    pub fn from_vec(data: Vec<Complex<f64>>) -> Result<Self, SimulatorError> {
+        if !data.len().is_power_of_two() {
+            return Err(SimulatorError::NotPowerOfTwo(data.len()));
+        }
+        let nqubits = data.len().ilog2() as usize;
+        Ok(StateVector { data, nqubits })
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.data.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt()
+    }
+
+    pub fn normalize(&mut self) {
+        let norm = self.norm();
+        self.data.iter_mut().for_each(|a| *a /= norm);
+    }
+
+    pub fn to_density_matrix(&self) -> DensityMatrix {
+        DensityMatrix::from_statevec(&self.data).unwrap()
+    }
+
+    pub fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        if op.nqubits != 1 {
+            return Err(SimulatorError::DimensionMismatch { expected: 1, actual: op.nqubits });
+        }
+        self.evolve(op, &[index])
+    }
+
+    pub fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        if !are_elements_unique(indices) {
+            return Err(SimulatorError::DuplicateIndices(indices.to_vec()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= self.nqubits) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        if op.nqubits != indices.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: indices.len(), actual: op.nqubits });
+        }
+        apply_left(&mut self.data, 1, &op.data.data, indices, self.nqubits);
+        Ok(())
+    }
+
+    pub fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        if index >= self.nqubits {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.nqubits });
+        }
+        let bit = 1 << (self.nqubits - 1 - index);
+        let p1 = self.data.iter().enumerate()
+            .filter(|(i, _)| i & bit != 0)
+            .map(|(_, a)| a.norm_sqr())
+            .sum::<f64>() / self.norm().powi(2);
+        let outcome = u8::from(rng.gen::<f64>() < p1);
+        self.data.iter_mut().enumerate()
+            .filter(|(i, _)| u8::from(i & bit != 0) != outcome)
+            .for_each(|(_, a)| *a = Complex::ZERO);
+        self.normalize();
+        Ok(outcome)
+    }
+
+    pub fn add_qubit(&mut self, state: State) {
+        self.tensor(&StateVector::new(1, state));
+    }
+
+    // Same as add_qubit, the enlarged buffer coming from the allocator.
+    pub fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        self.tensor_with(&StateVector::new(1, state), allocator)
+    }
+
+    pub fn remove_qubit(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.measure_and_remove(index, rng)
+    }
+
+    pub fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.measure_and_remove_with(index, rng, &SystemAllocator)
+    }
+
+    // Same as measure_and_remove, the reduced buffer coming from the allocator.
+    pub fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        let outcome = self.measure(index, rng)?;
+        let bit = 1 << (self.nqubits - 1 - index);
+        let mut data = config::allocate(allocator, self.data.len() / 2)?;
+        let kept = self.data.iter().enumerate().filter(|(i, _)| u8::from(i & bit != 0) == outcome);
+        data.iter_mut().zip(kept).for_each(|(x, (_, a))| *x = *a);
+        self.data = data;
+        self.nqubits -= 1;
+        Ok(outcome)
+    }
+
+    // Compute <psi|P|psi> for a Pauli string P covering every qubit.
+    pub fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        if pauli_string.nqubits() != self.nqubits {
+            return Err(SimulatorError::DimensionMismatch { expected: self.nqubits, actual: pauli_string.nqubits() });
+        }
+        let x_mask = pauli_string.x_mask();
+        let value = self.data.iter().enumerate()
+            .map(|(i, a)| self.data[i ^ x_mask].conj() * pauli_string.phase(i) * a)
+            .sum::<Complex<f64>>();
+        Ok(value.re)
+    }
+
+    pub fn tensor(&mut self, other: &StateVector) {
+        self.tensor_into(other, vec![Complex::ZERO; self.data.len() * other.data.len()]);
+    }
+
+    // Same as tensor, the product buffer coming from the allocator.
+    pub fn tensor_with(&mut self, other: &StateVector, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        let data = config::allocate(allocator, self.data.len() * other.data.len())?;
+        self.tensor_into(other, data);
+        Ok(())
+    }
+
+    // Kronecker product written into data, which holds len(self) * len(other) amplitudes.
+    fn tensor_into(&mut self, other: &StateVector, mut data: Vec<Complex<f64>>) {
+        let products = self.data.iter().flat_map(|a| other.data.iter().map(move |b| a * b));
+        data.iter_mut().zip(products).for_each(|(x, product)| *x = product);
+        self.data = data;
+        self.nqubits += other.nqubits;
+    }
+}
+
+impl QuantumBackend for StateVector {
+    fn nqubits(&self) -> usize {
+        self.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        StateVector::evolve_single(self, op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        StateVector::evolve(self, op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        StateVector::measure(self, index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        StateVector::expectation(self, pauli_string)
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        StateVector::tensor(self, other)
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        StateVector::add_qubit(self, state)
+    }
+
+    fn add_qubit_with(&mut self, state: State, allocator: &dyn BufferAllocator) -> Result<(), SimulatorError> {
+        StateVector::add_qubit_with(self, state, allocator)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        StateVector::measure_and_remove(self, index, rng)
+    }
+
+    fn measure_and_remove_with(&mut self, index: usize, rng: &mut dyn RngCore, allocator: &dyn BufferAllocator) -> Result<u8, SimulatorError> {
+        StateVector::measure_and_remove_with(self, index, rng, allocator)
+    }
+}
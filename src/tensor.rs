@@ -1,10 +1,25 @@
 use core::fmt;
-use num_traits::{Zero, One};
-use std::ops::{Add, Mul, AddAssign};
+use num_traits::Zero;
+use std::ops::{Add, Mul, AddAssign, Range};
 
-use crate::tools::{bitwise_bin_vec_to_int, bitwise_int_to_bin_vec};
+use crate::error::SimulatorError;
+use crate::tools::are_elements_unique;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Bound required on tensor elements, which must be shareable across threads when the contraction runs in parallel.
+#[cfg(feature = "parallel")]
+pub trait Element: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> Element for T {}
+#[cfg(not(feature = "parallel"))]
+pub trait Element {}
+#[cfg(not(feature = "parallel"))]
+impl<T> Element for T {}
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tensor<T> {
     pub data: Vec<T>,
     pub shape: Vec<usize>,
@@ -12,7 +27,7 @@ pub struct Tensor<T> {
 
 impl<T> Tensor<T>
 where
-    T: Zero + Clone + Mul<Output = T> + Add<Output = T> + AddAssign,
+    T: Zero + Clone + Mul<Output = T> + Add<Output = T> + AddAssign + Element,
 {
     // Initialize a new tensor with given shape
     pub fn new(shape: &[usize]) -> Self {
@@ -23,15 +38,27 @@ where
         }
     }
 
-    // Initialize a new tensor from a given vector and a given shape.
+    // Initialize a new tensor from a given vector and a given shape, panicking if they do not match.
     pub fn from_vec(vec: Vec<T>, shape: Vec<usize>) -> Self {
-        assert_eq!(vec.len(),  shape.iter().product(), "Vector length {} does not match the given tensor shape {:?}", vec.len(), shape);
+        assert_eq!(vec.len(),  shape.iter().product::<usize>(), "Vector length {} does not match the given tensor shape {:?}", vec.len(), shape);
         Self {
             data: vec,
             shape
         }
     }
 
+    // Same as from_vec, for vectors coming from outside the simulator.
+    pub fn try_from_vec(vec: Vec<T>, shape: Vec<usize>) -> Result<Self, SimulatorError> {
+        let expected = shape.iter().product::<usize>();
+        if vec.len() != expected {
+            return Err(SimulatorError::DimensionMismatch { expected, actual: vec.len() });
+        }
+        Ok(Self {
+            data: vec,
+            shape
+        })
+    }
+
     pub fn print(&self, f: &mut fmt::Formatter<'_>, shape: &[usize], data: &[T]) -> fmt::Result
     where
         T: fmt::Debug,
@@ -82,31 +109,55 @@ where
         self.data[index] = value;
     }
 
+    // Check that both tensors have the same shape, reporting the first differing axis.
+    fn check_same_shape(&self, other: &Tensor<T>) -> Result<(), SimulatorError> {
+        if self.shape.len() != other.shape.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: self.shape.len(), actual: other.shape.len() });
+        }
+        if let Some((&a, &b)) = self.shape.iter().zip(other.shape.iter()).find(|(a, b)| a != b) {
+            return Err(SimulatorError::DimensionMismatch { expected: a, actual: b });
+        }
+        Ok(())
+    }
+
+    // Check that the data holds exactly one element per multi-index of the shape.
+    fn check_data_len(&self) -> Result<(), SimulatorError> {
+        let expected = self.shape.iter().product::<usize>();
+        if self.data.len() != expected {
+            return Err(SimulatorError::DimensionMismatch { expected, actual: self.data.len() });
+        }
+        Ok(())
+    }
+
     // Perform tensor addition
-    pub fn add(&self, other: &Tensor<T>) -> Self {
-        assert_eq!(self.shape, other.shape);
+    pub fn add(&self, other: &Tensor<T>) -> Result<Self, SimulatorError> {
+        self.check_same_shape(other)?;
+        self.check_data_len()?;
+        other.check_data_len()?;
         let mut result = Self::new(&self.shape);
         for (i, self_data) in self.data.iter().enumerate() {
             result.data[i] = self_data.clone() + other.data[i].clone();
         }
-        result
+        Ok(result)
     }
 
     // Perform tensor multiplication (element-wise)
-    pub fn multiply(&self, other: &Tensor<T>) -> Self {
-        assert_eq!(self.shape, other.shape);
+    pub fn multiply(&self, other: &Tensor<T>) -> Result<Self, SimulatorError> {
+        self.check_same_shape(other)?;
+        self.check_data_len()?;
+        other.check_data_len()?;
         let mut result = Self::new(&self.shape);
         for (i, self_data) in self.data.iter().enumerate() {
             result.data[i] = self_data.clone() * other.data[i].clone();
         }
-        result
+        Ok(result)
     }
 
     // Method to compute the tensor product of two tensors
-    pub fn tensor_product(&self, other: &Tensor<T>) -> Tensor<T> {
+    pub fn tensor_product(&self, other: &Tensor<T>) -> Result<Tensor<T>, SimulatorError> {
         // Check if tensors are compatible for tensor product
-        assert_eq!(self.data.len(), self.shape.iter().product());
-        assert_eq!(other.data.len(), other.shape.iter().product());
+        self.check_data_len()?;
+        other.check_data_len()?;
 
         // Calculate the shape of the resulting tensor
         let mut new_shape = self.shape.clone();
@@ -119,157 +170,290 @@ where
             .flat_map(|x| other.data.iter().map(move |y| x.clone() * y.clone()))
             .collect();
 
-        Tensor {
+        Ok(Tensor {
             data: new_data,
             shape: new_shape,
-        }
+        })
     }
 
-    pub fn tensordot(&self, other: &Tensor<T>, axes: (&[usize], &[usize])) -> Result<Tensor<T>, &str> {
+    pub fn tensordot(&self, other: &Tensor<T>, axes: (&[usize], &[usize])) -> Result<Tensor<T>, SimulatorError> {
         if axes.0.len() != axes.1.len() {
-            return Err("Axes dimensions must match");
+            return Err(SimulatorError::DimensionMismatch { expected: axes.0.len(), actual: axes.1.len() });
         }
-        
-        let mut new_shape_self = self.shape.clone();
-        let mut new_shape_other = other.shape.clone();
-
-        let mut sorted_axes_self = axes.0.to_vec();
-        sorted_axes_self.sort_by(|a: &usize, b: &usize| b.cmp(a));
-        for &axis in sorted_axes_self.iter() {
-            if axis >= new_shape_self.len() {
-                return Err("Axis out of bounds for self");
-            }
-            new_shape_self.remove(axis);
+        if let Some(&index) = axes.0.iter().find(|&&axis| axis >= self.shape.len()) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.shape.len() });
+        }
+        if let Some(&index) = axes.1.iter().find(|&&axis| axis >= other.shape.len()) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: other.shape.len() });
+        }
+        if let Some((&a, &b)) = axes.0.iter().zip(axes.1.iter()).find(|(&a, &b)| self.shape[a] != other.shape[b]) {
+            return Err(SimulatorError::DimensionMismatch { expected: self.shape[a], actual: other.shape[b] });
         }
 
-        let mut sorted_axes_other = axes.1.to_vec();
-        sorted_axes_other.sort_by(|a, b| b.cmp(a));
-        for &axis in sorted_axes_other.iter() {
-            if axis >= new_shape_other.len() {
-                return Err("Axis out of bounds for other");
-            }
-            new_shape_other.remove(axis);
+        let free_self = (0..self.shape.len()).filter(|a| !axes.0.contains(a)).collect::<Vec<_>>();
+        let free_other = (0..other.shape.len()).filter(|a| !axes.1.contains(a)).collect::<Vec<_>>();
+        let mut result_shape = free_self.iter().map(|&a| self.shape[a]).collect::<Vec<_>>();
+        result_shape.extend(free_other.iter().map(|&a| other.shape[a]));
+
+        // View both operands as matrices: rows of self (free axes) x contracted axes x columns of other (free axes).
+        let rows = Self::axes_offsets(&self.shape, &free_self);
+        let common_self = Self::axes_offsets(&self.shape, axes.0);
+        let common_other = Self::axes_offsets(&other.shape, axes.1);
+        let cols = Self::axes_offsets(&other.shape, &free_other);
+
+        let mut result = Tensor::new(&result_shape);
+        if result.data.is_empty() {
+            return Ok(result);
         }
+        let contract_row = |(r, row): (usize, &mut [T])| {
+            for (&cs, &co) in common_self.iter().zip(common_other.iter()) {
+                let value_self = self.data[rows[r] + cs].clone();
+                for (k, &c) in cols.iter().enumerate() {
+                    row[k] += value_self.clone() * other.data[co + c].clone();
+                }
+            }
+        };
+        #[cfg(feature = "parallel")]
+        result.data.par_chunks_mut(cols.len()).enumerate().for_each(contract_row);
+        #[cfg(not(feature = "parallel"))]
+        result.data.chunks_mut(cols.len()).enumerate().for_each(contract_row);
 
-        new_shape_self.extend(new_shape_other);
-        
-        let result_shape = new_shape_self;
-        let result_data = vec![T::zero(); result_shape.iter().product()];
-        let mut result = Tensor::from_vec(result_data, result_shape.clone());
+        Ok(result)
+    }
 
-        for (i, value_self) in self.data.iter().enumerate() {
-            let indices_self = Self::unravel_index(i, &self.shape);
-            let indices_common: Vec<_> = axes.0.iter().map(|&axis| indices_self[axis]).collect();
-            let indices_self_reduced: Vec<_> = indices_self.iter().enumerate()
-                .filter(|&(idx, _)| !axes.0.contains(&idx))
-                .map(|(_, &val)| val)
+    // Flat offsets of every multi-index spanned by the given axes, in row-major order.
+    fn axes_offsets(shape: &[usize], axes: &[usize]) -> Vec<usize> {
+        let strides = row_major_strides(shape);
+        let mut offsets = vec![0];
+        for &axis in axes {
+            let stride = strides[axis];
+            offsets = offsets.iter()
+                .flat_map(|&o| (0..shape[axis]).map(move |i| o + i * stride))
                 .collect();
+        }
+        offsets
+    }
 
-            for (j, value_other) in other.data.iter().enumerate() {
-                let indices_other = Self::unravel_index(j, &other.shape);
-                let indices_common_other: Vec<_> = axes.1.iter().map(|&axis| indices_other[axis]).collect();
-
-                if indices_common == indices_common_other {
-                    let indices_other_reduces: Vec<_> = indices_other.iter().enumerate()
-                        .filter(|&(idx, _)| !axes.1.contains(&idx))
-                        .map(|(_, &val)| val)
-                        .collect();
+    // Contraction in numpy einsum notation, e.g. "ij,jk->ik" or "ii->" for a trace. Labels are
+    // ASCII letters, repeated labels within an operand take its diagonal, and without "->" the
+    // output holds the labels appearing once, in alphabetical order.
+    pub fn einsum(spec: &str, tensors: &[&Tensor<T>]) -> Result<Tensor<T>, SimulatorError> {
+        let spec = spec.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        let invalid = |reason: &str| SimulatorError::InvalidArgument(format!("Invalid einsum spec {:?}: {}.", spec, reason));
+        let (inputs, output) = match spec.split_once("->") {
+            Some((inputs, output)) => (inputs, Some(output)),
+            None => (spec.as_str(), None)
+        };
+        let inputs = inputs.split(',').map(|labels| labels.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+        if tensors.is_empty() {
+            return Err(invalid("no operands"));
+        }
+        if inputs.len() != tensors.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: inputs.len(), actual: tensors.len() });
+        }
 
-                    let mut result_indices = indices_self_reduced.clone();
-                    result_indices.extend(indices_other_reduces);
+        // Dimension of every label, in order of first appearance.
+        let mut labels: Vec<(char, usize)> = Vec::new();
+        for (input, tensor) in inputs.iter().zip(tensors) {
+            if input.len() != tensor.shape.len() {
+                return Err(SimulatorError::DimensionMismatch { expected: input.len(), actual: tensor.shape.len() });
+            }
+            for (&label, &dim) in input.iter().zip(&tensor.shape) {
+                if !label.is_ascii_alphabetic() {
+                    return Err(invalid(&format!("label {:?} is not a letter", label)));
+                }
+                match labels.iter().find(|(l, _)| *l == label) {
+                    Some(&(_, expected)) if expected != dim => return Err(SimulatorError::DimensionMismatch { expected, actual: dim }),
+                    Some(_) => {},
+                    None => labels.push((label, dim))
+                }
+            }
+        }
+        let output = match output {
+            Some(output) => output.chars().collect::<Vec<_>>(),
+            None => {
+                let mut once = labels.iter().map(|&(l, _)| l)
+                    .filter(|l| inputs.iter().flatten().filter(|&m| m == l).count() == 1)
+                    .collect::<Vec<_>>();
+                once.sort();
+                once
+            }
+        };
+        if let Some(label) = output.iter().find(|l| !labels.iter().any(|(m, _)| m == *l)) {
+            return Err(invalid(&format!("output label {:?} is not an input label", label)));
+        }
+        if (1..output.len()).any(|i| output[..i].contains(&output[i])) {
+            return Err(invalid("repeated output label"));
+        }
 
-                    let result_index = Self::ravel_index(&result_indices, &result_shape);
-                    result.data[result_index] += value_self.clone() * value_other.clone();
+        // Output labels first, so that the summed labels run fastest.
+        let order = output.iter().copied()
+            .chain(labels.iter().map(|&(l, _)| l).filter(|l| !output.contains(l)))
+            .collect::<Vec<_>>();
+        let dims = order.iter().map(|l| labels.iter().find(|(m, _)| m == l).unwrap().1).collect::<Vec<_>>();
+        // Stride of every label in a tensor, summed over the axes carrying it.
+        let label_strides = |input: &[char], shape: &[usize]| {
+            let strides = row_major_strides(shape);
+            order.iter()
+                .map(|l| input.iter().zip(&strides).filter(|(m, _)| *m == l).map(|(_, s)| s).sum::<usize>())
+                .collect::<Vec<_>>()
+        };
+        let operand_strides = inputs.iter().zip(tensors).map(|(input, tensor)| label_strides(input, &tensor.shape)).collect::<Vec<_>>();
+        let result_shape = dims[..output.len()].to_vec();
+        let result_strides = label_strides(&output, &result_shape);
+
+        let mut result = Tensor::new(&result_shape);
+        let mut index = vec![0; order.len()];
+        for _ in 0..dims.iter().product::<usize>() {
+            let offset = |strides: &[usize]| index.iter().zip(strides).map(|(i, s)| i * s).sum::<usize>();
+            let term = tensors.iter().zip(&operand_strides)
+                .map(|(tensor, strides)| tensor.data[offset(strides)].clone())
+                .reduce(|a, b| a * b)
+                .unwrap();
+            result.data[offset(&result_strides)] += term;
+            for axis in (0..order.len()).rev() {
+                index[axis] += 1;
+                if index[axis] < dims[axis] {
+                    break;
                 }
+                index[axis] = 0;
             }
         }
         Ok(result)
     }
-    // Helper function to unravel a flat index to a multidimensional index
-    fn unravel_index(index: usize, shape: &[usize]) -> Vec<usize> {
-        let mut idx = index;
-        let mut indices = vec![0; shape.len()];
-        for i in (0..shape.len()).rev() {
-            indices[i] = idx % shape[i];
-            idx /= shape[i];
+
+    // Non-owning view of the whole tensor, to slice and permute axes without copying.
+    pub fn view(&self) -> TensorView<'_, T> {
+        TensorView {
+            data: &self.data,
+            shape: self.shape.clone(),
+            strides: row_major_strides(&self.shape),
+            offset: 0
         }
-        indices
     }
 
-    // Helper function to ravel a multidimensional index to a flat index
-    fn ravel_index(indices: &[usize], shape: &[usize]) -> usize {
-        let mut index = 0;
-        let mut factor = 1;
-        for (i, &dim) in shape.iter().enumerate().rev() {
-            index += indices[i] * factor;
-            factor *= dim;
-        }
-        index
+    pub fn transpose(&self, axes: &[usize]) -> Result<Tensor<T>, SimulatorError> {
+        Ok(self.view().transpose(axes)?.to_tensor())
     }
 
-    pub fn transpose(&self, axes: &[usize]) -> Result<Tensor<T>, &str> {
-        let new_shape: Vec<usize>;
-        let new_axes: Vec<usize>;
-    
-        if axes.is_empty() {
-            // If axes are not provided, reverse the shape and create corresponding axes
-            new_shape = self.shape.iter().rev().cloned().collect();
-            new_axes = (0..self.shape.len()).rev().collect::<Vec<_>>();
-        } else {
-            if axes.len() != self.shape.len() {
-                return Err("Axes dimensions must match tensor dimensions");
-            }
-            new_shape = axes.iter().map(|&axis| self.shape[axis]).collect();
-            new_axes = axes.to_vec();
+    pub fn moveaxis(&self, source: &[i32], dest: &[i32]) -> Result<Tensor<T>, SimulatorError> {
+        Ok(self.view().moveaxis(source, dest)?.to_tensor())
+    }
+}
+
+fn row_major_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (1..shape.len()).rev() {
+        strides[i - 1] = strides[i] * shape[i];
+    }
+    strides
+}
+
+// Borrowed elements of a tensor seen through a shape, strides and offset. Slicing and axis
+// permutations only change those, the data being copied once by to_tensor if needed.
+#[derive(Debug, Clone)]
+pub struct TensorView<'a, T> {
+    data: &'a [T],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize
+}
+
+impl<'a, T: Clone> TensorView<'a, T> {
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Whether the view reads the data in row-major order without gaps.
+    pub fn is_contiguous(&self) -> bool {
+        self.shape.iter().zip(self.strides.iter().zip(row_major_strides(&self.shape)))
+            .all(|(&dim, (&stride, expected))| dim <= 1 || stride == expected)
+    }
+
+    fn check_axis(&self, axis: usize) -> Result<(), SimulatorError> {
+        if axis >= self.shape.len() {
+            return Err(SimulatorError::IndexOutOfRange { index: axis, nqubits: self.shape.len() });
         }
- 
-        let mut new_data = vec![T::zero(); self.data.len()];
+        Ok(())
+    }
 
-        let mut old_indices = vec![0; self.shape.len()];
-        let mut new_indices = vec![0; self.shape.len()];
-        let mut old_strides = vec![1; self.shape.len()];
-        let mut new_strides = vec![1; self.shape.len()];
+    pub fn get(&self, indices: &[usize]) -> Result<&'a T, SimulatorError> {
+        if indices.len() != self.shape.len() {
+            return Err(SimulatorError::DimensionMismatch { expected: self.shape.len(), actual: indices.len() });
+        }
+        if let Some((&index, &dim)) = indices.iter().zip(&self.shape).find(|(&i, &dim)| i >= dim) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: dim });
+        }
+        Ok(&self.data[self.offset + indices.iter().zip(&self.strides).map(|(i, s)| i * s).sum::<usize>()])
+    }
 
-        for i in (1..self.shape.len()).rev() {
-            old_strides[i - 1] = old_strides[i] * self.shape[i];
+    // Elements start..end of an axis, which keeps its place.
+    pub fn slice(&self, axis: usize, range: Range<usize>) -> Result<TensorView<'a, T>, SimulatorError> {
+        self.check_axis(axis)?;
+        if range.start > range.end || range.end > self.shape[axis] {
+            return Err(SimulatorError::InvalidArgument(format!("Range {:?} is not within axis {} of size {}.", range, axis, self.shape[axis])));
         }
+        let mut view = self.clone();
+        view.offset += range.start * self.strides[axis];
+        view.shape[axis] = range.len();
+        Ok(view)
+    }
 
-        for i in (1..new_shape.len()).rev() {
-            new_strides[i - 1] = new_strides[i] * new_shape[i];
+    // Fix an axis to one index, removing it.
+    pub fn index_axis(&self, axis: usize, index: usize) -> Result<TensorView<'a, T>, SimulatorError> {
+        self.check_axis(axis)?;
+        if index >= self.shape[axis] {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.shape[axis] });
         }
+        let mut view = self.clone();
+        view.offset += index * self.strides[axis];
+        view.shape.remove(axis);
+        view.strides.remove(axis);
+        Ok(view)
+    }
 
-        for old_idx in 0..self.data.len() {
-            let mut temp = old_idx;
-            for i in 0..self.shape.len() {
-                old_indices[i] = temp / old_strides[i];
-                temp %= old_strides[i];
+    // Axis i of the result is axis axes[i] of the view, the axes being reversed if none are given.
+    pub fn transpose(&self, axes: &[usize]) -> Result<TensorView<'a, T>, SimulatorError> {
+        let axes = if axes.is_empty() {
+            (0..self.shape.len()).rev().collect::<Vec<_>>()
+        } else {
+            if axes.len() != self.shape.len() {
+                return Err(SimulatorError::DimensionMismatch { expected: self.shape.len(), actual: axes.len() });
             }
-
-            for i in 0..self.shape.len() {
-                new_indices[i] = old_indices[new_axes[i]];
+            if let Some(&index) = axes.iter().find(|&&axis| axis >= self.shape.len()) {
+                return Err(SimulatorError::IndexOutOfRange { index, nqubits: self.shape.len() });
             }
-
-            let mut new_idx = 0;
-            for i in 0..self.shape.len() {
-                new_idx += new_indices[i] * new_strides[i];
+            if !are_elements_unique(axes) {
+                return Err(SimulatorError::DuplicateIndices(axes.to_vec()));
             }
-
-            new_data[new_idx] = self.data[old_idx].clone();
-        }
-
-        Ok(Tensor {
-            data: new_data,
-            shape: new_shape,
+            axes.to_vec()
+        };
+        Ok(TensorView {
+            data: self.data,
+            shape: axes.iter().map(|&axis| self.shape[axis]).collect(),
+            strides: axes.iter().map(|&axis| self.strides[axis]).collect(),
+            offset: self.offset
         })
     }
 
-    pub fn moveaxis(&self, source: &[i32], dest: &[i32]) -> Result<Tensor<T>, &str> {
+    // Move the source axes to the destination positions, negative positions counting from the end.
+    pub fn moveaxis(&self, source: &[i32], dest: &[i32]) -> Result<TensorView<'a, T>, SimulatorError> {
         if source.len() != dest.len() {
-            return Err("source and destination arguments must have the same number of elements");
+            return Err(SimulatorError::DimensionMismatch { expected: source.len(), actual: dest.len() });
         }
-
         let ndim = self.shape.len();
-
         let convert_index = |idx: i32| -> usize {
             if idx < 0 {
                 (ndim as isize + idx as isize) as usize
@@ -277,37 +461,55 @@ where
                 idx as usize
             }
         };
-
-        let source: Vec<usize> = source.iter()
-            .map(|&x| convert_index(x))
-            .collect();
-        let dest: Vec<usize> = dest.iter()
-            .map(|&x| convert_index(x))
-            .collect();
-        
-        let mut order: Vec<usize> = (0..ndim).collect();
-
-        // Remove the source indices from the order, starting from the highest index to avoid reindexing issues
-        let mut temp_source = source.clone();
-        temp_source.sort_by(|a, b| b.cmp(a));
-        for &src in &temp_source {
-            order.remove(src);
+        let source = source.iter().map(|&x| convert_index(x)).collect::<Vec<_>>();
+        let dest = dest.iter().map(|&x| convert_index(x)).collect::<Vec<_>>();
+        if let Some(&index) = source.iter().chain(dest.iter()).find(|&&axis| axis >= ndim) {
+            return Err(SimulatorError::IndexOutOfRange { index, nqubits: ndim });
+        }
+        if !are_elements_unique(&source) || !are_elements_unique(&dest) {
+            return Err(SimulatorError::DuplicateIndices([source, dest].concat()));
         }
 
-        // Insert the source indices at the destination positions, starting from the lowest index
-        let mut temp_dest = dest.clone();
-        let mut temp_pairs: Vec<(usize, usize)> = temp_dest.iter().cloned().zip(source.iter().cloned()).collect();
-        temp_pairs.sort_by(|a, b| a.0.cmp(&b.0));
-        for &(dst, src) in &temp_pairs {
+        // The remaining axes keep their order, the moved ones being inserted from the lowest destination.
+        let mut order = (0..ndim).filter(|axis| !source.contains(axis)).collect::<Vec<_>>();
+        let mut pairs = dest.iter().copied().zip(source.iter().copied()).collect::<Vec<_>>();
+        pairs.sort_by_key(|pair| pair.0);
+        for (dst, src) in pairs {
             order.insert(dst, src);
         }
         self.transpose(&order)
     }
+
+    // Elements in row-major order of the view.
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        let mut index = vec![0; self.shape.len()];
+        let mut position = self.offset;
+        (0..self.len()).map(move |_| {
+            let item = &self.data[position];
+            for axis in (0..index.len()).rev() {
+                index[axis] += 1;
+                position += self.strides[axis];
+                if index[axis] < self.shape[axis] {
+                    break;
+                }
+                position -= index[axis] * self.strides[axis];
+                index[axis] = 0;
+            }
+            item
+        })
+    }
+
+    pub fn to_tensor(&self) -> Tensor<T> {
+        Tensor {
+            data: self.iter().cloned().collect(),
+            shape: self.shape.clone()
+        }
+    }
 }
 
 impl<T> fmt::Display for Tensor<T>
 where
-    T: fmt::Debug + Clone + Add<Output = T> + Mul<Output = T> + AddAssign + Zero
+    T: fmt::Debug + Clone + Add<Output = T> + Mul<Output = T> + AddAssign + Zero + Element
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "array(")?;
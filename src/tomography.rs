@@ -0,0 +1,292 @@
+use num_complex::Complex;
+use rand::RngCore;
+
+use crate::channels::{self, Channel};
+use crate::config::TolerancePolicy;
+use crate::density_matrix::{Basis, DensityMatrix};
+use crate::linalg;
+use crate::pauli::{Pauli, PauliString};
+use crate::randomized;
+use crate::tensor::Tensor;
+
+// Process tomography: the superoperator S mapping the row by row vectorized inputs to the outputs
+// is reconstructed by linear inversion, S = sum_k |out_k>><<in_k| G^-1 with G = sum_k |in_k>><<in_k|,
+// which needs the inputs to span the operator space, and reshuffled into the Choi matrix in the
+// convention of `Channel::choi`. Noisy or sampled outputs give a Choi matrix that may be slightly
+// outside the completely positive trace preserving maps, hence the optional projection.
+
+// Stopping rule of the CPTP projection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectionOptions {
+    pub tolerance: f64,         // Largest change of an entry in the last iteration, and largest trace preserving defect.
+    pub max_iterations: usize
+}
+
+impl ProjectionOptions {
+    // Stops at the unitarity tolerance of the policy, within 1000 iterations.
+    pub fn new(tol: &TolerancePolicy) -> Self {
+        ProjectionOptions { tolerance: tol.unitarity, max_iterations: 1000 }
+    }
+}
+
+// Informationally complete inputs: the 4^n products of |0>, |1>, |+> and |+i>, qubit 0 slowest.
+pub fn tomography_inputs(nqubits: usize) -> Vec<DensityMatrix> {
+    let s = std::f64::consts::FRAC_1_SQRT_2;
+    let states = [
+        [Complex::ONE, Complex::ZERO],
+        [Complex::ZERO, Complex::ONE],
+        [Complex::new(s, 0.), Complex::new(s, 0.)],
+        [Complex::new(s, 0.), Complex::new(0., s)]
+    ];
+    (0..1usize << (2 * nqubits)).map(|t| {
+        let statevec = (0..nqubits).fold(vec![Complex::ONE], |acc, q| {
+            let state = states[(t >> (2 * (nqubits - 1 - q))) & 3];
+            acc.iter().flat_map(|a| state.iter().map(move |b| a * b)).collect()
+        });
+        DensityMatrix::from_statevec(&statevec).expect("product of qubit states")
+    }).collect()
+}
+
+// The inputs span the operator space when the smallest eigenvalue of G is above the unitarity
+// tolerance relative to the largest one.
+pub fn reconstruct_choi(inputs: &[DensityMatrix], outputs: &[DensityMatrix], tol: &TolerancePolicy) -> Result<Vec<Complex<f64>>, String> {
+    if inputs.len() != outputs.len() {
+        return Err(format!("Got {} inputs for {} outputs.", inputs.len(), outputs.len()));
+    }
+    let nqubits = match inputs.first() {
+        Some(rho) => rho.nqubits,
+        None => return Err("Process tomography needs at least one input.".to_string())
+    };
+    if inputs.iter().chain(outputs).any(|rho| rho.nqubits != nqubits) {
+        return Err("Inputs and outputs must all have the same number of qubits.".to_string());
+    }
+    let d = 1 << nqubits;
+    let n = d * d;
+    let mut gram = vec![Complex::ZERO; n * n];
+    let mut cross = vec![Complex::ZERO; n * n];
+    for (input, output) in inputs.iter().zip(outputs) {
+        for (i, x) in input.data.data.iter().enumerate() {
+            for (j, y) in input.data.data.iter().enumerate() {
+                gram[i * n + j] += x * y.conj();
+            }
+        }
+        for (i, x) in output.data.data.iter().enumerate() {
+            for (j, y) in input.data.data.iter().enumerate() {
+                cross[i * n + j] += x * y.conj();
+            }
+        }
+    }
+    let (values, vectors) = linalg::eigh(&gram, n);
+    let largest = values.iter().fold(0., |m: f64, v| m.max(v.abs()));
+    if values[0] < tol.unitarity * largest.max(1.) {
+        return Err("Inputs do not span the operator space, the channel is not determined.".to_string());
+    }
+    let inverse = linalg::from_eigen(&values.iter().map(|v| Complex::new(1. / v, 0.)).collect::<Vec<_>>(), &vectors, n);
+    Ok(channels::reshuffle(&linalg::matmul(&cross, &inverse, n), d))
+}
+
+// Closest completely positive trace preserving Choi matrix in Frobenius norm, by Dykstra's
+// alternating projections (Knee et al., Phys. Rev. A 98, 062336 (2018)) onto the positive
+// matrices, clipping negative eigenvalues, and onto the affine space of trace preserving maps,
+// sum_a J[(a, c), (a, e)] = delta_ce.
+pub fn project_cptp(choi: &[Complex<f64>], nqubits: usize, options: ProjectionOptions) -> Result<Vec<Complex<f64>>, String> {
+    let d = 1 << nqubits;
+    let n = d * d;
+    if choi.len() != n * n {
+        return Err(format!("Choi matrix of {} qubits should have {} entries, got {}.", nqubits, n * n, choi.len()));
+    }
+    let hermitian = |m: &[Complex<f64>]| {
+        let m_dag = linalg::adjoint(m, n);
+        m.iter().zip(m_dag).map(|(x, y)| (x + y) / 2.).collect::<Vec<_>>()
+    };
+    let positive = |m: &[Complex<f64>]| {
+        let (values, vectors) = linalg::eigh(&hermitian(m), n);
+        linalg::from_eigen(&values.iter().map(|v| Complex::new(v.max(0.), 0.)).collect::<Vec<_>>(), &vectors, n)
+    };
+    let trace_preserving = |m: &[Complex<f64>]| {
+        let mut projected = m.to_vec();
+        for c in 0..d {
+            for e in 0..d {
+                let partial = (0..d).map(|a| m[(a * d + c) * n + a * d + e]).sum::<Complex<f64>>();
+                let defect = (partial - if c == e { Complex::ONE } else { Complex::ZERO }) / d as f64;
+                (0..d).for_each(|a| projected[(a * d + c) * n + a * d + e] -= defect);
+            }
+        }
+        projected
+    };
+    let mut x = hermitian(choi);
+    let mut p = vec![Complex::ZERO; n * n];
+    let mut q = vec![Complex::ZERO; n * n];
+    for _ in 0..options.max_iterations {
+        let y = trace_preserving(&x.iter().zip(&p).map(|(a, b)| a + b).collect::<Vec<_>>());
+        p = x.iter().zip(&p).zip(&y).map(|((a, b), c)| a + b - c).collect();
+        let next = positive(&y.iter().zip(&q).map(|(a, b)| a + b).collect::<Vec<_>>());
+        q = y.iter().zip(&q).zip(&next).map(|((a, b), c)| a + b - c).collect();
+        let moved = linalg::max_abs_diff(&next, &x);
+        x = next;
+        if moved < options.tolerance && linalg::max_abs_diff(&trace_preserving(&x), &x) < options.tolerance {
+            return Ok(x);
+        }
+    }
+    Err(format!("CPTP projection did not converge within {} iterations.", options.max_iterations))
+}
+
+// Channel from the tomography data, projected onto the CPTP maps when projection options are given.
+pub fn reconstruct_channel(inputs: &[DensityMatrix], outputs: &[DensityMatrix], projection: Option<ProjectionOptions>, tol: &TolerancePolicy) -> Result<Channel, String> {
+    let nqubits = inputs.first().map_or(0, |rho| rho.nqubits);
+    let mut choi = reconstruct_choi(inputs, outputs, tol)?;
+    if let Some(options) = projection {
+        choi = project_cptp(&choi, nqubits, options)?;
+    }
+    Channel::from_choi(&choi, nqubits, tol)
+}
+
+// Tomography of a process given as a function of the input state, e.g. a noisy pattern fragment.
+pub fn characterize<F>(nqubits: usize, mut process: F, projection: Option<ProjectionOptions>, tol: &TolerancePolicy) -> Result<Channel, String>
+where
+    F: FnMut(DensityMatrix) -> Result<DensityMatrix, String>
+{
+    let inputs = tomography_inputs(nqubits);
+    let outputs = inputs.iter().cloned().map(&mut process).collect::<Result<Vec<_>, _>>()?;
+    reconstruct_channel(&inputs, &outputs, projection, tol)
+}
+
+// State tomography: every qubit is measured in the X, Y and Z bases, in all 3^n combinations,
+// qubit 0 slowest, and the state is rebuilt from the outcome counts either by linear inversion,
+// rho = sum_P <P> P / d with <P> averaged over the settings measuring P, which may give small
+// negative eigenvalues, or by maximum likelihood with the R rho R iterations (Hradil, Phys. Rev. A
+// 55, R1561 (1997)), which stay positive.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomographySetting {
+    pub bases: Vec<Basis>,      // Basis of every qubit.
+    pub counts: Vec<usize>      // Counts of every outcome, qubit 0 being the most significant bit.
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateTomography {
+    pub nqubits: usize,
+    pub settings: Vec<TomographySetting>
+}
+
+fn pauli_of(basis: Basis) -> Pauli {
+    match basis {
+        Basis::X => Pauli::X,
+        Basis::Y => Pauli::Y,
+        Basis::Z => Pauli::Z
+    }
+}
+
+pub fn simulate_state_tomography(rho: &DensityMatrix, shots: usize, rng: &mut dyn RngCore) -> Result<StateTomography, String> {
+    if shots == 0 {
+        return Err("At least one shot is needed.".to_string());
+    }
+    let n = rho.nqubits;
+    let settings = (0..3usize.pow(n as u32)).map(|t| {
+        let bases = (0..n)
+            .map(|q| [Basis::X, Basis::Y, Basis::Z][(t / 3usize.pow((n - 1 - q) as u32)) % 3])
+            .collect::<Vec<_>>();
+        let mut counts = vec![0; 1 << n];
+        for (outcome, count) in randomized::measure_in_bases(rho, &bases, shots, rng)? {
+            counts[outcome as usize] += count;
+        }
+        Ok(TomographySetting { bases, counts })
+    }).collect::<Result<Vec<_>, String>>()?;
+    Ok(StateTomography { nqubits: n, settings })
+}
+
+impl StateTomography {
+    fn check(&self) -> Result<(), String> {
+        if self.settings.is_empty() {
+            return Err("State tomography needs at least one setting.".to_string());
+        }
+        let size = 1 << self.nqubits;
+        if self.settings.iter().any(|s| s.bases.len() != self.nqubits || s.counts.len() != size) {
+            return Err(format!("Every setting should have {} bases and {} counts.", self.nqubits, size));
+        }
+        if self.settings.iter().any(|s| s.counts.iter().sum::<usize>() == 0) {
+            return Err("Every setting needs at least one shot.".to_string());
+        }
+        Ok(())
+    }
+
+    // Projector onto the outcome of a setting, the tensor product of (I + (-1)^bit P) / 2.
+    fn projector(&self, setting: &TomographySetting, outcome: usize) -> Vec<Complex<f64>> {
+        (0..self.nqubits).fold(vec![Complex::ONE], |acc, q| {
+            let pauli = pauli_of(setting.bases[q]);
+            let sign = if (outcome >> (self.nqubits - 1 - q)) & 1 == 1 { -1. } else { 1. };
+            let single = (0..4).map(|idx| (Pauli::I.element(idx / 2, idx % 2) + pauli.element(idx / 2, idx % 2) * sign) / 2.).collect::<Vec<_>>();
+            let d = 1 << q;
+            let mut product = vec![Complex::ZERO; 4 * d * d];
+            for (i, a) in acc.iter().enumerate() {
+                for (j, b) in single.iter().enumerate() {
+                    let (row, col) = ((i / d) * 2 + j / 2, (i % d) * 2 + j % 2);
+                    product[row * 2 * d + col] = a * b;
+                }
+            }
+            product
+        })
+    }
+
+    fn density_matrix(&self, data: Vec<Complex<f64>>) -> DensityMatrix {
+        DensityMatrix { data: Tensor::from_vec(data, vec![2; 2 * self.nqubits]), size: 1 << self.nqubits, nqubits: self.nqubits }
+    }
+
+    pub fn linear_inversion(&self) -> Result<DensityMatrix, String> {
+        self.check()?;
+        let n = self.nqubits;
+        let d = 1 << n;
+        let mut rho = vec![Complex::ZERO; d * d];
+        for t in 0..1usize << (2 * n) {
+            let paulis = (0..n).map(|q| [Pauli::I, Pauli::X, Pauli::Y, Pauli::Z][(t >> (2 * (n - 1 - q))) & 3]).collect::<Vec<_>>();
+            let mask = paulis.iter().fold(0, |acc, p| (acc << 1) | usize::from(*p != Pauli::I));
+            let compatible = self.settings.iter()
+                .filter(|s| s.bases.iter().zip(&paulis).all(|(b, p)| *p == Pauli::I || pauli_of(*b) == *p))
+                .collect::<Vec<_>>();
+            if compatible.is_empty() {
+                continue;
+            }
+            let expectation = compatible.iter().map(|s| {
+                let shots = s.counts.iter().sum::<usize>() as f64;
+                s.counts.iter().enumerate()
+                    .map(|(outcome, &c)| if (outcome & mask).count_ones() % 2 == 0 { c as f64 } else { -(c as f64) })
+                    .sum::<f64>() / shots
+            }).sum::<f64>() / compatible.len() as f64;
+            let matrix = PauliString::new(paulis).matrix();
+            rho.iter_mut().zip(matrix).for_each(|(r, m)| *r += m * expectation / d as f64);
+        }
+        Ok(self.density_matrix(rho))
+    }
+
+    // Iterates rho <- R rho R / tr(R rho R) from the maximally mixed state, with
+    // R = sum f / p Pi over the outcome projectors Pi of frequency f and probability p.
+    pub fn maximum_likelihood(&self, tol: f64, max_iterations: usize) -> Result<DensityMatrix, String> {
+        self.check()?;
+        let d = 1 << self.nqubits;
+        let projectors = self.settings.iter()
+            .flat_map(|s| {
+                let shots = s.counts.iter().sum::<usize>() as f64;
+                s.counts.iter().enumerate()
+                    .filter(|(_, &c)| c > 0)
+                    .map(move |(outcome, &c)| (c as f64 / shots, self.projector(s, outcome)))
+            })
+            .collect::<Vec<_>>();
+        let mut rho = linalg::identity(d).iter().map(|x| x / d as f64).collect::<Vec<_>>();
+        for _ in 0..max_iterations {
+            let mut r = vec![Complex::ZERO; d * d];
+            for (frequency, projector) in &projectors {
+                let probability = projector.iter().zip(&rho).map(|(p, x)| (p.conj() * x).re).sum::<f64>();
+                r.iter_mut().zip(projector).for_each(|(a, p)| *a += p * (frequency / probability.max(f64::MIN_POSITIVE)));
+            }
+            let mut next = linalg::matmul(&r, &linalg::matmul(&rho, &r, d), d);
+            let trace = (0..d).map(|i| next[i * d + i].re).sum::<f64>();
+            next.iter_mut().for_each(|x| *x /= trace);
+            let moved = linalg::max_abs_diff(&next, &rho);
+            rho = next;
+            if moved < tol {
+                return Ok(self.density_matrix(rho));
+            }
+        }
+        Err(format!("Maximum likelihood reconstruction did not converge within {} iterations.", max_iterations))
+    }
+}
@@ -1,7 +1,13 @@
 use core::fmt;
 use num_complex::Complex;
+use num_traits::{Float, Zero};
 use std::collections::HashSet;
 
+use crate::tensor::Element;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 pub struct DisplayComplex(pub Complex<f64>);
 
 impl fmt::Display for DisplayComplex {    
@@ -38,8 +44,6 @@ pub fn bitwise_bin_vec_to_int(bin_vec: &[u8]) -> usize {
 }
 
 pub fn complex_approx_eq(a: Complex<f64>, b: Complex<f64>, tol: f64) -> bool {
-    let re_diff = (a.re - b.re).abs();
-    let im_diff = (a.im - b.im).abs();
     (a.re - b.re).abs() < tol && (a.im - b.im).abs() < tol
 }
 
@@ -51,4 +55,68 @@ pub fn are_elements_unique<T: Eq + std::hash::Hash>(slice: &[T]) -> bool {
         }
     }
     true
-}
\ No newline at end of file
+}
+// Flat offsets of every basis state of the target qubits inside a register of nqubits,
+// the first target qubit being the most significant bit of the local index.
+pub fn target_offsets(targets: &[usize], nqubits: usize) -> Vec<usize> {
+    let k = targets.len();
+    (0..1 << k).map(|local| {
+        targets.iter().enumerate()
+            .filter(|(b, _)| (local >> (k - 1 - b)) & 1 == 1)
+            .fold(0, |offset, (_, &t)| offset | (1 << (nqubits - 1 - t)))
+    }).collect()
+}
+
+// data <- op . data, where data is a row-major matrix whose row index spans nqubits
+// and op acts on the target qubits of that row index.
+// Generic over the precision so that single precision states share the same kernels.
+pub fn apply_left<F: Float + Element>(data: &mut [Complex<F>], row_len: usize, op: &[Complex<F>], targets: &[usize], nqubits: usize) {
+    let offsets = target_offsets(targets, nqubits);
+    let dim = offsets.len();
+    let mask = offsets[dim - 1];
+
+    let mut rows = data.chunks_mut(row_len).map(Some).collect::<Vec<_>>();
+    let mut groups = (0..rows.len())
+        .filter(|i| i & mask == 0)
+        .map(|base| offsets.iter().map(|o| rows[base + o].take().unwrap()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let apply_group = |group: &mut Vec<&mut [Complex<F>]>| {
+        let mut buffer = vec![Complex::zero(); dim];
+        for j in 0..row_len {
+            for (c, row) in group.iter().enumerate() {
+                buffer[c] = row[j];
+            }
+            for (a, row) in group.iter_mut().enumerate() {
+                row[j] = op[a * dim..(a + 1) * dim].iter().zip(buffer.iter()).map(|(o, b)| o * b).sum();
+            }
+        }
+    };
+    #[cfg(feature = "parallel")]
+    groups.par_iter_mut().for_each(apply_group);
+    #[cfg(not(feature = "parallel"))]
+    groups.iter_mut().for_each(apply_group);
+}
+
+// data <- data . op^dagger, where op acts on the target qubits of the column index.
+pub fn apply_right_adjoint<F: Float + Element>(data: &mut [Complex<F>], row_len: usize, op: &[Complex<F>], targets: &[usize], nqubits: usize) {
+    let offsets = target_offsets(targets, nqubits);
+    let dim = offsets.len();
+    let mask = offsets[dim - 1];
+
+    let apply_row = |row: &mut [Complex<F>]| {
+        let mut buffer = vec![Complex::zero(); dim];
+        for base in (0..row_len).filter(|i| i & mask == 0) {
+            for (c, o) in offsets.iter().enumerate() {
+                buffer[c] = row[base + o];
+            }
+            for (a, o) in offsets.iter().enumerate() {
+                row[base + o] = op[a * dim..(a + 1) * dim].iter().zip(buffer.iter()).map(|(m, b)| b * m.conj()).sum();
+            }
+        }
+    };
+    #[cfg(feature = "parallel")]
+    data.par_chunks_mut(row_len).for_each(apply_row);
+    #[cfg(not(feature = "parallel"))]
+    data.chunks_mut(row_len).for_each(apply_row);
+}
@@ -0,0 +1,120 @@
+use rand::{Rng, RngCore};
+
+use crate::backend::QuantumBackend;
+use crate::density_matrix::State;
+use crate::error::SimulatorError;
+use crate::noise::NoiseModel;
+use crate::operators::Operator;
+use crate::pattern::Pattern;
+use crate::pauli::PauliString;
+use crate::rng::RngConfig;
+use crate::statevector::StateVector;
+
+// Monte Carlo wavefunction simulation: a channel with Kraus operators K_k applies one of them,
+// chosen with probability ||K_k psi||^2, and renormalizes. Averaging over trajectories recovers
+// sum_k K_k rho K_k^dagger while only storing 2^n amplitudes instead of 4^n entries.
+
+// State vector backend applying noise channels as sampled jumps, drawn from its own generator.
+pub struct Trajectory {
+    pub state: StateVector,
+    rng: Box<dyn RngCore>
+}
+
+impl Trajectory {
+    pub fn new(state: StateVector, rng: Box<dyn RngCore>) -> Self {
+        Trajectory { state, rng }
+    }
+}
+
+impl QuantumBackend for Trajectory {
+    fn nqubits(&self) -> usize {
+        self.state.nqubits
+    }
+
+    fn evolve_single(&mut self, op: &Operator, index: usize) -> Result<(), SimulatorError> {
+        self.state.evolve_single(op, index)
+    }
+
+    fn evolve(&mut self, op: &Operator, indices: &[usize]) -> Result<(), SimulatorError> {
+        self.state.evolve(op, indices)
+    }
+
+    fn measure(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.state.measure(index, rng)
+    }
+
+    fn expectation(&self, pauli_string: &PauliString) -> Result<f64, SimulatorError> {
+        self.state.expectation(pauli_string)
+    }
+
+    fn apply_channel(&mut self, kraus: &[Operator], indices: &[usize]) -> Result<(), SimulatorError> {
+        if kraus.is_empty() {
+            return Err(SimulatorError::InvalidArgument("A channel needs at least one Kraus operator.".to_string()));
+        }
+        let norm = self.state.norm().powi(2);
+        let mut r = self.rng.gen::<f64>() * norm;
+        let mut last = None;
+        for k in kraus {
+            let mut branch = self.state.clone();
+            branch.evolve(k, indices)?;
+            let p = branch.norm().powi(2);
+            if p > 0. {
+                last = Some(branch);
+            }
+            r -= p;
+            if r < 0. && last.is_some() {
+                break;
+            }
+        }
+        // Rounding can leave r slightly positive, keeping the last branch with a nonzero weight.
+        self.state = last.ok_or(SimulatorError::InvalidArgument("Every Kraus operator annihilates the state.".to_string()))?;
+        self.state.normalize();
+        Ok(())
+    }
+
+    fn tensor(&mut self, other: &Self) {
+        self.state.tensor(&other.state)
+    }
+
+    fn add_qubit(&mut self, state: State) {
+        self.state.add_qubit(state)
+    }
+
+    fn measure_and_remove(&mut self, index: usize, rng: &mut dyn RngCore) -> Result<u8, SimulatorError> {
+        self.state.measure_and_remove(index, rng)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryEstimate {
+    pub means: Vec<f64>,            // Average of every observable over the trajectories.
+    pub standard_errors: Vec<f64>
+}
+
+impl Pattern {
+    // Expectation values of the observables on the noisy output, averaged over trajectories.
+    // Trajectory i draws its jumps from the generator (trajectory, i) of the config and its
+    // measurement outcomes from (trajectory outcomes, i).
+    pub fn simulate_trajectories(&self, input: &StateVector, noise: &NoiseModel, trajectories: usize, observables: &[PauliString], rng: &RngConfig) -> Result<TrajectoryEstimate, String> {
+        if trajectories == 0 {
+            return Err("At least one trajectory is needed.".to_string());
+        }
+        let mut values = vec![Vec::with_capacity(trajectories); observables.len()];
+        for i in 0..trajectories as u64 {
+            let trajectory = Trajectory::new(input.clone(), rng.rng("trajectory", i));
+            let result = self.simulate_with_noise(trajectory, noise, &mut *rng.rng("trajectory outcomes", i))?;
+            for (observable, values) in observables.iter().zip(values.iter_mut()) {
+                values.push(result.state.expectation(observable)?);
+            }
+        }
+        let n = trajectories as f64;
+        let means = values.iter().map(|v| v.iter().sum::<f64>() / n).collect::<Vec<_>>();
+        let standard_errors = values.iter().zip(&means).map(|(v, mean)| {
+            if trajectories < 2 {
+                return 0.;
+            }
+            (v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.) / n).sqrt()
+        }).collect();
+        Ok(TrajectoryEstimate { means, standard_errors })
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use crate::config::TolerancePolicy;
+use crate::density_matrix::DensityMatrix;
+use crate::metrics;
+use crate::npy;
+use crate::tensor::Tensor;
+
+// Validation of simulated evolutions against reference states exported from other simulators,
+// e.g. QuTiP with numpy.save(path, np.array([rho.full() for rho in result.states])).
+
+#[derive(Debug, Clone)]
+pub struct TrajectoryReport {
+    pub trace_distances: Vec<f64>,  // Trace distance at each step.
+    pub max_trace_distance: f64,
+    pub worst_step: usize,
+}
+
+impl TrajectoryReport {
+    pub fn agrees(&self, tol: f64) -> bool {
+        self.max_trace_distance <= tol
+    }
+
+    pub fn agrees_within(&self, tol: &TolerancePolicy) -> bool {
+        self.agrees(tol.equality)
+    }
+}
+
+// Load a single d x d density matrix or a trajectory of shape (steps, d, d).
+pub fn load_states<P: AsRef<Path>>(path: P) -> Result<Vec<DensityMatrix>, String> {
+    let (shape, data) = npy::read_npy(path)?;
+    let (steps, rows, cols) = match shape[..] {
+        [rows, cols] => (1, rows, cols),
+        [steps, rows, cols] => (steps, rows, cols),
+        _ => return Err(format!("Expected a matrix or a stack of matrices, got shape {:?}.", shape))
+    };
+    if rows != cols || !rows.is_power_of_two() {
+        return Err(format!("Density matrices should be 2^n x 2^n, got {}x{}.", rows, cols));
+    }
+    if steps == 0 {
+        return Err("The reference file holds no state.".to_string());
+    }
+    let nqubits = rows.ilog2() as usize;
+    Ok(data.chunks_exact(rows * cols).take(steps).map(|chunk| DensityMatrix {
+        data: Tensor::from_vec(chunk.to_vec(), vec![2; 2 * nqubits]),
+        size: rows,
+        nqubits
+    }).collect())
+}
+
+pub fn compare_trajectory(reference: &[DensityMatrix], simulated: &[DensityMatrix]) -> Result<TrajectoryReport, String> {
+    if reference.len() != simulated.len() {
+        return Err(format!("Reference has {} steps but the simulation has {}.", reference.len(), simulated.len()));
+    }
+    if reference.is_empty() {
+        return Err("Cannot compare empty trajectories.".to_string());
+    }
+    let trace_distances = reference.iter().zip(simulated.iter())
+        .map(|(r, s)| metrics::trace_distance(r, s))
+        .collect::<Result<Vec<f64>, String>>()?;
+    let (worst_step, max_trace_distance) = trace_distances.iter().copied().enumerate()
+        .fold((0, 0.), |best, (i, d)| if d > best.1 { (i, d) } else { best });
+    Ok(TrajectoryReport { trace_distances, max_trace_distance, worst_step })
+}
+
+// Compare against a reference file, running step(rho, k) to produce the state after step k + 1
+// from the first reference state.
+pub fn validate_against_file<P, F, E>(path: P, mut step: F) -> Result<TrajectoryReport, String>
+where
+    P: AsRef<Path>,
+    F: FnMut(&mut DensityMatrix, usize) -> Result<(), E>,
+    E: Into<String>,
+{
+    let reference = load_states(path)?;
+    let mut rho = reference[0].clone();
+    let mut simulated = vec![rho.clone()];
+    for k in 1..reference.len() {
+        step(&mut rho, k - 1).map_err(Into::into)?;
+        simulated.push(rho.clone());
+    }
+    compare_trajectory(&reference, &simulated)
+}
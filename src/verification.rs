@@ -0,0 +1,103 @@
+use rand::{Rng, RngCore};
+
+use crate::density_matrix::{DensityMatrix, State};
+use crate::graph::GraphState;
+use crate::noise::NoiseModel;
+use crate::pattern::{Command, Pattern};
+use crate::pauli::{Pauli, PauliString};
+
+// Stabilizer testing of graph states. Each test measures a uniformly random element S of the
+// stabilizer group with local Pauli measurements and accepts on the +1 outcome. Since
+// |G><G| = 2^-n sum_S S, a state rho passes with probability p = (1 + F) / 2 where
+// F = <G|rho|G>, and Hoeffding's inequality turns the acceptance rate of N tests into the bound
+// F >= 2 (p_hat - sqrt(ln(1 / delta) / 2N)) - 1, holding with confidence 1 - delta.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerificationOptions {
+    pub tests: usize,
+    pub confidence: f64     // Probability that the certified bound holds, in (0, 1).
+}
+
+impl Default for VerificationOptions {
+    fn default() -> Self {
+        VerificationOptions { tests: 1000, confidence: 0.95 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationReport {
+    pub tests: usize,
+    pub accepted: usize,
+    pub acceptance_rate: f64,
+    pub fidelity_estimate: f64,     // 2 p_hat - 1.
+    pub fidelity_bound: f64,        // Certified lower bound on the fidelity, at least 0.
+    pub exact_fidelity: f64         // <G|rho|G>, which the tests do not have access to.
+}
+
+impl GraphState {
+    // Stabilizer element prod_{i in generators} X_i prod_{j in N(i)} Z_j, with its sign.
+    pub fn stabilizer(&self, generators: &[usize]) -> Result<(f64, PauliString), String> {
+        let mut stabilizer = (1., PauliString::new(vec![Pauli::I; self.nqubits]));
+        for &i in generators {
+            if i >= self.nqubits {
+                return Err(format!("Target qubit {} is not in the range [0-{}].", i, self.nqubits));
+            }
+            let mut paulis = vec![Pauli::I; self.nqubits];
+            paulis[i] = Pauli::X;
+            self.neighbors(i).into_iter().for_each(|j| paulis[j] = Pauli::Z);
+            let (phase, product) = stabilizer.1.product(&PauliString::new(paulis))?;
+            // Generators commute, so the phase of the product stays real.
+            stabilizer = (stabilizer.0 * phase.re, product);
+        }
+        Ok(stabilizer)
+    }
+
+    // Graph state prepared by N and E commands with the noise of the model.
+    pub fn prepare_noisy(&self, noise: &NoiseModel, rng: &mut dyn RngCore) -> Result<DensityMatrix, String> {
+        let mut pattern = Pattern::new(vec![]);
+        (0..self.nqubits).for_each(|node| pattern.add(Command::N(node)));
+        self.edges.iter().for_each(|&edge| pattern.add(Command::E(edge)));
+        Ok(pattern.simulate_with_noise(DensityMatrix::new(0, State::ZERO), noise, rng)?.state)
+    }
+
+    pub fn fidelity(&self, rho: &DensityMatrix) -> Result<f64, String> {
+        if rho.nqubits != self.nqubits {
+            return Err(format!("Graph state has {} qubits but the state has {}.", self.nqubits, rho.nqubits));
+        }
+        let g = self.amplitudes();
+        let size = g.len();
+        Ok((0..size).flat_map(|i| (0..size).map(move |j| (i, j)))
+            .map(|(i, j)| (g[i].conj() * rho.data.data[i * size + j] * g[j]).re)
+            .sum())
+    }
+
+    // Run the stabilizer tests on copies of rho.
+    pub fn verify(&self, rho: &DensityMatrix, options: &VerificationOptions, rng: &mut dyn RngCore) -> Result<VerificationReport, String> {
+        if options.tests == 0 {
+            return Err("At least one test is needed.".to_string());
+        }
+        if !(options.confidence > 0. && options.confidence < 1.) {
+            return Err(format!("Confidence {} is not in (0, 1).", options.confidence));
+        }
+        let exact_fidelity = self.fidelity(rho)?;
+        let mut accepted = 0;
+        for _ in 0..options.tests {
+            let generators = (0..self.nqubits).filter(|_| rng.gen::<bool>()).collect::<Vec<_>>();
+            let (sign, stabilizer) = self.stabilizer(&generators)?;
+            let p_accept = (1. + sign * rho.expectation(&stabilizer)?) / 2.;
+            if rng.gen::<f64>() < p_accept {
+                accepted += 1;
+            }
+        }
+        let acceptance_rate = accepted as f64 / options.tests as f64;
+        let deviation = ((1. / (1. - options.confidence)).ln() / (2. * options.tests as f64)).sqrt();
+        Ok(VerificationReport {
+            tests: options.tests,
+            accepted,
+            acceptance_rate,
+            fidelity_estimate: 2. * acceptance_rate - 1.,
+            fidelity_bound: (2. * (acceptance_rate - deviation) - 1.).max(0.),
+            exact_fidelity
+        })
+    }
+}
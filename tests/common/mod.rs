@@ -0,0 +1,14 @@
+use num_complex::Complex;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use dm_simu_rs::statevector::StateVector;
+
+pub fn random_state(nqubits: usize, rng: &mut StdRng) -> StateVector {
+    let data = (0..1 << nqubits)
+        .map(|_| Complex::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5))
+        .collect();
+    let mut state = StateVector::from_vec(data).unwrap();
+    state.normalize();
+    state
+}
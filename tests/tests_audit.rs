@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests_audit {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::audit::AuditedDensityMatrix;
+    use dm_simu_rs::backend::QuantumBackend;
+    use dm_simu_rs::channels;
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+
+    #[test]
+    fn test_records_first_violation() {
+        let mut rho = AuditedDensityMatrix::new(DensityMatrix::new(2, State::ZERO), TolerancePolicy::DOUBLE);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        rho.apply_channel(&channels::depolarizing(0.1).unwrap(), &[1]).unwrap();
+        assert!(rho.check().is_ok());
+
+        // Kraus operators of a channel that does not preserve the trace.
+        let mut kraus = channels::bit_flip(0.2).unwrap();
+        kraus.push(Operator::one_qubit(OneQubitOp::Z));
+        rho.apply_channel(&kraus, &[0]).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        rho.apply_channel(&kraus, &[1]).unwrap();
+
+        assert_eq!(rho.steps, 5);
+        let violation = rho.violation.as_ref().unwrap();
+        assert_eq!(violation.step, 2);
+        assert_eq!(violation.operation, "apply_channel");
+        assert_eq!(violation.indices, vec![0]);
+        assert_eq!(violation.operators.len(), 3);
+        assert!(violation.message.contains("trace"));
+        assert!(violation.state_before.check_invariants(&TolerancePolicy::DOUBLE).is_ok());
+        assert!(rho.check().is_err());
+    }
+
+    #[test]
+    fn test_pattern_run_is_clean() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.ry(1, 0.3);
+        let input = AuditedDensityMatrix::new(DensityMatrix::new(2, State::ZERO), TolerancePolicy::DOUBLE);
+        let result = circuit.to_pattern().simulate(input, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert!(result.state.check().is_ok());
+        assert!(result.state.steps > 0);
+        assert_eq!(result.state.nqubits(), 2);
+    }
+}
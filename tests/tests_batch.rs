@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests_batch {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::batch::Batch;
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::noise::NoiseModel;
+    use dm_simu_rs::operators::{Operator, TwoQubitsOp};
+    use dm_simu_rs::rng::RngConfig;
+
+    #[test]
+    fn test_evolve_all_matches_members() {
+        let mut states = vec![DensityMatrix::new(3, State::ZERO), DensityMatrix::new(3, State::PLUS)];
+        states[0].evolve_single(&Operator::ry(0.4), 1).unwrap();
+        let mut batch = Batch::from_states(&states).unwrap();
+        assert_eq!(batch.len(), 2);
+        let cx = Operator::two_qubits(TwoQubitsOp::CX);
+        batch.evolve_all(&cx, &[1, 2]).unwrap();
+        batch.evolve_all(&Operator::rx(0.7), &[0]).unwrap();
+        for (member, state) in batch.to_states().iter().zip(states.iter_mut()) {
+            state.evolve(&cx, &[1, 2]).unwrap();
+            state.evolve_single(&Operator::rx(0.7), 0).unwrap();
+            assert!(member.approx_eq(state, &TolerancePolicy::DOUBLE));
+        }
+        assert!(batch.evolve_all(&cx, &[1, 1]).is_err());
+        assert!(batch.evolve_all(&cx, &[0]).is_err());
+        assert!(Batch::from_states(&[DensityMatrix::new(1, State::ZERO), DensityMatrix::new(2, State::ZERO)]).is_err());
+    }
+
+    #[test]
+    fn test_measure_all() {
+        let mut batch = Batch::new(200, 2, State::PLUS);
+        let outcomes = batch.measure_all(1, &mut StdRng::seed_from_u64(1)).unwrap();
+        let ones = outcomes.iter().filter(|&&o| o == 1).count();
+        assert!(ones > 60 && ones < 140);
+        for (member, outcome) in batch.to_states().iter().zip(&outcomes) {
+            let mut expected = DensityMatrix::new(1, State::PLUS);
+            expected.add_qubit(State::ZERO);
+            if *outcome == 1 {
+                expected.evolve_single(&Operator::rx(std::f64::consts::PI), 1).unwrap();
+            }
+            assert!(member.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+        assert!(batch.measure_all(2, &mut StdRng::seed_from_u64(1)).is_err());
+    }
+
+    #[test]
+    fn test_simulate_batch() {
+        let mut circuit = Circuit::new(1);
+        circuit.h(0);
+        circuit.rz(0, 0.5);
+        let pattern = circuit.to_pattern();
+        let batch = Batch::new(16, 1, State::ZERO);
+        let noise = NoiseModel::depolarizing(0.05).unwrap();
+        let config = RngConfig { seed: 2, ..RngConfig::default() };
+        let result = pattern.simulate_batch(&batch, &noise, &config).unwrap();
+        assert_eq!(result.states.len(), 16);
+        assert_eq!(result.outcomes.len(), 16);
+        // Same seed, same results, member i using the generator (batch, i).
+        let again = pattern.simulate_batch(&batch, &noise, &config).unwrap();
+        assert_eq!(again.states, result.states);
+        assert_eq!(again.outcomes, result.outcomes);
+        let single = pattern.simulate_with_noise(batch.get(5).unwrap(), &noise, &mut *config.rng("batch", 5)).unwrap();
+        assert_eq!(single.outcomes, result.outcomes[5]);
+
+        let noiseless = pattern.simulate_batch(&batch, &NoiseModel::default(), &config).unwrap();
+        let mut expected = DensityMatrix::new(1, State::ZERO);
+        circuit.run(&mut expected).unwrap();
+        assert!(noiseless.states.to_states().iter().all(|state| state.approx_eq(&expected, &TolerancePolicy::DOUBLE)));
+    }
+}
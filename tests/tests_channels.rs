@@ -0,0 +1,278 @@
+#[cfg(test)]
+mod tests_channels {
+    use num_complex::Complex;
+    use dm_simu_rs::channels;
+    use dm_simu_rs::channels::{Channel, CptpViolation};
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::linalg;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::noise::{CommandKind, NoiseModel};
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+
+    const TOLERANCE: f64 = 1e-12;
+
+    // Check sum_k K^dagger K = I.
+    fn assert_trace_preserving(kraus: &[Operator]) {
+        let size = 1 << kraus[0].nqubits;
+        let mut sum: Vec<Complex<f64>> = vec![Complex::ZERO; size * size];
+        for k in kraus {
+            let k_dag = k.transconj();
+            for i in 0..size {
+                for j in 0..size {
+                    for l in 0..size {
+                        sum[i * size + j] += k_dag.data.data[i * size + l] * k.data.data[l * size + j];
+                    }
+                }
+            }
+        }
+        for i in 0..size {
+            for j in 0..size {
+                let expected = if i == j { Complex::ONE } else { Complex::ZERO };
+                assert!((sum[i * size + j] - expected).norm() < TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_channels_are_trace_preserving() {
+        for p in [0., 0.1, 0.5, 1.] {
+            assert_trace_preserving(&channels::depolarizing(p).unwrap());
+            assert_trace_preserving(&channels::two_qubit_depolarizing(p).unwrap());
+            assert_trace_preserving(&channels::dephasing(p).unwrap());
+            assert_trace_preserving(&channels::amplitude_damping(p).unwrap());
+            assert_trace_preserving(&channels::generalized_amplitude_damping(p, 1. - p).unwrap());
+            assert_trace_preserving(&channels::phase_damping(p).unwrap());
+            assert_trace_preserving(&channels::bit_flip(p).unwrap());
+            assert_trace_preserving(&channels::phase_flip(p).unwrap());
+        }
+    }
+    #[test]
+    fn test_invalid_probability() {
+        assert!(channels::depolarizing(-0.1).is_err());
+        assert!(channels::amplitude_damping(1.5).is_err());
+        assert!(channels::generalized_amplitude_damping(0.5, 1.5).is_err());
+        assert!(channels::two_qubit_depolarizing(f64::NAN).is_err());
+    }
+    #[test]
+    fn test_full_depolarizing_gives_maximally_mixed() {
+        let mut rho = DensityMatrix::new(1, State::PLUS);
+        rho.apply_channel(&channels::depolarizing(1.).unwrap(), &[0]).unwrap();
+        let expected = [0.5, 0., 0., 0.5];
+        for (a, b) in rho.data.data.iter().zip(expected) {
+            assert!((a - Complex::new(b, 0.)).norm() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_two_qubit_depolarizing() {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        rho.apply_channel(&channels::two_qubit_depolarizing(0.4).unwrap(), &[0, 1]).unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let mut expected = if i == j { 0.4 / 4. } else { 0. };
+                if i == 0 && j == 0 {
+                    expected += 0.6;
+                }
+                assert!((rho.data.data[i * 4 + j] - Complex::new(expected, 0.)).norm() < TOLERANCE);
+            }
+        }
+    }
+    #[test]
+    fn test_amplitude_damping_excited_state() {
+        let mut rho = DensityMatrix::from_statevec(&[Complex::ZERO, Complex::ONE]).unwrap();
+        rho.apply_channel(&channels::amplitude_damping(0.3).unwrap(), &[0]).unwrap();
+        let expected = [0.3, 0., 0., 0.7];
+        for (a, b) in rho.data.data.iter().zip(expected) {
+            assert!((a - Complex::new(b, 0.)).norm() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_generalized_amplitude_damping_relaxes_to_thermal_state() {
+        let kraus = channels::generalized_amplitude_damping(0.4, 0.2).unwrap();
+        for initial in [State::ZERO, State::PLUS] {
+            let mut rho = DensityMatrix::new(1, initial);
+            for _ in 0..200 {
+                rho.apply_channel(&kraus, &[0]).unwrap();
+            }
+            let expected = [0.8, 0., 0., 0.2];
+            for (a, b) in rho.data.data.iter().zip(expected) {
+                assert!((a - Complex::new(b, 0.)).norm() < TOLERANCE);
+            }
+        }
+        // Without thermal population it reduces to amplitude damping.
+        let mut rho = DensityMatrix::from_statevec(&[Complex::ZERO, Complex::ONE]).unwrap();
+        rho.apply_channel(&channels::generalized_amplitude_damping(0.3, 0.).unwrap(), &[0]).unwrap();
+        assert!((rho.data.data[0] - Complex::new(0.3, 0.)).norm() < TOLERANCE);
+    }
+    #[test]
+    fn test_dephasing_shrinks_coherences() {
+        for (kraus, factor) in [
+            (channels::dephasing(0.2).unwrap(), 0.8),
+            (channels::phase_damping(0.36).unwrap(), 0.8),
+            (channels::phase_flip(0.1).unwrap(), 0.8),
+        ] {
+            let mut rho = DensityMatrix::new(1, State::PLUS);
+            rho.apply_channel(&kraus, &[0]).unwrap();
+            let expected = [0.5, 0.5 * factor, 0.5 * factor, 0.5];
+            for (a, b) in rho.data.data.iter().zip(expected) {
+                assert!((a - Complex::new(b, 0.)).norm() < TOLERANCE);
+            }
+        }
+    }
+    #[test]
+    fn test_bit_flip() {
+        let mut rho = DensityMatrix::new(1, State::ZERO);
+        rho.apply_channel(&channels::bit_flip(0.1).unwrap(), &[0]).unwrap();
+        let expected = [0.9, 0., 0., 0.1];
+        for (a, b) in rho.data.data.iter().zip(expected) {
+            assert!((a - Complex::new(b, 0.)).norm() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_properties_depolarizing() {
+        let channel = Channel::new(channels::depolarizing(0.2).unwrap()).unwrap();
+        let props = channel.properties(None, &TolerancePolicy::DOUBLE).unwrap();
+        assert_eq!(props.choi_rank, 4);
+        assert!(props.unital);
+        assert!(props.trace_preservation_defect < TOLERANCE);
+        // F = 1 - p / 2 for the depolarizing channel.
+        assert!((props.average_gate_fidelity - 0.9).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_properties_amplitude_damping() {
+        let props = Channel::new(channels::amplitude_damping(0.3).unwrap()).unwrap().properties(None, &TolerancePolicy::DOUBLE).unwrap();
+        assert_eq!(props.choi_rank, 2);
+        assert!(!props.unital);
+        assert!(props.trace_preservation_defect < TOLERANCE);
+    }
+    #[test]
+    fn test_properties_unitary_and_non_trace_preserving() {
+        let x = Operator::one_qubit(OneQubitOp::X);
+        let channel = Channel::new(vec![x.clone()]).unwrap();
+        let props = channel.properties(Some(&x), &TolerancePolicy::DOUBLE).unwrap();
+        assert_eq!(props.choi_rank, 1);
+        assert!((props.average_gate_fidelity - 1.).abs() < TOLERANCE);
+        assert!((channel.properties(None, &TolerancePolicy::DOUBLE).unwrap().average_gate_fidelity - 1. / 3.).abs() < TOLERANCE);
+
+        // Keeping only one of the amplitude damping Kraus operators loses probability.
+        let lossy = Channel::new(channels::amplitude_damping(0.5).unwrap()[..1].to_vec()).unwrap();
+        assert!((lossy.properties(None, &TolerancePolicy::DOUBLE).unwrap().trace_preservation_defect - 0.5).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_channel_invalid() {
+        assert!(Channel::new(vec![]).is_err());
+        let mixed = vec![Operator::one_qubit(OneQubitOp::I), Operator::two_qubits(TwoQubitsOp::CZ)];
+        assert!(Channel::new(mixed).is_err());
+        let channel = Channel::new(channels::bit_flip(0.1).unwrap()).unwrap();
+        assert!(channel.average_gate_fidelity(&Operator::two_qubits(TwoQubitsOp::CZ)).is_err());
+    }
+    #[test]
+    fn test_average_gate_fidelity_of_noisy_gate() {
+        // Hadamard followed by bit flip noise: F = (|Tr(H^dagger K_0)|^2 + 2) / 6 = 1 - 2p / 3.
+        let h = Operator::one_qubit(OneQubitOp::H);
+        let kraus = channels::bit_flip(0.3).unwrap().iter()
+            .map(|k| Operator::new(linalg::matmul(&k.data.data, &h.data.data, 2)).unwrap())
+            .collect();
+        let channel = Channel::new(kraus).unwrap();
+        assert!((channels::average_gate_fidelity(&channel, &h, &TolerancePolicy::DOUBLE).unwrap() - 0.8).abs() < TOLERANCE);
+        assert!(channels::average_gate_fidelity(&channel, &Operator::new(vec![Complex::ONE; 4]).unwrap(), &TolerancePolicy::DOUBLE).is_err());
+    }
+    #[test]
+    fn test_unitarity() {
+        let unitary = Channel::new(vec![Operator::two_qubits(TwoQubitsOp::CX)]).unwrap();
+        assert!((channels::unitarity(&unitary) - 1.).abs() < TOLERANCE);
+        // Depolarizing shrinks every Pauli by (1 - p).
+        let depolarizing = Channel::new(channels::depolarizing(0.2).unwrap()).unwrap();
+        assert!((channels::unitarity(&depolarizing) - 0.64).abs() < TOLERANCE);
+        // Dephasing keeps Z and shrinks X, Y by (1 - p).
+        let dephasing = Channel::new(channels::dephasing(0.5).unwrap()).unwrap();
+        assert!((channels::unitarity(&dephasing) - 0.5).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_representation_round_trips() {
+        let channel = Channel::new(channels::generalized_amplitude_damping(0.3, 0.2).unwrap()).unwrap();
+        let superoperator = channel.superoperator();
+        let ptm = channel.ptm();
+        let from_choi = Channel::from_choi(&channel.choi(), 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!(linalg::max_abs_diff(&from_choi.superoperator(), &superoperator) < 1e-10);
+        let from_superoperator = Channel::from_superoperator(&superoperator, 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!(linalg::max_abs_diff(&from_superoperator.choi(), &channel.choi()) < 1e-10);
+        let from_ptm = Channel::from_ptm(&ptm, 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!(from_ptm.ptm().iter().zip(ptm.iter()).all(|(a, b)| (a - b).abs() < 1e-10));
+        // The Choi rank is the minimal number of Kraus operators.
+        assert_eq!(Channel::from_choi(&Channel::new(channels::depolarizing(0.).unwrap()).unwrap().choi(), 1, &TolerancePolicy::DOUBLE).unwrap().kraus.len(), 1);
+        assert!(Channel::from_choi(&[Complex::ONE; 4], 1, &TolerancePolicy::DOUBLE).is_err());
+        let mut not_cp = channel.choi();
+        not_cp[0] = Complex::new(-1., 0.);
+        assert!(Channel::from_choi(&not_cp, 1, &TolerancePolicy::DOUBLE).is_err());
+    }
+
+    #[test]
+    fn test_superoperator_acts_on_vectorized_state() {
+        let channel = Channel::new(channels::amplitude_damping(0.4).unwrap()).unwrap();
+        let rho = [Complex::new(0.25, 0.), Complex::new(0.3, -0.1), Complex::new(0.3, 0.1), Complex::new(0.75, 0.)];
+        let superoperator = channel.superoperator();
+        let image = (0..4).map(|i| (0..4).map(|j| superoperator[i * 4 + j] * rho[j]).sum::<Complex<f64>>()).collect::<Vec<_>>();
+        let mut dm = DensityMatrix::from_statevec(&[Complex::ONE, Complex::ZERO]).unwrap();
+        dm.data.data = rho.to_vec();
+        dm.apply_channel(&channel.kraus, &[0]).unwrap();
+        assert!(linalg::max_abs_diff(&image, &dm.data.data) < TOLERANCE);
+    }
+
+    #[test]
+    fn test_ptm_of_depolarizing() {
+        let ptm = Channel::new(channels::depolarizing(0.2).unwrap()).unwrap().ptm();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = match (i, j) {
+                    (0, 0) => 1.,
+                    _ if i == j => 0.8,
+                    _ => 0.
+                };
+                assert!((ptm[i * 4 + j] - expected).abs() < TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compose_and_tensor() {
+        let flip = Channel::new(channels::bit_flip(0.1).unwrap()).unwrap();
+        // Two bit flips with probability p flip with probability 2p (1 - p).
+        let twice = flip.compose(&flip).unwrap();
+        let expected = Channel::new(channels::bit_flip(0.18).unwrap()).unwrap();
+        assert!(linalg::max_abs_diff(&twice.choi(), &expected.choi()) < TOLERANCE);
+        let dephasing = Channel::new(channels::dephasing(0.3).unwrap()).unwrap();
+        let product = flip.tensor(&dephasing);
+        assert_eq!(product.nqubits, 2);
+        assert_eq!(product.kraus.len(), 4);
+        // PTMs of product channels are Kronecker products.
+        let (a, b, r) = (flip.ptm(), dephasing.ptm(), product.ptm());
+        for i in 0..16 {
+            for j in 0..16 {
+                let expected = a[(i / 4) * 4 + j / 4] * b[(i % 4) * 4 + j % 4];
+                assert!((r[i * 16 + j] - expected).abs() < TOLERANCE);
+            }
+        }
+        assert!(flip.compose(&product).is_err());
+    }
+
+    #[test]
+    fn test_is_cptp() {
+        for kraus in [channels::depolarizing(0.3).unwrap(), channels::amplitude_damping(0.4).unwrap(), channels::two_qubit_depolarizing(0.1).unwrap()] {
+            assert_eq!(Channel::new(kraus).unwrap().is_cptp(TOLERANCE), Ok(()));
+        }
+        let mut kraus = channels::dephasing(0.2).unwrap();
+        kraus.iter_mut().for_each(|k| k.data.data.iter_mut().for_each(|c| *c *= 1.1));
+        let lossy = Channel::new(kraus).unwrap();
+        match lossy.is_cptp(TOLERANCE) {
+            Err(CptpViolation::NotTracePreserving { defect }) => assert!((defect - 0.21).abs() < TOLERANCE),
+            other => panic!("Unexpected result {:?}", other)
+        }
+        assert_eq!(lossy.is_cptp(0.3), Ok(()));
+
+        let mut model = NoiseModel::depolarizing(0.01).unwrap();
+        assert!(model.validate(TOLERANCE).is_ok());
+        model.node_channels.insert((CommandKind::M, 4), lossy);
+        let message = model.validate(TOLERANCE).unwrap_err();
+        assert!(message.starts_with("M channel of node 4: "), "{}", message);
+    }
+}
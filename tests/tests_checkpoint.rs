@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests_checkpoint {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::channels;
+    use dm_simu_rs::checkpoint::{self, CheckpointOptions, Precision};
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::metrics;
+    use dm_simu_rs::noise::{CommandKind, IdleNoise, NoiseModel, RelaxationTimes};
+    use dm_simu_rs::operators::Operator;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    fn noisy_state() -> DensityMatrix {
+        let mut rho = DensityMatrix::from_graph(&[(0, 1), (1, 2), (2, 3)], 4).unwrap();
+        rho.evolve_single(&Operator::rx(0.3), 2).unwrap();
+        rho.apply_channel(&channels::amplitude_damping(0.05).unwrap(), &[1]).unwrap();
+        rho
+    }
+
+    #[test]
+    fn test_lossless_roundtrip() {
+        let rho = noisy_state();
+        for compress in [false, true] {
+            let (bytes, report) = checkpoint::encode_checkpoint(&rho, CheckpointOptions { threshold: 0., compress, ..CheckpointOptions::default() }).unwrap();
+            assert_eq!(report.dropped_entries, 0);
+            assert_eq!(report.bytes, bytes.len());
+            assert!(checkpoint::decode_checkpoint(&bytes).unwrap().equals(noisy_state(), TOLERANCE));
+        }
+    }
+    #[test]
+    fn test_threshold_report() {
+        let rho = noisy_state();
+        let (bytes, report) = checkpoint::encode_checkpoint(&rho, CheckpointOptions { threshold: 0.06, compress: true, ..CheckpointOptions::default() }).unwrap();
+        assert!(report.dropped_entries > 0);
+        let restored = checkpoint::decode_checkpoint(&bytes).unwrap();
+        let error = metrics::hilbert_schmidt_distance(&rho, &restored).unwrap();
+        assert!((error - report.dropped_norm).abs() < TOLERANCE);
+        assert!((rho.trace().re - restored.trace().re).abs() <= report.trace_error + TOLERANCE);
+
+        let (_, lossless) = checkpoint::encode_checkpoint(&rho, CheckpointOptions::default()).unwrap();
+        assert!(report.bytes < lossless.bytes);
+    }
+    #[test]
+    fn test_sparse_state_is_small() {
+        // |0...0><0...0| on 10 qubits is a single entry out of 2^20.
+        let rho = DensityMatrix::new(10, State::ZERO);
+        let path = std::env::temp_dir().join("dm_simu_rs_checkpoint.dmck");
+        let report = checkpoint::save_checkpoint(&rho, &path, CheckpointOptions { threshold: 0., compress: false, ..CheckpointOptions::default() }).unwrap();
+        assert_eq!(report.stored_entries, 1);
+        assert!(report.bytes < 64);
+        let restored = checkpoint::load_checkpoint(&path).unwrap();
+        assert_eq!(restored.nqubits, 10);
+        assert!(restored.equals(DensityMatrix::new(10, State::ZERO), TOLERANCE));
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_invalid_checkpoint() {
+        assert!(checkpoint::decode_checkpoint(b"nope").is_err());
+        let (mut bytes, _) = checkpoint::encode_checkpoint(&noisy_state(), CheckpointOptions { threshold: 0., compress: false, ..CheckpointOptions::default() }).unwrap();
+        bytes.truncate(bytes.len() - 3);
+        assert!(checkpoint::decode_checkpoint(&bytes).is_err());
+        assert!(checkpoint::encode_checkpoint(&noisy_state(), CheckpointOptions { threshold: -1., compress: false, ..CheckpointOptions::default() }).is_err());
+    }
+    #[test]
+    fn test_header() {
+        let rho = noisy_state();
+        let single = CheckpointOptions { precision: Precision::Single, compress: false, ..CheckpointOptions::default() };
+        let (bytes, report) = checkpoint::encode_checkpoint(&rho, single).unwrap();
+        let (_, double) = checkpoint::encode_checkpoint(&rho, CheckpointOptions { compress: false, ..CheckpointOptions::default() }).unwrap();
+        assert_eq!(&bytes[4..8], &[2, 0, 4, if cfg!(target_endian = "big") { b'>' } else { b'<' }]);
+        assert!(report.bytes < double.bytes);
+        assert!(metrics::hilbert_schmidt_distance(&rho, &checkpoint::decode_checkpoint(&bytes).unwrap()).unwrap() < 1e-6);
+
+        // |1><1| written big-endian, and in the little-endian version 1 layout.
+        let mut big = b"DMCK\x02\x00\x08>".to_vec();
+        big.extend_from_slice(&1u32.to_be_bytes());
+        big.extend_from_slice(&1u64.to_be_bytes());
+        big.extend_from_slice(&3u64.to_be_bytes());
+        big.extend_from_slice(&1f64.to_be_bytes());
+        big.extend_from_slice(&0f64.to_be_bytes());
+        let mut one = DensityMatrix::new(1, State::ZERO);
+        one.evolve_single(&Operator::one_qubit(dm_simu_rs::operators::OneQubitOp::X), 0).unwrap();
+        assert!(checkpoint::decode_checkpoint(&big).unwrap().equals(one.clone(), TOLERANCE));
+        let mut v1 = b"DMCK\x01\x00".to_vec();
+        v1.extend_from_slice(&1u32.to_le_bytes());
+        v1.extend_from_slice(&1u64.to_le_bytes());
+        v1.extend_from_slice(&3u64.to_le_bytes());
+        v1.extend_from_slice(&1f64.to_le_bytes());
+        v1.extend_from_slice(&0f64.to_le_bytes());
+        assert!(checkpoint::decode_checkpoint(&v1).unwrap().equals(one, TOLERANCE));
+        big[6] = 5;
+        assert!(checkpoint::decode_checkpoint(&big).is_err());
+
+        // Headers claiming huge states are refused before allocating, in both versions.
+        for nqubits in [16u32, 30, 64, u32::MAX] {
+            let mut huge = b"DMCK\x01\x00".to_vec();
+            huge.extend_from_slice(&nqubits.to_le_bytes());
+            huge.extend_from_slice(&0u64.to_le_bytes());
+            assert!(checkpoint::decode_checkpoint(&huge).is_err());
+            huge.splice(4..6, *b"\x02\x00\x08<");
+            assert!(checkpoint::decode_checkpoint(&huge).is_err());
+        }
+
+        let path = std::env::temp_dir().join("dm_simu_rs_save.dmck");
+        rho.save(&path).unwrap();
+        assert!(DensityMatrix::load(&path).unwrap().equals(noisy_state(), TOLERANCE));
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_resume_run() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rx(1, 0.4);
+        circuit.rz(0, 1.3);
+        let pattern = circuit.to_pattern();
+        // Without noise the output does not depend on the outcomes drawn after resuming.
+        let noise = NoiseModel::default();
+        let input = DensityMatrix::new(2, State::PLUS);
+        let expected = pattern.simulate_with_noise(input.clone(), &noise, &mut StdRng::seed_from_u64(0)).unwrap().state;
+
+        // A run interrupted halfway, as if it crashed after saving.
+        let path = std::env::temp_dir().join("dm_simu_rs_run.dmck");
+        let mut cursor = dm_simu_rs::runner::ExecutionCursor::new(&pattern, input.clone()).unwrap();
+        cursor.set_noise(noise.clone());
+        cursor.run(&pattern.seq()[..pattern.seq().len() / 2], &mut StdRng::seed_from_u64(1)).unwrap();
+        checkpoint::save_run_checkpoint(&cursor, &path).unwrap();
+        let saved = checkpoint::load_run_checkpoint(&path).unwrap();
+        assert_eq!(saved.executed, pattern.seq().len() / 2);
+        assert_eq!(saved.nodes, cursor.nodes);
+        assert_eq!(saved.outcomes, cursor.outcomes);
+
+        let resumed = pattern.simulate_with_checkpoints(DensityMatrix::new(0, State::ZERO), &noise, &path, 3, &mut StdRng::seed_from_u64(2)).unwrap();
+        assert!(resumed.state.equals(expected.clone(), 1e-9));
+        assert!(!path.exists());
+        let fresh = pattern.simulate_with_checkpoints(input, &noise, &path, 1, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert!(fresh.state.equals(expected, 1e-9));
+        assert!(pattern.simulate_with_checkpoints(DensityMatrix::new(2, State::PLUS), &noise, &path, 0, &mut StdRng::seed_from_u64(3)).is_err());
+        assert!(checkpoint::load_run_checkpoint(std::env::temp_dir().join("dm_simu_rs_missing.dmck")).is_err());
+    }
+    #[test]
+    fn test_resume_keeps_history() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rx(1, 0.4);
+        circuit.rz(0, 1.3);
+        let pattern = circuit.to_pattern();
+        let noise = NoiseModel {
+            idle: IdleNoise {
+                durations: [(CommandKind::E, 2.), (CommandKind::M, 1.), (CommandKind::X, 0.5), (CommandKind::Z, 0.5)].into_iter().collect(),
+                default_times: Some(RelaxationTimes::new(30., 20.).unwrap()),
+                ..IdleNoise::default()
+            },
+            ..NoiseModel::default()
+        };
+        let input = DensityMatrix::new(2, State::PLUS);
+        let half = pattern.seq().len() / 2;
+        // The resumed run draws the same outcomes as the uninterrupted one, so the states agree
+        // only if the idle clock survives the checkpoint.
+        let expected = pattern.simulate_with_noise(input.clone(), &noise, &mut StdRng::seed_from_u64(4)).unwrap();
+        let path = std::env::temp_dir().join("dm_simu_rs_history.dmck");
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut cursor = dm_simu_rs::runner::ExecutionCursor::new(&pattern, input).unwrap();
+        cursor.set_noise(noise.clone());
+        cursor.run(&pattern.seq()[..half], &mut rng).unwrap();
+        checkpoint::save_run_checkpoint(&cursor, &path).unwrap();
+        let saved = checkpoint::load_run_checkpoint(&path).unwrap();
+        assert_eq!(saved.edges, cursor.edges());
+        assert!(saved.clock > 0.);
+        assert_eq!(saved.clock, cursor.idle_scheduler().unwrap().clock);
+        assert_eq!(saved.last_active, cursor.idle_scheduler().unwrap().last_active);
+
+        let resumed = pattern.simulate_with_checkpoints(DensityMatrix::new(0, State::ZERO), &noise, &path, 2, &mut rng).unwrap();
+        assert_eq!(resumed.outcomes, expected.outcomes);
+        assert!(resumed.state.equals(expected.state, 1e-12));
+    }
+}
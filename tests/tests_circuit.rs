@@ -0,0 +1,88 @@
+mod common;
+
+#[cfg(test)]
+mod tests_circuit {
+    use std::f64::consts::PI;
+
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::DensityMatrix;
+    use dm_simu_rs::metrics::trace_distance;
+
+    use crate::common::random_state;
+
+    const TOLERANCE: f64 = 1e-8;
+
+    // Run the circuit directly and through its pattern, for several sampled outcomes.
+    fn assert_pattern_matches(circuit: &Circuit) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let pattern = circuit.to_pattern();
+        for _ in 0..4 {
+            let input = random_state(circuit.width(), &mut rng);
+            let mut expected = input.clone();
+            circuit.run(&mut expected).unwrap();
+            let expected = expected.to_density_matrix();
+
+            let result = pattern.simulate(input.clone(), &mut rng).unwrap();
+            assert!(trace_distance(&result.state.to_density_matrix(), &expected).unwrap() < TOLERANCE);
+
+            let result = pattern.simulate(input.to_density_matrix(), &mut rng).unwrap();
+            assert!(trace_distance(&result.state, &expected).unwrap() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn test_one_qubit_gates() {
+        for gate in 0..9 {
+            let mut circuit = Circuit::new(1);
+            match gate {
+                0 => circuit.h(0),
+                1 => circuit.s(0),
+                2 => circuit.x(0),
+                3 => circuit.y(0),
+                4 => circuit.z(0),
+                5 => circuit.i(0),
+                6 => circuit.rx(0, 0.3 * PI),
+                7 => circuit.ry(0, 0.7),
+                _ => circuit.rz(0, -1.1),
+            }
+            assert_pattern_matches(&circuit);
+        }
+    }
+
+    #[test]
+    fn test_two_qubit_gates() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rzz(1, 0, 0.4);
+        circuit.swap(0, 1);
+        circuit.ry(1, 1.3);
+        assert_pattern_matches(&circuit);
+    }
+
+    #[test]
+    fn test_ccx() {
+        let mut circuit = Circuit::new(3);
+        circuit.ccx(2, 0, 1);
+        assert_pattern_matches(&circuit);
+    }
+
+    #[test]
+    fn test_pattern_nodes() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(1);
+        circuit.swap(0, 1);
+        let pattern = circuit.to_pattern();
+        assert_eq!(pattern.input_nodes(), &[0, 1]);
+        assert_eq!(pattern.output_nodes(), &[2, 0]);
+        assert_eq!(pattern.n_nodes(), 3);
+
+        let result = pattern.simulate(DensityMatrix::from_statevec(&[Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO]).unwrap(), &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(result.outcomes.len(), 1);
+        assert!(result.outcomes.contains_key(&1));
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(all(test, feature = "cli"))]
+mod tests_cli {
+    use std::process::Command as Process;
+
+    use dm_simu_rs::density_matrix::DensityMatrix;
+    use dm_simu_rs::pauli::PauliString;
+
+    fn run(args: &[&str]) -> (bool, String) {
+        let output = Process::new(env!("CARGO_BIN_EXE_mbqc-sim")).args(args).output().unwrap();
+        (output.status.success(), String::from_utf8(output.stdout).unwrap())
+    }
+
+    fn histogram(stdout: &str) -> Vec<(String, usize)> {
+        stdout.lines().map(|line| {
+            let (bits, count) = line.split_once(' ').unwrap();
+            (bits.to_string(), count.parse().unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn test_bell_circuit() {
+        let dir = std::env::temp_dir();
+        let circuit = dir.join("dm_simu_rs_cli_bell.qasm");
+        std::fs::write(&circuit, "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\nh q[0];\ncx q[0], q[1];\n").unwrap();
+        let circuit = circuit.to_str().unwrap();
+
+        let (success, stdout) = run(&[circuit, "--shots", "200", "--seed", "7"]);
+        assert!(success);
+        let counts = histogram(&stdout);
+        assert!(counts.iter().all(|(bits, _)| bits == "00" || bits == "11"));
+        assert_eq!(counts.iter().map(|(_, count)| count).sum::<usize>(), 200);
+        assert_eq!(run(&[circuit, "--shots", "200", "--seed", "7"]).1, stdout);
+
+        // Dephased preparations degrade the saved average state.
+        let noise = dir.join("dm_simu_rs_cli_noise.toml");
+        std::fs::write(&noise, "[N]\nchannel = \"dephasing\"\np = 0.5\n").unwrap();
+        let state = dir.join("dm_simu_rs_cli_state.dmck");
+        let (success, stdout) = run(&[circuit, "--noise", noise.to_str().unwrap(), "--shots", "20", "--seed", "1", "--save", state.to_str().unwrap()]);
+        assert!(success);
+        assert_eq!(histogram(&stdout).iter().map(|(_, count)| count).sum::<usize>(), 20);
+        let rho = DensityMatrix::load(&state).unwrap();
+        assert!((rho.trace().re - 1.).abs() < 1e-9);
+        assert!(rho.expectation(&"XX".parse::<PauliString>().unwrap()).unwrap() < 0.99);
+        std::fs::remove_file(&state).unwrap();
+
+        assert!(!run(&[circuit, "--shots"]).0);
+        assert!(!run(&[]).0);
+    }
+
+    #[test]
+    fn test_readout_error() {
+        let dir = std::env::temp_dir();
+        let circuit = dir.join("dm_simu_rs_cli_readout.qasm");
+        std::fs::write(&circuit, "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[1];\n").unwrap();
+        let circuit = circuit.to_str().unwrap();
+        assert_eq!(histogram(&run(&[circuit, "--shots", "50", "--seed", "3"]).1), vec![("0".to_string(), 50)]);
+
+        // The output qubit is never measured by the pattern, so only its readout is flipped.
+        let noise = dir.join("dm_simu_rs_cli_readout.toml");
+        std::fs::write(&noise, "readout_error = 1\n").unwrap();
+        let (success, stdout) = run(&[circuit, "--noise", noise.to_str().unwrap(), "--shots", "50", "--seed", "3"]);
+        assert!(success);
+        assert_eq!(histogram(&stdout), vec![("1".to_string(), 50)]);
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests_css {
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::css::{foliate, measure_parities, parity_expectations, CssState, FoliatedCode};
+    use dm_simu_rs::density_matrix::{Basis, State};
+    use dm_simu_rs::graph::GraphState;
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::pattern::{Command, Pattern};
+    use dm_simu_rs::pauli::{Pauli, PauliString};
+    use dm_simu_rs::stabilizer::Stabilizer;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    fn square() -> CssState {
+        let graph = GraphState::new(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]).unwrap();
+        CssState::from_graph(graph).unwrap()
+    }
+
+    #[test]
+    fn test_checks() {
+        let css = square();
+        assert_eq!(css.colors, vec![0, 1, 0, 1]);
+        assert_eq!(css.x_checks, vec![vec![0, 1, 3], vec![1, 2, 3]]);
+        assert_eq!(css.z_checks, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+        assert_eq!(css.checks(Basis::X).unwrap()[0].to_string(), "XXIX");
+        assert!(css.checks(Basis::Y).is_err());
+    }
+
+    #[test]
+    fn test_odd_cycle() {
+        let graph = GraphState::new(3, &[(0, 1), (1, 2), (2, 0)]).unwrap();
+        assert!(graph.two_coloring().is_none());
+        assert!(CssState::from_graph(graph).is_err());
+    }
+
+    #[test]
+    fn test_checks_stabilize_state() {
+        let css = square();
+        let rho = css.to_density_matrix();
+        for (checks, basis) in [(&css.x_checks, Basis::X), (&css.z_checks, Basis::Z)] {
+            for value in parity_expectations(&rho, checks, basis).unwrap() {
+                assert!((value - 1.).abs() < TOLERANCE);
+            }
+            let mut rng = StdRng::seed_from_u64(7);
+            let parities = measure_parities(&rho, checks, basis, 50, &mut rng).unwrap();
+            assert_eq!(parities.len(), 50);
+            assert!(parities.iter().all(|shot| shot.iter().all(|&p| p == 0)));
+        }
+    }
+
+    #[test]
+    fn test_error_flips_parities() {
+        let css = square();
+        let mut rho = css.to_density_matrix();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 1).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let parities = measure_parities(&rho, &css.x_checks, Basis::X, 20, &mut rng).unwrap();
+        assert!(parities.iter().all(|shot| shot == &vec![1, 1]));
+        let parities = measure_parities(&rho, &css.z_checks, Basis::Z, 20, &mut rng).unwrap();
+        assert!(parities.iter().all(|shot| shot == &vec![0, 0]));
+        assert!(measure_parities(&rho, &[vec![4]], Basis::Z, 1, &mut rng).is_err());
+    }
+
+    fn steane() -> FoliatedCode {
+        let h = vec![
+            vec![0, 0, 0, 1, 1, 1, 1],
+            vec![0, 1, 1, 0, 0, 1, 1],
+            vec![1, 0, 1, 0, 1, 0, 1]
+        ];
+        foliate(&h, &h, 5).unwrap()
+    }
+
+    fn fired(code: &FoliatedCode, pattern: &Pattern, seed: u64) -> Vec<usize> {
+        let result = pattern.simulate(Stabilizer::new(0, State::PLUS), &mut StdRng::seed_from_u64(seed)).unwrap();
+        (0..code.detectors.len())
+            .filter(|&d| code.detectors[d].iter().fold(0, |parity, node| parity ^ result.outcomes[node]) == 1)
+            .collect()
+    }
+
+    #[test]
+    fn test_foliation_layout() {
+        let code = steane();
+        assert_eq!(code.layers.len(), 5);
+        assert_eq!(code.checks.iter().map(Vec::len).collect::<Vec<_>>(), vec![3; 5]);
+        assert_eq!(code.detectors.len(), 12);
+        assert_eq!(code.pattern.output_nodes(), code.layers[4].as_slice());
+        assert!(code.pattern.is_clifford(&TolerancePolicy::DOUBLE));
+        assert!(foliate(&[vec![1, 1]], &[vec![0, 1]], 2).is_err());
+        assert!(foliate(&[vec![1, 2]], &[], 2).is_err());
+    }
+
+    #[test]
+    fn test_foliated_detectors() {
+        let code = steane();
+        for seed in 0..10 {
+            assert!(fired(&code, &code.pattern, seed).is_empty());
+        }
+        // A Z error on a code qubit of layer 1 flips its X outcome, seen by the Z checks of layer 2.
+        let node = code.layers[1][6];
+        let mut pattern = Pattern::new(Vec::new());
+        for command in code.pattern.seq() {
+            if matches!(command, Command::M(n, ..) if *n == node) {
+                pattern.add(Command::C(node, 3));
+            }
+            pattern.add(command.clone());
+        }
+        assert_eq!(fired(&code, &pattern, 0), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_foliated_output() {
+        let code = foliate(&[vec![1, 1, 1, 1]], &[vec![1, 1, 1, 1]], 3).unwrap();
+        let result = code.pattern.simulate(Stabilizer::new(0, State::PLUS), &mut StdRng::seed_from_u64(5)).unwrap();
+        let x = PauliString::new(vec![Pauli::X; 4]);
+        let z = PauliString::new(vec![Pauli::Z; 4]);
+        assert!((result.state.expectation(&x).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((result.state.expectation(&z).unwrap().abs() - 1.).abs() < TOLERANCE);
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests_dag {
+    use dm_simu_rs::circuit::{Circuit, Instruction};
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::dag::commute;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::metrics::trace_distance;
+
+    const TOLERANCE: f64 = 1e-10;
+
+    #[test]
+    fn test_commute() {
+        assert!(commute(&Instruction::CNOT(0, 1), &Instruction::CNOT(0, 2), &TolerancePolicy::DOUBLE));
+        assert!(commute(&Instruction::RZ(0, 0.3), &Instruction::CNOT(0, 1), &TolerancePolicy::DOUBLE));
+        assert!(commute(&Instruction::X(1), &Instruction::CNOT(0, 1), &TolerancePolicy::DOUBLE));
+        assert!(commute(&Instruction::RZZ(0, 1, 0.2), &Instruction::Z(1), &TolerancePolicy::DOUBLE));
+        assert!(!commute(&Instruction::H(0), &Instruction::CNOT(0, 1), &TolerancePolicy::DOUBLE));
+        assert!(!commute(&Instruction::CNOT(0, 1), &Instruction::CNOT(1, 0), &TolerancePolicy::DOUBLE));
+        assert!(commute(&Instruction::H(2), &Instruction::CNOT(0, 1), &TolerancePolicy::DOUBLE));
+    }
+    #[test]
+    fn test_dag_edges_and_layers() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);          // 0
+        circuit.cnot(0, 1);    // 1
+        circuit.rz(0, 0.5);    // 2 commutes with 1
+        circuit.cnot(0, 2);    // 3 commutes with 1 and 2
+        circuit.h(0);          // 4
+        let dag = circuit.dag(&TolerancePolicy::DOUBLE);
+        assert_eq!(dag.len(), 5);
+        assert_eq!(dag.edges(), vec![(0, 1), (0, 2), (0, 3), (1, 4), (2, 4), (3, 4)]);
+        assert_eq!(dag.layers(), vec![vec![0], vec![1, 2, 3], vec![4]]);
+        assert_eq!(dag.depth(), 3);
+    }
+    #[test]
+    fn test_dag_transitive_edges_are_skipped() {
+        let mut circuit = Circuit::new(1);
+        circuit.h(0);
+        circuit.s(0);
+        circuit.h(0);
+        let dag = circuit.dag(&TolerancePolicy::DOUBLE);
+        assert_eq!(dag.edges(), vec![(0, 1), (1, 2)]);
+        assert_eq!(dag.depth(), 3);
+    }
+    #[test]
+    fn test_topological_order_preserves_the_circuit() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.h(2);
+        circuit.cnot(0, 1);
+        circuit.rz(1, 0.7);
+        circuit.cnot(2, 1);
+        circuit.rzz(0, 2, 0.4);
+        circuit.x(1);
+        circuit.ccx(0, 2, 1);
+        circuit.ry(0, 1.1);
+        let dag = circuit.dag(&TolerancePolicy::DOUBLE);
+
+        let mut expected = DensityMatrix::new(3, State::ZERO);
+        circuit.run(&mut expected).unwrap();
+        // Run each layer backwards, which is another valid order of the gates.
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        for layer in dag.layers() {
+            for &g in layer.iter().rev() {
+                rho.evolve(&dag.nodes[g].operator(), &dag.nodes[g].qubits()).unwrap();
+            }
+        }
+        assert!(trace_distance(&rho, &expected).unwrap() < TOLERANCE);
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests_demos {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::demos;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::graph::GraphState;
+    use dm_simu_rs::metrics::fidelity;
+    use dm_simu_rs::operators::Operator;
+    use dm_simu_rs::pauli::PauliString;
+
+    const TOLERANCE: f64 = 1e-8;
+
+    #[test]
+    fn test_teleportation() {
+        let mut expected = DensityMatrix::new(1, State::ZERO);
+        expected.evolve_single(&Operator::ry(1.2), 0).unwrap();
+        for seed in 0..4 {
+            let result = demos::teleportation(1.2, &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert_eq!(result.outcomes.len(), 2);
+            assert!(result.state.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+    }
+
+    #[test]
+    fn test_cz_via_cluster() {
+        let expected = GraphState::new(2, &[(0, 1)]).unwrap().to_density_matrix();
+        for seed in 0..4 {
+            let result = demos::cz_via_cluster(&mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+    }
+
+    #[test]
+    fn test_noisy_ghz() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let ideal = demos::noisy_ghz(3, 0., &mut rng).unwrap().state;
+        let zz = "ZZI".parse::<PauliString>().unwrap();
+        let xxx = "XXX".parse::<PauliString>().unwrap();
+        assert!((ideal.expectation(&zz).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((ideal.expectation(&xxx).unwrap() - 1.).abs() < TOLERANCE);
+
+        let noisy = demos::noisy_ghz(3, 0.1, &mut rng).unwrap().state;
+        assert!((noisy.expectation(&zz).unwrap() - 0.9 * 0.9).abs() < TOLERANCE);
+        assert!(fidelity(&noisy, &ideal, &TolerancePolicy::DOUBLE).unwrap() < 1. - 1e-3);
+        assert!(demos::noisy_ghz(0, 0.1, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_qaoa() {
+        let edges = [(0, 1), (1, 2), (0, 2)];
+        let mut rng = StdRng::seed_from_u64(11);
+        // Without rotations the state stays uniform, cutting half of the edges on average.
+        let uniform = demos::qaoa(3, &edges, 0., 0., &mut rng).unwrap().state;
+        assert!((demos::maxcut_expectation(&uniform, &edges).unwrap() - 1.5).abs() < TOLERANCE);
+
+        let optimized = demos::qaoa(3, &edges, 0.6, 0.4, &mut rng).unwrap().state;
+        assert!(demos::maxcut_expectation(&optimized, &edges).unwrap() > 1.5);
+        assert!(demos::qaoa(3, &[(0, 3)], 0.6, 0.4, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_run_demo() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for name in demos::DEMOS {
+            let result = demos::run_demo(name, &mut rng).unwrap();
+            assert!(result.state.validate(&TolerancePolicy::DOUBLE).is_ok());
+        }
+        assert!(demos::run_demo("grover", &mut rng).is_err());
+    }
+}
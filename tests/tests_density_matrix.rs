@@ -1,10 +1,17 @@
 #[cfg(test)]
 mod tests_dm { 
+    use std::collections::BTreeSet;
+
     use num_complex::Complex;
-    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use dm_simu_rs::config::{BufferAllocator, FirstTouchAllocator, SimulationConfig, TolerancePolicy};
+    use dm_simu_rs::density_matrix::{Basis, DensityMatrix, State};
+    use dm_simu_rs::density_matrix_f32::DensityMatrixF32;
+    use dm_simu_rs::error::{Context, SimulatorError};
+    use dm_simu_rs::noise::{Confusion, ReadoutError};
     use dm_simu_rs::operators::{Operator, OneQubitOp, TwoQubitsOp};
     use dm_simu_rs::tensor::Tensor;
-    use num_traits::pow;
 
     const TOLERANCE: f64 = 1e-15;
 
@@ -86,7 +93,7 @@ mod tests_dm {
     fn test_one_qubit_evolve_single_i() {
 
         let mut rho = DensityMatrix::new(1, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::I), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::I), 0).unwrap();
 
         let expected_data = &[Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.)];
         assert_eq!(rho.data.data, expected_data);
@@ -94,47 +101,47 @@ mod tests_dm {
     #[test]
     fn test_one_qubit_evolve_single_h() {
         let mut rho = DensityMatrix::new(1, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![Complex::new(0.5, 0.), Complex::new(0.5, 0.), Complex::new(0.5, 0.), Complex::new(0.5, 0.)],
             vec![2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_one_qubit_evolve_single_x() {
         let mut rho = DensityMatrix::new(1, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(1., 0.)],
             vec![2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_one_qubit_evolve_single_y() {
         let mut rho = DensityMatrix::new(1, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Y), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Y), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(1., 0.)],
             vec![2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_one_qubit_evolve_single_z() {
         let mut rho = DensityMatrix::new(1, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.)],
             vec![2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_two_qubits_evolve_single_i() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::I), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::I), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![
                 Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -144,12 +151,12 @@ mod tests_dm {
             ],
             vec![2, 2, 2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_two_qubits_evolve_single_h() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![
                 Complex::new(0.5, 0.), Complex::new(0., 0.), Complex::new(0.5, 0.), Complex::new(0., 0.),
@@ -159,12 +166,12 @@ mod tests_dm {
             ],
             vec![2, 2, 2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_two_qubits_evolve_single_x() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![
                 Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -174,12 +181,12 @@ mod tests_dm {
             ],
             vec![2, 2, 2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_two_qubits_evolve_single_y() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Y), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Y), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![
                 Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -189,12 +196,12 @@ mod tests_dm {
             ],
             vec![2, 2, 2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_two_qubits_evolve_single_z() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0).unwrap();
         let expected_data = Tensor::from_vec(
             vec![
                 Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -204,12 +211,12 @@ mod tests_dm {
             ],
             vec![2, 2, 2, 2]
         );
-        assert_eq!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE), true);
+        assert!(rho.equals(DensityMatrix { data: expected_data, size: 2, nqubits: 1 }, TOLERANCE));
     }
     #[test]
     fn test_evolve_cx_ket00_1() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -221,7 +228,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_cx_ket00_2() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[1, 0]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[1, 0]).unwrap();
         let expected_data = vec![
             Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -233,7 +240,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_cx_ket01() {
         let mut rho = DensityMatrix::from_statevec(&[Complex::new(0., 0.), Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.)]).unwrap();
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[1, 0]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[1, 0]).unwrap();
         let expected_data = vec![
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -245,7 +252,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_cx_ket10() {
         let mut rho = DensityMatrix::from_statevec(&[Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(1., 0.), Complex::new(0., 0.)]).unwrap();
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -257,7 +264,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_cx_ket11() {
         let mut rho = DensityMatrix::from_statevec(&[Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(1., 0.)]).unwrap();
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -269,7 +276,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_cz_ket00() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -282,7 +289,7 @@ mod tests_dm {
     #[test]
     fn test_evolve_swap_ket00() {
         let mut rho = DensityMatrix::new(2, State::ZERO);
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::new(1., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
             Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.), Complex::new(0., 0.),
@@ -299,7 +306,7 @@ mod tests_dm {
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[2, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[2, 1]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -319,7 +326,7 @@ mod tests_dm {
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[1, 2]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[1, 2]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -339,7 +346,7 @@ mod tests_dm {
             Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[2, 0]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[2, 0]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -359,7 +366,7 @@ mod tests_dm {
             Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 2]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 2]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -379,7 +386,7 @@ mod tests_dm {
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ONE
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 2]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 2]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -399,7 +406,7 @@ mod tests_dm {
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ONE
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 1]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[0, 1]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -419,7 +426,7 @@ mod tests_dm {
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ONE
         ]).unwrap();
 
-        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[1, 2]);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::SWAP), &[1, 2]).unwrap();
         let expected_data = vec![
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
             Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO,
@@ -459,4 +466,524 @@ mod tests_dm {
         let mut rho = DensityMatrix::new(3, State::ZERO);
         rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 0]).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_apply_channel_bit_flip() {
+        let p: f64 = 0.25;
+        let mut rho = DensityMatrix::new(1, State::ZERO);
+        let mut k0 = Operator::one_qubit(OneQubitOp::I);
+        k0.data.data.iter_mut().for_each(|c| *c *= (1. - p).sqrt());
+        let mut k1 = Operator::one_qubit(OneQubitOp::X);
+        k1.data.data.iter_mut().for_each(|c| *c *= p.sqrt());
+        rho.apply_channel(&[k0, k1], &[0]).unwrap();
+        let expected_data = &[
+            Complex::new(0.75, 0.), Complex::new(0., 0.),
+            Complex::new(0., 0.), Complex::new(0.25, 0.)
+        ];
+        assert!(rho.data.data.iter().zip(expected_data.iter()).all(|(a, b)| (a - b).norm() < 1e-12));
+    }
+    #[test]
+    fn test_apply_channel_unitary_matches_evolve() {
+        let mut rho = DensityMatrix::new(3, State::PLUS);
+        let mut expected = DensityMatrix::new(3, State::PLUS);
+        rho.apply_channel(&[Operator::two_qubits(TwoQubitsOp::CX)], &[2, 0]).unwrap();
+        expected.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[2, 0]).unwrap();
+        assert!(rho.equals(expected, TOLERANCE));
+    }
+    #[test]
+    fn test_apply_channel_two_qubit_dephasing() {
+        let mut rho = DensityMatrix::new(2, State::PLUS);
+        let mut k0 = Operator::one_qubit(OneQubitOp::I);
+        k0.data.data.iter_mut().for_each(|c| *c *= 0.5_f64.sqrt());
+        let mut k1 = Operator::one_qubit(OneQubitOp::Z);
+        k1.data.data.iter_mut().for_each(|c| *c *= 0.5_f64.sqrt());
+        rho.apply_channel(&[k0, k1], &[1]).unwrap();
+        // Full dephasing of qubit 1 kills every coherence between its |0> and |1>.
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if (i & 1) == (j & 1) { 0.25 } else { 0. };
+                assert!((rho.data.data[i * 4 + j] - Complex::new(expected, 0.)).norm() < 1e-12);
+            }
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_apply_channel_wrong_arity() {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        rho.apply_channel(&[Operator::two_qubits(TwoQubitsOp::CZ)], &[0]).unwrap();
+    }
+    #[test]
+    #[should_panic]
+    fn test_apply_channel_empty() {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        rho.apply_channel(&[], &[0]).unwrap();
+    }
+    #[test]
+    fn test_evolve_non_adjacent_control_target() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 2).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[2, 0]).unwrap();
+        // |000> -> |001> -> |101>
+        for (i, value) in rho.data.data.iter().enumerate() {
+            let expected = if i == 5 * 8 + 5 { Complex::ONE } else { Complex::ZERO };
+            assert_eq!(*value, expected);
+        }
+    }
+    #[test]
+    fn test_evolve_preserves_trace_and_hermiticity() {
+        let mut rho = DensityMatrix::new(4, State::PLUS);
+        rho.evolve_single(&Operator::rx(0.3), 1).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[3, 1]).unwrap();
+        rho.evolve_single(&Operator::ry(1.1), 3).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 2]).unwrap();
+        assert!((rho.trace() - Complex::ONE).norm() < 1e-12);
+        for i in 0..16 {
+            for j in 0..16 {
+                assert!((rho.data.data[i * 16 + j] - rho.data.data[j * 16 + i].conj()).norm() < 1e-12);
+            }
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_evolve_wrong_operator_size() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1, 2]).unwrap();
+    }
+    #[test]
+    fn test_tensor_product_of_registers() {
+        let mut plus = DensityMatrix::new(1, State::ZERO);
+        plus.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        let zero = DensityMatrix::new(2, State::ZERO);
+        let rho = plus.tensor(&zero);
+        assert_eq!((rho.nqubits, rho.size), (3, 8));
+        assert_eq!(plus.nqubits, 1);
+        let mut expected = vec![Complex::ZERO; 64];
+        for i in [0, 4] {
+            for j in [0, 4] {
+                expected[i * 8 + j] = Complex::new(0.5, 0.);
+            }
+        }
+        let matrix = DensityMatrix::from_tensor(Tensor::from_vec(expected.clone(), vec![8, 8])).unwrap();
+        assert_eq!((matrix.nqubits, matrix.size, matrix.data.shape.clone()), (3, 8, vec![2; 6]));
+        assert!(rho.equals(matrix, 1e-12));
+        assert_eq!(DensityMatrix::from_tensor(Tensor::from_vec(expected.clone(), vec![2; 6])).unwrap().nqubits, 3);
+        assert!(DensityMatrix::from_tensor(Tensor::from_vec(expected.clone(), vec![4, 16])).is_err());
+        assert!(DensityMatrix::from_tensor(Tensor::from_vec(expected[..32].to_vec(), vec![2; 5])).is_err());
+        // The composite register is usable with multi qubit operators.
+        let mut rho = rho;
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 2]).unwrap();
+        assert!((rho.expectation(&"ZIZ".parse().unwrap()).unwrap() - 1.).abs() < 1e-12);
+    }
+    #[test]
+    fn test_ptrace_updates_size() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.ptrace(&[1]).unwrap();
+        assert_eq!((rho.nqubits, rho.size), (2, 4));
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 1).unwrap();
+        assert!((rho.expectation(&"ZZ".parse().unwrap()).unwrap() + 1.).abs() < 1e-12);
+    }
+    #[test]
+    fn test_purity_and_entropy() {
+        let pure = DensityMatrix::new(2, State::PLUS);
+        assert!((pure.purity() - 1.).abs() < 1e-12);
+        assert!(pure.entropy(&TolerancePolicy::DOUBLE).abs() < 1e-10);
+
+        let mut mixed = DensityMatrix::new(2, State::ZERO);
+        let depolarizing = dm_simu_rs::channels::depolarizing(1.).unwrap();
+        mixed.apply_channel(&depolarizing, &[0]).unwrap();
+        mixed.apply_channel(&depolarizing, &[1]).unwrap();
+        assert!((mixed.purity() - 0.25).abs() < 1e-12);
+        assert!((mixed.entropy(&TolerancePolicy::DOUBLE) - 2.).abs() < 1e-10);
+    }
+    #[test]
+    fn test_entanglement_entropy_linear_cluster() {
+        let rho = DensityMatrix::from_graph(&[(0, 1), (1, 2), (2, 3)], 4).unwrap();
+        assert!((rho.entanglement_entropy(&[0], &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < 1e-10);
+        assert!((rho.entanglement_entropy(&[0, 1], &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < 1e-10);
+        assert!((rho.entanglement_entropy(&[0, 2], &TolerancePolicy::DOUBLE).unwrap() - 2.).abs() < 1e-10);
+        assert!(rho.entanglement_entropy(&[0, 1, 2, 3], &TolerancePolicy::DOUBLE).unwrap().abs() < 1e-10);
+        assert_eq!(rho.entanglement_entropy(&[4], &TolerancePolicy::DOUBLE), Err(SimulatorError::IndexOutOfRange { index: 4, nqubits: 4 }));
+        assert_eq!(rho.entanglement_entropy(&[1, 1], &TolerancePolicy::DOUBLE), Err(SimulatorError::DuplicateIndices(vec![1, 1])));
+    }
+    #[test]
+    fn test_outcome_distribution() {
+        // |0> on qubit 0, |1> on qubit 1 and |+> on qubit 2.
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 1).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 2).unwrap();
+        let before = rho.data.data.clone();
+
+        let z = rho.outcome_distribution(&[0, 1], Basis::Z).unwrap();
+        let swapped = rho.outcome_distribution(&[1, 0], Basis::Z).unwrap();
+        for (p, expected) in z.iter().zip([0., 1., 0., 0.]).chain(swapped.iter().zip([0., 0., 1., 0.])) {
+            assert!((p - expected).abs() < 1e-12);
+        }
+        let x = rho.outcome_distribution(&[2], Basis::X).unwrap();
+        assert!((x[0] - 1.).abs() < 1e-12 && x[1].abs() < 1e-12);
+        let y = rho.outcome_distribution(&[2], Basis::Y).unwrap();
+        assert!((y[0] - 0.5).abs() < 1e-12 && (y[1] - 0.5).abs() < 1e-12);
+        assert_eq!(rho.data.data, before);
+    }
+    #[test]
+    fn test_outcome_distribution_bell_pair() {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        // |00> + |11> is correlated in X and anti correlated in Y.
+        let x = rho.outcome_distribution(&[0, 1], Basis::X).unwrap();
+        let y = rho.outcome_distribution(&[0, 1], Basis::Y).unwrap();
+        for (p, expected) in x.iter().zip([0.5, 0., 0., 0.5]).chain(y.iter().zip([0., 0.5, 0.5, 0.])) {
+            assert!((p - expected).abs() < 1e-12);
+        }
+        assert_eq!(rho.outcome_distribution(&[0, 0], Basis::Z), Err(SimulatorError::DuplicateIndices(vec![0, 0])));
+        assert_eq!(rho.outcome_distribution(&[2], Basis::Z), Err(SimulatorError::IndexOutOfRange { index: 2, nqubits: 2 }));
+    }
+    #[test]
+    fn test_rotate_to_bases() {
+        // |+> on qubit 0 and S|+> on qubit 1 are read as 0 in X and Y, qubit 2 stays in |1>.
+        let mut rho = DensityMatrix::new(3, State::PLUS);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::S), 1).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 2).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 2).unwrap();
+        rho.rotate_to_bases(&[Basis::X, Basis::Y]).unwrap();
+        assert!((rho.data.data[9].re - 1.).abs() < 1e-12);
+        assert!(rho.rotate_to_bases(&[Basis::Z; 4]).is_err());
+    }
+    #[test]
+    fn test_tolerance_policy() {
+        let rho = DensityMatrix::new(1, State::PLUS);
+        assert!(rho.validate(&TolerancePolicy::DOUBLE).is_ok());
+
+        // A perturbation of 1e-7 only passes the single precision thresholds.
+        let mut perturbed = rho.clone();
+        perturbed.data.data[0] += Complex::new(1e-7, 0.);
+        assert!(perturbed.validate(&TolerancePolicy::DOUBLE).is_err());
+        assert!(perturbed.validate(&TolerancePolicy::SINGLE).is_ok());
+        assert!(!perturbed.approx_eq(&rho, &TolerancePolicy::DOUBLE));
+        assert!(perturbed.approx_eq(&rho, &TolerancePolicy::SINGLE));
+
+        let mut not_hermitian = rho.clone();
+        not_hermitian.data.data[1] = Complex::new(0.5, 0.1);
+        assert!(not_hermitian.validate(&TolerancePolicy::SINGLE).is_err());
+
+        let mut negative = rho.clone();
+        negative.data.data[1] = Complex::new(0.9, 0.);
+        negative.data.data[2] = Complex::new(0.9, 0.);
+        assert!(negative.validate(&TolerancePolicy::SINGLE).is_err());
+
+        let mut empty = DensityMatrix::new(1, State::ZERO);
+        empty.data.data[0] = Complex::new(1e-8, 0.);
+        assert!(empty.normalize_checked(&TolerancePolicy::SINGLE).is_err());
+        assert!(empty.normalize_checked(&TolerancePolicy::DOUBLE).is_ok());
+        assert!(empty.validate(&TolerancePolicy::DOUBLE).is_ok());
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        let h = Operator::one_qubit(OneQubitOp::H);
+        let cz = Operator::two_qubits(TwoQubitsOp::CZ);
+        assert_eq!(rho.evolve_single(&h, 2), Err(SimulatorError::IndexOutOfRange { index: 2, nqubits: 2 }));
+        assert_eq!(rho.evolve_single(&cz, 0), Err(SimulatorError::DimensionMismatch { expected: 1, actual: 2 }));
+        assert_eq!(rho.evolve(&cz, &[1, 1]), Err(SimulatorError::DuplicateIndices(vec![1, 1])));
+        assert_eq!(rho.ptrace(&[3]), Err(SimulatorError::IndexOutOfRange { index: 3, nqubits: 2 }));
+        assert!(matches!(DensityMatrix::from_statevec(&[Complex::ONE; 3]), Err(SimulatorError::NotPowerOfTwo(3))));
+        assert!(matches!(Operator::new(vec![Complex::ONE; 8]), Err(SimulatorError::NotPowerOfTwo(8))));
+        assert_eq!(Tensor::<Complex<f64>>::try_from_vec(vec![Complex::ONE; 3], vec![2, 2]).unwrap_err(), SimulatorError::DimensionMismatch { expected: 4, actual: 3 });
+
+        let mut unnormalized = DensityMatrix::new(1, State::ZERO);
+        unnormalized.data.data[0] = Complex::new(2., 0.);
+        assert_eq!(unnormalized.check_invariants(&TolerancePolicy::DOUBLE), Err(SimulatorError::NotNormalized(2.)));
+        // Errors still convert into the plain messages used across the crate.
+        let message: String = SimulatorError::NotUnitary.into();
+        assert_eq!(message, "Matrix is not unitary.");
+
+        // Contexts keep the kind of the error they wrap.
+        let error = rho.evolve_single(&h, 2).context("Rotation").with_context(|| "Layer 1").unwrap_err();
+        let source = SimulatorError::Context { context: "Rotation".to_string(), source: Box::new(SimulatorError::IndexOutOfRange { index: 2, nqubits: 2 }) };
+        assert_eq!(error, SimulatorError::Context { context: "Layer 1".to_string(), source: Box::new(source) });
+        assert_eq!(error.to_string(), "Layer 1: Rotation: Target qubit 2 is not in the range [0-2].");
+        assert_eq!(Err::<(), String>("Failed.".to_string()).context("Step").unwrap_err(), "Step: Failed.");
+    }
+
+    #[test]
+    fn test_trace_and_is_normalized() {
+        let mut rho = DensityMatrix::new(2, State::PLUS);
+        assert!(rho.is_normalized(&TolerancePolicy::DOUBLE));
+        rho.data.data.iter_mut().for_each(|c| *c *= 3.);
+        assert!((rho.trace() - Complex::new(3., 0.)).norm() < TOLERANCE * 10.);
+        assert!(!rho.is_normalized(&TolerancePolicy::SINGLE));
+        rho.normalize();
+        assert!(rho.is_normalized(&TolerancePolicy::DOUBLE));
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // (|00> + |11>) / sqrt(2) only yields 00 and 11.
+        let mut bell = DensityMatrix::new(2, State::ZERO);
+        bell.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        bell.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        let before = bell.clone();
+        let histogram = bell.sample(2000, &mut rng);
+        assert_eq!(histogram.values().sum::<usize>(), 2000);
+        assert_eq!(histogram.keys().copied().collect::<BTreeSet<_>>(), [0b00, 0b11].into());
+        assert!((histogram[&0b00] as f64 / 2000. - 0.5).abs() < 0.05);
+        assert!(bell.equals(before, TOLERANCE));
+
+        // Qubit 0 is the most significant bit.
+        let one_zero = DensityMatrix::from_statevec(&[Complex::ZERO, Complex::ZERO, Complex::ONE, Complex::ZERO]).unwrap();
+        assert_eq!(one_zero.sample(10, &mut rng), [(0b10, 10)].into());
+    }
+
+    #[test]
+    fn test_expectation_operator() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 1).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[1, 2]).unwrap();
+        // ZZ on the Bell pair (1, 2), and on (2, 1) which is the same observable.
+        let zz = Operator::new([1., 0., 0., 0., 0., -1., 0., 0., 0., 0., -1., 0., 0., 0., 0., 1.].map(|x| Complex::new(x, 0.)).to_vec()).unwrap();
+        assert!((rho.expectation_operator(&zz, &[1, 2], &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((rho.expectation_operator(&zz, &[2, 1], &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((rho.expectation_operator(&Operator::one_qubit(OneQubitOp::Z), &[0], &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < TOLERANCE);
+        assert!(rho.expectation_operator(&Operator::one_qubit(OneQubitOp::X), &[2], &TolerancePolicy::DOUBLE).unwrap().abs() < TOLERANCE);
+        // Agrees with the Pauli string expectation.
+        let swap = Operator::two_qubits(TwoQubitsOp::SWAP);
+        let swap_expected = (1. + rho.expectation(&"IXX".parse().unwrap()).unwrap() + rho.expectation(&"IYY".parse().unwrap()).unwrap() + rho.expectation(&"IZZ".parse().unwrap()).unwrap()) / 2.;
+        assert!((rho.expectation_operator(&swap, &[1, 2], &TolerancePolicy::DOUBLE).unwrap() - swap_expected).abs() < TOLERANCE);
+
+        assert_eq!(rho.expectation_operator(&Operator::one_qubit(OneQubitOp::S), &[0], &TolerancePolicy::DOUBLE), Err(SimulatorError::NotHermitian));
+        assert_eq!(rho.expectation_operator(&zz, &[1, 1], &TolerancePolicy::DOUBLE), Err(SimulatorError::DuplicateIndices(vec![1, 1])));
+        assert_eq!(rho.expectation_operator(&zz, &[0], &TolerancePolicy::DOUBLE), Err(SimulatorError::DimensionMismatch { expected: 1, actual: 2 }));
+    }
+    #[test]
+    fn test_summary() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.apply_channel(&dm_simu_rs::channels::depolarizing(1.).unwrap(), &[2]).unwrap();
+        let summary = rho.summary(&TolerancePolicy::DOUBLE);
+        assert_eq!(summary.nqubits, 3);
+        assert!((summary.trace - 1.).norm() < 1e-12);
+        assert!((summary.purity - 0.5).abs() < 1e-12);
+        assert_eq!(summary.support, 2);
+        assert_eq!(summary.top_eigenvalues.len(), 4);
+        assert!((summary.top_eigenvalues[0] - 0.5).abs() < 1e-10 && (summary.top_eigenvalues[1] - 0.5).abs() < 1e-10);
+        assert!(summary.top_eigenvalues[2].abs() < 1e-10);
+
+        let text = format!("{:#}", rho);
+        assert_eq!(text, summary.to_string());
+        assert!(text.contains("support: 2 / 8"));
+        assert!(text.contains("purity: 0.500000"));
+    }
+    #[test]
+    fn test_matrix_functions() {
+        let mut rho = DensityMatrix::new(2, State::PLUS);
+        rho.apply_channel(&dm_simu_rs::channels::amplitude_damping(0.3).unwrap(), &[0]).unwrap();
+        rho.apply_channel(&dm_simu_rs::channels::depolarizing(0.2).unwrap(), &[1]).unwrap();
+        let values = rho.eigenvalues();
+        assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!((values.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        let (_, vectors) = rho.eigh();
+        let column = |j: usize| (0..4).map(|i| vectors[i * 4 + j]).collect::<Vec<_>>();
+        assert!((0..4).all(|j| (column(j).iter().map(|c| c.norm_sqr()).sum::<f64>() - 1.).abs() < 1e-12));
+
+        // sqrt(rho)^2 = rho, and -Tr(rho ln rho) is the entropy of rho in nats.
+        let root = rho.sqrtm(&TolerancePolicy::DOUBLE).unwrap();
+        let squared = dm_simu_rs::linalg::matmul(&root.data.data, &root.data.data, 4);
+        assert!(squared.iter().zip(&rho.data.data).all(|(a, b)| (a - b).norm() < 1e-10));
+        let log = rho.logm(&TolerancePolicy::DOUBLE).unwrap();
+        let entropy = -dm_simu_rs::linalg::matmul(&rho.data.data, &log.data.data, 4).iter().step_by(5).map(|c| c.re).sum::<f64>();
+        assert!((entropy - rho.entropy(&TolerancePolicy::DOUBLE) * std::f64::consts::LN_2).abs() < 1e-10);
+
+        // Pure states have no logarithm, and negative eigenvalues are refused.
+        let pure = DensityMatrix::new(1, State::ZERO);
+        assert!(pure.sqrtm(&TolerancePolicy::DOUBLE).unwrap().data.data.iter().zip(&pure.data.data).all(|(a, b)| (a - b).norm() < 1e-12));
+        assert!(pure.logm(&TolerancePolicy::DOUBLE).is_err());
+        let mut negative = DensityMatrix::new(1, State::ZERO);
+        negative.data.data[3] = Complex::new(-0.2, 0.);
+        assert!(matches!(negative.sqrtm(&TolerancePolicy::DOUBLE), Err(SimulatorError::NotPositive(_))));
+    }
+    #[test]
+    fn test_bloch_vector() {
+        // |+> x |0> x (|0> + i|1>) / sqrt(2), then dephase the first qubit.
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 2).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::S), 2).unwrap();
+        rho.apply_channel(&dm_simu_rs::channels::dephasing(0.4).unwrap(), &[0]).unwrap();
+        let expected = [[0.6, 0., 0.], [0., 0., 1.], [0., 1., 0.]];
+        for (q, e) in expected.iter().enumerate() {
+            let v = rho.bloch_vector(q).unwrap();
+            assert!(v.iter().zip(e.iter()).all(|(a, b)| (a - b).abs() < 1e-12), "{:?}", v);
+        }
+        // Entangled qubits have a shrunk Bloch vector.
+        let bell = DensityMatrix::from_graph(&[(0, 1)], 2).unwrap();
+        assert!(bell.bloch_vector(1).unwrap().iter().all(|c| c.abs() < 1e-12));
+        assert_eq!(bell.bloch_vector(2), Err(SimulatorError::IndexOutOfRange { index: 2, nqubits: 2 }));
+    }
+
+    #[test]
+    fn test_evolve_five_qubit_operator_on_scattered_targets() {
+        let mut rng = StdRng::seed_from_u64(5);
+        // Eigenvectors of a random Hermitian matrix form a generic unitary.
+        let random_entries = |len: usize, rng: &mut StdRng| (0..len)
+            .map(|_| Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect::<Vec<Complex<f64>>>();
+        let a = random_entries(32 * 32, &mut rng);
+        let hermitian = a.iter().zip(dm_simu_rs::linalg::adjoint(&a, 32).iter()).map(|(x, y)| x + y).collect::<Vec<_>>();
+        let (_, unitary) = dm_simu_rs::linalg::eigh(&hermitian, 32);
+        let op = Operator::new(unitary.clone()).unwrap();
+
+        let (nqubits, targets) = (7, [4, 0, 6, 2, 5]);
+        let size = 1 << nqubits;
+        let psi = random_entries(size, &mut rng);
+        let mut rho = DensityMatrix::from_statevec(&psi).unwrap();
+        rho.evolve(&op, &targets).unwrap();
+
+        // Dense reference: the local index of a basis state reads the target bits in order.
+        let local = |i: usize| targets.iter().fold(0, |acc, &t| (acc << 1) | ((i >> (nqubits - 1 - t)) & 1));
+        let target_mask = targets.iter().fold(0, |m, &t| m | (1 << (nqubits - 1 - t)));
+        let mut phi = vec![Complex::ZERO; size];
+        for (r, p) in phi.iter_mut().enumerate() {
+            for (c, x) in psi.iter().enumerate().filter(|(c, _)| c & !target_mask == r & !target_mask) {
+                *p += unitary[local(r) * 32 + local(c)] * x;
+            }
+        }
+        let expected = DensityMatrix::from_statevec(&phi).unwrap();
+        assert!(dm_simu_rs::linalg::max_abs_diff(&rho.data.data, &expected.data.data) < 1e-10);
+    }
+
+    #[test]
+    fn test_application_conventions() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let data = (0..16).map(|_| Complex::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5)).collect::<Vec<_>>();
+        let k = Operator::from_matrix(&data, 2).unwrap();
+        let mut rho = DensityMatrix::new(3, State::PLUS);
+        rho.evolve(&Operator::rx(0.3), &[1]).unwrap();
+
+        // K rho K^dagger is K applied on the left, then K^dagger on the right.
+        let mut sandwiched = rho.clone();
+        sandwiched.sandwich(&k, &[2, 0]).unwrap();
+        let mut sides = rho.clone();
+        sides.apply_left(&k, &[2, 0]).unwrap();
+        sides.apply_right(&k.transconj(), &[2, 0]).unwrap();
+        for (a, b) in sandwiched.data.data.iter().zip(&sides.data.data) {
+            assert!((a - b).norm() < 1e-12);
+        }
+
+        // Tr(A rho) = Tr(rho A).
+        let mut left = rho.clone();
+        left.apply_left(&k, &[0, 1]).unwrap();
+        let mut right = rho.clone();
+        right.apply_right(&k, &[0, 1]).unwrap();
+        assert!((left.trace() - right.trace()).norm() < 1e-12);
+        assert!(rho.apply_right(&k, &[0, 0]).is_err());
+        assert!(rho.apply_left(&k, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_allocator_hook() {
+        // Records the requested lengths, standing in for a NUMA or huge page provider.
+        #[derive(Debug, Default)]
+        struct Recording(std::sync::Mutex<Vec<usize>>);
+        impl BufferAllocator for Recording {
+            fn allocate(&self, len: usize) -> Vec<Complex<f64>> {
+                self.0.lock().unwrap().push(len);
+                vec![Complex::ZERO; len]
+            }
+            fn allocate_f32(&self, len: usize) -> Vec<Complex<f32>> {
+                self.0.lock().unwrap().push(len);
+                vec![Complex::ZERO; len]
+            }
+        }
+        let recording = std::sync::Arc::new(Recording::default());
+        let config = SimulationConfig { allocator: recording.clone(), ..SimulationConfig::default() };
+        for state in [State::ZERO, State::PLUS] {
+            assert!(DensityMatrix::with_config(3, state, &config).unwrap().equals(DensityMatrix::new(3, state), TOLERANCE));
+        }
+        assert_eq!(*recording.0.lock().unwrap(), vec![64, 64]);
+
+        // Qubits added and removed by a pattern run resize the state through the allocator too.
+        recording.0.lock().unwrap().clear();
+        let mut circuit = dm_simu_rs::circuit::Circuit::new(1);
+        circuit.h(0);
+        let pattern = circuit.to_pattern();
+        let input = DensityMatrix::new(1, State::ZERO);
+        let result = pattern.simulate_with_config(input.clone(), &config, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert!(result.state.equals(pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(0)).unwrap().state, 1e-12));
+        assert_eq!(*recording.0.lock().unwrap(), vec![16, 4]);
+        assert_eq!(input.tensor_with(&input, recording.as_ref()).unwrap().nqubits, 2);
+
+        // Same for state vectors.
+        recording.0.lock().unwrap().clear();
+        let input = dm_simu_rs::statevector::StateVector::new(1, State::ZERO);
+        let result = pattern.simulate_with_config(input.clone(), &config, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(result.state.data, pattern.simulate(input, &mut StdRng::seed_from_u64(0)).unwrap().state.data);
+        assert_eq!(*recording.0.lock().unwrap(), vec![4, 2]);
+
+        // Same for single precision density matrices.
+        recording.0.lock().unwrap().clear();
+        for state in [State::ZERO, State::PLUS] {
+            assert_eq!(DensityMatrixF32::with_config(3, state, &config).unwrap().data, DensityMatrixF32::new(3, state).data);
+        }
+        let input = DensityMatrixF32::new(1, State::ZERO);
+        let result = pattern.simulate_with_config(input.clone(), &config, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(result.state.data, pattern.simulate(input, &mut StdRng::seed_from_u64(0)).unwrap().state.data);
+        assert_eq!(*recording.0.lock().unwrap(), vec![64, 64, 16, 4]);
+
+        let config = SimulationConfig { allocator: std::sync::Arc::new(FirstTouchAllocator { chunk: 5 }), ..SimulationConfig::default() };
+        assert!(DensityMatrix::with_config(4, State::PLUS, &config).unwrap().equals(DensityMatrix::new(4, State::PLUS), TOLERANCE));
+        let psi = dm_simu_rs::statevector::StateVector::with_config(4, State::PLUS, &config).unwrap();
+        assert_eq!(psi.data, dm_simu_rs::statevector::StateVector::new(4, State::PLUS).data);
+        assert_eq!(DensityMatrixF32::with_config(4, State::PLUS, &config).unwrap().data, DensityMatrixF32::new(4, State::PLUS).data);
+    }
+    #[test]
+    fn test_short_allocator() {
+        #[derive(Debug)]
+        struct Short;
+        impl BufferAllocator for Short {
+            fn allocate(&self, len: usize) -> Vec<Complex<f64>> {
+                vec![Complex::ZERO; len / 2]
+            }
+            fn allocate_f32(&self, len: usize) -> Vec<Complex<f32>> {
+                vec![Complex::ZERO; len / 2]
+            }
+        }
+        let mismatch = SimulatorError::DimensionMismatch { expected: 16, actual: 8 };
+        assert_eq!(DensityMatrix::new(1, State::ZERO).add_qubit_with(State::ZERO, &Short).unwrap_err(), mismatch);
+        assert_eq!(DensityMatrixF32::new(1, State::ZERO).add_qubit_with(State::ZERO, &Short).unwrap_err(), mismatch);
+        let config = SimulationConfig { allocator: std::sync::Arc::new(Short), ..SimulationConfig::default() };
+        assert_eq!(DensityMatrix::with_config(2, State::ZERO, &config).err(), Some(mismatch));
+        assert!(dm_simu_rs::statevector::StateVector::with_config(2, State::ZERO, &config).is_err());
+
+        // A pattern run reports the allocator failure instead of panicking.
+        let mut circuit = dm_simu_rs::circuit::Circuit::new(1);
+        circuit.h(0);
+        let result = circuit.to_pattern().simulate_with_config(DensityMatrix::new(1, State::ZERO), &config, &mut StdRng::seed_from_u64(0));
+        assert!(result.err().unwrap().contains("Expected dimension 16 but got 8."));
+    }
+
+    #[test]
+    fn test_readout_error() {
+        assert!(Confusion::new(0.1, 1.5).is_err());
+        assert_eq!(Confusion::new(0.1, 0.2).unwrap().matrix(), [[0.9, 0.1], [0.2, 0.8]]);
+        let mut rng = StdRng::seed_from_u64(4);
+
+        let rho = DensityMatrix::new(2, State::ZERO);
+        let readout = ReadoutError { confusion: vec![Confusion::new(1., 0.).unwrap()], ..ReadoutError::default() };
+        assert_eq!(rho.sample_with_readout(20, &readout, &mut rng).get(&0b10), Some(&20));
+        let shots = rho.sample_shots_with_readout(5, &readout, &mut rng);
+        assert!(shots.iter().all(|shot| shot.ideal == 0 && shot.recorded == 0b10));
+
+        let noisy = ReadoutError::uniform(2, Confusion::symmetric(0.2).unwrap());
+        let histogram = rho.sample_with_readout(20000, &noisy, &mut rng);
+        let flipped_first = (histogram.get(&0b10).unwrap_or(&0) + histogram.get(&0b11).unwrap_or(&0)) as f64 / 20000.;
+        assert!((flipped_first - 0.2).abs() < 0.02);
+
+        let mut one = DensityMatrix::new(1, State::ZERO);
+        one.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        let readout = ReadoutError { confusion: vec![Confusion::new(0., 1.).unwrap()], ..ReadoutError::default() };
+        let outcome = one.measure_with_readout(0, &readout, &mut rng).unwrap();
+        assert_eq!((outcome.recorded, outcome.ideal), (0, 1));
+        assert!((one.data.data[3].re - 1.).abs() < TOLERANCE);
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests_density_matrix_f32 {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::channels;
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::density_matrix_f32::DensityMatrixF32;
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+
+    fn circuit() -> Circuit {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rx(2, 0.4);
+        circuit.rzz(1, 2, 0.9);
+        circuit.ccx(0, 2, 1);
+        circuit.ry(0, 1.3);
+        circuit
+    }
+
+    #[test]
+    fn test_circuit_matches_double_precision() {
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        let mut rho32 = DensityMatrixF32::new(3, State::ZERO);
+        circuit().run(&mut rho).unwrap();
+        circuit().run(&mut rho32).unwrap();
+        let kraus = channels::depolarizing(0.1).unwrap();
+        rho.apply_channel(&kraus, &[1]).unwrap();
+        rho32.apply_channel(&kraus, &[1]).unwrap();
+        assert!(rho32.to_density_matrix().approx_eq(&rho, &TolerancePolicy::SINGLE));
+        assert!((rho32.trace().re - 1.).abs() < TolerancePolicy::SINGLE.trace);
+        let zz = "ZZI".parse().unwrap();
+        assert!((rho32.expectation(&zz).unwrap() - rho.expectation(&zz).unwrap()).abs() < TolerancePolicy::SINGLE.equality);
+        assert_eq!(std::mem::size_of_val(&rho32.data[..]) * 2, std::mem::size_of_val(&rho.data.data[..]));
+    }
+    #[test]
+    fn test_pattern_on_single_precision() {
+        let pattern = circuit().to_pattern();
+        let input = DensityMatrix::new(3, State::PLUS);
+        let result = pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(3)).unwrap();
+        let result32 = pattern.simulate(DensityMatrixF32::from_density_matrix(&input), &mut StdRng::seed_from_u64(3)).unwrap();
+        assert_eq!(result.outcomes, result32.outcomes);
+        assert!(result32.state.to_density_matrix().approx_eq(&result.state, &TolerancePolicy::SINGLE));
+        assert!(result32.state.check_invariants().is_ok());
+    }
+    #[test]
+    fn test_errors() {
+        let mut rho32 = DensityMatrixF32::new(2, State::ZERO);
+        let h = Operator::one_qubit(OneQubitOp::H);
+        assert!(rho32.evolve_single(&h, 2).is_err());
+        assert!(rho32.measure(5, &mut StdRng::seed_from_u64(0)).is_err());
+        rho32.data.iter_mut().for_each(|c| *c *= 1e-7);
+        assert!(rho32.check_invariants().is_err());
+        assert!(rho32.normalize_checked().is_err());
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests_diagnostics {
+    use std::process::Command as Process;
+
+    use num_complex::Complex;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::diagnostics::{self, Severity};
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+
+    fn severity(pattern: &Pattern, check: &str) -> Severity {
+        pattern.diagnostics(&TolerancePolicy::DOUBLE).iter().filter(|d| d.check == check).map(|d| d.severity).max().unwrap()
+    }
+
+    #[test]
+    fn test_circuit_pattern_is_valid() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rz(1, 0.3);
+        let diagnostics = circuit.to_pattern().diagnostics(&TolerancePolicy::DOUBLE);
+        assert!(!diagnostics::has_errors(&diagnostics));
+        for check in ["commands", "physicality", "standardization", "flow", "determinism"] {
+            assert!(diagnostics.iter().any(|d| d.check == check && d.severity == Severity::Info));
+        }
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        // Without corrections, the output depends on the outcomes.
+        let mut uncorrected = Pattern::new(vec![0]);
+        uncorrected.extend(vec![
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0.25, vec![], vec![], 0),
+        ]);
+        assert_eq!(severity(&uncorrected, "commands"), Severity::Info);
+        assert_eq!(severity(&uncorrected, "determinism"), Severity::Error);
+
+        let mut malformed = Pattern::new(vec![0]);
+        malformed.extend(vec![
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::X(1, vec![0]),
+            Command::C(1, 99),
+            Command::M(0, Plane::XY, f64::NAN, vec![], vec![], 0),
+        ]);
+        assert_eq!(severity(&malformed, "commands"), Severity::Error);
+        assert_eq!(severity(&malformed, "physicality"), Severity::Error);
+        assert_eq!(severity(&malformed, "determinism"), Severity::Info);
+        let json: serde_json::Value = serde_json::from_str(&diagnostics::to_json(&malformed.diagnostics(&TolerancePolicy::DOUBLE))).unwrap();
+        assert_eq!(json["valid"], false);
+        assert!(json["diagnostics"].as_array().unwrap().iter().any(|d| d["check"] == "physicality" && d["severity"] == "error"));
+    }
+
+    #[test]
+    fn test_diagnostics_tolerance() {
+        // A preparation whose norm is off by 1e-7 is only accepted in single precision.
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(vec![Command::NState(0, [Complex::new(1. + 1e-7, 0.), Complex::new(0., 0.)])]);
+        assert_eq!(severity(&pattern, "physicality"), Severity::Error);
+        let relaxed = pattern.diagnostics(&TolerancePolicy::SINGLE);
+        assert!(!relaxed.iter().any(|d| d.check == "physicality" && d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_cli_validate() {
+        let mut circuit = Circuit::new(1);
+        circuit.h(0);
+        let path = std::env::temp_dir().join("dm_simu_rs_validate_pattern.json");
+        std::fs::write(&path, circuit.to_pattern().to_json()).unwrap();
+        let output = Process::new(env!("CARGO_BIN_EXE_mbqc")).arg("validate").arg(&path).output().unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["valid"], true);
+
+        std::fs::write(&path, "{}").unwrap();
+        let output = Process::new(env!("CARGO_BIN_EXE_mbqc")).arg("validate").arg(&path).output().unwrap();
+        assert!(!output.status.success());
+        assert!(output.stderr.is_empty());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["diagnostics"][0]["check"], "parse");
+        std::fs::remove_file(path).unwrap();
+    }
+}
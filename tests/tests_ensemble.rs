@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests_ensemble {
+    use rand::distributions::Uniform;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use dm_simu_rs::channels;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::ensemble::{from_distribution, run_ensemble, Observable};
+    use dm_simu_rs::operators::OneQubitOp;
+
+    fn x_expectation(rho: &DensityMatrix) -> Result<f64, String> {
+        Ok(rho.expectation_single(OneQubitOp::X, 0)?.re)
+    }
+
+    #[test]
+    fn test_ensemble_depolarized_plus_state() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let distributions = vec![from_distribution(Uniform::new(0., 0.2))];
+        let observables: [Observable; 1] = [&x_expectation];
+        let estimates = run_ensemble(&distributions, 4000, |params| {
+            let mut rho = DensityMatrix::new(1, State::PLUS);
+            rho.apply_channel(&channels::depolarizing(params[0])?, &[0])?;
+            Ok(rho)
+        }, &observables, &TolerancePolicy::DOUBLE, &mut rng).unwrap();
+
+        let estimate = &estimates[0];
+        // <X> = 1 - p with p uniform in [0, 0.2].
+        assert!((estimate.exact_mean - 0.9).abs() < 5e-3);
+        assert!((estimate.mean - 0.9).abs() < 3e-2);
+        assert!((estimate.parameter_variance - 0.04 / 12.).abs() < 5e-4);
+        // E[1 - (1 - p)^2] = 2 E[p] - E[p^2] = 0.2 - 0.04 / 3.
+        assert!((estimate.shot_variance - (0.2 - 0.04 / 3.)).abs() < 5e-3);
+        // Law of total variance.
+        let decomposed = estimate.shot_variance + estimate.parameter_variance;
+        assert!((estimate.total_variance - decomposed).abs() < 3e-2);
+    }
+    #[test]
+    fn test_ensemble_without_parameter_noise() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let observables: [Observable; 1] = [&x_expectation];
+        let estimates = run_ensemble(&[], 100, |_| Ok(DensityMatrix::new(1, State::PLUS)), &observables, &TolerancePolicy::DOUBLE, &mut rng).unwrap();
+        assert_eq!(estimates[0].mean, 1.);
+        assert_eq!(estimates[0].parameter_variance, 0.);
+        assert_eq!(estimates[0].shot_variance, 0.);
+    }
+    #[test]
+    fn test_ensemble_zero_shots() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let observables: [Observable; 1] = [&x_expectation];
+        assert!(run_ensemble(&[], 0, |_| Ok(DensityMatrix::new(1, State::PLUS)), &observables, &TolerancePolicy::DOUBLE, &mut rng).is_err());
+    }
+    #[test]
+    fn test_ensemble_rounding() {
+        // Values past 1 by rounding errors are clamped, larger ones are refused.
+        let mut rng = StdRng::seed_from_u64(0);
+        let rounded = |_: &DensityMatrix| Ok(1. + 1e-14);
+        let observables: [Observable; 1] = [&rounded];
+        let estimates = run_ensemble(&[], 10, |_| Ok(DensityMatrix::new(1, State::PLUS)), &observables, &TolerancePolicy::DOUBLE, &mut rng).unwrap();
+        assert_eq!(estimates[0].exact_mean, 1.);
+        assert_eq!(estimates[0].shot_variance, 0.);
+        let invalid = |_: &DensityMatrix| Ok(-1. - 1e-6);
+        let observables: [Observable; 1] = [&invalid];
+        assert!(run_ensemble(&[], 10, |_| Ok(DensityMatrix::new(1, State::PLUS)), &observables, &TolerancePolicy::DOUBLE, &mut rng).is_err());
+        let nan = |_: &DensityMatrix| Ok(f64::NAN);
+        let observables: [Observable; 1] = [&nan];
+        assert!(run_ensemble(&[], 10, |_| Ok(DensityMatrix::new(1, State::PLUS)), &observables, &TolerancePolicy::DOUBLE, &mut rng).is_err());
+    }
+}
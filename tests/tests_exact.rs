@@ -0,0 +1,86 @@
+#[cfg(all(test, feature = "exact"))]
+mod tests_exact {
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::State;
+    use dm_simu_rs::exact::{ExactComplex, ExactStateVector};
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+    use dm_simu_rs::pauli::PauliString;
+    use dm_simu_rs::statevector::StateVector;
+
+    #[test]
+    fn test_ring_arithmetic() {
+        let omega = ExactComplex::omega(1);
+        assert_eq!((0..8).fold(ExactComplex::ONE, |x, _| x * omega), ExactComplex::ONE);
+        assert_eq!(omega * omega, ExactComplex::I);
+        let half = ExactComplex::inv_sqrt2_pow(2);
+        assert_eq!(half + half, ExactComplex::ONE);
+        assert_eq!(ExactComplex::inv_sqrt2_pow(1).mul_sqrt2_pow(3), ExactComplex::new([2, 0, 0, 0], 0));
+        assert_eq!(omega + omega.conj(), ExactComplex::inv_sqrt2_pow(1).mul_sqrt2_pow(2));
+        assert_eq!(ExactComplex::from_complex(Complex::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2), &TolerancePolicy::DOUBLE), Some(omega));
+        assert_eq!(ExactComplex::from_complex(Complex::new(-0.25, 0.), &TolerancePolicy::DOUBLE), Some(-ExactComplex::inv_sqrt2_pow(4)));
+        assert_eq!(ExactComplex::from_complex(Complex::new(0.3, 0.), &TolerancePolicy::DOUBLE), None);
+        assert_eq!((omega - omega).k(), 0);
+        assert!((omega.to_complex() - Complex::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2)).norm() < 1e-15);
+    }
+
+    #[test]
+    fn test_exact_gates() {
+        let h = Operator::one_qubit(OneQubitOp::H);
+        let mut psi = ExactStateVector::new(1, State::ZERO);
+        psi.evolve_single(&h, 0).unwrap();
+        assert_eq!(psi, ExactStateVector::new(1, State::PLUS));
+        let t = Operator::one_qubit(OneQubitOp::T);
+        for _ in 0..8 {
+            psi.evolve_single(&t, 0).unwrap();
+        }
+        psi.evolve_single(&h, 0).unwrap();
+        assert_eq!(psi, ExactStateVector::new(1, State::ZERO));
+        assert!(psi.evolve_single(&Operator::one_qubit(OneQubitOp::I), 1).is_err());
+        let rotation = Operator::from_matrix(&[Complex::new(0.6, 0.), Complex::new(-0.8, 0.), Complex::new(0.8, 0.), Complex::new(0.6, 0.)], 1).unwrap();
+        assert!(psi.evolve_single(&rotation, 0).is_err());
+
+        let mut bell = ExactStateVector::new(2, State::PLUS);
+        bell.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]).unwrap();
+        bell.evolve_single(&h, 1).unwrap();
+        let zz = "ZZ".parse::<PauliString>().unwrap();
+        assert_eq!(bell.expectation_exact(&zz).unwrap(), ExactComplex::ONE);
+        let mut rng = StdRng::seed_from_u64(2);
+        let outcome = bell.measure(0, &mut rng).unwrap();
+        assert_eq!(bell.measure(1, &mut rng).unwrap(), outcome);
+        let mut t_state = ExactStateVector::new(1, State::PLUS);
+        t_state.evolve_single(&t, 0).unwrap();
+        t_state.evolve_single(&h, 0).unwrap();
+        assert!(t_state.project(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_exact_pattern() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.s(1);
+        circuit.cnot(1, 2);
+        circuit.x(2);
+        circuit.z(0);
+        let pattern = circuit.to_pattern();
+        let mut expected = StateVector::new(3, State::PLUS);
+        circuit.run(&mut expected).unwrap();
+        let expected = ExactStateVector::from_statevector(&expected, &TolerancePolicy::DOUBLE).unwrap();
+        let mut previous: Option<ExactStateVector> = None;
+        for seed in 0..5 {
+            let result = pattern.simulate(ExactStateVector::new(3, State::PLUS), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.equals_up_to_phase(&expected));
+            if let Some(previous) = &previous {
+                assert!(result.state.equals_up_to_phase(previous));
+            }
+            previous = Some(result.state);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests_feedback {
+    use std::collections::{BTreeSet, HashMap};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::feedback::Condition;
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+
+    #[test]
+    fn test_parse_and_evaluate() {
+        let condition = Condition::parse("s[3] ^ s[7] & !s[1]").unwrap();
+        // & binds tighter than ^.
+        assert_eq!(condition, Condition::Xor(vec![
+            Condition::Outcome(3),
+            Condition::And(vec![Condition::Outcome(7), Condition::Not(Box::new(Condition::Outcome(1)))])
+        ]));
+        assert_eq!(condition.nodes(), BTreeSet::from([1, 3, 7]));
+        let outcomes = |bits: [u8; 3]| HashMap::from([(1, bits[0]), (3, bits[1]), (7, bits[2])]);
+        assert!(condition.evaluate(&outcomes([0, 0, 1])).unwrap());
+        assert!(!condition.evaluate(&outcomes([1, 0, 0])).unwrap());
+        assert!(!condition.evaluate(&outcomes([0, 1, 1])).unwrap());
+        assert!(condition.evaluate(&HashMap::from([(3, 1)])).is_err());
+
+        assert_eq!(Condition::parse(&condition.to_string()).unwrap(), condition);
+        assert_eq!(Condition::parse(" ( s[ 0 ]|1 ) ").unwrap().to_string(), "(s[0] | 1)");
+        assert_eq!(Condition::parity(&[]), Condition::Constant(false));
+        assert_eq!(Condition::parity(&[2, 5]), Condition::parse("s[2] ^ s[5]").unwrap());
+        assert_eq!(Condition::parse("s[0] ^ (s[1] ^ s[2]) ^ s[3]").unwrap().to_string(), "(s[0] ^ (s[1] ^ s[2]) ^ s[3])");
+        for invalid in ["", "s[1] ^", "s1", "(s[0]", "s[0] s[1]", "2", "s[-1]"] {
+            assert!(Condition::parse(invalid).is_err(), "{}", invalid);
+        }
+
+        // Deep nesting is refused instead of overflowing the stack.
+        assert!(Condition::parse(&format!("{}s[0]{}", "(".repeat(200), ")".repeat(200))).is_ok());
+        assert!(Condition::parse(&format!("{}s[0]{}", "(".repeat(100_000), ")".repeat(100_000))).is_err());
+        assert!(Condition::parse(&format!("{}s[0]", "!".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn test_long_domains() {
+        // Chains are printed flat, so domains longer than the nesting limit survive a round trip.
+        let domain = (1..100_001).collect::<Vec<_>>();
+        let parity = Condition::parity(&domain);
+        assert_eq!(Condition::parse(&parity.to_string()).unwrap(), parity);
+        assert_eq!(parity.nodes().len(), domain.len());
+        let outcomes = domain.iter().map(|&node| (node, (node % 3 == 0) as u8)).collect::<HashMap<_, _>>();
+        assert!(parity.evaluate(&outcomes).unwrap());
+
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(domain[..300].iter().map(|&node| Command::N(node)).collect());
+        pattern.extend(domain[..300].iter().map(|&node| Command::M(node, Plane::XY, 0., vec![], vec![], 0)).collect());
+        pattern.extend(vec![Command::N(0), Command::ZIf(0, Condition::parity(&domain[..300])), Command::XIf(0, Condition::parse(&vec!["s[1]"; 300].join(" & ")).unwrap())]);
+        assert_eq!(Pattern::from_json(&pattern.to_json()).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_conditional_corrections() {
+        // |+> measured along X always gives 0, so only the negated condition flips node 1 to |->.
+        for (condition, coherence) in [("s[0]", 0.5), ("!s[0]", -0.5), ("s[0] | !s[0] & 1", -0.5)] {
+            let json = format!(r#"{{"input_nodes": [], "seq": [["N", 0], ["N", 1], ["M", 0, "XY", 0.0, [], []], ["Z", 1, "{}"]]}}"#, condition);
+            let pattern = Pattern::from_json(&json).unwrap();
+            assert!(matches!(pattern.seq()[3], Command::ZIf(1, _)));
+            assert_eq!(Pattern::from_json(&pattern.to_json()).unwrap(), pattern);
+            let result = pattern.simulate(DensityMatrix::new(0, State::ZERO), &mut StdRng::seed_from_u64(0)).unwrap();
+            assert!((result.state.data.data[1].re - coherence).abs() < 1e-12);
+        }
+
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(vec![Command::N(0), Command::XIf(0, Condition::parse("s[1]").unwrap())]);
+        assert!(pattern.simulate(DensityMatrix::new(0, State::ZERO), &mut StdRng::seed_from_u64(0)).is_err());
+        assert!(pattern.standardize().is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [], "seq": [["N", 0], ["X", 0, "s[0] &"]]}"#).is_err());
+    }
+}
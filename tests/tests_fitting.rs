@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests_fitting {
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::fitting::{fit_noise, simulate_tomography, NoiseFit};
+    use dm_simu_rs::noise::CommandKind;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const TOLERANCE: f64 = 1e-9;
+
+    fn reference() -> NoiseFit {
+        NoiseFit { depolarizing: 0.02, damping: 0.05, dephasing: 0.03, residual: 0. }
+    }
+
+    #[test]
+    fn test_model_matches_channel() {
+        let fit = reference();
+        let ptm = fit.channel().unwrap().ptm();
+        fit.ptm().iter().zip(&ptm).for_each(|(a, b)| assert!((a - b).abs() < TOLERANCE));
+    }
+
+    #[test]
+    fn test_fit_exact_data() {
+        let expected = reference();
+        let fit = fit_noise(&expected.channel().unwrap().ptm()).unwrap();
+        assert!((fit.depolarizing - expected.depolarizing).abs() < 1e-6);
+        assert!((fit.damping - expected.damping).abs() < 1e-6);
+        assert!((fit.dephasing - expected.dephasing).abs() < 1e-6);
+        assert!(fit.residual < 1e-8);
+        assert!(fit_noise(&[1.; 4]).is_err());
+    }
+
+    #[test]
+    fn test_fit_simulated_tomography() {
+        let expected = reference();
+        let mut rng = StdRng::seed_from_u64(11);
+        let data = simulate_tomography(&expected.channel().unwrap(), 200_000, &TolerancePolicy::DOUBLE, &mut rng).unwrap();
+        let fit = fit_noise(&data).unwrap();
+        assert!((fit.depolarizing - expected.depolarizing).abs() < 0.01);
+        assert!((fit.damping - expected.damping).abs() < 0.01);
+        assert!((fit.dephasing - expected.dephasing).abs() < 0.02);
+        assert!(fit.residual < 0.01);
+        let model = fit.noise_model().unwrap();
+        assert_eq!(model.channels[&CommandKind::E].nqubits, 2);
+        assert_eq!(model.channels.len(), 6);
+    }
+}
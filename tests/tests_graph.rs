@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests_graph {
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::graph::GraphState;
+    use dm_simu_rs::operators::{Operator, TwoQubitsOp};
+
+    const TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn test_from_graph_matches_entangle() {
+        let edges = [(0, 1), (1, 2), (0, 3), (2, 3)];
+        let mut expected = DensityMatrix::new(4, State::PLUS);
+        let cz = Operator::two_qubits(TwoQubitsOp::CZ);
+        for (a, b) in edges {
+            expected.evolve(&cz, &[a, b]).unwrap();
+        }
+        let dm = DensityMatrix::from_graph(&edges, 4).unwrap();
+        assert!(dm.equals(expected, TOLERANCE));
+    }
+    #[test]
+    fn test_statevector_matches_density_matrix() {
+        let graph = GraphState::new(3, &[(0, 2), (1, 2)]).unwrap();
+        assert!(graph.to_density_matrix().equals(graph.to_statevector().to_density_matrix(), TOLERANCE));
+        let mut neighbors = graph.neighbors(2);
+        neighbors.sort();
+        assert_eq!(neighbors, vec![0, 1]);
+    }
+    #[test]
+    fn test_stabilizers() {
+        // K_v = X_v prod_{w in N(v)} Z_w stabilizes the graph state.
+        let dm = DensityMatrix::from_graph(&[(0, 1), (1, 2)], 3).unwrap();
+        for k in ["XZI", "ZXZ", "IZX"] {
+            assert!((dm.expectation(&k.parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_invalid_edges() {
+        assert!(GraphState::new(2, &[(0, 2)]).is_err());
+        assert!(DensityMatrix::from_graph(&[(1, 1)], 2).is_err());
+    }
+}
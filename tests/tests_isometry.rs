@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests_isometry {
+    use num_complex::Complex;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::isometry::Isometry;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    fn basis(index: usize, size: usize) -> Vec<Complex<f64>> {
+        (0..size).map(|i| if i == index { Complex::ONE } else { Complex::ZERO }).collect()
+    }
+
+    // Three qubit repetition code, |0_L> = |000>, |1_L> = |111>.
+    fn repetition_code() -> Isometry {
+        Isometry::from_codewords(&[basis(0, 8), basis(7, 8)], &TolerancePolicy::DOUBLE).unwrap()
+    }
+
+    #[test]
+    fn test_encode_plus() {
+        let encoded = DensityMatrix::new(1, State::PLUS).encode(&repetition_code()).unwrap();
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let ghz = DensityMatrix::from_statevec(&[h, 0., 0., 0., 0., 0., 0., h].map(|x| Complex::new(x, 0.))).unwrap();
+        assert_eq!(encoded.nqubits, 3);
+        assert!(encoded.equals(ghz, TOLERANCE));
+    }
+    #[test]
+    fn test_project_onto_code_space() {
+        let code = repetition_code();
+        let mut rho = DensityMatrix::new(3, State::PLUS);
+        let probability = rho.project(&code, &TolerancePolicy::DOUBLE).unwrap();
+        assert!((probability - 0.25).abs() < TOLERANCE);
+        assert!((rho.trace().re - 1.).abs() < TOLERANCE);
+        assert!((rho.expectation(&"XXX".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+
+        let mut outside = DensityMatrix::from_statevec(&basis(1, 8)).unwrap();
+        assert!(outside.project(&code, &TolerancePolicy::DOUBLE).is_err());
+    }
+    #[test]
+    fn test_invalid_isometry() {
+        assert!(Isometry::new(vec![Complex::ONE; 4], 1, 1, &TolerancePolicy::DOUBLE).is_err());
+        assert!(Isometry::new(vec![Complex::ONE; 8], 2, 1, &TolerancePolicy::DOUBLE).is_err());
+        assert!(Isometry::from_codewords(&[basis(0, 4), basis(0, 4)], &TolerancePolicy::DOUBLE).is_err());
+        assert!(DensityMatrix::new(2, State::ZERO).encode(&repetition_code()).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests_latex {
+    use num_complex::Complex;
+
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::latex::LatexOptions;
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+    use dm_simu_rs::statevector::StateVector;
+
+    fn dirac() -> LatexOptions {
+        LatexOptions { dirac: true, ..LatexOptions::default() }
+    }
+
+    #[test]
+    fn test_operator_latex() {
+        let h = Operator::one_qubit(OneQubitOp::H);
+        assert_eq!(h.to_latex().unwrap(), "\\begin{pmatrix}\n0.707 & 0.707 \\\\\n0.707 & -0.707\n\\end{pmatrix}");
+        let y = Operator::one_qubit(OneQubitOp::Y);
+        assert_eq!(y.to_latex().unwrap(), "\\begin{pmatrix}\n0 & -i \\\\\ni & 0\n\\end{pmatrix}");
+        assert_eq!(y.to_latex_with(&dirac()).unwrap(), "-i|0\\rangle\\langle 1| + i|1\\rangle\\langle 0|");
+        let cz = Operator::two_qubits(TwoQubitsOp::CZ);
+        assert!(cz.to_latex_with(&dirac()).unwrap().ends_with(" - |11\\rangle\\langle 11|"));
+        let small = LatexOptions { max_qubits: 1, ..LatexOptions::default() };
+        assert!(cz.to_latex_with(&small).is_err());
+    }
+
+    #[test]
+    fn test_state_latex() {
+        let rho = DensityMatrix::new(1, State::PLUS);
+        assert_eq!(rho.to_latex().unwrap(), "\\begin{pmatrix}\n0.5 & 0.5 \\\\\n0.5 & 0.5\n\\end{pmatrix}");
+        let psi = StateVector::from_vec(vec![Complex::new(0.6, 0.), Complex::new(-0.48, 0.64)]).unwrap();
+        assert_eq!(psi.to_latex().unwrap(), "\\begin{pmatrix}\n0.6 \\\\\n-0.48 + 0.64i\n\\end{pmatrix}");
+        assert_eq!(psi.to_latex_with(&dirac()).unwrap(), "0.6|0\\rangle + (-0.48 + 0.64i)|1\\rangle");
+        let rounded = LatexOptions { digits: 1, ..dirac() };
+        assert_eq!(StateVector::new(2, State::ZERO).to_latex_with(&rounded).unwrap(), "|00\\rangle");
+    }
+}
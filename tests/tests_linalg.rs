@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests_linalg {
+    use num_complex::Complex;
+    use dm_simu_rs::linalg::{eigh, eig_normal, from_eigen, max_abs_diff};
+
+    const TOLERANCE: f64 = 1e-10;
+
+    #[test]
+    fn test_eigh_pauli_y() {
+        let y = [Complex::ZERO, Complex::new(0., -1.), Complex::new(0., 1.), Complex::ZERO];
+        let (values, vectors) = eigh(&y, 2);
+        assert!((values[0] + 1.).abs() < TOLERANCE);
+        assert!((values[1] - 1.).abs() < TOLERANCE);
+        let values = values.iter().map(|&v| Complex::new(v, 0.)).collect::<Vec<_>>();
+        assert!(max_abs_diff(&from_eigen(&values, &vectors, 2), &y) < TOLERANCE);
+    }
+    #[test]
+    fn test_eigh_reconstructs_hermitian_matrix() {
+        let n = 4;
+        let mut a = vec![Complex::ZERO; n * n];
+        for i in 0..n {
+            for j in i..n {
+                let value = Complex::new((i + 2 * j) as f64 * 0.3 - 1., if i == j { 0. } else { (j as f64) - 0.7 * i as f64 });
+                a[i * n + j] = value;
+                a[j * n + i] = value.conj();
+            }
+        }
+        let (values, vectors) = eigh(&a, n);
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        let values = values.iter().map(|&v| Complex::new(v, 0.)).collect::<Vec<_>>();
+        assert!(max_abs_diff(&from_eigen(&values, &vectors, n), &a) < TOLERANCE);
+    }
+    #[test]
+    fn test_eig_normal_phase_gate() {
+        let s = [Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::I];
+        let (values, vectors) = eig_normal(&s, 2);
+        assert!(values.iter().any(|v| (v - Complex::ONE).norm() < TOLERANCE));
+        assert!(values.iter().any(|v| (v - Complex::I).norm() < TOLERANCE));
+        assert!(max_abs_diff(&from_eigen(&values, &vectors, 2), &s) < TOLERANCE);
+    }
+}
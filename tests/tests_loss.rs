@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests_loss {
+    use std::collections::BTreeSet;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::State;
+    use dm_simu_rs::loss::{LossModel, WireComputation};
+    use dm_simu_rs::operators::Operator;
+    use dm_simu_rs::statevector::StateVector;
+
+    // Grid of `rows` x `cols` nodes, node r * cols + c, the wire running along row 0.
+    fn grid(rows: usize, cols: usize, angles: Vec<f64>) -> WireComputation {
+        let mut edges = vec![];
+        for r in 0..rows {
+            for c in 0..cols {
+                let node = r * cols + c;
+                if c + 1 < cols {
+                    edges.push((node, node + 1));
+                }
+                if r + 1 < rows {
+                    edges.push((node, node + cols));
+                }
+            }
+        }
+        WireComputation::new(&edges, 0, cols - 1, angles).unwrap()
+    }
+
+    fn input() -> StateVector {
+        let mut input = StateVector::new(1, State::ZERO);
+        input.evolve_single(&Operator::ry(0.7), 0).unwrap();
+        input.evolve_single(&Operator::rz(0.3), 0).unwrap();
+        input
+    }
+
+    #[test]
+    fn test_rerouting() {
+        let wire = grid(3, 4, vec![0.25, -0.4, 0.1]);
+        assert_eq!(wire.route(&BTreeSet::new()).unwrap(), vec![0, 1, 2, 3]);
+        let expected = wire.adapt(&BTreeSet::new()).unwrap().simulate(input(), &mut StdRng::seed_from_u64(0)).unwrap().state.to_density_matrix();
+
+        // Losing node 1 detours through the second row, two extra nodes being measured at angle 0.
+        let lost = BTreeSet::from([1, 6]);
+        assert_eq!(wire.route(&lost).unwrap(), vec![0, 4, 5, 9, 10, 11, 7, 3]);
+        let pattern = wire.adapt(&lost).unwrap();
+        for seed in 0..8 {
+            let result = pattern.simulate(input(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.to_density_matrix().approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+
+        assert!(wire.route(&BTreeSet::from([0])).is_err());
+        assert!(wire.adapt(&BTreeSet::from([1, 5, 9])).is_err());
+    }
+
+    #[test]
+    fn test_wire_validation() {
+        // Odd cycles and angle counts of the wrong parity are refused.
+        assert!(WireComputation::new(&[(0, 1), (1, 2), (0, 2)], 0, 2, vec![]).is_err());
+        assert!(WireComputation::new(&[(0, 1), (1, 2)], 0, 2, vec![0.5]).is_err());
+        assert!(WireComputation::new(&[(0, 1), (1, 2)], 0, 2, vec![0.5, 0.1, 0.2]).is_err());
+        assert!(LossModel::uniform(1.5).is_err());
+    }
+
+    #[test]
+    fn test_adaptation_report() {
+        let wire = grid(3, 5, vec![0.2, 0.3]);
+        let mut rng = StdRng::seed_from_u64(3);
+        let report = wire.estimate_adaptation(&LossModel::uniform(0.).unwrap(), 20, &mut rng);
+        assert_eq!((report.successes, report.lost_nodes, report.extra_nodes), (20, 0, 0));
+        assert_eq!(wire.estimate_adaptation(&LossModel::uniform(1.).unwrap(), 20, &mut rng).success_rate(), 0.);
+
+        let loss = LossModel::uniform(0.1).unwrap();
+        let report = wire.estimate_adaptation(&loss, 500, &mut rng);
+        assert!(report.success_rate() > 0.5 && report.success_rate() < 1.);
+        assert!(report.extra_nodes.is_multiple_of(2));
+
+        // End to end, every surviving run carries the loss free output state.
+        let expected = wire.adapt(&BTreeSet::new()).unwrap().simulate(input(), &mut rng).unwrap().state.to_density_matrix();
+        for _ in 0..10 {
+            let run = wire.run(input(), &loss, &mut rng).unwrap();
+            if let Some(result) = run.result {
+                assert!(result.state.to_density_matrix().approx_eq(&expected, &TolerancePolicy::DOUBLE));
+            }
+        }
+    }
+}
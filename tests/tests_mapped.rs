@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests_mapped {
+    use dm_simu_rs::channels;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::DensityMatrix;
+    use dm_simu_rs::mapped::MappedDensityMatrix;
+    use dm_simu_rs::npy;
+    use num_complex::Complex;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn test_mapped_analysis_matches_in_memory() {
+        let mut rho = DensityMatrix::from_graph(&[(0, 1), (1, 2)], 3).unwrap();
+        rho.apply_channel(&channels::dephasing(0.3).unwrap(), &[1]).unwrap();
+        let path = std::env::temp_dir().join("dm_simu_rs_mapped.npy");
+        npy::write_npy(&path, &[rho.size, rho.size], &rho.data.data).unwrap();
+
+        let mapped = MappedDensityMatrix::open(&path).unwrap();
+        assert_eq!(mapped.nqubits, 3);
+        assert!((mapped.trace() - rho.trace()).norm() < TOLERANCE);
+        assert!((mapped.purity() - rho.purity()).abs() < TOLERANCE);
+        assert!((mapped.entropy(&TolerancePolicy::DOUBLE) - rho.entropy(&TolerancePolicy::DOUBLE)).abs() < 1e-10);
+        for p in ["XZI", "ZXZ", "IZX", "YYZ"] {
+            let p = p.parse().unwrap();
+            assert!((mapped.expectation(&p).unwrap() - rho.expectation(&p).unwrap()).abs() < TOLERANCE);
+        }
+        assert!(mapped.to_density_matrix().equals(rho, TOLERANCE));
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_open_invalid_files() {
+        let path = std::env::temp_dir().join("dm_simu_rs_mapped_invalid.npy");
+        npy::write_npy(&path, &[2, 4], &[Complex::ZERO; 8]).unwrap();
+        assert!(MappedDensityMatrix::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(MappedDensityMatrix::open(std::env::temp_dir().join("dm_simu_rs_mapped_missing.npy")).is_err());
+
+        // Shapes whose byte length overflows are refused instead of wrapping around.
+        let header = "{'descr': '<c16', 'fortran_order': False, 'shape': (4294967296, 4294967296), }\n";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert!(MappedDensityMatrix::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests_metrics {
+    use dm_simu_rs::channels;
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::{SimulationConfig, TolerancePolicy};
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::metrics;
+    use dm_simu_rs::noise::NoiseModel;
+    use dm_simu_rs::operators::{Operator, OneQubitOp};
+    use dm_simu_rs::pauli::PauliString;
+
+    const TOLERANCE: f64 = 1e-10;
+
+    #[test]
+    fn test_identical_states() {
+        let rho = DensityMatrix::from_graph(&[(0, 1), (1, 2)], 3).unwrap();
+        let sigma = DensityMatrix::from_graph(&[(0, 1), (1, 2)], 3).unwrap();
+        assert!((metrics::fidelity(&rho, &sigma, &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < TOLERANCE);
+        assert!(metrics::trace_distance(&rho, &sigma).unwrap() < TOLERANCE);
+        assert!(metrics::hilbert_schmidt_distance(&rho, &sigma).unwrap() < TOLERANCE);
+    }
+    #[test]
+    fn test_pure_states() {
+        // |0> and |+>: F = |<0|+>|^2 = 1/2 and T = sqrt(1 - F).
+        let zero = DensityMatrix::new(1, State::ZERO);
+        let plus = DensityMatrix::new(1, State::PLUS);
+        assert!((metrics::fidelity(&zero, &plus, &TolerancePolicy::DOUBLE).unwrap() - 0.5).abs() < TOLERANCE);
+        assert!((metrics::trace_distance(&zero, &plus).unwrap() - 0.5f64.sqrt()).abs() < TOLERANCE);
+        assert!((metrics::hilbert_schmidt_distance(&zero, &plus).unwrap() - 1.).abs() < TOLERANCE);
+
+        let mut one = DensityMatrix::new(1, State::ZERO);
+        one.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        assert!(metrics::fidelity(&zero, &one, &TolerancePolicy::DOUBLE).unwrap().abs() < TOLERANCE);
+        assert!((metrics::trace_distance(&zero, &one).unwrap() - 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_depolarized_state() {
+        // (1 - p) |0><0| + p I / 2 against |0>: F = 1 - p / 2, T = p / 2.
+        let ideal = DensityMatrix::new(1, State::ZERO);
+        let mut noisy = DensityMatrix::new(1, State::ZERO);
+        noisy.apply_channel(&channels::depolarizing(0.4).unwrap(), &[0]).unwrap();
+        assert!((metrics::fidelity(&noisy, &ideal, &TolerancePolicy::DOUBLE).unwrap() - 0.8).abs() < TOLERANCE);
+        assert!((metrics::fidelity(&ideal, &noisy, &TolerancePolicy::DOUBLE).unwrap() - 0.8).abs() < TOLERANCE);
+        assert!((metrics::trace_distance(&noisy, &ideal).unwrap() - 0.2).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_size_mismatch() {
+        let rho = DensityMatrix::new(1, State::ZERO);
+        let sigma = DensityMatrix::new(2, State::ZERO);
+        assert!(metrics::fidelity(&rho, &sigma, &TolerancePolicy::DOUBLE).is_err());
+        assert!(metrics::trace_distance(&rho, &sigma).is_err());
+        assert!(metrics::hilbert_schmidt_distance(&rho, &sigma).is_err());
+    }
+    #[test]
+    fn test_fidelity_vs_ideal() {
+        let observables = ["XX", "ZZ"].map(|s| s.parse::<PauliString>().unwrap());
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        let config = SimulationConfig::default();
+        let comparison = metrics::fidelity_vs_ideal(&circuit.to_pattern(), State::ZERO, &NoiseModel::default(), 3, &observables, &config).unwrap();
+        assert!((comparison.fidelity - 1.).abs() < 1e-9);
+        assert!(comparison.observables.iter().all(|o| o.delta().abs() < 1e-9));
+
+        // Non-Clifford patterns take the state vector reference, and noise lowers the fidelity.
+        circuit.rz(1, 0.3);
+        let comparison = metrics::fidelity_vs_ideal(&circuit.to_pattern(), State::ZERO, &NoiseModel::depolarizing(0.05).unwrap(), 20, &observables, &config).unwrap();
+        assert!(comparison.fidelity < 0.99 && comparison.fidelity > 0.5);
+        assert!((comparison.observables[1].ideal - 1.).abs() < 1e-9);
+        assert!(comparison.observables[1].delta() < 0.);
+        assert!(metrics::fidelity_vs_ideal(&circuit.to_pattern(), State::ZERO, &NoiseModel::default(), 0, &observables, &config).is_err());
+    }
+}
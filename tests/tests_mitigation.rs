@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests_mitigation {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use dm_simu_rs::channels;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::mitigation;
+    use dm_simu_rs::mitigation::ReadoutModel;
+    use dm_simu_rs::noise::{Confusion, ReadoutError};
+    use dm_simu_rs::operators::{Operator, OneQubitOp, TwoQubitsOp};
+    use dm_simu_rs::pauli::PauliString;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    // Bell pair |00> + |11> (even parity) hit by bit flip noise on the first qubit.
+    fn noisy_bell_pair(p: f64) -> DensityMatrix {
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        rho.apply_channel(&channels::bit_flip(p).unwrap(), &[0]).unwrap();
+        rho
+    }
+
+    #[test]
+    fn test_project_symmetry_restores_parity() {
+        let rho = noisy_bell_pair(0.2);
+        let parity: PauliString = "ZZ".parse().unwrap();
+        let (projected, probability) = mitigation::project_symmetry(&rho, &parity, 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!((probability - 0.8).abs() < TOLERANCE);
+        assert!((projected.trace().re - 1.).abs() < TOLERANCE);
+        assert!(projected.equals(noisy_bell_pair(0.), TOLERANCE));
+    }
+    #[test]
+    fn test_symmetry_verified_expectation() {
+        let rho = noisy_bell_pair(0.2);
+        let estimate = mitigation::symmetry_verified_expectation(&rho, &"XX".parse().unwrap(), &"ZZ".parse().unwrap(), 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!((estimate.raw_value - 1.).abs() < TOLERANCE);
+        assert!((estimate.value - 1.).abs() < TOLERANCE);
+        let estimate = mitigation::symmetry_verified_expectation(&rho, &"YY".parse().unwrap(), &"ZZ".parse().unwrap(), 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!((estimate.raw_value + 0.6).abs() < TOLERANCE);
+        assert!((estimate.value + 1.).abs() < TOLERANCE);
+        assert!((estimate.discarded_fraction - 0.2).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_postselect_shots() {
+        let rho = noisy_bell_pair(0.2);
+        let mut rng = StdRng::seed_from_u64(11);
+        let estimate = mitigation::postselect_shots(&rho, &"YY".parse().unwrap(), &"ZZ".parse().unwrap(), 1, 20000, &mut rng).unwrap();
+        assert!((estimate.value + 1.).abs() < TOLERANCE);
+        assert!((estimate.raw_value + 0.6).abs() < 0.03);
+        assert!((estimate.discarded_fraction - 0.2).abs() < 0.02);
+    }
+    #[test]
+    fn test_invalid_arguments() {
+        let rho = noisy_bell_pair(0.2);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(mitigation::project_symmetry(&rho, &"ZZ".parse().unwrap(), 0, &TolerancePolicy::DOUBLE).is_err());
+        assert!(mitigation::project_symmetry(&rho, &"Z".parse().unwrap(), 1, &TolerancePolicy::DOUBLE).is_err());
+        assert!(mitigation::postselect_shots(&rho, &"XI".parse().unwrap(), &"ZZ".parse().unwrap(), 1, 10, &mut rng).is_err());
+        assert!(mitigation::project_symmetry(&noisy_bell_pair(0.), &"ZZ".parse().unwrap(), -1, &TolerancePolicy::DOUBLE).is_err());
+    }
+    #[test]
+    fn test_readout_correction() {
+        let model = ReadoutModel::new(vec![0.1, 0.05, 0.2]).unwrap();
+        let zzi: PauliString = "ZZI".parse().unwrap();
+        assert!((model.damping(&zzi).unwrap() - 0.8 * 0.9).abs() < TOLERANCE);
+        let noisy = model.noisy_expectation(0.5, &zzi).unwrap();
+        let corrected = model.correct_expectation(noisy, &zzi).unwrap();
+        assert!((corrected.value - 0.5).abs() < TOLERANCE);
+        assert!((corrected.raw_value - noisy).abs() < TOLERANCE);
+        assert!((model.damping(&"III".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_readout_correction_matches_sampled_flips() {
+        // Sample Z outcomes of |0>, flip each with probability p and correct the average.
+        let p = 0.15;
+        let model = ReadoutModel::new(vec![p]).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        let shots = 20000;
+        let measured = (0..shots).map(|_| if rng.gen::<f64>() < p { -1. } else { 1. }).sum::<f64>() / shots as f64;
+        let corrected = model.correct_expectation(measured, &"Z".parse().unwrap()).unwrap();
+        assert!((corrected.value - 1.).abs() < 0.03);
+        assert!(ReadoutModel::new(vec![0.5]).is_err());
+        assert!(model.damping(&"ZZ".parse().unwrap()).is_err());
+
+        let readout = ReadoutError::uniform(1, Confusion::symmetric(p).unwrap());
+        assert!((ReadoutModel::from_readout(&readout, 2).unwrap().damping(&"ZZ".parse().unwrap()).unwrap() - (1. - 2. * p)).abs() < TOLERANCE);
+        assert!(ReadoutModel::from_confusion(vec![Confusion::new(0.1, 0.2).unwrap()]).is_err());
+    }
+}
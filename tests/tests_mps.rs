@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests_mps {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::State;
+    use dm_simu_rs::mps::{Mps, MpsOptions};
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+    use dm_simu_rs::pauli::{Pauli, PauliString};
+    use dm_simu_rs::statevector::StateVector;
+
+    fn fidelity(a: &StateVector, b: &StateVector) -> f64 {
+        a.data.iter().zip(&b.data).map(|(x, y)| x.conj() * y).sum::<num_complex::Complex<f64>>().norm_sqr()
+    }
+
+    #[test]
+    fn test_gates_match_statevector() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = 5;
+        let mut mps = Mps::new(n, State::ZERO, MpsOptions::default());
+        let mut state = StateVector::new(n, State::ZERO);
+        for _ in 0..40 {
+            let a = rng.gen_range(0..n);
+            let b = (a + rng.gen_range(1..n)) % n;
+            let (op, targets) = match rng.gen_range(0..4) {
+                0 => (Operator::rx(rng.gen::<f64>() * 6.), vec![a]),
+                1 => (Operator::one_qubit(OneQubitOp::H), vec![a]),
+                2 => (Operator::two_qubits(TwoQubitsOp::CX), vec![a, b]),
+                _ => (Operator::two_qubits(TwoQubitsOp::CZ), vec![a, b])
+            };
+            mps.evolve(&op, &targets).unwrap();
+            state.evolve(&op, &targets).unwrap();
+        }
+        assert!((fidelity(&mps.to_statevector(), &state) - 1.).abs() < 1e-9);
+        for paulis in [[Pauli::Z, Pauli::I, Pauli::X, Pauli::Y, Pauli::Z], [Pauli::I, Pauli::Y, Pauli::I, Pauli::I, Pauli::X]] {
+            let pauli = PauliString::new(paulis.to_vec());
+            assert!((mps.expectation(&pauli).unwrap() - state.expectation(&pauli).unwrap()).abs() < 1e-9);
+        }
+        assert!(mps.truncation_error < 1e-9);
+
+        let first = mps.measure_and_remove(2, &mut StdRng::seed_from_u64(1)).unwrap();
+        let second = state.measure_and_remove(2, &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(first, second);
+        assert!((fidelity(&mps.to_statevector(), &state) - 1.).abs() < 1e-9);
+
+        assert!(mps.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 4]).is_err());
+        assert!(mps.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[1, 1]).is_err());
+    }
+
+    #[test]
+    fn test_truncation() {
+        let options = MpsOptions { max_bond: 1, ..MpsOptions::default() };
+        let mut mps = Mps::new(2, State::ZERO, options);
+        mps.evolve(&Operator::one_qubit(OneQubitOp::H), &[0]).unwrap();
+        mps.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        assert_eq!(mps.bond_dimensions(), vec![1]);
+        assert!((mps.truncation_error - 0.5).abs() < 1e-12);
+        let zz = "ZZ".parse::<PauliString>().unwrap();
+        assert!((mps.expectation(&zz).unwrap() - 1.).abs() < 1e-12);
+
+        // A Schmidt weight of 1e-18 is resolved, far below the rounding of the weights near 1.
+        let weight = 1e-18_f64;
+        for (cutoff, bond) in [(1e-24, 2), (MpsOptions::default().cutoff, 1)] {
+            let mut mps = Mps::new(2, State::ZERO, MpsOptions { cutoff, ..MpsOptions::default() });
+            mps.evolve(&Operator::rx(2. * weight.sqrt().asin()), &[0]).unwrap();
+            mps.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+            assert_eq!(mps.bond_dimensions(), vec![bond]);
+            let tail = mps.to_statevector().data[3].norm_sqr();
+            if bond == 2 {
+                assert!((tail / weight - 1.).abs() < 1e-6);
+            } else {
+                assert!((mps.truncation_error / weight - 1.).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_circuit_pattern() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 2);
+        circuit.rz(2, 0.7);
+        circuit.rx(1, 1.1);
+        circuit.cnot(1, 0);
+        let pattern = circuit.to_pattern();
+        let mut expected = StateVector::new(3, State::PLUS);
+        circuit.run(&mut expected).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        let result = pattern.simulate(Mps::new(3, State::PLUS, MpsOptions::default()), &mut rng).unwrap();
+        assert!((fidelity(&result.state.to_statevector(), &expected) - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cluster_state() {
+        // Teleportation along a linear cluster of n nodes, each measured in the XY plane at an
+        // angle, the bond dimension staying at 2.
+        let n = 300;
+        let mut pattern = Pattern::new(vec![0]);
+        for node in 1..n {
+            pattern.add(Command::N(node));
+            pattern.add(Command::E((node - 1, node)));
+            pattern.add(Command::M(node - 1, Plane::XY, 0.1, vec![], vec![], 0));
+        }
+        let mut rng = StdRng::seed_from_u64(3);
+        let result = pattern.simulate(Mps::new(1, State::PLUS, MpsOptions::default()), &mut rng).unwrap();
+        assert_eq!(result.state.nqubits(), 1);
+        assert_eq!(result.outcomes.len(), n - 1);
+
+        let mut wide = Mps::new(0, State::ZERO, MpsOptions::default());
+        let mut pattern = Pattern::new(vec![]);
+        (0..n).for_each(|node| pattern.add(Command::N(node)));
+        (0..n - 1).for_each(|node| pattern.add(Command::E((node, node + 1))));
+        wide = pattern.simulate(wide, &mut rng).unwrap().state;
+        assert!(wide.bond_dimensions().iter().all(|&d| d <= 2));
+        let mut paulis = vec![Pauli::I; n];
+        paulis[99] = Pauli::Z;
+        paulis[100] = Pauli::X;
+        paulis[101] = Pauli::Z;
+        assert!((wide.expectation(&PauliString::new(paulis)).unwrap() - 1.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,97 @@
+mod common;
+
+#[cfg(test)]
+mod tests_multi_controlled {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::State;
+    use dm_simu_rs::metrics::trace_distance;
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::statevector::StateVector;
+
+    use crate::common::random_state;
+
+    const TOLERANCE: f64 = 1e-8;
+
+    // The circuit acts as op on qubits 0..n_controls + 1 (controls first) and leaves the ancillas in |0>.
+    fn assert_matches(circuit: &Circuit, op: &Operator, n_ancillas: usize) {
+        let mut rng = StdRng::seed_from_u64(3);
+        let nqubits = circuit.width() - n_ancillas;
+        // Random state on the first nqubits qubits, with the ancillas in |0>.
+        let mut input = random_state(nqubits, &mut rng);
+        input.tensor(&StateVector::new(n_ancillas, State::ZERO));
+        let mut result = input.clone();
+        circuit.run(&mut result).unwrap();
+        let mut expected = input;
+        expected.evolve(op, &(0..nqubits).collect::<Vec<_>>()).unwrap();
+        assert!(trace_distance(&result.to_density_matrix(), &expected.to_density_matrix()).unwrap() < TOLERANCE);
+    }
+
+    #[test]
+    fn test_multi_controlled_operator() {
+        let x = Operator::one_qubit(OneQubitOp::X);
+        assert_eq!(Operator::multi_controlled(&x, 3).data.data, x.controlled(3).data.data);
+    }
+
+    #[test]
+    fn test_mcx_without_ancillas() {
+        let x = Operator::one_qubit(OneQubitOp::X);
+        for n in 0..5 {
+            let mut circuit = Circuit::new(n + 1);
+            circuit.mcx(&(0..n).collect::<Vec<_>>(), n, &[]);
+            assert_matches(&circuit, &x.controlled(n), 0);
+        }
+    }
+
+    #[test]
+    fn test_mcx_with_ancillas() {
+        let x = Operator::one_qubit(OneQubitOp::X);
+        for n in 3..6 {
+            let mut circuit = Circuit::new(2 * n - 1);
+            circuit.mcx(&(0..n).collect::<Vec<_>>(), n, &(n + 1..2 * n - 1).collect::<Vec<_>>());
+            assert_matches(&circuit, &x.controlled(n), n - 2);
+        }
+        let mut circuit = Circuit::new(6);
+        circuit.mcz(&[0, 1, 2], 3, &[4, 5]);
+        assert_matches(&circuit, &Operator::one_qubit(OneQubitOp::Z).controlled(3), 2);
+    }
+
+    #[test]
+    fn test_multi_controlled_rotations() {
+        let angle = 0.83;
+        let controls = [0, 1, 2];
+        let mut circuit = Circuit::new(4);
+        circuit.mcz(&controls, 3, &[]);
+        assert_matches(&circuit, &Operator::one_qubit(OneQubitOp::Z).controlled(3), 0);
+        let mut circuit = Circuit::new(4);
+        circuit.mcrz(&controls, 3, angle);
+        assert_matches(&circuit, &Operator::rz(angle).controlled(3), 0);
+        let mut circuit = Circuit::new(4);
+        circuit.mcrx(&controls, 3, angle);
+        assert_matches(&circuit, &Operator::rx(angle).controlled(3), 0);
+        let mut circuit = Circuit::new(4);
+        circuit.mcry(&controls, 3, angle);
+        assert_matches(&circuit, &Operator::ry(angle).controlled(3), 0);
+    }
+
+    #[test]
+    fn test_mcx_pattern() {
+        let mut circuit = Circuit::new(4);
+        circuit.mcx(&[0, 1, 2], 3, &[]);
+        let mut rng = StdRng::seed_from_u64(5);
+        let input = random_state(4, &mut rng);
+        let mut expected = input.clone();
+        circuit.run(&mut expected).unwrap();
+        let result = circuit.to_pattern().simulate(input, &mut rng).unwrap();
+        assert!(trace_distance(&result.state.to_density_matrix(), &expected.to_density_matrix()).unwrap() < TOLERANCE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mcx_rejects_shared_qubits() {
+        let mut circuit = Circuit::new(4);
+        circuit.mcx(&[0, 1, 2], 2, &[]);
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests_open_graph {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::open_graph::{GFlow, OpenGraph};
+    use dm_simu_rs::operators::Operator;
+    use dm_simu_rs::pattern::Plane;
+
+    fn planes(nodes: &[(usize, Plane)]) -> BTreeMap<usize, Plane> {
+        nodes.iter().copied().collect()
+    }
+
+    // Check the definition of a generalized flow against its layers.
+    fn assert_valid_gflow(graph: &OpenGraph, gflow: &GFlow) {
+        let before = |u: usize, v: usize| gflow.layers[&u] > gflow.layers[&v];
+        for (&u, set) in &gflow.correction {
+            let odd = graph.odd_neighborhood(set);
+            assert!(set.iter().all(|v| !graph.inputs.contains(v)));
+            assert!(set.iter().all(|&v| v == u || before(u, v)));
+            assert!(odd.iter().all(|&v| v == u || before(u, v)));
+            match graph.planes[&u] {
+                Plane::XY => assert!(!set.contains(&u) && odd.contains(&u)),
+                Plane::ZX => assert!(set.contains(&u) && odd.contains(&u)),
+                Plane::YZ => assert!(set.contains(&u) && !odd.contains(&u)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_flow_on_chain() {
+        let graph = OpenGraph::new(&[(0, 1), (1, 2)], vec![0], vec![2], planes(&[(0, Plane::XY), (1, Plane::XY)])).unwrap();
+        let flow = graph.find_flow().unwrap();
+        assert_eq!(flow.correction, BTreeMap::from([(0, 1), (1, 2)]));
+        assert_eq!(flow.layers, BTreeMap::from([(0, 2), (1, 1), (2, 0)]));
+        assert_eq!(flow.measurement_order(), vec![0, 1, 2]);
+
+        let gflow = graph.find_gflow().unwrap();
+        assert_eq!(gflow.correction[&0], BTreeSet::from([1]));
+        assert_valid_gflow(&graph, &gflow);
+    }
+
+    #[test]
+    fn test_gflow_without_flow() {
+        let edges = [(0, 2), (0, 3), (0, 4), (1, 3), (1, 4), (2, 3)];
+        let graph = OpenGraph::new(&edges, vec![0, 1], vec![3, 4], planes(&[(0, Plane::XY), (1, Plane::XY), (2, Plane::XY)])).unwrap();
+        assert!(graph.find_flow().is_err());
+        let gflow = graph.find_gflow().unwrap();
+        assert_valid_gflow(&graph, &gflow);
+        assert_eq!(gflow.correction[&2], BTreeSet::from([3, 4]));
+
+        // The generated pattern gives the same output whatever the measurement outcomes.
+        let pattern = graph.to_pattern(&BTreeMap::from([(0, 0.3), (1, -0.7), (2, 0.45)])).unwrap();
+        assert_eq!(pattern.output_nodes(), &[3, 4]);
+        let mut input = DensityMatrix::new(2, State::ZERO);
+        input.evolve_single(&Operator::ry(0.4), 0).unwrap();
+        input.evolve_single(&Operator::rx(1.1), 1).unwrap();
+        let expected = pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(0)).unwrap().state;
+        for seed in 1..16 {
+            let result = pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+    }
+
+    #[test]
+    fn test_gflow_with_planes() {
+        // A YZ node needs no neighbor to be corrected, unlike the XY node 1.
+        let graph = OpenGraph::new(&[(0, 1), (1, 2)], vec![], vec![2], planes(&[(0, Plane::YZ), (1, Plane::XY)])).unwrap();
+        assert!(graph.find_flow().is_err());
+        let gflow = graph.find_gflow().unwrap();
+        assert_valid_gflow(&graph, &gflow);
+        assert!(graph.to_pattern(&BTreeMap::from([(0, 0.25)])).is_err());
+
+        let graph = OpenGraph::new(&[(0, 1), (1, 2)], vec![0], vec![2], planes(&[(0, Plane::ZX), (1, Plane::XY)])).unwrap();
+        assert!(graph.find_gflow().is_err());
+    }
+
+    #[test]
+    fn test_from_pattern() {
+        let graph = OpenGraph::new(&[(0, 1), (0, 2), (1, 2)], vec![0], vec![1, 2], planes(&[(0, Plane::XY)])).unwrap();
+        let pattern = graph.to_pattern(&BTreeMap::from([(0, 0.5)])).unwrap();
+        assert_eq!(OpenGraph::from_pattern(&pattern).unwrap(), graph);
+        assert!(OpenGraph::new(&[(0, 0)], vec![], vec![0], BTreeMap::new()).is_err());
+        assert!(OpenGraph::new(&[(0, 1)], vec![], vec![1], BTreeMap::new()).is_err());
+    }
+}
@@ -1,22 +1,39 @@
 #[cfg(test)]
 mod tests_operators {
-    use std::f64::consts::FRAC_1_SQRT_2;
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
-    use dm_simu_rs::operators::{Operator, OneQubitOp, TwoQubitsOp};
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{Basis, DensityMatrix, State};
+    use dm_simu_rs::operators::{MatrixLayout, Operator, OneQubitOp, ThreeQubitsOp, TwoQubitsOp};
     use num_complex::Complex;
-    use dm_simu_rs::tools::complex_approx_eq;
 
     #[test]
     fn test_operator_h() {
         let h_gate = Operator::one_qubit(OneQubitOp::H);
+        // The last entry is -1/sqrt(2): H|1> = |->. It used to be +1/sqrt(2), which is not unitary.
         let expected = vec![
             Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(FRAC_1_SQRT_2, 0.),
-            Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(FRAC_1_SQRT_2, 0.)
+            Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(-FRAC_1_SQRT_2, 0.)
         ];
         assert_eq!(h_gate.data.shape, vec![2, 2]);
         assert_eq!(h_gate.data.data, expected);
     }
     #[test]
+    fn test_operator_h_is_unitary() {
+        // The matrix with four +1/sqrt(2) entries, which the old expectations described, is singular.
+        let h_gate = Operator::one_qubit(OneQubitOp::H);
+        assert!(h_gate.is_unitary(1e-12));
+        assert!(!Operator::from_matrix(&[Complex::new(FRAC_1_SQRT_2, 0.); 4], 1).unwrap().is_unitary(1e-12));
+        let mut rho = DensityMatrix::new(1, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        rho.evolve_single(&h_gate, 0).unwrap();
+        let x = rho.outcome_distribution(&[0], Basis::X).unwrap();
+        assert!(x[0].abs() < 1e-12 && (x[1] - 1.).abs() < 1e-12);
+        rho.evolve_single(&h_gate, 0).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        assert!(rho.equals(DensityMatrix::new(1, State::ZERO), 1e-12));
+    }
+    #[test]
     fn test_operator_x() {
         let x_gate = Operator::one_qubit(OneQubitOp::X);
         let expected = vec![
@@ -119,9 +136,10 @@ mod tests_operators {
     fn test_transconjugate_h() {
         let mut h = Operator::one_qubit(OneQubitOp::H);
         h = h.transconj();
+        // H is Hermitian, so it is its own adjoint, -1/sqrt(2) entry included.
         let expected = vec![
             Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(FRAC_1_SQRT_2, 0.),
-            Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(FRAC_1_SQRT_2, 0.)
+            Complex::new(FRAC_1_SQRT_2, 0.), Complex::new(-FRAC_1_SQRT_2, 0.)
         ];
         assert_eq!(h.data.shape, vec![2, 2]);
         assert_eq!(h.data.data, expected);
@@ -141,4 +159,178 @@ mod tests_operators {
         assert_eq!(u.data.shape, vec![2, 2]);
         assert_eq!(u.data.data, expected);
     }
-}
\ No newline at end of file
+    fn assert_op_eq(op: &Operator, expected: &[Complex<f64>]) {
+        assert_eq!(op.data.data.len(), expected.len());
+        for (a, b) in op.data.data.iter().zip(expected) {
+            assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+        }
+    }
+    #[test]
+    fn test_rotations_at_zero_are_identity() {
+        let id = [Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ONE];
+        assert_op_eq(&Operator::rx(0.), &id);
+        assert_op_eq(&Operator::ry(0.), &id);
+        assert_op_eq(&Operator::rz(0.), &id);
+    }
+    #[test]
+    fn test_rotations_at_pi() {
+        let i = Complex::I;
+        assert_op_eq(&Operator::rx(PI), &[Complex::ZERO, -i, -i, Complex::ZERO]);
+        assert_op_eq(&Operator::ry(PI), &[Complex::ZERO, -Complex::ONE, Complex::ONE, Complex::ZERO]);
+        assert_op_eq(&Operator::rz(PI), &[-i, Complex::ZERO, Complex::ZERO, i]);
+    }
+    #[test]
+    fn test_rx_half_pi() {
+        let c = Complex::new(FRAC_1_SQRT_2, 0.);
+        let s = Complex::new(0., -FRAC_1_SQRT_2);
+        assert_op_eq(&Operator::rx(PI / 2.), &[c, s, s, c]);
+    }
+    #[test]
+    fn test_rotations_are_unitary() {
+        for theta in [0.3, 1.2, -2.5] {
+            for op in [Operator::rx(theta), Operator::ry(theta), Operator::rz(theta)] {
+                let dag = op.transconj();
+                let mut product: [Complex<f64>; 4] = [Complex::ZERO; 4];
+                for r in 0..2 {
+                    for c in 0..2 {
+                        for k in 0..2 {
+                            product[r * 2 + c] += op.data.data[r * 2 + k] * dag.data.data[k * 2 + c];
+                        }
+                    }
+                }
+                for (idx, value) in product.iter().enumerate() {
+                    let expected = if idx == 0 || idx == 3 { Complex::ONE } else { Complex::ZERO };
+                    assert!((value - expected).norm() < 1e-12);
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_sqrt_x_squared() {
+        let x = Operator::one_qubit(OneQubitOp::X);
+        let sqrt_x = x.sqrt(&TolerancePolicy::DOUBLE).unwrap();
+        let half = Complex::new(0.5, 0.);
+        let i_half = Complex::new(0., 0.5);
+        assert_op_eq(&sqrt_x, &[half + i_half, half - i_half, half - i_half, half + i_half]);
+        let squared = Operator::new(dm_simu_rs::linalg::matmul(&sqrt_x.data.data, &sqrt_x.data.data, 2)).unwrap();
+        assert_op_eq(&squared, &x.data.data);
+    }
+    #[test]
+    fn test_sqrt_cz() {
+        let sqrt_cz = Operator::two_qubits(TwoQubitsOp::CZ).sqrt(&TolerancePolicy::DOUBLE).unwrap();
+        let mut expected = vec![Complex::ZERO; 16];
+        expected[0] = Complex::ONE;
+        expected[5] = Complex::ONE;
+        expected[10] = Complex::ONE;
+        expected[15] = Complex::I;
+        assert_op_eq(&sqrt_cz, &expected);
+        assert_eq!(sqrt_cz.data.shape, vec![2, 2, 2, 2]);
+    }
+    #[test]
+    fn test_powf_swap() {
+        let swap = Operator::two_qubits(TwoQubitsOp::SWAP);
+        let root = swap.powf(0.5, &TolerancePolicy::DOUBLE).unwrap();
+        assert!(root.is_unitary(1e-10));
+        let squared = dm_simu_rs::linalg::matmul(&root.data.data, &root.data.data, 4);
+        assert_op_eq(&Operator::new(squared).unwrap(), &swap.data.data);
+        let id = swap.powf(0., &TolerancePolicy::DOUBLE).unwrap();
+        assert_op_eq(&id, &dm_simu_rs::linalg::identity(4));
+    }
+    #[test]
+    fn test_powf_rotation_interpolates_angle() {
+        let rz = Operator::rz(1.2);
+        assert_op_eq(&rz.powf(0.25, &TolerancePolicy::DOUBLE).unwrap(), &Operator::rz(0.3).data.data);
+    }
+    #[test]
+    fn test_powf_non_unitary() {
+        let op = Operator::new(vec![Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO]).unwrap();
+        assert!(!op.is_unitary(1e-10));
+        assert!(op.sqrt(&TolerancePolicy::DOUBLE).is_err());
+    }
+    #[test]
+    fn test_from_matrix() {
+        let s = [Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::I];
+        let op = Operator::from_unitary(&s, 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert_eq!(op.data.shape, vec![2, 2]);
+        assert_op_eq(&op.powf(2., &TolerancePolicy::DOUBLE).unwrap(), &Operator::one_qubit(OneQubitOp::Z).data.data);
+
+        // Three qubit CCZ applied on qubits (3, 0, 1), leaving qubit 2 untouched.
+        let mut ccz = vec![Complex::ZERO; 64];
+        for i in 0..8 {
+            ccz[i * 8 + i] = if i == 7 { -Complex::ONE } else { Complex::ONE };
+        }
+        let op = Operator::from_unitary(&ccz, 3, &TolerancePolicy::DOUBLE).unwrap();
+        let mut rho = DensityMatrix::new(4, State::PLUS);
+        rho.evolve(&op, &[3, 0, 1]).unwrap();
+        // <X> on a CCZ target of |+++> is 1 - 2 * P(controls = 11) = 1 / 2.
+        assert!((rho.expectation(&"IIIX".parse().unwrap()).unwrap() - 0.5).abs() < 1e-12);
+        assert!((rho.expectation(&"IIXI".parse().unwrap()).unwrap() - 1.).abs() < 1e-12);
+        assert!((rho.trace().re - 1.).abs() < 1e-12);
+    }
+    #[test]
+    fn test_matrix_layouts() {
+        // |0><1|, which lowers |1> to |0>.
+        let row_major = [Complex::ZERO, Complex::ONE, Complex::ZERO, Complex::ZERO];
+        let column_major = [Complex::ZERO, Complex::ZERO, Complex::ONE, Complex::ZERO];
+        let op = Operator::from_matrix_layout(&column_major, 1, MatrixLayout::ColumnMajor).unwrap();
+        assert_eq!(op.data.data, row_major);
+        assert_eq!(op.to_matrix(MatrixLayout::RowMajor), row_major);
+        assert_eq!(op.to_matrix(MatrixLayout::ColumnMajor), column_major);
+        let mut rho = DensityMatrix::new(1, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        rho.sandwich(&op, &[0]).unwrap();
+        assert_eq!(rho.data.data[0], Complex::ONE);
+    }
+    #[test]
+    fn test_from_matrix_invalid() {
+        assert!(Operator::from_matrix(&[Complex::ONE; 3], 1).is_err());
+        assert!(Operator::from_matrix(&[Complex::ONE; 4], 2).is_err());
+        assert!(Operator::from_matrix(&[Complex::ONE; 4], 1).is_ok());
+        assert!(Operator::from_unitary(&[Complex::ONE; 4], 1, &TolerancePolicy::DOUBLE).is_err());
+    }
+    #[test]
+    fn test_controlled_matches_native_gates() {
+        let cx = Operator::one_qubit(OneQubitOp::X).controlled(1);
+        assert_op_eq(&cx, &Operator::two_qubits(TwoQubitsOp::CX).data.data);
+        let cz = Operator::one_qubit(OneQubitOp::Z).controlled(1);
+        assert_op_eq(&cz, &Operator::two_qubits(TwoQubitsOp::CZ).data.data);
+        assert_op_eq(&Operator::two_qubits(TwoQubitsOp::CZ).controlled(1), &Operator::three_qubits(ThreeQubitsOp::CCZ).data.data);
+        assert_op_eq(&Operator::one_qubit(OneQubitOp::X).controlled(0), &Operator::one_qubit(OneQubitOp::X).data.data);
+        assert!(Operator::rx(0.4).controlled(3).is_unitary(1e-12));
+    }
+    #[test]
+    fn test_toffoli_truth_table() {
+        let ccx = Operator::three_qubits(ThreeQubitsOp::CCX);
+        assert_eq!(ccx.nqubits, 3);
+        for input in 0..8usize {
+            let output = if input >> 1 == 3 { input ^ 1 } else { input };
+            for row in 0..8 {
+                let expected = if row == output { Complex::ONE } else { Complex::ZERO };
+                assert_eq!(ccx.data.data[row * 8 + input], expected);
+            }
+        }
+        // Controls on qubits 2 and 0 of |101> flip the target qubit 1.
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::X), 2).unwrap();
+        rho.evolve(&ccx, &[2, 0, 1]).unwrap();
+        assert!((rho.expectation(&"ZZZ".parse().unwrap()).unwrap() + 1.).abs() < 1e-12);
+        assert!((rho.expectation(&"IZI".parse().unwrap()).unwrap() + 1.).abs() < 1e-12);
+    }
+    #[test]
+    fn test_phase_gates() {
+        let s = Operator::one_qubit(OneQubitOp::S);
+        assert_op_eq(&s, &[Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::I]);
+        let t = Operator::one_qubit(OneQubitOp::T);
+        assert_op_eq(&t.powf(2., &TolerancePolicy::DOUBLE).unwrap(), &s.data.data);
+        assert_op_eq(&s.powf(2., &TolerancePolicy::DOUBLE).unwrap(), &Operator::one_qubit(OneQubitOp::Z).data.data);
+        assert_op_eq(&Operator::one_qubit(OneQubitOp::SDG), &s.transconj().data.data);
+        assert_op_eq(&Operator::one_qubit(OneQubitOp::TDG), &t.transconj().data.data);
+        assert_op_eq(&Operator::phase(PI), &Operator::one_qubit(OneQubitOp::Z).data.data);
+
+        // S maps |+> to |+i>.
+        let mut rho = DensityMatrix::new(1, State::PLUS);
+        rho.evolve_single(&s, 0).unwrap();
+        assert!((rho.expectation(&"Y".parse().unwrap()).unwrap() - 1.).abs() < 1e-12);
+    }
+}
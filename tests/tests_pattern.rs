@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests_pattern {
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+
+    // Pattern of a Hadamard gate from input 0 to output 1, as exported by graphix.
+    const GRAPHIX_H: &str = r#"{
+        "input_nodes": [0],
+        "output_nodes": [1],
+        "Nnode": 2,
+        "seq": [["N", 1], ["E", [0, 1]], ["M", 0, "XY", 0.0, [], [], 0], ["X", 1, [0]]]
+    }"#;
+
+    #[test]
+    fn test_from_json() {
+        let pattern = Pattern::from_json(GRAPHIX_H).unwrap();
+        assert_eq!(pattern.input_nodes(), &[0]);
+        assert_eq!(pattern.output_nodes(), &[1]);
+        assert_eq!(pattern.n_nodes(), 2);
+        assert_eq!(pattern.seq(), &[
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+            Command::X(1, vec![0]),
+        ]);
+    }
+    #[test]
+    fn test_json_roundtrip() {
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.add(Command::N(1));
+        pattern.add(Command::N(2));
+        pattern.add(Command::E((0, 1)));
+        pattern.add(Command::E((1, 2)));
+        pattern.add(Command::M(0, Plane::ZX, 0.25, vec![], vec![], 0));
+        pattern.add(Command::M(1, Plane::YZ, -0.5, vec![0], vec![], 2));
+        pattern.add(Command::C(2, 6));
+        pattern.add(Command::Z(2, vec![0]));
+        pattern.add(Command::S(2, vec![1]));
+        pattern.add(Command::T);
+        let json = pattern.to_json();
+        assert!(json.contains(r#"["M",0,"XZ",0.25,[],[],0]"#));
+        assert_eq!(Pattern::from_json(&json).unwrap(), pattern);
+    }
+    #[test]
+    fn test_from_json_without_vop() {
+        let json = r#"{"input_nodes": [0], "seq": [["N", 1], ["M", 1, "YZ", 0.5, [0], []]]}"#;
+        let pattern = Pattern::from_json(json).unwrap();
+        assert_eq!(pattern.seq()[1], Command::M(1, Plane::YZ, 0.5, vec![0], vec![], 0));
+        assert_eq!(pattern.output_nodes(), &[0]);
+    }
+    #[test]
+    fn test_from_json_invalid() {
+        assert!(Pattern::from_json("not json").is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0]}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "seq": [["Q", 0]]}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "seq": [["M", 0, "AB", 0.0, [], []]]}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "seq": [["M", 1, "XY", 0.0, [], []]]}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "seq": [["N", 0]]}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "output_nodes": [1], "seq": []}"#).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [0], "seq": [["E", [0]]]}"#).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests_pattern_library {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use dm_simu_rs::open_graph::OpenGraph;
+    use dm_simu_rs::pattern::Plane;
+    use dm_simu_rs::pattern_library::PatternLibrary;
+
+    fn library(name: &str) -> (PatternLibrary, PathBuf) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        (PatternLibrary::open(&dir).unwrap(), dir)
+    }
+
+    // Two XY measurements along a chain, e.g. the J(0.25) J(0) gadget.
+    fn chain(edges: &[(usize, usize)]) -> OpenGraph {
+        OpenGraph::new(edges, vec![0], vec![2], BTreeMap::from([(0, Plane::XY), (1, Plane::XY)])).unwrap()
+    }
+
+    fn entries(dir: &PathBuf) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn test_miss_and_hit() {
+        let (library, dir) = library("dm_simu_rs_library_hit");
+        let graph = chain(&[(0, 1), (1, 2)]);
+        let angles = BTreeMap::from([(0, 0.25), (1, 0.)]);
+        assert_eq!(library.get(&graph, &angles).unwrap(), None);
+
+        // A miss compiles the pattern and stores its JSON under the key.
+        let pattern = library.get_or_compile(&graph, &angles).unwrap();
+        assert_eq!(pattern, graph.to_pattern(&angles).unwrap());
+        let path = library.path(&PatternLibrary::key(&graph, &angles).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), pattern.to_json());
+        assert_eq!(entries(&dir), 1);
+
+        // A hit loads the stored entry instead of compiling: replacing it shows in the result.
+        let stored = graph.to_pattern(&BTreeMap::from([(0, 0.5), (1, 0.)])).unwrap();
+        fs::write(&path, stored.to_json()).unwrap();
+        assert_eq!(library.get_or_compile(&graph, &angles).unwrap(), stored);
+
+        assert!(library.get_or_compile(&graph, &BTreeMap::from([(0, 0.25)])).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_deduplication() {
+        let (library, dir) = library("dm_simu_rs_library_dedup");
+        let angles = BTreeMap::from([(0, 0.25), (1, 0.)]);
+        // The same sub-pattern built twice, with its edges listed differently and a -0 angle.
+        let a = chain(&[(0, 1), (1, 2)]);
+        let b = chain(&[(2, 1), (1, 0)]);
+        let key = PatternLibrary::key(&a, &angles).unwrap();
+        assert_eq!(PatternLibrary::key(&b, &BTreeMap::from([(0, 0.25), (1, -0.)])).unwrap(), key);
+        // Angles of unmeasured nodes do not matter.
+        assert_eq!(PatternLibrary::key(&a, &BTreeMap::from([(0, 0.25), (1, 0.), (2, 1.)])).unwrap(), key);
+
+        for _ in 0..3 {
+            library.get_or_compile(&a, &angles).unwrap();
+            library.get_or_compile(&b, &angles).unwrap();
+        }
+        assert_eq!(entries(&dir), 1);
+
+        // Other angles or another graph are other entries.
+        library.get_or_compile(&a, &BTreeMap::from([(0, 0.5), (1, 0.)])).unwrap();
+        let c = OpenGraph::new(&[(0, 1)], vec![0], vec![1], BTreeMap::from([(0, Plane::XY)])).unwrap();
+        assert_ne!(PatternLibrary::key(&c, &angles).unwrap(), key);
+        library.get_or_compile(&c, &angles).unwrap();
+        assert_eq!(entries(&dir), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_corrupted_entry() {
+        let (library, dir) = library("dm_simu_rs_library_corrupted");
+        let graph = chain(&[(0, 1), (1, 2)]);
+        let angles = BTreeMap::from([(0, 0.25), (1, 0.)]);
+        let expected = graph.to_pattern(&angles).unwrap();
+        let path = library.path(&PatternLibrary::key(&graph, &angles).unwrap());
+
+        // Truncated JSON and a pattern measuring an unprepared node are both refused by get.
+        for corrupted in [&expected.to_json()[..20], r#"{"input_nodes": [0], "seq": [["M", 5, "XY", 0.0, [], [], 0]]}"#] {
+            fs::write(&path, corrupted).unwrap();
+            assert!(library.get(&graph, &angles).is_err());
+            // get_or_compile rebuilds the entry and writes it again.
+            assert_eq!(library.get_or_compile(&graph, &angles).unwrap(), expected);
+            assert_eq!(library.get(&graph, &angles).unwrap(), Some(expected.clone()));
+        }
+        assert_eq!(entries(&dir), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests_pauli {
+    use num_complex::Complex;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::operators::{Operator, OneQubitOp, TwoQubitsOp};
+    use dm_simu_rs::pauli::{Pauli, PauliString, SparsePauliOp};
+    use dm_simu_rs::linalg;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn test_parse_pauli_string() {
+        let p: PauliString = "XZIY".parse().unwrap();
+        assert_eq!(p.paulis, vec![Pauli::X, Pauli::Z, Pauli::I, Pauli::Y]);
+        assert_eq!(p.to_string(), "XZIY");
+        assert_eq!("xz".parse::<PauliString>().unwrap().paulis, vec![Pauli::X, Pauli::Z]);
+    }
+    #[test]
+    fn test_parse_invalid_pauli_string() {
+        assert!("XA".parse::<PauliString>().is_err());
+        assert!("".parse::<PauliString>().is_err());
+    }
+    #[test]
+    fn test_expectation_product_states() {
+        let zero = DensityMatrix::new(2, State::ZERO);
+        assert!((zero.expectation(&"ZZ".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((zero.expectation(&"ZI".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        assert!(zero.expectation(&"XI".parse().unwrap()).unwrap().abs() < TOLERANCE);
+
+        let plus = DensityMatrix::new(2, State::PLUS);
+        assert!((plus.expectation(&"XX".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        assert!(plus.expectation(&"ZX".parse().unwrap()).unwrap().abs() < TOLERANCE);
+        assert!(plus.expectation(&"YI".parse().unwrap()).unwrap().abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_expectation_single_qubit_order() {
+        // |01>: qubit 0 in |0>, qubit 1 in |1>.
+        let rho = DensityMatrix::from_statevec(&[Complex::ZERO, Complex::ONE, Complex::ZERO, Complex::ZERO]).unwrap();
+        assert!((rho.expectation(&"ZI".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        assert!((rho.expectation(&"IZ".parse().unwrap()).unwrap() + 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_expectation_y_eigenstate() {
+        let s = 1. / 2_f64.sqrt();
+        let rho = DensityMatrix::from_statevec(&[Complex::new(s, 0.), Complex::new(0., s)]).unwrap();
+        assert!((rho.expectation(&"Y".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_expectation_graph_state_stabilizers() {
+        let mut rho = DensityMatrix::new(3, State::PLUS);
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]).unwrap();
+        rho.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[1, 2]).unwrap();
+        for stabilizer in ["XZI", "ZXZ", "IZX", "YYZ"] {
+            let value = rho.expectation(&stabilizer.parse().unwrap()).unwrap();
+            assert!((value - 1.).abs() < TOLERANCE, "{} -> {}", stabilizer, value);
+        }
+        rho.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0).unwrap();
+        assert!((rho.expectation(&"XZI".parse().unwrap()).unwrap() + 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_expectation_wrong_size() {
+        let rho = DensityMatrix::new(2, State::ZERO);
+        assert!(rho.expectation(&"Z".parse().unwrap()).is_err());
+    }
+    #[test]
+    fn test_sparse_pauli_op_algebra() {
+        let xy: SparsePauliOp = "XY".parse().unwrap();
+        let yz: SparsePauliOp = "-YZ".parse().unwrap();
+        let product = xy.product(&yz).unwrap();
+        // -(XY)(YZ) = -(iZ)(iX) = ZX.
+        assert_eq!(product.to_string(), "ZX");
+        assert!(!xy.commutes_with(&"ZI".parse().unwrap()));
+        assert!(xy.commutes_with(&"ZZ".parse().unwrap()));
+        assert_eq!("-iXIZ".parse::<SparsePauliOp>().unwrap().to_string(), "-iXIZ");
+        assert_eq!("IXIZ".parse::<SparsePauliOp>().unwrap().weight(), 2);
+        assert!(xy.product(&"X".parse().unwrap()).is_err());
+
+        // Qubits past the first 64 bit word.
+        let mut far = SparsePauliOp::single(70, 66, Pauli::X).unwrap();
+        far.set(3, Pauli::Z).unwrap();
+        let other = SparsePauliOp::single(70, 66, Pauli::Z).unwrap();
+        assert!(!far.commutes_with(&other));
+        let square = far.product(&far).unwrap();
+        assert!(square.is_identity() && square.phase == 0);
+        assert_eq!(far.product(&other).unwrap().pauli(66), Pauli::Y);
+    }
+    #[test]
+    fn test_sparse_pauli_op_matches_dense_product() {
+        let labels = ["XYZ", "iZZX", "-YIY", "-iXXI", "IZY"];
+        for a in labels {
+            for b in labels {
+                let (pa, pb) = (a.parse::<SparsePauliOp>().unwrap(), b.parse::<SparsePauliOp>().unwrap());
+                let dense = linalg::matmul(&pa.to_operator().data.data, &pb.to_operator().data.data, 8);
+                let sparse = pa.product(&pb).unwrap().to_operator().data.data;
+                assert!(linalg::max_abs_diff(&dense, &sparse) < TOLERANCE);
+                let commutator = linalg::matmul(&pb.to_operator().data.data, &pa.to_operator().data.data, 8);
+                assert_eq!(pa.commutes_with(&pb), linalg::max_abs_diff(&dense, &commutator) < TOLERANCE);
+            }
+        }
+    }
+}
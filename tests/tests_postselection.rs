@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests_postselection {
+    use dm_simu_rs::postselection;
+    use dm_simu_rs::prelude::*;
+
+    // Measuring node 0 of an edge along X without correcting node 1 leaves it in |s0>.
+    fn uncorrected() -> Pattern {
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(vec![
+            Command::N(0),
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0)
+        ]);
+        pattern
+    }
+
+    #[test]
+    fn test_conditional_expectation() {
+        let result = Simulator::new(DensityMatrix::new(0, State::ZERO)).load(uncorrected()).with_seed(3).run(40).unwrap();
+        let z = "Z".parse::<PauliString>().unwrap();
+        let zero = result.conditional_expectation(&[(0, 0)], &z, 5).unwrap();
+        let one = result.conditional_expectation(&[(0, 1)], &z, 5).unwrap();
+        assert!((zero.mean - 1.).abs() < 1e-12 && (one.mean + 1.).abs() < 1e-12);
+        assert_eq!(zero.shots + one.shots, 40);
+        assert!(zero.standard_error < 1e-12 && zero.warning.is_none());
+        // Without post-selection the outcomes average out.
+        assert!(result.conditional_expectation(&[], &z, 5).unwrap().mean.abs() < 0.5);
+
+        // Small bins are estimated with a warning, empty ones and unmeasured nodes are errors.
+        let small = result.conditional_expectation(&[(0, 1)], &z, 40).unwrap();
+        assert_eq!(small.mean, one.mean);
+        assert!(small.warning.unwrap().contains(&format!("Only {} of 40 shots", one.shots)));
+        assert!(result.conditional_expectation(&[(1, 0)], &z, 5).is_err());
+        let single = Simulator::new(DensityMatrix::new(0, State::ZERO)).load(uncorrected()).run(1).unwrap();
+        let outcome = single.shots[0].outcomes[&0];
+        assert!(single.conditional_expectation(&[(0, 1 - outcome)], &z, 1).is_err());
+        assert_eq!(single.conditional_expectation(&[(0, outcome)], &z, 1).unwrap().standard_error, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_conditional_correlator() {
+        let nodes = vec![3, 5, 8];
+        let shots = vec![vec![0, 0, 1], vec![1, 1, 0], vec![1, 0, 1], vec![1, 1, 1], vec![0, 1, 1]];
+        let estimate = postselection::records_correlator(&nodes, &shots, &[(3, 1)], &[5, 8], 2).unwrap();
+        // Parities 1, 1 and 0 among the three shots with s[3] = 1.
+        assert_eq!(estimate.shots, 3);
+        assert!((estimate.mean + 1. / 3.).abs() < 1e-12);
+        assert!(estimate.warning.is_none());
+        assert!(postselection::records_correlator(&nodes, &shots, &[(3, 1), (8, 0)], &[5], 2).unwrap().warning.is_some());
+        assert!(postselection::records_correlator(&nodes, &shots, &[(4, 1)], &[5], 2).is_err());
+        assert!(postselection::records_correlator(&nodes, &[vec![0, 1]], &[], &[5], 2).is_err());
+
+        // Simulated shots and their records agree.
+        let result = Simulator::new(DensityMatrix::new(0, State::ZERO)).load(uncorrected()).with_seed(5).run(30).unwrap();
+        let shots = result.shots.iter().map(|shot| vec![shot.outcomes[&0]]).collect::<Vec<_>>();
+        assert_eq!(result.conditional_correlator(&[], &[0], 1).unwrap(), postselection::records_correlator(&[0], &shots, &[], &[0], 1).unwrap());
+        assert_eq!(result.conditional_correlator(&[(0, 1)], &[0], 1).unwrap().mean, -1.);
+    }
+}
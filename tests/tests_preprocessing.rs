@@ -0,0 +1,97 @@
+mod common;
+
+#[cfg(test)]
+mod tests_preprocessing {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::clifford::Clifford;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::pattern::{Command, Pattern};
+    use dm_simu_rs::pauli::Pauli;
+
+    use crate::common::random_state;
+
+    fn assert_same_output(a: &Pattern, b: &Pattern, nqubits: usize, rng: &mut StdRng) {
+        for _ in 0..3 {
+            let input = random_state(nqubits, rng);
+            let expected = a.simulate(input.clone(), rng).unwrap().state.to_density_matrix();
+            let result = b.simulate(input, rng).unwrap().state.to_density_matrix();
+            assert!(result.approx_eq(&expected, &TolerancePolicy { equality: 1e-8, ..TolerancePolicy::DOUBLE }));
+        }
+    }
+
+    fn measurements(pattern: &Pattern) -> usize {
+        pattern.seq().iter().filter(|c| matches!(c, Command::M(..))).count()
+    }
+
+    #[test]
+    fn test_clifford_table() {
+        let h = Clifford::new(6).unwrap();
+        assert_eq!(h.conjugate(Pauli::X), (1., Pauli::Z));
+        assert_eq!(Clifford::new(4).unwrap().conjugate(Pauli::X), (-1., Pauli::Y));
+        assert_eq!(h.then(&h), Clifford::identity());
+        for i in 0..Clifford::COUNT {
+            let c = Clifford::new(i).unwrap();
+            assert_eq!(c.then(&c.adjoint()), Clifford::identity());
+        }
+        assert!(Clifford::new(Clifford::COUNT).is_err());
+    }
+
+    #[test]
+    fn test_standardize() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.ry(1, 0.4);
+        circuit.rzz(0, 1, 1.2);
+        let pattern = circuit.to_pattern();
+        let standard = pattern.standardize().unwrap();
+        assert_eq!(standard.output_nodes(), pattern.output_nodes());
+        // N, E, M and then the corrections.
+        let rank = |c: &Command| match c { Command::N(_) => 0, Command::E(_) => 1, Command::M(..) => 2, _ => 3 };
+        assert!(standard.seq().windows(2).all(|w| rank(&w[0]) <= rank(&w[1])));
+        assert_same_output(&pattern, &standard, 2, &mut rng);
+    }
+
+    #[test]
+    fn test_pauli_measurements() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.s(2);
+        circuit.y(1);
+        circuit.rx(2, 0.7);
+        circuit.cnot(2, 0);
+        circuit.rz(1, -0.3);
+        circuit.swap(0, 2);
+        circuit.x(0);
+        circuit.ry(0, 1.1);
+        let pattern = circuit.to_pattern();
+        for _ in 0..4 {
+            let reduced = pattern.perform_pauli_measurements(&TolerancePolicy::DOUBLE, &mut rng).unwrap();
+            assert!(measurements(&reduced) < measurements(&pattern));
+            assert_eq!(reduced.input_nodes(), pattern.input_nodes());
+            assert_eq!(reduced.output_nodes(), pattern.output_nodes());
+            assert_same_output(&pattern, &reduced, 3, &mut rng);
+        }
+    }
+
+    #[test]
+    fn test_clifford_circuit_leaves_no_measurement() {
+        // Only the input nodes, which are never simulated ahead, stay measured.
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.s(1);
+        circuit.z(0);
+        let pattern = circuit.to_pattern();
+        let reduced = pattern.perform_pauli_measurements(&TolerancePolicy::DOUBLE, &mut rng).unwrap();
+        assert!(measurements(&reduced) <= 2);
+        assert_same_output(&pattern, &reduced, 2, &mut rng);
+    }
+}
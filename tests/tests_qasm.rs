@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests_qasm {
+    use std::f64::consts::PI;
+
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+    use dm_simu_rs::statevector::StateVector;
+
+    const TOLERANCE: f64 = 1e-10;
+
+    // Generic product state, so that every gate acts non-trivially.
+    fn input(nqubits: usize) -> StateVector {
+        let mut state = StateVector::new(nqubits, State::ZERO);
+        for q in 0..nqubits {
+            state.evolve_single(&Operator::ry(0.4 + 0.5 * q as f64), q).unwrap();
+            state.evolve_single(&Operator::rz(1.1 - 0.3 * q as f64), q).unwrap();
+        }
+        state
+    }
+
+    // |<expected|actual>|^2 between the outputs of the QASM source and of the operator on qubits.
+    fn overlap(source: &str, op: &Operator, qubits: &[usize]) -> f64 {
+        let circuit = Circuit::from_qasm(source).unwrap();
+        let mut actual = input(circuit.width());
+        circuit.run(&mut actual).unwrap();
+        let mut expected = input(circuit.width());
+        expected.evolve(op, qubits).unwrap();
+        expected.data.iter().zip(actual.data.iter()).map(|(e, a)| e.conj() * a).sum::<Complex<f64>>().norm_sqr()
+    }
+
+    fn program(body: &str) -> String {
+        format!("OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\ncreg c[2];\n{}", body)
+    }
+
+    #[test]
+    fn test_standard_gates() {
+        let one = |gate| Operator::one_qubit(gate);
+        let cases = [
+            ("h q[1];", one(OneQubitOp::H), vec![1]),
+            ("sdg q[0];", one(OneQubitOp::SDG), vec![0]),
+            ("tdg q[0];", one(OneQubitOp::TDG), vec![0]),
+            ("u3(0.3, -pi/4, 2*0.5) q[1];", rz_ry_rz(0.3, -PI / 4., 1.), vec![1]),
+            ("cx q[1], q[0];", Operator::two_qubits(TwoQubitsOp::CX), vec![1, 0]),
+            ("cz q[0],q[1];", one(OneQubitOp::Z).controlled(1), vec![0, 1]),
+            ("cy q[0], q[1];", one(OneQubitOp::Y).controlled(1), vec![0, 1]),
+            ("ch q[1], q[0];", one(OneQubitOp::H).controlled(1), vec![1, 0]),
+            ("crx(0.7) q[0], q[1];", Operator::rx(0.7).controlled(1), vec![0, 1]),
+            ("cry(-1.2) q[0], q[1];", Operator::ry(-1.2).controlled(1), vec![0, 1]),
+            ("cu1(pi / 3) q[0], q[1];", Operator::phase(PI / 3.).controlled(1), vec![0, 1]),
+            ("swap q[0], q[1];", Operator::two_qubits(TwoQubitsOp::SWAP), vec![0, 1]),
+        ];
+        for (body, op, qubits) in cases {
+            assert!((overlap(&program(body), &op, &qubits) - 1.).abs() < TOLERANCE, "{}", body);
+        }
+    }
+
+    fn rz_ry_rz(theta: f64, phi: f64, lambda: f64) -> Operator {
+        let mut product = Operator::rz(lambda);
+        for op in [Operator::ry(theta), Operator::rz(phi)] {
+            let mut data = vec![Complex::ZERO; 4];
+            for i in 0..2 {
+                for j in 0..2 {
+                    data[i * 2 + j] = (0..2).map(|k| op.data.data[i * 2 + k] * product.data.data[k * 2 + j]).sum();
+                }
+            }
+            product = Operator::new(data).unwrap();
+        }
+        product
+    }
+
+    #[test]
+    fn test_bell_program_runs_and_transpiles() {
+        let source = "OPENQASM 2.0;
+            include \"qelib1.inc\";
+            // Bell pair, measured at the end.
+            qreg a[1];
+            qreg b[1];
+            creg c[2];
+            h a[0];
+            cx a[0], b[0];
+            barrier a, b;
+            measure a[0] -> c[0];
+            measure b -> c[1];";
+        let circuit = Circuit::from_qasm(source).unwrap();
+        assert_eq!(circuit.width(), 2);
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        circuit.run(&mut rho).unwrap();
+        for (k, c) in rho.data.data.iter().enumerate() {
+            let expected = if [0, 3, 12, 15].contains(&k) { 0.5 } else { 0. };
+            assert!((c - expected).norm() < TOLERANCE);
+        }
+
+        let pattern = circuit.to_pattern();
+        let result = pattern.simulate(DensityMatrix::new(2, State::ZERO), &mut StdRng::seed_from_u64(3)).unwrap();
+        assert!((result.state.data.data[0].re - 0.5).abs() < TOLERANCE);
+        assert!((result.state.data.data[15].re - 0.5).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn test_broadcast_and_errors() {
+        let error = Circuit::from_qasm(&program("h q;\ncx q[0], q;")).unwrap_err();
+        assert!(error.contains("repeated"));
+        assert_eq!(Circuit::from_qasm(&program("x q;")).unwrap().instructions().len(), 2);
+        for body in [
+            "foo q[0];",
+            "h q[2];",
+            "rx q[0];",
+            "rx(1 +) q[0];",
+            "reset q[0];",
+            "gate g a { h a; }",
+            "measure q[0] -> c[0];\nh q[0];",
+            "h r[0];",
+        ] {
+            assert!(Circuit::from_qasm(&program(body)).is_err(), "{}", body);
+        }
+        assert!(Circuit::from_qasm("qreg q[1];").is_err());
+        assert!(Circuit::from_qasm("OPENQASM 2.0;\ninclude \"other.inc\";").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests_randomized {
+    use rand::rngs::StdRng;
+    use rand::{Rng, RngCore, SeedableRng};
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::{Basis, DensityMatrix, State};
+    use dm_simu_rs::randomized::{self, RandomizedSetting};
+
+    #[test]
+    fn test_bell_state_purities() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.ry(2, 0.8);
+        let mut rho = DensityMatrix::new(3, State::ZERO);
+        circuit.run(&mut rho).unwrap();
+        let settings = randomized::sample(&rho, 300, 100, &mut StdRng::seed_from_u64(11)).unwrap();
+        assert_eq!(settings.len(), 300);
+        assert!(settings.iter().all(|s| s.bases.len() == 3 && s.outcomes.len() == 100));
+
+        assert!((randomized::estimate_purity(&settings, &[0]).unwrap() - 0.5).abs() < 0.1);
+        assert!((randomized::estimate_purity(&settings, &[0, 1]).unwrap() - 1.).abs() < 0.15);
+        assert!((randomized::estimate_purity(&settings, &[2]).unwrap() - 1.).abs() < 0.1);
+        assert!((randomized::estimate_renyi2(&settings, &[1]).unwrap() - 1.).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let setting = RandomizedSetting { bases: vec![Basis::Z, Basis::X], outcomes: vec![0b01, 0b11] };
+        assert!(randomized::estimate_purity(&[], &[0]).is_err());
+        assert!(randomized::estimate_purity(std::slice::from_ref(&setting), &[2]).is_err());
+        assert!(randomized::estimate_purity(std::slice::from_ref(&setting), &[0, 0]).is_err());
+        let single = RandomizedSetting { outcomes: vec![0], ..setting.clone() };
+        assert!(randomized::estimate_purity(&[setting.clone(), single], &[0]).is_err());
+        // Two shots disagreeing on qubit 0: 2 * (-1/2 + -1/2) / 2.
+        assert_eq!(randomized::estimate_purity(std::slice::from_ref(&setting), &[0]).unwrap(), -1.);
+        assert!(randomized::estimate_renyi2(&[setting], &[0]).is_err());
+    }
+
+    #[test]
+    fn test_frame_potential() {
+        assert_eq!(randomized::haar_frame_potential(2, 2), 1. / 3.);
+        assert_eq!(randomized::haar_frame_potential(4, 1), 0.25);
+
+        // Single qubit rotations compiled to patterns, Haar random when cos(theta) is uniform but not
+        // when theta stays in the northern hemisphere.
+        let rotated = |uniform_cos: bool| move |rng: &mut dyn RngCore| {
+            let theta = if uniform_cos { (1. - 2. * rng.gen::<f64>()).acos() } else { rng.gen_range(0.0..std::f64::consts::FRAC_PI_2) };
+            let mut circuit = Circuit::new(1);
+            circuit.ry(0, theta);
+            circuit.rz(0, rng.gen_range(0.0..2. * std::f64::consts::PI));
+            Ok(circuit.to_pattern().simulate(DensityMatrix::new(1, State::ZERO), rng)?.state)
+        };
+        let mut rng = StdRng::seed_from_u64(5);
+        let haar = randomized::sample_frame_potential(rotated(true), 300, 2, &mut rng).unwrap();
+        assert!(haar.deviation().abs() < 3.);
+        let biased = randomized::sample_frame_potential(rotated(false), 300, 2, &mut rng).unwrap();
+        assert!(biased.deviation() > 3.);
+        assert!(randomized::sample_frame_potential(rotated(true), 300, 1, &mut rng).unwrap().deviation().abs() < 3.);
+
+        // Computational basis states form a 1-design but not a 2-design.
+        let basis = [DensityMatrix::new(1, State::ZERO), DensityMatrix::from_statevec(&[(0.).into(), (1.).into()]).unwrap()];
+        let states = (0..40).map(|i| basis[i % 2].clone()).collect::<Vec<_>>();
+        assert!((randomized::frame_potential(&states, 1).unwrap().value - 0.5).abs() < 0.02);
+        assert!((randomized::frame_potential(&states, 2).unwrap().value - 0.5).abs() < 0.02);
+        assert!(randomized::frame_potential(&states[..1], 2).is_err());
+    }
+}
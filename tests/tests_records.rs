@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests_records {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use dm_simu_rs::records::{self, RecordReader, RecordWriter};
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let nodes = (0..19).map(|n| 3 * n).collect::<Vec<usize>>();
+        let shots = (0..100).map(|_| (0..19).map(|_| rng.gen_range(0..2)).collect()).collect::<Vec<Vec<u8>>>();
+        let path = std::env::temp_dir().join("dm_simu_rs_records.bin");
+        records::save_records(&path, nodes.clone(), &shots).unwrap();
+        // One header of 9 + 8 * 19 bytes, then 3 bytes per shot.
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 9 + 8 * 19 + 3 * 100);
+        let (loaded_nodes, loaded_shots) = records::load_records(&path).unwrap();
+        assert_eq!(loaded_nodes, nodes);
+        assert_eq!(loaded_shots, shots);
+        std::fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_streaming_outcomes() {
+        let mut writer = RecordWriter::new(Vec::new(), vec![4, 1]).unwrap();
+        writer.write_outcomes(&HashMap::from([(1, 1), (4, 0), (7, 1)])).unwrap();
+        writer.write_outcomes(&HashMap::from([(1, 0), (4, 1)])).unwrap();
+        assert!(writer.write_outcomes(&HashMap::from([(1, 0)])).is_err());
+        assert!(writer.write_shot(&[0, 2]).is_err());
+        assert_eq!(writer.shots(), 2);
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = RecordReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.nodes(), &[4, 1]);
+        assert_eq!(reader.read_shot().unwrap(), Some(vec![0, 1]));
+        assert_eq!(reader.read_shot().unwrap(), Some(vec![1, 0]));
+        assert_eq!(reader.read_shot().unwrap(), None);
+    }
+    #[test]
+    fn test_invalid_records() {
+        assert!(RecordReader::new(&b"nope"[..]).is_err());
+        let mut writer = RecordWriter::new(Vec::new(), vec![0; 9]).unwrap();
+        writer.write_shot(&[1; 9]).unwrap();
+        let mut bytes = writer.finish().unwrap();
+        bytes.pop();
+        let shots = RecordReader::new(&bytes[..]).unwrap().collect::<Vec<_>>();
+        assert_eq!(shots.len(), 1);
+        assert!(shots[0].is_err());
+        bytes[4] = 9;
+        assert!(RecordReader::new(&bytes[..]).is_err());
+        // A header claiming u32::MAX nodes fails on the missing nodes instead of allocating them.
+        let mut huge = b"MREC\x01".to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        huge.extend_from_slice(&[0; 16]);
+        assert!(RecordReader::new(&huge[..]).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests_resources {
+    use std::process::Command as Process;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+
+    #[test]
+    fn test_chain_resources() {
+        // Teleportation along a chain: the second measurement depends on the first one.
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![
+            Command::N(1),
+            Command::N(2),
+            Command::E((0, 1)),
+            Command::E((1, 2)),
+            Command::M(0, Plane::XY, 0.25, vec![], vec![], 0),
+            Command::M(1, Plane::XY, 0.5, vec![0], vec![], 0),
+            Command::X(2, vec![1]),
+            Command::Z(2, vec![0]),
+        ]);
+        let report = pattern.resources(&TolerancePolicy::DOUBLE);
+        assert_eq!(report.nodes, 3);
+        assert_eq!(report.edges, 2);
+        assert_eq!(report.measurements, 2);
+        assert_eq!(report.depth, 2);
+        assert_eq!(report.t_count, 1);
+        assert_eq!(report.max_width, 3);
+        assert_eq!(report.memory[0], ("statevector", 128.));
+        assert_eq!(report.memory[1], ("density_matrix", 1024.));
+
+        // Angles just below or above a multiple of pi / 2 are Clifford.
+        let mut rounded = Pattern::new(vec![0, 1, 2]);
+        rounded.extend(vec![
+            Command::M(0, Plane::XY, 0.5 - 1e-13, vec![], vec![], 0),
+            Command::M(1, Plane::XY, -1e-13, vec![], vec![], 0),
+            Command::M(2, Plane::XY, 1.5 + 1e-13, vec![], vec![], 0),
+        ]);
+        assert_eq!(rounded.resources(&TolerancePolicy::DOUBLE).t_count, 0);
+    }
+    #[test]
+    fn test_cli_resources() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rz(1, 0.3);
+        let pattern = circuit.to_pattern();
+        let path = std::env::temp_dir().join("dm_simu_rs_resources_pattern.json");
+        std::fs::write(&path, pattern.to_json()).unwrap();
+
+        let output = Process::new(env!("CARGO_BIN_EXE_mbqc")).arg("resources").arg(&path).output().unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout, pattern.resources(&TolerancePolicy::DOUBLE).to_string());
+        assert!(stdout.contains(&format!("nodes: {}", pattern.n_nodes())));
+        assert!(stdout.contains("density_matrix: "));
+        std::fs::remove_file(path).unwrap();
+
+        let usage = Process::new(env!("CARGO_BIN_EXE_mbqc")).arg("resources").output().unwrap();
+        assert!(!usage.status.success());
+        assert!(String::from_utf8(usage.stderr).unwrap().starts_with("Usage"));
+    }
+}
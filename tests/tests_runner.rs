@@ -0,0 +1,353 @@
+#[cfg(test)]
+mod tests_runner {
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::{SimulationConfig, TolerancePolicy};
+    use dm_simu_rs::decoder::{Decoder, LookupDecoder};
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::channels::{self, Channel};
+    use dm_simu_rs::noise::{CommandKind, Confusion, IdleNoise, MeasurementCrosstalk, NoiseModel, ReadoutError, RelaxationTimes};
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+    use dm_simu_rs::pauli::{Pauli, PauliString};
+    use dm_simu_rs::runner::ExecutionCursor;
+    use dm_simu_rs::statevector::StateVector;
+
+    // Teleportation along a chain, without the corrections.
+    fn uncorrected_chain() -> Pattern {
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![
+            Command::N(1),
+            Command::N(2),
+            Command::E((0, 1)),
+            Command::E((1, 2)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+            Command::M(1, Plane::XY, 0., vec![], vec![], 0),
+        ]);
+        pattern
+    }
+
+    struct FailingDecoder {
+        nodes: Vec<usize>
+    }
+
+    impl Decoder for FailingDecoder {
+        fn syndrome_nodes(&self) -> &[usize] {
+            &self.nodes
+        }
+
+        fn decode(&mut self, _syndrome: &[u8]) -> Result<Vec<(usize, Pauli)>, String> {
+            Err("decoder failure".to_string())
+        }
+    }
+
+    #[test]
+    fn test_resume_matches_simulate() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.rx(1, 0.8);
+        let pattern = circuit.to_pattern();
+        let input = DensityMatrix::new(2, State::ZERO);
+        for end in [0, 5, pattern.seq().len()] {
+            let mut rng = StdRng::seed_from_u64(4);
+            let cursor = pattern.run_until(input.clone(), end, &mut rng).unwrap();
+            assert_eq!(cursor.executed, end);
+            let resumed = pattern.resume(cursor, &mut rng).unwrap();
+            let direct = pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(4)).unwrap();
+            assert_eq!(resumed.outcomes, direct.outcomes);
+            assert!(resumed.state.approx_eq(&direct.state, &TolerancePolicy::DOUBLE));
+        }
+        assert!(pattern.run_until(input, pattern.seq().len() + 1, &mut StdRng::seed_from_u64(4)).is_err());
+    }
+
+    #[test]
+    fn test_external_feedback() {
+        // Teleport along a chain, computing the corrections outside of the pattern.
+        let pattern = uncorrected_chain();
+        let mut input = DensityMatrix::new(1, State::ZERO);
+        input.evolve_single(&Operator::ry(0.9), 0).unwrap();
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut cursor = pattern.run_until(input.clone(), pattern.seq().len(), &mut rng).unwrap();
+            let index = cursor.nodes.iter().position(|&node| node == 2).unwrap();
+            if cursor.outcomes[&1] == 1 {
+                cursor.backend.evolve_single(&Operator::one_qubit(OneQubitOp::X), index).unwrap();
+            }
+            if cursor.outcomes[&0] == 1 {
+                cursor.backend.evolve_single(&Operator::one_qubit(OneQubitOp::Z), index).unwrap();
+            }
+            let result = cursor.resume(&[], &[2], &mut rng).unwrap();
+            assert!(result.state.approx_eq(&input, &TolerancePolicy::DOUBLE));
+        }
+    }
+
+    #[test]
+    fn test_measurement_callbacks() {
+        // Same teleportation, the callback of the last measurement appending the corrections.
+        let pattern = uncorrected_chain();
+        let mut input = DensityMatrix::new(1, State::ZERO);
+        input.evolve_single(&Operator::ry(0.9), 0).unwrap();
+        for seed in 0..8 {
+            let mut cursor = ExecutionCursor::new(&pattern, input.clone()).unwrap();
+            cursor.add_callback(0, 1, Box::new(|node, _, next| {
+                assert_eq!(node, 0);
+                assert_eq!(next, &[Command::M(1, Plane::XY, 0., vec![], vec![], 0)]);
+                Ok(())
+            }));
+            cursor.add_callback(1, 4, Box::new(|_, outcomes, next| {
+                assert!(next.is_empty());
+                if outcomes[&1] == 1 {
+                    next.push(Command::X(2, vec![1]));
+                }
+                next.push(Command::Z(2, vec![0]));
+                Ok(())
+            }));
+            let result = cursor.resume(pattern.seq(), &[2], &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.approx_eq(&input, &TolerancePolicy::DOUBLE));
+        }
+
+        // Errors of a callback stop the run.
+        let mut cursor = ExecutionCursor::new(&pattern, input).unwrap();
+        cursor.add_callback(0, 0, Box::new(|_, _, _| Err("no angle".to_string())));
+        let error = cursor.resume(pattern.seq(), &[2], &mut StdRng::seed_from_u64(0)).err().unwrap();
+        assert!(error.contains("Callback of node 0") && error.contains("no angle"), "{}", error);
+    }
+
+    #[test]
+    fn test_decoders() {
+        let pattern = uncorrected_chain();
+        let mut input = DensityMatrix::new(1, State::ZERO);
+        input.evolve_single(&Operator::rx(0.6), 0).unwrap();
+        for seed in 0..8 {
+            let mut decoder = LookupDecoder::new(vec![0, 1]);
+            decoder.insert(vec![0, 1], vec![(2, Pauli::X)]);
+            decoder.insert(vec![1, 0], vec![(2, Pauli::Z)]);
+            decoder.insert(vec![1, 1], vec![(2, Pauli::X), (2, Pauli::Z)]);
+            let result = pattern.simulate_with_decoders(input.clone(), vec![Box::new(decoder)], &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.approx_eq(&input, &TolerancePolicy::DOUBLE));
+        }
+
+        let failing = FailingDecoder { nodes: vec![0] };
+        assert!(pattern.simulate_with_decoders(input.clone(), vec![Box::new(failing)], &mut StdRng::seed_from_u64(0)).is_err());
+        // A syndrome that is never completed does not run the decoder.
+        let never = FailingDecoder { nodes: vec![0, 2] };
+        assert!(pattern.simulate_with_decoders(input, vec![Box::new(never)], &mut StdRng::seed_from_u64(0)).is_ok());
+    }
+    #[test]
+    fn test_measurement_crosstalk_dephases_neighbors() {
+        // J(0) = H on |0>, so the output is |+> and the crosstalk shows up in its coherence.
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+            Command::X(1, vec![0]),
+        ]);
+        let input = DensityMatrix::new(1, State::ZERO);
+        for seed in 0..4 {
+            let ideal = pattern.simulate(input.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!((ideal.state.data.data[1].re - 0.5).abs() < TolerancePolicy::DOUBLE.equality);
+
+            let mut crosstalk = MeasurementCrosstalk::uniform(0.1);
+            crosstalk.dephasing.insert((1, 0), 0.4);
+            let noisy = pattern.simulate_with_crosstalk(input.clone(), crosstalk, &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!((noisy.state.data.data[1].re - 0.3).abs() < TolerancePolicy::DOUBLE.equality);
+            assert!((noisy.state.data.data[0].re - 0.5).abs() < TolerancePolicy::DOUBLE.equality);
+        }
+        // Noiseless edges leave pure state backends untouched, noisy ones need a density matrix.
+        let sv = StateVector::new(1, State::ZERO);
+        assert!(pattern.simulate_with_crosstalk(sv.clone(), MeasurementCrosstalk::default(), &mut StdRng::seed_from_u64(0)).is_ok());
+        assert!(pattern.simulate_with_crosstalk(sv, MeasurementCrosstalk::uniform(0.1), &mut StdRng::seed_from_u64(0)).is_err());
+    }
+    #[test]
+    fn test_noise_model() {
+        let tol = TolerancePolicy::DOUBLE.equality;
+        let mut prepare = Pattern::new(vec![]);
+        prepare.extend(vec![Command::N(0)]);
+        let mut model = NoiseModel::default();
+        model.channels.insert(CommandKind::N, Channel::new(channels::dephasing(0.4).unwrap()).unwrap());
+        let noisy = prepare.simulate_with_noise(DensityMatrix::new(0, State::ZERO), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert!((noisy.state.data.data[1].re - 0.3).abs() < tol);
+        // Node channels replace the default of their kind.
+        model.node_channels.insert((CommandKind::N, 0), Channel::new(channels::dephasing(0.).unwrap()).unwrap());
+        let ideal = prepare.simulate_with_noise(DensityMatrix::new(0, State::ZERO), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert!((ideal.state.data.data[1].re - 0.5).abs() < tol);
+
+        // Fully depolarizing the edge leaves the maximally mixed state.
+        let mut graph = Pattern::new(vec![]);
+        graph.extend(vec![Command::N(0), Command::N(1), Command::E((0, 1))]);
+        let mut model = NoiseModel::default();
+        model.channels.insert(CommandKind::E, Channel::new(channels::two_qubit_depolarizing(1.).unwrap()).unwrap());
+        let mixed = graph.simulate_with_noise(DensityMatrix::new(0, State::ZERO), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        for (k, c) in mixed.state.data.data.iter().enumerate() {
+            let expected = if k % 5 == 0 { 0.25 } else { 0. };
+            assert!((c - expected).norm() < tol);
+        }
+
+        // |+> always gives 0 in the X basis, which a certain readout error flips.
+        let mut measure = Pattern::new(vec![]);
+        measure.extend(vec![Command::N(0), Command::M(0, Plane::XY, 0., vec![], vec![], 0)]);
+        let model = NoiseModel { readout: ReadoutError::uniform(1, Confusion::symmetric(1.).unwrap()), ..NoiseModel::default() };
+        let result = measure.simulate_with_noise(StateVector::new(0, State::ZERO), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(result.outcomes[&0], 1);
+    }
+
+    #[test]
+    fn test_noise_model_errors() {
+        let pattern = uncorrected_chain();
+        let rng = &mut StdRng::seed_from_u64(0);
+        let model = NoiseModel::depolarizing(0.1).unwrap();
+        assert!(pattern.simulate_with_noise(DensityMatrix::new(1, State::ZERO), &model, rng).is_ok());
+        assert!(pattern.simulate_with_noise(StateVector::new(1, State::ZERO), &model, rng).is_err());
+        assert!(NoiseModel::depolarizing(1.5).is_err());
+        let mut model = NoiseModel::default();
+        model.node_channels.insert((CommandKind::M, 0), Channel::new(channels::two_qubit_depolarizing(0.1).unwrap()).unwrap());
+        assert!(pattern.simulate_with_noise(DensityMatrix::new(1, State::ZERO), &model, rng).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_noise_model_from_toml() {
+        let model = NoiseModel::from_toml("readout_error = 0.05\ndepolarizing = 0.01  # Default.\n\n[E]\nchannel = \"dephasing\"\np = 0.2\n\n[M]\nchannel = \"generalized_amplitude_damping\"\ngamma = 0.1\nexcited_population = 0.3\n").unwrap();
+        assert_eq!(model.readout.confusion(7), Confusion::symmetric(0.05).unwrap());
+        assert_eq!(model.channels.len(), 6);
+        assert_eq!(model.channels[&CommandKind::E].nqubits, 1);
+        assert_eq!(model.channels[&CommandKind::M].kraus.len(), 4);
+        assert_eq!(model.channels[&CommandKind::N].kraus.len(), 4);
+        assert!(NoiseModel::from_toml("").unwrap().channels.is_empty());
+        assert!(NoiseModel::from_toml("[Q]\nchannel = \"dephasing\"\np = 0.1").is_err());
+        assert!(NoiseModel::from_toml("[N]\nchannel = \"amplitude_damping\"\np = 0.1").is_err());
+        assert!(NoiseModel::from_toml("[N]\nchannel = \"dephasing\"\np = \"high\"").is_err());
+        assert!(NoiseModel::from_toml("temperature = 3").is_err());
+        assert!(NoiseModel::from_toml("readout_error").is_err());
+        assert!(NoiseModel::from_toml("readout_error = 1.5").is_err());
+
+        // Comments only start outside strings, and keys or sections given twice are refused.
+        let error = NoiseModel::from_toml("[N]\nchannel = \"dephasing # not a comment\"\np = 0.1").err().unwrap();
+        assert!(error.contains("dephasing # not a comment"), "{}", error);
+        assert!(NoiseModel::from_toml("[N]\nchannel = 'dephasing'  # Comment.\np = 1_0e-2").is_ok());
+        assert!(NoiseModel::from_toml("depolarizing = 0.1\ndepolarizing = 0.2").is_err());
+        assert!(NoiseModel::from_toml("[N]\nchannel = \"dephasing\"\np = 0.1\n[N]\nchannel = \"dephasing\"\np = 0.2").is_err());
+        assert!(NoiseModel::from_toml("[N]\nchannel = \"dephasing\"\np = 0.1\ngamma = 0.2").is_err());
+        assert!(NoiseModel::from_toml("[N]\nchannel = \"dephasing\" p = 0.1").is_err());
+
+        let model = NoiseModel::from_toml("[idle]\nt1 = 50\nt2 = 40\nE = 2\nM = 1.5").unwrap();
+        assert_eq!(model.idle.default_times, Some(RelaxationTimes::new(50., 40.).unwrap()));
+        assert_eq!(model.idle.duration(CommandKind::E), 2.);
+        assert_eq!(model.idle.duration(CommandKind::N), 0.);
+        assert!(NoiseModel::from_toml("[idle]\nt1 = 50\nE = 2").is_err());
+        assert!(NoiseModel::from_toml("[idle]\nt1 = 50\nt2 = 40\nW = 2").is_err());
+    }
+
+    #[test]
+    fn test_idle_noise() {
+        let times = RelaxationTimes::new(10., 8.).unwrap();
+        assert!(RelaxationTimes::new(10., 25.).is_err());
+        assert!(RelaxationTimes::new(0., 1.).is_err());
+        let mut rho = DensityMatrix::new(1, State::PLUS);
+        rho.apply_channel(&times.idle_channel(3.).unwrap().kraus, &[0]).unwrap();
+        let x = rho.expectation(&PauliString::new(vec![Pauli::X])).unwrap();
+        let z = rho.expectation(&PauliString::new(vec![Pauli::Z])).unwrap();
+        assert!((x - (-3f64 / 8.).exp()).abs() < TolerancePolicy::DOUBLE.equality);
+        assert!((z - (1. - (-3f64 / 10.).exp())).abs() < TolerancePolicy::DOUBLE.equality);
+
+        // Each N lasts one unit: the input node waits 2 units, node 1 waits 1 and node 2 none.
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![Command::N(1), Command::N(2)]);
+        let mut model = NoiseModel::default();
+        model.idle.durations.insert(CommandKind::N, 1.);
+        model.idle.default_times = Some(times);
+        let result = pattern.simulate_with_noise(DensityMatrix::new(1, State::PLUS), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        for (qubit, waited) in [2f64, 1., 0.].into_iter().enumerate() {
+            let mut paulis = vec![Pauli::I; 3];
+            paulis[qubit] = Pauli::X;
+            let x = result.state.expectation(&PauliString::new(paulis)).unwrap();
+            assert!((x - (-waited / 8.).exp()).abs() < TolerancePolicy::DOUBLE.equality);
+        }
+        assert!(IdleNoise::default().is_empty());
+    }
+
+    #[test]
+    fn test_zz_crosstalk() {
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(vec![Command::N(0), Command::N(1), Command::N(2), Command::E((0, 1)), Command::E((1, 2))]);
+        let strengths = vec![vec![0., 1., 0.], vec![1., 0., 0.], vec![0., 0., 0.]];
+        let mut model = NoiseModel::default();
+        model.add_zz_crosstalk(&strengths).unwrap();
+        let result = pattern.simulate_with_noise(DensityMatrix::new(0, State::PLUS), &model, &mut StdRng::seed_from_u64(0)).unwrap();
+        let mut expected = pattern.simulate(DensityMatrix::new(0, State::PLUS), &mut StdRng::seed_from_u64(0)).unwrap().state;
+        expected.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 0).unwrap();
+        expected.evolve_single(&Operator::one_qubit(OneQubitOp::Z), 1).unwrap();
+        assert!(result.state.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+
+        let mut model = NoiseModel::depolarizing(0.1).unwrap();
+        model.add_zz_crosstalk(&[vec![0., 0.2], vec![0.2, 0.]]).unwrap();
+        assert_eq!(model.edge_channel(1, 0).unwrap().kraus.len(), 32);
+        assert_eq!(model.edge_channel(1, 2).unwrap().kraus.len(), 16);
+        assert!(model.add_zz_crosstalk(&[vec![0., 0.2], vec![0.1, 0.]]).is_err());
+        assert!(model.add_zz_crosstalk(&[vec![0.1]]).is_err());
+        assert!(model.add_zz_crosstalk(&[vec![0., 0.2]]).is_err());
+    }
+
+    #[test]
+    fn test_operator_cache() {
+        let n = 30;
+        let mut pattern = Pattern::new(vec![0]);
+        for node in 1..n {
+            pattern.extend(vec![Command::N(node), Command::E((node - 1, node)), Command::M(node - 1, Plane::XY, 0.25, vec![], vec![], 0)]);
+        }
+        pattern.add(Command::C(n - 1, 3));
+        pattern.add(Command::C(n - 1, 3));
+        let cursor = pattern.run_until(StateVector::new(1, State::PLUS), pattern.seq().len(), &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(cursor.cache.len(), 2);
+        assert_eq!(cursor.cache.misses, 2);
+        assert_eq!(cursor.cache.hits, n - 1);
+    }
+
+    #[test]
+    fn test_error_context() {
+        let pattern = uncorrected_chain();
+        let mut model = NoiseModel::default();
+        model.channels.insert(CommandKind::E, Channel::new(channels::dephasing(0.1).unwrap()).unwrap());
+        let error = pattern.simulate_with_noise(StateVector::new(1, State::ZERO), &model, &mut StdRng::seed_from_u64(0)).err().unwrap();
+        assert_eq!(error, "Command 2 E((0, 1)) on StateVector with register [0, 1, 2]: E noise on nodes 0 and 1: This backend cannot apply noise channels.");
+
+        let mut dangling = uncorrected_chain();
+        dangling.add(Command::X(2, vec![5]));
+        let error = dangling.simulate(DensityMatrix::new(1, State::PLUS), &mut StdRng::seed_from_u64(0)).err().unwrap();
+        assert!(error.starts_with("Command 6 X(2, [5]) on DensityMatrix with register [2]: "));
+    }
+
+    #[test]
+    fn test_state_injection() {
+        let psi = [Complex::new(0.6, 0.), Complex::from_polar(0.8, 0.3)];
+        let mut pattern = Pattern::new(vec![]);
+        pattern.add(Command::NState(0, psi));
+        pattern.extend(uncorrected_chain().seq().to_vec());
+        pattern.extend(vec![Command::X(2, vec![1]), Command::Z(2, vec![0])]);
+        let expected = StateVector::from_vec(psi.to_vec()).unwrap().to_density_matrix();
+        for seed in 0..4 {
+            let result = pattern.simulate(DensityMatrix::new(0, State::ZERO), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(result.state.approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        }
+        let parsed = Pattern::from_json(&pattern.to_json()).unwrap();
+        assert_eq!(parsed, pattern);
+        assert!(pattern.perform_pauli_measurements(&TolerancePolicy::DOUBLE, &mut StdRng::seed_from_u64(0)).is_err());
+
+        let mut unnormalized = Pattern::new(vec![]);
+        unnormalized.add(Command::NState(0, [Complex::new(1., 0.), Complex::new(1., 0.)]));
+        assert!(unnormalized.simulate(StateVector::new(0, State::ZERO), &mut StdRng::seed_from_u64(0)).is_err());
+        assert!(Pattern::from_json(r#"{"input_nodes": [], "seq": [["N", 0, [1, 0]]]}"#).is_err());
+
+        // A state rounded to single precision only passes with the single precision tolerances.
+        let mut rounded = Pattern::new(vec![]);
+        rounded.add(Command::NState(0, [Complex::new(1. + 1e-7, 0.), Complex::ZERO]));
+        let single = SimulationConfig { tolerance: TolerancePolicy::SINGLE, ..SimulationConfig::default() };
+        assert!(rounded.simulate(StateVector::new(0, State::ZERO), &mut StdRng::seed_from_u64(0)).is_err());
+        assert!(rounded.simulate_with_config(StateVector::new(0, State::ZERO), &single, &mut StdRng::seed_from_u64(0)).is_ok());
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(all(test, feature = "serde"))]
+mod tests_serde {
+    use num_complex::Complex;
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::density_matrix::DensityMatrix;
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::pattern::Pattern;
+
+    #[test]
+    fn test_json_round_trips() {
+        let rho = DensityMatrix::from_statevec(&[Complex::new(0.6, 0.), Complex::new(0., 0.8)]).unwrap();
+        let restored: DensityMatrix = serde_json::from_str(&serde_json::to_string(&rho).unwrap()).unwrap();
+        assert_eq!(restored.nqubits, rho.nqubits);
+        assert_eq!(restored.size, rho.size);
+        assert_eq!(restored.data.shape, rho.data.shape);
+        assert_eq!(restored.data.data, rho.data.data);
+
+        let op = Operator::one_qubit(OneQubitOp::H);
+        let restored: Operator = serde_json::from_str(&serde_json::to_string(&op).unwrap()).unwrap();
+        assert_eq!(restored.data.data, op.data.data);
+
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        let pattern = circuit.to_pattern();
+        let restored: Pattern = serde_json::from_str(&serde_json::to_string(&pattern).unwrap()).unwrap();
+        assert_eq!(restored, pattern);
+    }
+
+    #[test]
+    fn test_invalid_input_is_refused() {
+        let rho = DensityMatrix::from_statevec(&[Complex::new(0.6, 0.), Complex::new(0., 0.8)]).unwrap();
+        let mut value = serde_json::to_value(&rho).unwrap();
+        value["nqubits"] = 2.into();
+        assert!(serde_json::from_value::<DensityMatrix>(value).is_err());
+        let mut value = serde_json::to_value(&rho).unwrap();
+        value["data"]["shape"] = serde_json::json!([2, 2, 2]);
+        assert!(serde_json::from_value::<DensityMatrix>(value).is_err());
+        // Twice the state has trace 2, and a lone off-diagonal entry is not Hermitian.
+        let mut doubled = rho.clone();
+        doubled.data.data.iter_mut().for_each(|c| *c *= 2.);
+        assert!(serde_json::from_str::<DensityMatrix>(&serde_json::to_string(&doubled).unwrap()).is_err());
+        let mut skewed = rho.clone();
+        skewed.data.data[1] = Complex::new(0.3, 0.);
+        assert!(serde_json::from_str::<DensityMatrix>(&serde_json::to_string(&skewed).unwrap()).is_err());
+
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        let value = serde_json::to_value(circuit.to_pattern()).unwrap();
+        let mut unprepared = value.clone();
+        unprepared["seq"].as_array_mut().unwrap().retain(|command| command.get("N").is_none());
+        assert!(serde_json::from_value::<Pattern>(unprepared).is_err());
+        let mut miscounted = value.clone();
+        miscounted["n_nodes"] = 1.into();
+        assert!(serde_json::from_value::<Pattern>(miscounted).is_err());
+        let mut outputs = value;
+        outputs["output_nodes"] = serde_json::json!([0]);
+        assert!(serde_json::from_value::<Pattern>(outputs).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests_shards {
+    use dm_simu_rs::channels;
+    use dm_simu_rs::density_matrix::DensityMatrix;
+    use dm_simu_rs::shards;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn test_streamed_expectations() {
+        let mut rho = DensityMatrix::from_graph(&[(0, 1), (1, 2), (2, 3)], 4).unwrap();
+        rho.apply_channel(&channels::depolarizing(0.1).unwrap(), &[2]).unwrap();
+        let dir = std::env::temp_dir().join("dm_simu_rs_shards");
+        // 16 rows in shards of 5 rows, the last shard being shorter.
+        let written = shards::write_shards(&rho, &dir, 5).unwrap();
+        assert_eq!(written.len(), 4);
+        let paths = shards::list_shards(&dir).unwrap();
+        assert_eq!(paths, written);
+
+        assert!((shards::trace(&paths).unwrap() - rho.trace()).norm() < TOLERANCE);
+        for p in ["XZII", "ZXZI", "IZXZ", "IIZX", "YZZY"] {
+            let p = p.parse().unwrap();
+            assert!((shards::expectation(&paths, &p).unwrap() - rho.expectation(&p).unwrap()).abs() < TOLERANCE);
+        }
+        assert!(shards::expectation(&paths, &"XZ".parse().unwrap()).is_err());
+        // A missing shard is detected.
+        assert!(shards::trace(&paths[..3]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn test_invalid_shards() {
+        let rho = DensityMatrix::from_graph(&[(0, 1)], 2).unwrap();
+        assert!(shards::write_shards(&rho, std::env::temp_dir().join("dm_simu_rs_no_shards"), 0).is_err());
+        assert!(shards::list_shards(std::env::temp_dir().join("dm_simu_rs_no_shards")).is_err());
+        assert!(shards::trace(&[]).is_err());
+
+        // Shapes whose byte length overflows are refused instead of wrapping around.
+        let path = std::env::temp_dir().join("dm_simu_rs_overflowing_shard.npy");
+        let header = "{'descr': '<c16', 'fortran_order': False, 'shape': (1, 4611686018427387904), }\n";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        let paths = [path];
+        assert!(shards::trace(&paths).is_err());
+        std::fs::remove_file(&paths[0]).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests_simulator {
+    use std::sync::Arc;
+
+    use num_complex::Complex;
+
+    use dm_simu_rs::config::{SimulationConfig, TolerancePolicy};
+    use dm_simu_rs::noise::NoiseSchedule;
+    use dm_simu_rs::prelude::*;
+    use dm_simu_rs::simulator::StateAverage;
+
+    // One step of teleportation along a chain, mapping |+> to H |+> = |0>.
+    fn hadamard() -> Pattern {
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+            Command::X(1, vec![0])
+        ]);
+        pattern
+    }
+
+    #[test]
+    fn test_simulator() {
+        let simulator = Simulator::new(DensityMatrix::new(1, State::PLUS)).load(hadamard()).with_seed(7);
+        let result = simulator.run(50).unwrap();
+        assert_eq!(result.shots.len(), 50);
+        let counts = result.counts(&[0]).unwrap();
+        assert_eq!(counts.values().sum::<usize>(), 50);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(simulator.run(50).unwrap().counts(&[0]).unwrap(), counts);
+        assert!(result.counts(&[1]).is_err());
+        let expected = DensityMatrix::new(1, State::ZERO);
+        assert!(result.average_state().unwrap().approx_eq(&expected, &TolerancePolicy::DOUBLE));
+        assert!(result.states().all(|state| state.approx_eq(&expected, &TolerancePolicy::DOUBLE)));
+    }
+
+    #[test]
+    fn test_simulator_noise_and_backends() {
+        let noisy = Simulator::new(DensityMatrix::new(1, State::PLUS))
+            .load(hadamard())
+            .with_noise(NoiseModel::depolarizing(0.1).unwrap())
+            .run(10)
+            .unwrap();
+        assert!(noisy.average_state().unwrap().purity() < 0.99);
+
+        let result = Simulator::new(StateVector::new(1, State::PLUS)).load(hadamard()).run(3).unwrap();
+        assert!(result.states().all(|state| (state.data[0].norm() - 1.).abs() < 1e-12));
+        assert!(Simulator::new(Stabilizer::new(1, State::PLUS)).run(1).is_err());
+        assert!(Simulator::new(DensityMatrix::new(2, State::PLUS)).load(hadamard()).run(1).is_err());
+    }
+
+    #[test]
+    fn test_simulator_config() {
+        // A state rounded to single precision only passes with the single precision tolerances.
+        let mut rounded = Pattern::new(vec![]);
+        rounded.add(Command::NState(0, [Complex::new(1. + 1e-7, 0.), Complex::ZERO]));
+        let single = SimulationConfig { tolerance: TolerancePolicy::SINGLE, ..SimulationConfig::default() };
+        assert!(Simulator::new(StateVector::new(0, State::ZERO)).load(rounded.clone()).run(1).is_err());
+        assert!(Simulator::new(StateVector::new(0, State::ZERO)).load(rounded).with_config(single).run(1).is_ok());
+
+        // Streaming the shots gives the same results as collecting them.
+        let simulator = Simulator::new(DensityMatrix::new(1, State::PLUS))
+            .load(hadamard())
+            .with_noise(NoiseModel::depolarizing(0.1).unwrap())
+            .with_seed(3);
+        let result = simulator.run(10).unwrap();
+        let mut average = StateAverage::default();
+        simulator.for_each_shot(10, |shot, run| {
+            assert_eq!(run.outcomes, result.shots[shot as usize].outcomes);
+            average.add(&run.state).unwrap();
+            Ok(())
+        }).unwrap();
+        assert!(average.average().unwrap().approx_eq(&result.average_state().unwrap(), &TolerancePolicy::DOUBLE));
+        assert!(StateAverage::default().average().is_err());
+
+        // States on different numbers of qubits cannot be averaged.
+        let mut average = StateAverage::default();
+        average.add(&DensityMatrix::new(1, State::PLUS)).unwrap();
+        assert!(average.add(&DensityMatrix::new(2, State::PLUS)).is_err());
+    }
+
+    #[test]
+    fn test_noise_schedule() {
+        // Depolarizing noise drifting from none to p = 0.4 over the shots.
+        let schedule: NoiseSchedule = Arc::new(|shot| NoiseModel::depolarizing(0.1 * shot as f64));
+        let result = Simulator::new(DensityMatrix::new(1, State::PLUS)).load(hadamard()).with_noise_schedule(schedule).run(5).unwrap();
+        let purities = result.states().map(|state| state.purity()).collect::<Vec<_>>();
+        assert!((purities[0] - 1.).abs() < 1e-12);
+        assert!(purities.windows(2).all(|pair| pair[1] < pair[0]));
+
+        // The fixed model replaces the schedule, and errors of the schedule name their shot.
+        let failing: NoiseSchedule = Arc::new(|shot| NoiseModel::depolarizing(if shot < 2 { 0. } else { 2. }));
+        let simulator = Simulator::new(DensityMatrix::new(1, State::PLUS)).load(hadamard()).with_noise_schedule(failing);
+        assert!(simulator.run(3).err().unwrap().contains("Noise schedule of shot 2"));
+        assert!(simulator.with_noise(NoiseModel::default()).run(3).is_ok());
+    }
+}
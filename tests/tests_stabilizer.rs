@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests_stabilizer {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::{SimulationConfig, TolerancePolicy};
+    use dm_simu_rs::density_matrix::State;
+    use dm_simu_rs::error::SimulatorError;
+    use dm_simu_rs::operators::{OneQubitOp, Operator, TwoQubitsOp};
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+    use dm_simu_rs::pauli::{Pauli, PauliString};
+    use dm_simu_rs::stabilizer::{AutoResult, Stabilizer};
+    use dm_simu_rs::statevector::StateVector;
+
+    fn pauli_strings(n: usize) -> Vec<PauliString> {
+        (0..1usize << (2 * n)).map(|index| {
+            PauliString::new((0..n).map(|q| [Pauli::I, Pauli::X, Pauli::Y, Pauli::Z][(index >> (2 * q)) & 3]).collect())
+        }).collect()
+    }
+
+    #[test]
+    fn test_gates_match_statevector() {
+        let mut tableau = Stabilizer::new(3, State::ZERO);
+        let mut state = StateVector::new(3, State::ZERO);
+        let gates = [
+            (Operator::one_qubit(OneQubitOp::H), vec![0]),
+            (Operator::two_qubits(TwoQubitsOp::CX), vec![0, 2]),
+            (Operator::one_qubit(OneQubitOp::S), vec![2]),
+            (Operator::one_qubit(OneQubitOp::H), vec![1]),
+            (Operator::two_qubits(TwoQubitsOp::CZ), vec![1, 2]),
+            (Operator::one_qubit(OneQubitOp::Y), vec![0]),
+            (Operator::two_qubits(TwoQubitsOp::SWAP), vec![0, 1])
+        ];
+        for (op, targets) in &gates {
+            tableau.evolve(op, targets).unwrap();
+            state.evolve(op, targets).unwrap();
+        }
+        for pauli in pauli_strings(3) {
+            assert!((tableau.expectation(&pauli).unwrap() - state.expectation(&pauli).unwrap()).abs() < 1e-12);
+        }
+        let dense = tableau.to_statevector().unwrap();
+        let overlap = dense.data.iter().zip(&state.data).map(|(a, b)| a.conj() * b).sum::<num_complex::Complex<f64>>();
+        assert!((overlap.norm() - 1.).abs() < 1e-12);
+
+        assert!(tableau.evolve(&Operator::rx(0.3), &[0]).is_err());
+        assert!(tableau.evolve(&Operator::one_qubit(OneQubitOp::H), &[3]).is_err());
+
+        // Rows edited into -I project every basis state out.
+        let mut empty = Stabilizer::new(1, State::ZERO);
+        empty.stabilizers[0] = "-I".parse().unwrap();
+        assert!(matches!(empty.to_statevector(), Err(SimulatorError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_measure_and_remove() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut tableau = Stabilizer::new(3, State::PLUS);
+            tableau.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]).unwrap();
+            tableau.evolve(&Operator::one_qubit(OneQubitOp::H), &[1]).unwrap();
+            // Bell pair on qubits 0 and 1, qubit 2 in |+>.
+            let first = tableau.measure_and_remove(0, &mut rng).unwrap();
+            let second = tableau.measure(0, &mut rng).unwrap();
+            assert_eq!(first, second);
+            assert_eq!(tableau.nqubits, 2);
+            assert_eq!(tableau.expectation(&"IX".parse::<PauliString>().unwrap()).unwrap(), 1.);
+        }
+
+        // GHZ state spanning several words of the bitsets.
+        let n = 150;
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut tableau = Stabilizer::new(n, State::ZERO);
+        tableau.evolve(&Operator::one_qubit(OneQubitOp::H), &[0]).unwrap();
+        (1..n).for_each(|q| tableau.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[q - 1, q]).unwrap());
+        let outcome = tableau.measure_and_remove(70, &mut rng).unwrap();
+        let mut paulis = vec![Pauli::I; n - 1];
+        paulis[63] = Pauli::Z;
+        paulis[140] = Pauli::Z;
+        assert_eq!(tableau.expectation(&PauliString::new(paulis.clone())).unwrap(), 1.);
+        paulis[63] = Pauli::I;
+        assert_eq!(tableau.expectation(&PauliString::new(paulis)).unwrap(), 1. - 2. * outcome as f64);
+    }
+
+    #[test]
+    fn test_text_format() {
+        let mut tableau = Stabilizer::new(3, State::ZERO);
+        tableau.evolve(&Operator::one_qubit(OneQubitOp::H), &[0]).unwrap();
+        tableau.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+        tableau.evolve(&Operator::one_qubit(OneQubitOp::X), &[2]).unwrap();
+        let text = tableau.to_string();
+        assert_eq!(text, "+Z__\n+_X_\n+__X\n----\n+XX_\n+ZZ_\n-__Z\n");
+        assert_eq!(text.parse::<Stabilizer>().unwrap(), tableau);
+
+        // Stabilizers alone, as printed by stim's canonical_stabilizers.
+        let ghz = "+XXX\n+ZZ_\n+_ZZ".parse::<Stabilizer>().unwrap();
+        assert_eq!(ghz.expectation(&"XXX".parse::<PauliString>().unwrap()).unwrap(), 1.);
+        assert_eq!(ghz.expectation(&"ZIZ".parse::<PauliString>().unwrap()).unwrap(), 1.);
+        assert_eq!(ghz.expectation(&"YYX".parse::<PauliString>().unwrap()).unwrap(), -1.);
+        assert_eq!(ghz.expectation(&"ZII".parse::<PauliString>().unwrap()).unwrap(), 0.);
+        let reparsed = ghz.to_string().parse::<Stabilizer>().unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut measured = reparsed.clone();
+        let outcome = measured.measure(0, &mut rng).unwrap();
+        assert_eq!(measured.measure(2, &mut rng).unwrap(), outcome);
+
+        assert!("+XZ\n+ZX\n+YY".parse::<Stabilizer>().is_err());
+        assert!("+X_\n+Z_".parse::<Stabilizer>().is_err());
+        assert!(matches!("+XX\n+XX".parse::<Stabilizer>(), Err(SimulatorError::InvalidArgument(_))));
+        assert!("+Z_\n+_Z\n---\n+X_\n+_X".parse::<Stabilizer>().is_ok());
+        assert!("+X_\n+_Z\n---\n+X_\n+_X".parse::<Stabilizer>().is_err());
+        assert!("+iZ".parse::<Stabilizer>().is_err());
+    }
+
+    #[test]
+    fn test_circuit_pattern() {
+        let mut circuit = Circuit::new(3);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.s(1);
+        circuit.cnot(1, 2);
+        circuit.x(2);
+        circuit.z(0);
+        let pattern = circuit.to_pattern();
+        assert!(pattern.is_clifford(&TolerancePolicy::DOUBLE));
+
+        let mut expected = StateVector::new(3, State::PLUS);
+        circuit.run(&mut expected).unwrap();
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let result = pattern.simulate(Stabilizer::new(3, State::PLUS), &mut rng).unwrap();
+            for pauli in pauli_strings(3) {
+                assert!((result.state.expectation(&pauli).unwrap() - expected.expectation(&pauli).unwrap()).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simulate_auto() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        assert!(matches!(circuit.to_pattern().simulate_auto(State::ZERO, &SimulationConfig::default(), &mut rng).unwrap(), AutoResult::Stabilizer(_)));
+        circuit.rz(1, 0.3);
+        assert!(!circuit.to_pattern().is_clifford(&TolerancePolicy::DOUBLE));
+        assert!(matches!(circuit.to_pattern().simulate_auto(State::ZERO, &SimulationConfig::default(), &mut rng).unwrap(), AutoResult::DensityMatrix(_)));
+    }
+
+    #[test]
+    fn test_large_cluster() {
+        // Linear cluster state of n nodes, all measured in X but the last.
+        let n = 1000;
+        let mut pattern = Pattern::new(vec![]);
+        (0..n).for_each(|node| pattern.add(Command::N(node)));
+        (0..n - 1).for_each(|node| pattern.add(Command::E((node, node + 1))));
+        (0..n - 1).for_each(|node| pattern.add(Command::M(node, Plane::XY, 0., vec![], vec![], 0)));
+        assert!(pattern.is_clifford(&TolerancePolicy::DOUBLE));
+        let mut rng = StdRng::seed_from_u64(1);
+        let result = pattern.simulate(Stabilizer::new(0, State::ZERO), &mut rng).unwrap();
+        assert_eq!(result.state.nqubits, 1);
+        assert_eq!(result.outcomes.len(), n - 1);
+        let certain = ["X", "Y", "Z"].iter().filter(|p| result.state.expectation(&p.parse::<PauliString>().unwrap()).unwrap().abs() == 1.).count();
+        assert_eq!(certain, 1);
+    }
+}
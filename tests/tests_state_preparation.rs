@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests_state_preparation {
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::error::SimulatorError;
+    use dm_simu_rs::metrics::fidelity;
+    use dm_simu_rs::statevector::StateVector;
+
+    const TOLERANCE: f64 = 1e-8;
+
+    // |<target|psi>|^2 for the state prepared by the circuit from |0...0>.
+    fn overlap(target: &[Complex<f64>], circuit: &Circuit) -> f64 {
+        let mut state = StateVector::new(circuit.width(), State::ZERO);
+        circuit.run(&mut state).unwrap();
+        let norm = target.iter().map(|a| a.norm_sqr()).sum::<f64>();
+        target.iter().zip(state.data.iter()).map(|(t, s)| t.conj() * s).sum::<Complex<f64>>().norm_sqr() / norm
+    }
+
+    #[test]
+    fn test_prepare_random_states() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for n in 1..=4 {
+            for _ in 0..3 {
+                let target = (0..1 << n)
+                    .map(|_| Complex::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5))
+                    .collect::<Vec<_>>();
+                let circuit = Circuit::prepare_state(&target, &TolerancePolicy::DOUBLE).unwrap();
+                assert!((overlap(&target, &circuit) - 1.).abs() < TOLERANCE);
+            }
+        }
+    }
+    #[test]
+    fn test_prepare_sparse_and_real_states() {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let ghz = [Complex::new(h, 0.), Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::ZERO, Complex::new(h, 0.)];
+        let circuit = Circuit::prepare_state(&ghz, &TolerancePolicy::DOUBLE).unwrap();
+        assert!((overlap(&ghz, &circuit) - 1.).abs() < TOLERANCE);
+
+        let basis = [Complex::ZERO, Complex::ZERO, Complex::new(0., 2.), Complex::ZERO];
+        assert!((overlap(&basis, &Circuit::prepare_state(&basis, &TolerancePolicy::DOUBLE).unwrap()) - 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_prepared_state_as_pattern() {
+        let target = [Complex::new(0.1, 0.3), Complex::new(-0.5, 0.2), Complex::new(0.4, 0.), Complex::new(0.2, -0.6)];
+        let pattern = Circuit::prepare_state(&target, &TolerancePolicy::DOUBLE).unwrap().to_pattern();
+        let result = pattern.simulate(DensityMatrix::new(2, State::ZERO), &mut StdRng::seed_from_u64(5)).unwrap();
+        let mut state = StateVector::from_vec(target.to_vec()).unwrap();
+        state.normalize();
+        let expected = state.to_density_matrix();
+        assert!((fidelity(&result.state, &expected, &TolerancePolicy::DOUBLE).unwrap() - 1.).abs() < TOLERANCE);
+    }
+    #[test]
+    fn test_prepare_state_errors() {
+        assert_eq!(Circuit::prepare_state(&[Complex::ONE; 3], &TolerancePolicy::DOUBLE).unwrap_err(), SimulatorError::NotPowerOfTwo(3));
+        assert_eq!(Circuit::prepare_state(&[Complex::ONE], &TolerancePolicy::DOUBLE).unwrap_err(), SimulatorError::NotPowerOfTwo(1));
+        assert!(matches!(Circuit::prepare_state(&[Complex::ZERO; 4], &TolerancePolicy::DOUBLE), Err(SimulatorError::NotNormalized(_))));
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests_statevector {
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use dm_simu_rs::backend::QuantumBackend;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::operators::{Operator, OneQubitOp, TwoQubitsOp};
+    use dm_simu_rs::pauli::PauliString;
+    use dm_simu_rs::statevector::StateVector;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    // Small circuit written once against the trait and run on both backends.
+    fn prepare<B: QuantumBackend>(backend: &mut B) {
+        let h = Operator::one_qubit(OneQubitOp::H);
+        let cx = Operator::two_qubits(TwoQubitsOp::CX);
+        backend.evolve_single(&h, 0).unwrap();
+        backend.evolve(&cx, &[0, 2]).unwrap();
+        backend.evolve_single(&Operator::ry(0.3), 1).unwrap();
+        backend.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[2, 1]).unwrap();
+    }
+
+    #[test]
+    fn test_new_zero_and_plus() {
+        let zero = StateVector::new(2, State::ZERO);
+        assert_eq!(zero.data[0], Complex::ONE);
+        assert!((zero.norm() - 1.).abs() < TOLERANCE);
+        let plus = StateVector::new(3, State::PLUS);
+        assert!(plus.data.iter().all(|a| (a.re - 1. / 8f64.sqrt()).abs() < TOLERANCE));
+    }
+    #[test]
+    fn test_from_vec_wrong_size() {
+        assert!(StateVector::from_vec(vec![Complex::ONE; 3]).is_err());
+    }
+    #[test]
+    fn test_matches_density_matrix() {
+        let mut sv = StateVector::new(3, State::ZERO);
+        let mut dm = DensityMatrix::new(3, State::ZERO);
+        prepare(&mut sv);
+        prepare(&mut dm);
+        assert!(dm.equals(sv.to_density_matrix(), TOLERANCE));
+        for p in ["ZIZ", "XIX", "XZY", "IXI", "YYZ"] {
+            let p: PauliString = p.parse().unwrap();
+            let expected = dm.expectation(&p).unwrap();
+            assert!((QuantumBackend::expectation(&sv, &p).unwrap() - expected).abs() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_measure_collapses_bell_pair() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..10 {
+            let mut sv = StateVector::new(2, State::ZERO);
+            sv.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+            sv.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+            let first = sv.measure(0, &mut rng).unwrap();
+            assert_eq!(sv.measure(1, &mut rng).unwrap(), first);
+            assert!((sv.norm() - 1.).abs() < TOLERANCE);
+
+            let mut dm = DensityMatrix::new(2, State::ZERO);
+            dm.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+            dm.evolve(&Operator::two_qubits(TwoQubitsOp::CX), &[0, 1]).unwrap();
+            let first = dm.measure(1, &mut rng).unwrap();
+            assert_eq!(dm.measure(0, &mut rng).unwrap(), first);
+            assert!((dm.trace().re - 1.).abs() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_measure_deterministic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sv = StateVector::new(2, State::ZERO);
+        sv.evolve_single(&Operator::one_qubit(OneQubitOp::X), 1).unwrap();
+        assert_eq!(sv.measure(0, &mut rng).unwrap(), 0);
+        assert_eq!(sv.measure(1, &mut rng).unwrap(), 1);
+        assert!(sv.measure(2, &mut rng).is_err());
+    }
+    #[test]
+    fn test_tensor() {
+        let mut sv = StateVector::new(1, State::ZERO);
+        sv.tensor(&StateVector::new(2, State::PLUS));
+        let mut dm = DensityMatrix::new(1, State::ZERO);
+        QuantumBackend::tensor(&mut dm, &DensityMatrix::new(2, State::PLUS));
+        assert_eq!(sv.nqubits, 3);
+        assert!(dm.equals(sv.to_density_matrix(), TOLERANCE));
+        prepare(&mut sv);
+        prepare(&mut dm);
+        assert!(dm.equals(sv.to_density_matrix(), TOLERANCE));
+    }
+    #[test]
+    fn test_grow_and_shrink_register() {
+        // Teleport |+> along a two qubit cluster, X-measuring the first qubit: the second
+        // qubit ends up in H|+> = |0> up to the byproduct X^s.
+        fn teleport<B: QuantumBackend>(backend: &mut B, rng: &mut StdRng) {
+            backend.add_qubit(State::PLUS);
+            backend.evolve(&Operator::two_qubits(TwoQubitsOp::CZ), &[0, 1]).unwrap();
+            backend.evolve_single(&Operator::one_qubit(OneQubitOp::H), 0).unwrap();
+            let s = backend.measure_and_remove(0, rng).unwrap();
+            if s == 1 {
+                backend.evolve_single(&Operator::one_qubit(OneQubitOp::X), 0).unwrap();
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..5 {
+            let mut sv = StateVector::new(1, State::PLUS);
+            teleport(&mut sv, &mut rng);
+            assert_eq!(sv.nqubits, 1);
+            assert!((sv.expectation(&"Z".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+
+            let mut dm = DensityMatrix::new(1, State::PLUS);
+            teleport(&mut dm, &mut rng);
+            assert_eq!((dm.nqubits, dm.size), (1, 2));
+            assert!((dm.expectation(&"Z".parse().unwrap()).unwrap() - 1.).abs() < TOLERANCE);
+        }
+    }
+    #[test]
+    fn test_remove_qubit_keeps_remaining_state() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut dm = DensityMatrix::new(1, State::ZERO);
+        dm.add_qubit(State::PLUS);
+        dm.add_qubit(State::ZERO);
+        dm.evolve_single(&Operator::one_qubit(OneQubitOp::X), 2).unwrap();
+        assert_eq!(dm.remove_qubit(0, &mut rng).unwrap(), 0);
+        assert!((dm.expectation(&"XZ".parse().unwrap()).unwrap() + 1.).abs() < TOLERANCE);
+        assert!(dm.remove_qubit(2, &mut rng).is_err());
+
+        let mut sv = StateVector::new(2, State::ZERO);
+        sv.evolve_single(&Operator::one_qubit(OneQubitOp::X), 1).unwrap();
+        assert_eq!(sv.remove_qubit(1, &mut rng).unwrap(), 1);
+        assert_eq!(sv.data, vec![Complex::ONE, Complex::ZERO]);
+    }
+}
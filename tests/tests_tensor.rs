@@ -1,7 +1,9 @@
 #[cfg(test)]
+#[allow(non_snake_case)]
 mod tests_tensor {
     use num_complex::Complex;
     use dm_simu_rs::tensor::Tensor;
+    use dm_simu_rs::error::SimulatorError;
 
     #[test]
     fn test_tensor_creation() {
@@ -44,7 +46,7 @@ mod tests_tensor {
             shape: shape.clone(),
         };
 
-        let result = tensor1.add(&tensor2);
+        let result = tensor1.add(&tensor2).unwrap();
         assert_eq!(result.data, vec![Complex::new(6., 0.), Complex::new(8., 0.), Complex::new(10., 0.), Complex::new(12., 0.)]);
     }
 
@@ -60,7 +62,7 @@ mod tests_tensor {
             shape: shape.clone(),
         };
 
-        let result = tensor1.multiply(&tensor2);
+        let result = tensor1.multiply(&tensor2).unwrap();
         assert_eq!(result.data, vec![Complex::new(5., 0.), Complex::new(12., 0.), Complex::new(21., 0.), Complex::new(32., 0.)]);
     }
 
@@ -74,7 +76,7 @@ mod tests_tensor {
 
         // Calculate the tensor product
         
-        let result_tensor = tensor1.tensor_product(&tensor2);
+        let result_tensor = tensor1.tensor_product(&tensor2).unwrap();
 
         // Expected result:
         // Shape: [3, 2]
@@ -83,6 +85,20 @@ mod tests_tensor {
         assert_eq!(result_tensor.data, vec![Complex::new(4., 0.), Complex::new(5., 0.), Complex::new(8., 0.),  Complex::new(10., 0.), Complex::new(12., 0.), Complex::new(15., 0.)]);
     }
 
+    #[test]
+    fn test_elementwise_shape_mismatch() {
+        let tensor1 = Tensor::from_vec(vec![Complex::new(1., 0.); 4], vec![2, 2]);
+        let tensor2 = Tensor::from_vec(vec![Complex::new(1., 0.); 4], vec![4]);
+        let tensor3 = Tensor::from_vec(vec![Complex::new(1., 0.); 6], vec![2, 3]);
+        assert_eq!(tensor1.add(&tensor2).unwrap_err(), SimulatorError::DimensionMismatch { expected: 2, actual: 1 });
+        assert_eq!(tensor1.multiply(&tensor3).unwrap_err(), SimulatorError::DimensionMismatch { expected: 2, actual: 3 });
+
+        // Tensors built from their public fields can hold the wrong number of elements.
+        let ragged = Tensor { data: vec![Complex::new(1., 0.); 3], shape: vec![2, 2] };
+        assert_eq!(tensor1.add(&ragged).unwrap_err(), SimulatorError::DimensionMismatch { expected: 4, actual: 3 });
+        assert_eq!(tensor1.tensor_product(&ragged).unwrap_err(), SimulatorError::DimensionMismatch { expected: 4, actual: 3 });
+    }
+
     #[test]
     fn test_tensordot_2D_1() {
         // Tensor A (shape: [2, 2])
@@ -546,7 +562,7 @@ fn test_moveaxis_with_negative_indices_2() {
 }
     #[test]
     fn test_moveaxis_3d() {
-        let data = (0..24).into_iter().map(|e| Complex::new(e as f64, 0.)).collect();
+        let data = (0..24).map(|e| Complex::new(e as f64, 0.)).collect();
         let tensor_3d = Tensor {
             data,
             shape: vec![2, 3, 4], // Shape (2, 3, 4)
@@ -573,4 +589,89 @@ fn test_moveaxis_with_negative_indices_2() {
         ]);
         assert_eq!(moved_tensor_3d.shape, vec![4, 3, 2]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tensordot_dimension_mismatch() {
+        let a: Tensor<Complex<f64>> = Tensor::new(&[2, 3]);
+        let b: Tensor<Complex<f64>> = Tensor::new(&[2, 3]);
+        assert!(a.tensordot(&b, (&[1], &[0])).is_err());
+    }
+
+    #[test]
+    fn test_tensordot_full_contraction() {
+        let a = Tensor::from_vec((1..=4).map(|e| Complex::new(e as f64, 0.)).collect(), vec![2, 2]);
+        let b = Tensor::from_vec((1..=4).map(|e| Complex::new(e as f64, 0.)).collect(), vec![2, 2]);
+        let result = a.tensordot(&b, (&[0, 1], &[1, 0])).unwrap();
+        // Tr(A B) = 1*1 + 2*3 + 3*2 + 4*4
+        assert!(result.shape.is_empty());
+        assert_eq!(result.data, vec![Complex::new(29., 0.)]);
+    }
+
+    #[test]
+    fn test_einsum() {
+        let a = Tensor::from_vec((1..=6).map(|e| Complex::new(e as f64, 0.)).collect(), vec![2, 3]);
+        let b = Tensor::from_vec((1..=12).map(|e| Complex::new(e as f64, -1.)).collect(), vec![3, 4]);
+        let c = Tensor::from_vec((1..=8).map(|e| Complex::new(0., e as f64)).collect(), vec![4, 2]);
+        let matmul = Tensor::einsum("ij,jk->ik", &[&a, &b]).unwrap();
+        let expected = a.tensordot(&b, (&[1], &[0])).unwrap();
+        assert_eq!(matmul.shape, vec![2, 4]);
+        assert_eq!(matmul.data, expected.data);
+        // Implicit output, in alphabetical order.
+        assert_eq!(Tensor::einsum("jk,ij", &[&b, &a]).unwrap().data, expected.data);
+        assert_eq!(Tensor::einsum("ij->ji", &[&a]).unwrap().data, a.transpose(&[1, 0]).unwrap().data);
+
+        // Tr(A B C) through a chain of three operands.
+        let trace = Tensor::einsum("ij, jk, ki ->", &[&a, &b, &c]).unwrap();
+        let abc = expected.tensordot(&c, (&[1], &[0])).unwrap();
+        assert!(trace.shape.is_empty());
+        assert_eq!(trace.data, vec![abc.data[0] + abc.data[3]]);
+        assert_eq!(Tensor::einsum("ii->i", &[&abc]).unwrap().data, vec![abc.data[0], abc.data[3]]);
+    }
+
+    #[test]
+    fn test_einsum_invalid() {
+        let a: Tensor<Complex<f64>> = Tensor::new(&[2, 3]);
+        assert!(Tensor::einsum("ij,ij->ij", &[&a]).is_err());
+        assert!(Tensor::einsum("ijk->i", &[&a]).is_err());
+        assert!(Tensor::einsum("ij,jk->ik", &[&a, &a]).is_err());
+        assert!(Tensor::einsum("ij->ik", &[&a]).is_err());
+        assert!(Tensor::einsum("ij->ii", &[&a]).is_err());
+        assert!(Tensor::einsum("i1->i", &[&a]).is_err());
+    }
+
+    #[test]
+    fn test_view_slicing() {
+        let tensor = Tensor::from_vec((0..24).collect::<Vec<i64>>(), vec![2, 3, 4]);
+        let view = tensor.view();
+        assert!(view.is_contiguous());
+        assert_eq!(*view.get(&[1, 2, 3]).unwrap(), 23);
+        assert!(view.get(&[2, 0, 0]).is_err());
+
+        let sliced = view.slice(2, 1..3).unwrap();
+        assert_eq!(sliced.shape(), &[2, 3, 2]);
+        assert!(!sliced.is_contiguous());
+        assert_eq!(sliced.iter().copied().collect::<Vec<_>>(), vec![1, 2, 5, 6, 9, 10, 13, 14, 17, 18, 21, 22]);
+        let row = sliced.index_axis(0, 1).unwrap().index_axis(0, 2).unwrap();
+        assert_eq!(row.to_tensor().data, vec![21, 22]);
+        assert!(view.slice(1, 2..4).is_err());
+        assert!(view.index_axis(3, 0).is_err());
+    }
+
+    #[test]
+    fn test_view_permutations() {
+        let tensor = Tensor::from_vec((0..24).map(|e| Complex::new(e as f64, 0.)).collect(), vec![2, 3, 4]);
+        let view = tensor.view();
+        let transposed = view.transpose(&[2, 0, 1]).unwrap();
+        assert_eq!(transposed.shape(), &[4, 2, 3]);
+        assert_eq!(transposed.strides(), &[1, 12, 4]);
+        assert_eq!(transposed.get(&[3, 1, 2]).unwrap(), tensor.view().get(&[1, 2, 3]).unwrap());
+        assert_eq!(transposed.to_tensor().data, tensor.transpose(&[2, 0, 1]).unwrap().data);
+        assert_eq!(view.transpose(&[]).unwrap().shape(), &[4, 3, 2]);
+        assert_eq!(view.moveaxis(&[0], &[-1]).unwrap().to_tensor().data, tensor.moveaxis(&[0], &[-1]).unwrap().data);
+        // Permuting a slice composes both stride changes.
+        let sliced = view.slice(1, 1..2).unwrap().transpose(&[]).unwrap();
+        assert_eq!(sliced.to_tensor().data, tensor.transpose(&[]).unwrap().view().slice(1, 1..2).unwrap().to_tensor().data);
+        assert!(view.transpose(&[0, 0, 1]).is_err());
+        assert!(view.moveaxis(&[0], &[]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests_tomography {
+    use num_complex::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::channels::{self, Channel};
+    use dm_simu_rs::circuit::Circuit;
+    use dm_simu_rs::config::TolerancePolicy;
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::metrics;
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::pattern::{Command, Pattern, Plane};
+    use dm_simu_rs::noise::NoiseModel;
+    use dm_simu_rs::simulator::Simulator;
+    use dm_simu_rs::tomography::{characterize, project_cptp, reconstruct_channel, reconstruct_choi, simulate_state_tomography, tomography_inputs, ProjectionOptions, StateTomography};
+
+    fn max_diff(a: &[Complex<f64>], b: &[Complex<f64>]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y).norm()).fold(0., f64::max)
+    }
+
+    #[test]
+    fn test_reconstruct_channel() {
+        let channel = Channel::new(channels::amplitude_damping(0.3).unwrap()).unwrap()
+            .compose(&Channel::new(channels::dephasing(0.1).unwrap()).unwrap()).unwrap();
+        let two_qubits = channel.tensor(&Channel::new(channels::depolarizing(0.2).unwrap()).unwrap());
+        for target in [channel, two_qubits] {
+            let reconstructed = characterize(target.nqubits, |mut rho| {
+                let indices = (0..rho.nqubits).collect::<Vec<_>>();
+                rho.apply_channel(&target.kraus, &indices).map_err(|e| e.to_string())?;
+                Ok(rho)
+            }, None, &TolerancePolicy::DOUBLE).unwrap();
+            assert!(max_diff(&reconstructed.choi(), &target.choi()) < 1e-9);
+        }
+
+        let inputs = tomography_inputs(1);
+        assert_eq!(inputs.len(), 4);
+        assert!(reconstruct_choi(&inputs[..3], &inputs[..3], &TolerancePolicy::DOUBLE).is_err());
+        assert!(reconstruct_choi(&inputs, &inputs[..3], &TolerancePolicy::DOUBLE).is_err());
+    }
+
+    #[test]
+    fn test_project_cptp() {
+        let options = ProjectionOptions { tolerance: 1e-10, ..ProjectionOptions::new(&TolerancePolicy::DOUBLE) };
+        let channel = Channel::new(channels::amplitude_damping(0.4).unwrap()).unwrap();
+        let choi = channel.choi();
+        assert!(max_diff(&project_cptp(&choi, 1, options).unwrap(), &choi) < 1e-8);
+
+        // Shot noise like perturbation breaking both positivity and trace preservation.
+        let mut perturbed = choi.clone();
+        perturbed[15] -= Complex::new(0.05, 0.);
+        perturbed[0] += Complex::new(0.03, 0.);
+        perturbed[3] += Complex::new(0.02, 0.01);
+        perturbed[12] += Complex::new(0.02, -0.01);
+        let projected = Channel::from_choi(&project_cptp(&perturbed, 1, options).unwrap(), 1, &TolerancePolicy::DOUBLE).unwrap();
+        assert!(projected.is_cptp(1e-8).is_ok());
+        assert!(max_diff(&projected.choi(), &choi) < 0.1);
+        assert!(project_cptp(&choi[..4], 1, options).is_err());
+
+        // The projection stops at the iteration cap, and reconstruct_channel passes the options on.
+        assert!(project_cptp(&perturbed, 1, ProjectionOptions { max_iterations: 1, ..options }).is_err());
+        let inputs = tomography_inputs(1);
+        let outputs = inputs.iter().map(|rho| {
+            let mut rho = rho.clone();
+            rho.apply_channel(&channel.kraus, &[0]).unwrap();
+            rho
+        }).collect::<Vec<_>>();
+        assert!(reconstruct_channel(&inputs, &outputs, Some(options), &TolerancePolicy::DOUBLE).is_ok());
+        assert!(reconstruct_channel(&inputs, &outputs, Some(ProjectionOptions { max_iterations: 0, ..options }), &TolerancePolicy::DOUBLE).is_err());
+    }
+
+    #[test]
+    fn test_characterize_pattern() {
+        // Teleportation through one XY measurement at angle 0 implements H.
+        let mut pattern = Pattern::new(vec![0]);
+        pattern.extend(vec![
+            Command::N(1),
+            Command::E((0, 1)),
+            Command::M(0, Plane::XY, 0., vec![], vec![], 0),
+            Command::X(1, vec![0])
+        ]);
+        let run = |noise: Option<NoiseModel>, rho: DensityMatrix| {
+            let mut simulator = Simulator::new(rho).load(pattern.clone()).with_seed(1);
+            if let Some(noise) = noise {
+                simulator = simulator.with_noise(noise);
+            }
+            simulator.run(30)?.average_state()
+        };
+        let ideal = characterize(1, |rho| run(None, rho), None, &TolerancePolicy::DOUBLE).unwrap();
+        let h = Operator::one_qubit(OneQubitOp::H);
+        assert!((ideal.average_gate_fidelity(&h).unwrap() - 1.).abs() < 1e-9);
+
+        let noisy = characterize(1, |rho| run(Some(NoiseModel::depolarizing(0.05).unwrap()), rho), Some(ProjectionOptions::new(&TolerancePolicy::DOUBLE)), &TolerancePolicy::DOUBLE).unwrap();
+        assert!(noisy.is_cptp(1e-8).is_ok());
+        assert!(noisy.average_gate_fidelity(&h).unwrap() < 0.99);
+        assert!(characterize(2, |_| Ok(DensityMatrix::new(1, State::ZERO)), None, &TolerancePolicy::DOUBLE).is_err());
+    }
+
+    #[test]
+    fn test_state_tomography() {
+        let mut circuit = Circuit::new(2);
+        circuit.h(0);
+        circuit.cnot(0, 1);
+        circuit.ry(1, 0.4);
+        let mut rho = DensityMatrix::new(2, State::ZERO);
+        circuit.run(&mut rho).unwrap();
+        let data = simulate_state_tomography(&rho, 2000, &mut StdRng::seed_from_u64(2)).unwrap();
+        assert_eq!(data.settings.len(), 9);
+        assert!(data.settings.iter().all(|s| s.counts.iter().sum::<usize>() == 2000));
+
+        let linear = data.linear_inversion().unwrap();
+        assert!((linear.trace().re - 1.).abs() < 1e-12);
+        assert!(metrics::trace_distance(&linear, &rho).unwrap() < 0.05);
+        let mle = data.maximum_likelihood(1e-9, 10000).unwrap();
+        assert!(metrics::fidelity(&mle, &rho, &TolerancePolicy::DOUBLE).unwrap() > 0.99);
+        assert!(mle.purity() <= 1. + 1e-9);
+
+        // A mixed state is recovered as well.
+        let mut noisy = DensityMatrix::new(1, State::ZERO);
+        noisy.apply_channel(&channels::depolarizing(0.5).unwrap(), &[0]).unwrap();
+        let data = simulate_state_tomography(&noisy, 5000, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert!(metrics::trace_distance(&data.maximum_likelihood(1e-9, 10000).unwrap(), &noisy).unwrap() < 0.03);
+
+        assert!(simulate_state_tomography(&rho, 0, &mut StdRng::seed_from_u64(0)).is_err());
+        assert!(StateTomography { nqubits: 1, settings: vec![] }.linear_inversion().is_err());
+    }
+}
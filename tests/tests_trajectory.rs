@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests_trajectory {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::backend::QuantumBackend;
+    use dm_simu_rs::channels::{self, Channel};
+    use dm_simu_rs::density_matrix::{DensityMatrix, State};
+    use dm_simu_rs::noise::{CommandKind, NoiseModel};
+    use dm_simu_rs::operators::{OneQubitOp, Operator};
+    use dm_simu_rs::pattern::{Command, Pattern};
+    use dm_simu_rs::pauli::PauliString;
+    use dm_simu_rs::rng::RngConfig;
+    use dm_simu_rs::statevector::StateVector;
+    use dm_simu_rs::trajectory::Trajectory;
+
+    #[test]
+    fn test_jumps() {
+        let mut state = StateVector::new(2, State::ZERO);
+        state.evolve_single(&Operator::one_qubit(OneQubitOp::X), 1).unwrap();
+        let mut trajectory = Trajectory::new(state, RngConfig::default().rng("trajectory", 0));
+        trajectory.apply_channel(&channels::amplitude_damping(1.).unwrap(), &[1]).unwrap();
+        assert!((trajectory.expectation(&"ZZ".parse::<PauliString>().unwrap()).unwrap() - 1.).abs() < 1e-12);
+        assert!((trajectory.state.norm() - 1.).abs() < 1e-12);
+        assert!(trajectory.apply_channel(&[], &[0]).is_err());
+    }
+
+    #[test]
+    fn test_matches_density_matrix() {
+        let mut pattern = Pattern::new(vec![]);
+        pattern.extend(vec![Command::N(0), Command::N(1), Command::N(2), Command::E((0, 1)), Command::E((1, 2))]);
+        let mut noise = NoiseModel::default();
+        noise.channels.insert(CommandKind::N, Channel::new(channels::amplitude_damping(0.3).unwrap()).unwrap());
+        noise.channels.insert(CommandKind::E, Channel::new(channels::depolarizing(0.1).unwrap()).unwrap());
+        let observables = ["XZI", "ZXZ", "IZX", "ZII"].map(|s| s.parse::<PauliString>().unwrap());
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let exact = pattern.simulate_with_noise(DensityMatrix::new(0, State::ZERO), &noise, &mut rng).unwrap().state;
+        let config = RngConfig { seed: 3, ..RngConfig::default() };
+        let estimate = pattern.simulate_trajectories(&StateVector::new(0, State::ZERO), &noise, 2000, &observables, &config).unwrap();
+        for (observable, (mean, error)) in observables.iter().zip(estimate.means.iter().zip(&estimate.standard_errors)) {
+            let expected = exact.expectation(observable).unwrap();
+            assert!((mean - expected).abs() < 4. * error + 1e-9, "{:?}: {} vs {}", observable, mean, expected);
+        }
+        let again = pattern.simulate_trajectories(&StateVector::new(0, State::ZERO), &noise, 2000, &observables, &config).unwrap();
+        assert_eq!(again.means, estimate.means);
+        assert!(pattern.simulate_trajectories(&StateVector::new(0, State::ZERO), &noise, 0, &observables, &config).is_err());
+    }
+}
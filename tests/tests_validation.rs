@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests_validation {
+    use num_complex::Complex;
+    use dm_simu_rs::channels;
+    use dm_simu_rs::npy;
+    use dm_simu_rs::validation;
+
+    // Closed form amplitude damping of |+>: rho_11 = (1 - g)^k / 2, rho_01 = (1 - g)^(k / 2) / 2.
+    fn reference_trajectory(gamma: f64, steps: usize) -> Vec<Complex<f64>> {
+        (0..steps).flat_map(|k| {
+            let decay = (1. - gamma).powi(k as i32);
+            let p1 = decay / 2.;
+            let coherence = decay.sqrt() / 2.;
+            [1. - p1, coherence, coherence, p1].map(|x| Complex::new(x, 0.))
+        }).collect()
+    }
+
+    #[test]
+    fn test_npy_roundtrip() {
+        let data = (0..8).map(|i| Complex::new(i as f64, -(i as f64) / 2.)).collect::<Vec<_>>();
+        let bytes = npy::to_npy(&[2, 4], &data).unwrap();
+        assert_eq!((bytes.len() - 8 * 16) % 64, 0);
+        let (shape, parsed) = npy::parse_npy(&bytes).unwrap();
+        assert_eq!(shape, vec![2, 4]);
+        assert_eq!(parsed, data);
+        assert!(npy::to_npy(&[3], &data).is_err());
+    }
+    fn npy_bytes(header: &str, values: &[f64]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        values.iter().for_each(|x| bytes.extend_from_slice(&x.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_parse_float_npy() {
+        let bytes = npy_bytes("{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }", &[1.5, -2.]);
+        let (shape, data) = npy::parse_npy(&bytes).unwrap();
+        assert_eq!(shape, vec![2]);
+        assert_eq!(data, vec![Complex::new(1.5, 0.), Complex::new(-2., 0.)]);
+
+        let fortran = npy_bytes("{'descr': '<f8', 'fortran_order': True, 'shape': (2,), }", &[1.5, -2.]);
+        assert!(npy::parse_npy(&fortran).is_err());
+        let int = npy_bytes("{'descr': '<i8', 'fortran_order': False, 'shape': (2,), }", &[1.5, -2.]);
+        assert!(npy::parse_npy(&int).is_err());
+    }
+
+    #[test]
+    fn test_validate_amplitude_damping_trajectory() {
+        let gamma = 0.1;
+        let steps = 6;
+        let path = std::env::temp_dir().join("dm_simu_rs_amplitude_damping.npy");
+        npy::write_npy(&path, &[steps, 2, 2], &reference_trajectory(gamma, steps)).unwrap();
+
+        let kraus = channels::amplitude_damping(gamma).unwrap();
+        let report = validation::validate_against_file(&path, |rho, _| rho.apply_channel(&kraus, &[0])).unwrap();
+        assert_eq!(report.trace_distances.len(), steps);
+        assert!(report.agrees(1e-12));
+
+        // A wrong damping rate is caught.
+        let kraus = channels::amplitude_damping(0.2).unwrap();
+        let report = validation::validate_against_file(&path, |rho, _| rho.apply_channel(&kraus, &[0])).unwrap();
+        assert!(!report.agrees(1e-3));
+        assert_eq!(report.worst_step, steps - 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_load_states_errors() {
+        let path = std::env::temp_dir().join("dm_simu_rs_not_square.npy");
+        npy::write_npy(&path, &[2, 3], &[Complex::ZERO; 6]).unwrap();
+        assert!(validation::load_states(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(validation::load_states(std::env::temp_dir().join("dm_simu_rs_missing.npy")).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests_verification {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use dm_simu_rs::graph::GraphState;
+    use dm_simu_rs::noise::NoiseModel;
+    use dm_simu_rs::verification::VerificationOptions;
+
+    #[test]
+    fn test_stabilizers_of_ideal_state() {
+        let graph = GraphState::new(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]).unwrap();
+        let rho = graph.to_density_matrix();
+        for generators in [vec![0], vec![0, 1], vec![1, 3], vec![0, 1, 2, 3]] {
+            let (sign, stabilizer) = graph.stabilizer(&generators).unwrap();
+            assert!((sign * rho.expectation(&stabilizer).unwrap() - 1.).abs() < 1e-10);
+        }
+        assert!(graph.stabilizer(&[4]).is_err());
+
+        let report = graph.verify(&rho, &VerificationOptions::default(), &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(report.accepted, report.tests);
+        assert!((report.exact_fidelity - 1.).abs() < 1e-10);
+        // 2 sqrt(ln(20) / 2000) below 1.
+        assert!((report.fidelity_bound - 0.9226).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_noisy_preparation() {
+        let graph = GraphState::new(3, &[(0, 1), (1, 2)]).unwrap();
+        let noise = NoiseModel::depolarizing(0.05).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let rho = graph.prepare_noisy(&noise, &mut rng).unwrap();
+        let options = VerificationOptions { tests: 20000, confidence: 0.99 };
+        let report = graph.verify(&rho, &options, &mut rng).unwrap();
+        assert!(report.exact_fidelity < 0.95);
+        assert!((report.fidelity_estimate - report.exact_fidelity).abs() < 0.03);
+        assert!(report.fidelity_bound <= report.exact_fidelity);
+
+        assert!(graph.verify(&rho, &VerificationOptions { tests: 0, ..options }, &mut rng).is_err());
+        assert!(graph.verify(&rho, &VerificationOptions { confidence: 1., ..options }, &mut rng).is_err());
+        assert!(graph.verify(&GraphState::new(2, &[(0, 1)]).unwrap().to_density_matrix(), &options, &mut rng).is_err());
+    }
+}